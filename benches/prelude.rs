@@ -0,0 +1,113 @@
+//! Time spent receiving and applying device preludes, e.g. `cargo bench --bench prelude`. Also
+//! times applying the label blocks alone, in place as the client does and by clearing the map and
+//! cloning every label as it did before, so the two can be compared on the same machine.
+
+use futures_util::SinkExt;
+use rship_blackmagic_videohub::client::{VideohubClient, update_label};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
+use videohub::{Label, Route, VideohubCodec, VideohubMessage};
+
+// A large router, e.g. a 288x288 Universal Videohub
+const PORTS: u32 = 288;
+const PRELUDES: usize = 200;
+
+fn prelude() -> Vec<VideohubMessage> {
+    let labels = |prefix: &str| {
+        (0..PORTS)
+            .map(|id| Label {
+                id,
+                name: format!("{prefix} {} camera feed", id + 1),
+            })
+            .collect()
+    };
+    vec![
+        VideohubMessage::InputLabels(labels("Input")),
+        VideohubMessage::OutputLabels(labels("Output")),
+        VideohubMessage::VideoOutputRouting(
+            (0..PORTS)
+                .map(|output| Route {
+                    to_output: output,
+                    from_input: (output * 7) % PORTS,
+                })
+                .collect(),
+        ),
+        VideohubMessage::EndPrelude,
+    ]
+}
+
+// Apply every label block of PRELUDES preludes to a label map
+fn apply_labels(
+    prelude: &[VideohubMessage],
+    apply: impl Fn(&mut HashMap<u32, String>, &[Label]),
+) -> Duration {
+    let mut labels = HashMap::new();
+    let started = Instant::now();
+    for _ in 0..PRELUDES {
+        for message in prelude {
+            if let VideohubMessage::InputLabels(block) | VideohubMessage::OutputLabels(block) =
+                message
+            {
+                apply(&mut labels, block);
+            }
+        }
+    }
+    std::hint::black_box(&labels);
+    started.elapsed()
+}
+
+fn compare_label_updates() {
+    let prelude = prelude();
+    let baseline = apply_labels(&prelude, |labels, block| {
+        labels.clear();
+        for label in block {
+            labels.insert(label.id, label.name.clone());
+        }
+    });
+    let in_place = apply_labels(&prelude, |labels, block| {
+        for label in block {
+            update_label(labels, label);
+        }
+    });
+    println!(
+        "Label blocks of {PRELUDES} preludes: {in_place:?} updated in place, {baseline:?} cleared and cloned"
+    );
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+
+    // Fake device sending the same prelude over and over, like repeated reconnects
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let mut device = Framed::new(stream, VideohubCodec);
+        let prelude = prelude();
+        for _ in 0..PRELUDES {
+            for message in &prelude {
+                device.feed(message.clone()).await?;
+            }
+            device.flush().await?;
+        }
+        anyhow::Ok(())
+    });
+
+    let mut client = VideohubClient::new("127.0.0.1".to_string(), port);
+    client.connect().await?;
+
+    let started = Instant::now();
+    for _ in 0..PRELUDES {
+        client.receive_initial_state().await?;
+    }
+    let elapsed = started.elapsed();
+
+    println!(
+        "{PRELUDES} preludes of {PORTS}x{PORTS}: {elapsed:?} total, {:?} per prelude",
+        elapsed / PRELUDES as u32
+    );
+    compare_label_updates();
+    Ok(())
+}
@@ -1,42 +1,365 @@
-use schemars::JsonSchema;
+use rship_sdk::{ActionArgs, TargetProxy};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use tokio::sync::mpsc;
+
+use crate::pending::ExpectedChange;
+use crate::service::{CommandRequest, VideohubCommand, route_command};
+use crate::simulation::ActionPayload;
+use crate::translations::DisplayNames;
+
+// Register an action that queues the command request its payload converts to. Used by the
+// feature modules, which keep their actions' conversions next to the logic running them.
+pub async fn add_command_action<T>(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    name: &str,
+    short_id: &str,
+    command_tx: &mpsc::Sender<CommandRequest>,
+    request: fn(T) -> CommandRequest,
+) where
+    T: DeserializeOwned + JsonSchema + Send + 'static,
+{
+    let tx = command_tx.clone();
+    let action = short_id.to_string();
+    target
+        .add_action(
+            ActionArgs::<T>::new(display_names.get(name), short_id.into()),
+            move |_action, data| {
+                let tx = tx.clone();
+                let action = action.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = tx.send(request(data)).await {
+                        log::error!("Failed to send {action} command: {e}");
+                    }
+                });
+            },
+        )
+        .await;
+}
+
+// A port given by number, or by its current label on the device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum PortRef {
+    Number(u32),
+    Label(String),
+}
+
+impl PortRef {
+    // Convert a number from the 1-indexed action payload to a 0-indexed port; labels are kept
+    pub fn zero_indexed(self) -> Self {
+        match self {
+            Self::Number(port) => Self::Number(zero_indexed(port)),
+            label => label,
+        }
+    }
+}
+
+// A port number from a 1-indexed action payload, 0-indexed as the device numbers it
+fn zero_indexed(port: u32) -> u32 {
+    port.clamp(1, u32::MAX) - 1
+}
+
+// A duration in milliseconds, shared by actions taking durations so rship renders them alike.
+// Zero and durations over a day are rejected when the payload is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u64", into = "u64")]
+pub struct DurationMs(u64);
+
+impl DurationMs {
+    pub const MIN: u64 = 1;
+    pub const MAX: u64 = 24 * 60 * 60 * 1000;
+
+    pub fn as_millis(self) -> u64 {
+        self.0
+    }
+}
+
+impl TryFrom<u64> for DurationMs {
+    type Error = String;
+
+    fn try_from(ms: u64) -> Result<Self, Self::Error> {
+        if !(Self::MIN..=Self::MAX).contains(&ms) {
+            return Err(format!(
+                "duration {ms} ms is outside {}..={} ms",
+                Self::MIN,
+                Self::MAX
+            ));
+        }
+        Ok(Self(ms))
+    }
+}
+
+impl From<DurationMs> for u64 {
+    fn from(duration: DurationMs) -> Self {
+        duration.0
+    }
+}
+
+impl JsonSchema for DurationMs {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "DurationMs".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "integer",
+            "format": "uint64",
+            "minimum": Self::MIN,
+            "maximum": Self::MAX,
+            "description": "Duration in milliseconds"
+        })
+    }
+}
+
+// A contiguous span of ports, 1-indexed and inclusive like the rship actions ("outputs 10-20").
+// Empty or reversed spans are rejected when the payload is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "PortSpan")]
+pub struct PortRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+// PortRange before validation
+#[derive(Deserialize)]
+struct PortSpan {
+    start: u32,
+    end: u32,
+}
+
+impl PortRange {
+    // The ports of the span, 0-indexed
+    pub fn ports(self) -> impl Iterator<Item = u32> {
+        (self.start - 1)..self.end
+    }
+}
+
+impl TryFrom<PortSpan> for PortRange {
+    type Error = String;
+
+    fn try_from(span: PortSpan) -> Result<Self, Self::Error> {
+        let PortSpan { start, end } = span;
+        if start == 0 {
+            return Err("port ranges start at port 1".into());
+        }
+        if start > end {
+            return Err(format!("port range {start}-{end} ends before it starts"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl JsonSchema for PortRange {
+    fn schema_name() -> Cow<'static, str> {
+        "PortRange".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "description": "Ports start to end, inclusive (1-indexed)",
+            "properties": {
+                "start": { "type": "integer", "format": "uint32", "minimum": 1, "description": "First port" },
+                "end": { "type": "integer", "format": "uint32", "minimum": 1, "description": "Last port" }
+            },
+            "required": ["start", "end"]
+        })
+    }
+}
+
+// Actions that change outputs take an optional `owner`, the owner the caller acts for, which is
+// checked against OWNERSHIP_FILE. Ports in action payloads are 1-indexed; `command` gives the
+// command an action queues, with the ports 0-indexed as the device numbers them.
 
 // DEVICE-LEVEL ACTIONS (for main device target - include output fields)
 
 // Action data for setting a video route
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetRouteAction {
-    // Output port number (0-indexed)
-    pub output: u32,
-    // Input port number (0-indexed)
+    // Output port number (1-indexed) or label
+    pub output: PortRef,
+    // Input port number (1-indexed) or label
+    pub input: PortRef,
+    pub owner: Option<String>,
+}
+
+impl SetRouteAction {
+    pub fn command(&self) -> VideohubCommand {
+        route_command(
+            self.output.clone().zero_indexed(),
+            self.input.clone().zero_indexed(),
+            false,
+        )
+    }
+}
+
+// Action data for routing one input to several outputs in one write
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetDestinationsAction {
+    // Input port number (1-indexed)
     pub input: u32,
+    // Output port numbers (1-indexed) to route the input to
+    pub outputs: Vec<u32>,
+    pub owner: Option<String>,
+}
+
+impl SetDestinationsAction {
+    pub fn command(&self) -> VideohubCommand {
+        let input = zero_indexed(self.input);
+        let changes = self
+            .outputs
+            .iter()
+            .map(|&output| ExpectedChange::Route {
+                output: zero_indexed(output),
+                input,
+            })
+            .collect();
+        VideohubCommand::WriteChanges { changes }
+    }
+}
+
+// Action data for routing one input to a span of outputs in one write
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RouteOutputRangeAction {
+    // Input port number (1-indexed)
+    pub input: u32,
+    // Outputs to route the input to
+    pub outputs: PortRange,
+    pub owner: Option<String>,
+}
+
+impl RouteOutputRangeAction {
+    pub fn command(&self) -> VideohubCommand {
+        let input = zero_indexed(self.input);
+        let changes = self
+            .outputs
+            .ports()
+            .map(|output| ExpectedChange::Route { output, input })
+            .collect();
+        VideohubCommand::WriteChanges { changes }
+    }
+}
+
+// Action data for numbering the labels of a span of outputs, e.g. "REC 1" to "REC 8"
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LabelOutputRangeAction {
+    // Outputs to label
+    pub outputs: PortRange,
+    // Text before the number, including any separator ("REC ")
+    pub prefix: String,
+    // Number of the first output's label; later outputs count up from it (default 1)
+    #[serde(default = "default_first_number")]
+    pub first_number: u32,
+    pub owner: Option<String>,
+}
+
+impl LabelOutputRangeAction {
+    pub fn command(&self) -> VideohubCommand {
+        let changes = self
+            .outputs
+            .ports()
+            .zip(self.first_number..)
+            .map(|(output, number)| ExpectedChange::OutputLabel {
+                output,
+                label: format!("{}{number}", self.prefix),
+            })
+            .collect();
+        VideohubCommand::WriteChanges { changes }
+    }
+}
+
+fn default_first_number() -> u32 {
+    1
+}
+
+// Action data for locking or unlocking a span of outputs in one write
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LockOutputRangeAction {
+    // Outputs to lock or unlock
+    pub outputs: PortRange,
+    // Whether to lock the outputs
+    pub locked: bool,
+    pub owner: Option<String>,
+}
+
+impl LockOutputRangeAction {
+    pub fn command(&self) -> VideohubCommand {
+        VideohubCommand::OutputLocks {
+            outputs: self.outputs.ports().collect(),
+            locked: self.locked,
+        }
+    }
+}
+
+// Action data for predicting the effect of another action without sending it to the device
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SimulateCommandAction {
+    // Action to simulate and its payload
+    pub command: ActionPayload,
 }
 
 // Action data for setting an input label
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetInputLabelAction {
-    // Input port number (0-indexed)
+    // Input port number (1-indexed)
     pub input: u32,
     // New label for the input
     pub label: String,
 }
 
+impl SetInputLabelAction {
+    pub fn command(&self) -> VideohubCommand {
+        VideohubCommand::InputLabel {
+            input: zero_indexed(self.input),
+            label: self.label.clone(),
+        }
+    }
+}
+
 // Action data for setting an output label
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetOutputLabelAction {
-    // Output port number (0-indexed)
+    // Output port number (1-indexed)
     pub output: u32,
     // New label for the output
     pub label: String,
+    pub owner: Option<String>,
+}
+
+impl SetOutputLabelAction {
+    pub fn command(&self) -> VideohubCommand {
+        VideohubCommand::OutputLabel {
+            output: zero_indexed(self.output),
+            label: self.label.clone(),
+        }
+    }
 }
 
 // Action data for setting output lock state
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetOutputLockAction {
-    // Output port number (0-indexed)
+    // Output port number (1-indexed)
     pub output: u32,
     // Whether to lock the output
     pub locked: bool,
+    pub owner: Option<String>,
+}
+
+impl SetOutputLockAction {
+    pub fn command(&self) -> VideohubCommand {
+        VideohubCommand::OutputLock {
+            output: zero_indexed(self.output),
+            locked: self.locked,
+        }
+    }
 }
 
 // Action data for setting take mode on an output
@@ -46,6 +369,333 @@ pub struct SetTakeModeAction {
     pub output: u32,
     // Whether to enable take mode
     pub enabled: bool,
+    pub owner: Option<String>,
+}
+
+// Action data for enabling or disabling raw protocol tracing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetProtocolTraceAction {
+    // Whether to pulse every raw block sent to and received from the device
+    pub enabled: bool,
+}
+
+// Action data for changing the log level at runtime
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetLogLevelAction {
+    // Log level ("trace", "debug", "info", "warn", "error" or "off")
+    pub level: String,
+    // Module path to apply the level to (e.g. "videohub"); the default level if omitted
+    pub module: Option<String>,
+}
+
+// Action data for exporting outputs, routes, labels and locks to a CSV file on the executor host
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportCsvAction {
+    // Path of the CSV file to write
+    pub path: String,
+}
+
+// Action data for writing a commissioning report to a file on the executor host
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GenerateReportAction {
+    // Path of the report to write; Markdown if it ends in .md, JSON otherwise
+    pub path: String,
+}
+
+// Action data for exporting per-owner usage to a file on the executor host
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportUsageReportAction {
+    // Path of the report to write; CSV if it ends in .csv, JSON otherwise
+    pub path: String,
+    // Start a new reporting period after exporting
+    #[serde(default)]
+    pub reset: bool,
+}
+
+// Action data for applying labels (and optionally routes) from a CSV file on the executor host
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportCsvAction {
+    // Path of the CSV file to read
+    pub path: String,
+    // Whether to also apply the routes in the file
+    pub apply_routes: bool,
+    pub owner: Option<String>,
+}
+
+// Action data for momentarily routing an input to an output
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HoldRouteAction {
+    // Output port number (0-indexed)
+    pub output: u32,
+    // Input port number (0-indexed)
+    pub input: u32,
+    // How long to hold the route before reverting to the previous input
+    pub duration_ms: DurationMs,
+    pub owner: Option<String>,
+}
+
+// Action data for ending a held route early
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseHoldAction {
+    // Output port number (0-indexed)
+    pub output: u32,
+    pub owner: Option<String>,
+}
+
+// Action data for cycling an output through a list of inputs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SequenceInputsAction {
+    // Output port number (0-indexed)
+    pub output: u32,
+    // Input port numbers (0-indexed), in the order they are shown
+    pub inputs: Vec<u32>,
+    // How long each input stays on the output
+    pub dwell_ms: DurationMs,
+    // Start over after the last input instead of stopping
+    #[serde(rename = "loop", default)]
+    pub repeat: bool,
+    pub owner: Option<String>,
+}
+
+// Action data for stopping a running input sequence
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StopSequenceAction {
+    // Output port number (0-indexed)
+    pub output: u32,
+    pub owner: Option<String>,
+}
+
+// Action data for routing a test generator input across outputs, one at a time
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SweepTestAction {
+    // Input port number of the test generator (0-indexed)
+    pub input: u32,
+    // Output port numbers (0-indexed), in the order they are swept
+    pub outputs: Vec<u32>,
+    // How long each output shows the test input before its route is restored
+    pub dwell_ms: DurationMs,
+    pub owner: Option<String>,
+}
+
+// Action data for writing a protocol block the executor does not model (RAW_BLOCKS)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SendRawBlockAction {
+    // The block: a header line ending in ':' followed by its lines, without a blank line
+    pub text: String,
+}
+
+// Action data for requesting the device-inventory emitter
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetInventoryAction {}
+
+// Action data for changing a setting of the device's CONFIGURATION block
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetDeviceConfigurationAction {
+    // Setting name as reported by the device (e.g. "Take Mode")
+    pub setting: String,
+    pub value: String,
+}
+
+// Action data for flashing the device's LEDs to find it in the rack
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IdentifyDeviceAction {}
+
+// Action data for restarting the device
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RebootDeviceAction {}
+
+// Action data for re-pulsing the current input of every output on input-changed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetRoutingTableAction {}
+
+// Action data for requesting everything known about a port on the port-info emitter
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetPortInfoAction {
+    // "input" or "output"
+    pub port_type: String,
+    // Port number (0-indexed)
+    pub port: u32,
+}
+
+// Action data for applying a saved show file (routes and labels)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LoadShowAction {
+    // Path of the show file on the executor host
+    pub path: String,
+    pub owner: Option<String>,
+}
+
+// Action data for writing the staged bulk changes to the device
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApplyStagedChangesAction {
+    pub owner: Option<String>,
+}
+
+// Action data for opening a transaction; the caller chooses its id
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BeginTransactionAction {
+    pub transaction: String,
+}
+
+// Action data for adding a routing or label change to an open transaction
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddToTransactionAction {
+    pub transaction: String,
+    // Action to add and its payload
+    pub command: ActionPayload,
+}
+
+// Action data for writing a transaction's changes as one salvo
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommitTransactionAction {
+    pub transaction: String,
+}
+
+// Action data for running routing, label and lock actions in order, each once the previous one
+// is confirmed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RunBatchAction {
+    // Name of the batch, e.g. the cue it implements, reported on batch-progress
+    pub batch: String,
+    // Actions and their payloads, in order
+    pub commands: Vec<ActionPayload>,
+    // Owner the caller acts for, for the actions that do not name their own
+    pub owner: Option<String>,
+}
+
+// Action data for discarding an open transaction
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RollbackTransactionAction {
+    pub transaction: String,
+}
+
+// Action data for dropping the staged bulk changes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiscardStagedChangesAction {}
+
+// Action data for presetting a route, written on the next take
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PresetRouteAction {
+    // Output port number (1-indexed)
+    pub output: u32,
+    // Input port number (1-indexed)
+    pub input: u32,
+    pub owner: Option<String>,
+}
+
+impl PresetRouteAction {
+    pub fn command(&self) -> VideohubCommand {
+        VideohubCommand::PresetRoute {
+            output: zero_indexed(self.output),
+            input: zero_indexed(self.input),
+        }
+    }
+}
+
+// Action data for writing all preset routes at once
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TakeAction {}
+
+// Action data for dropping the preset routes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClearPendingAction {}
+
+// Action data for saving the current routing as a named salvo
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SaveSalvoAction {
+    // Name of the salvo, e.g. "rehearsal"; a salvo of the same name is replaced
+    pub name: String,
+}
+
+// Action data for writing the routes of a saved salvo
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecallSalvoAction {
+    pub name: String,
+    pub owner: Option<String>,
+}
+
+// Action data for forgetting a saved salvo
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteSalvoAction {
+    pub name: String,
+}
+
+// Action data for switching an output to its backup input, or back to its primary
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FailoverAction {
+    // Output port number (0-indexed)
+    pub output: u32,
+    pub owner: Option<String>,
+}
+
+// Action data for suspending or resuming a failover rule from FAILOVER_FILE
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetRuleEnabledAction {
+    // Rule id, "failover-<output>" unless the file names it
+    pub rule_id: String,
+    pub enabled: bool,
+    // When disabling, enable the rule again at this time (RFC 3339, e.g. "2025-06-01T06:00:00Z")
+    pub until: Option<String>,
+}
+
+// Action data for freezing an input on the outputs it currently feeds
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FreezeInputAction {
+    // Input port number (1-indexed)
+    pub input: u32,
+}
+
+// Action data for lifting a freeze
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnfreezeInputAction {
+    // Input port number (1-indexed)
+    pub input: u32,
+}
+
+// Action data for repointing the executor at another Videohub, e.g. a backup router
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetDeviceAddressAction {
+    pub host: String,
+    pub port: u16,
+}
+
+// Action data for claiming the device for one operator, e.g. during a rehearsal
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClaimControlAction {
+    // Operator claiming the device; their actions name them as owner
+    pub operator: String,
+    // How long the claim lasts unless it is claimed again
+    pub ttl_ms: DurationMs,
+}
+
+// Action data for giving up a claim
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseControlAction {
+    pub operator: String,
+}
+
+// Action data for switching to a profile from PROFILES_FILE
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetProfileAction {
+    // Profile name, e.g. "show"
+    pub profile: String,
+}
+
+// Action data for handing outputs over to another owner
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TransferOutputOwnershipAction {
+    // Output port numbers (0-indexed) to hand over
+    pub outputs: Vec<u32>,
+    // Owner that receives the outputs
+    pub to_owner: String,
+    pub owner: Option<String>,
+}
+
+// Action data for starting or stopping a configured rotation group
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RotationAction {
+    // Name of the group in the rotations file
+    pub group: String,
 }
 
 // OUTPUT-LEVEL ACTIONS (for output subtargets - NO output fields, output is implicit)
@@ -53,15 +703,17 @@ pub struct SetTakeModeAction {
 // Action data for setting input on this output (output is implicit from target)
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetInputAction {
-    // Input port number (0-indexed)
-    pub input: u32,
+    // Input port number (0-indexed) or label
+    pub input: PortRef,
+    pub owner: Option<String>,
 }
 
-// Action data for setting label on this output (output is implicit from target)
+// Action data for setting label on this output or input (port is implicit from target)
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SetLabelAction {
-    // New label for the output
+    // New label for the port
     pub label: String,
+    pub owner: Option<String>,
 }
 
 // Action data for setting lock state on this output (output is implicit from target)
@@ -69,6 +721,7 @@ pub struct SetLabelAction {
 pub struct SetLockAction {
     // Whether to lock the output
     pub locked: bool,
+    pub owner: Option<String>,
 }
 
 // Action data for setting take mode on this output (output is implicit from target)
@@ -76,4 +729,85 @@ pub struct SetLockAction {
 pub struct SetTakeModeOnThisOutputAction {
     // Whether to enable take mode
     pub enabled: bool,
+    pub owner: Option<String>,
+}
+
+// INPUT-LEVEL ACTIONS (for input subtargets - NO input fields, input is implicit)
+
+// Action data for routing this input to an output (input is implicit from target)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RouteToOutputAction {
+    // Output port number or label
+    pub output: PortRef,
+    pub owner: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_out_of_range_durations_and_reversed_port_ranges() {
+        assert!(serde_json::from_str::<DurationMs>("0").is_err());
+        assert!(serde_json::from_str::<DurationMs>("86400001").is_err());
+        assert_eq!(
+            serde_json::from_str::<DurationMs>("1500")
+                .unwrap()
+                .as_millis(),
+            1500
+        );
+
+        assert!(serde_json::from_str::<PortRange>(r#"{"start": 0, "end": 4}"#).is_err());
+        assert!(serde_json::from_str::<PortRange>(r#"{"start": 5, "end": 4}"#).is_err());
+        let range: PortRange = serde_json::from_str(r#"{"start": 10, "end": 12}"#).unwrap();
+        assert_eq!(range.ports().collect::<Vec<_>>(), vec![9, 10, 11]);
+    }
+
+    #[test]
+    fn converts_payload_ports_to_device_ports() {
+        let route: SetRouteAction =
+            serde_json::from_str(r#"{"output": 3, "input": "CAM 1"}"#).unwrap();
+        assert!(matches!(
+            route.command(),
+            VideohubCommand::RouteByLabel {
+                output: PortRef::Number(2),
+                input: PortRef::Label(_),
+                set_input: false,
+            }
+        ));
+
+        let destinations: SetDestinationsAction =
+            serde_json::from_str(r#"{"input": 1, "outputs": [4, 0]}"#).unwrap();
+        let VideohubCommand::WriteChanges { changes } = destinations.command() else {
+            panic!("not a write");
+        };
+        // Port 0 is taken as the first port, as everywhere else
+        assert_eq!(
+            changes,
+            vec![
+                ExpectedChange::Route {
+                    output: 3,
+                    input: 0
+                },
+                ExpectedChange::Route {
+                    output: 0,
+                    input: 0
+                },
+            ]
+        );
+
+        let labels: LabelOutputRangeAction =
+            serde_json::from_str(r#"{"outputs": {"start": 2, "end": 3}, "prefix": "REC "}"#)
+                .unwrap();
+        let VideohubCommand::WriteChanges { changes } = labels.command() else {
+            panic!("not a write");
+        };
+        assert_eq!(
+            changes[1],
+            ExpectedChange::OutputLabel {
+                output: 2,
+                label: "REC 2".into()
+            }
+        );
+    }
 }
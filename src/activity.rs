@@ -0,0 +1,96 @@
+//! Change counts per time window, pulsed periodically, so long-horizon dashboards can follow the
+//! activity on the router without storing every individual pulse
+
+use std::collections::HashMap;
+use tokio::time::Instant;
+
+use crate::service::VideohubEvent;
+
+// Outputs with the most changes included in the summary
+const BUSIEST_OUTPUTS: usize = 5;
+
+#[derive(Debug)]
+pub struct ChangeWindow {
+    started: Instant,
+    routes: u64,
+    labels: u64,
+    locks: u64,
+    // Route and lock changes per output
+    outputs: HashMap<u32, u64>,
+}
+
+impl Default for ChangeWindow {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            routes: 0,
+            labels: 0,
+            locks: 0,
+            outputs: HashMap::new(),
+        }
+    }
+}
+
+impl ChangeWindow {
+    pub fn route(&mut self, output: u32) {
+        self.routes += 1;
+        *self.outputs.entry(output).or_default() += 1;
+    }
+
+    pub fn label(&mut self) {
+        self.labels += 1;
+    }
+
+    pub fn lock(&mut self, output: u32) {
+        self.locks += 1;
+        *self.outputs.entry(output).or_default() += 1;
+    }
+
+    // The summary of the window so far; the next window starts now
+    pub fn take(&mut self) -> VideohubEvent {
+        let window = std::mem::take(self);
+        let mut busiest_outputs: Vec<(u32, u64)> = window.outputs.into_iter().collect();
+        busiest_outputs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        busiest_outputs.truncate(BUSIEST_OUTPUTS);
+        VideohubEvent::ChangeSummary {
+            window_ms: window.started.elapsed().as_millis() as u64,
+            route_changes: window.routes,
+            label_changes: window.labels,
+            lock_changes: window.locks,
+            busiest_outputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_changes_per_window() {
+        let mut window = ChangeWindow::default();
+        for output in [3, 1, 3, 2, 3, 1] {
+            window.route(output);
+        }
+        window.lock(2);
+        window.label();
+
+        let VideohubEvent::ChangeSummary {
+            route_changes,
+            label_changes,
+            lock_changes,
+            busiest_outputs,
+            ..
+        } = window.take()
+        else {
+            panic!("not a change summary");
+        };
+        assert_eq!((route_changes, label_changes, lock_changes), (6, 1, 1));
+        assert_eq!(busiest_outputs, vec![(3, 3), (1, 2), (2, 2)]);
+
+        let VideohubEvent::ChangeSummary { route_changes, .. } = window.take() else {
+            panic!("not a change summary");
+        };
+        assert_eq!(route_changes, 0);
+    }
+}
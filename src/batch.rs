@@ -0,0 +1,246 @@
+//! Batches of routing, label and lock actions run in order, the building block for one-button
+//! cues. Each step is sent once the previous one is confirmed (as reported on action-feedback);
+//! the first step that fails stops the batch, and the steps after it are not sent.
+
+use anyhow::{Result, bail};
+use rship_sdk::TargetProxy;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::actions::{RunBatchAction, add_command_action};
+use crate::service::{CommandRequest, CommandScope, EventSender, VideohubCommand, VideohubEvent};
+use crate::simulation::ActionPayload;
+use crate::translations::DisplayNames;
+
+// How long a step may take to be confirmed or fail before the batch gives up on it
+pub const BATCH_STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// One action of a batch, with the owner it acts for
+#[derive(Debug, Clone)]
+pub struct BatchStep {
+    pub action: &'static str,
+    pub command: VideohubCommand,
+    pub owner: Option<String>,
+}
+
+// The steps of the actions in a batch; actions naming no owner act for the batch's owner
+pub fn steps(actions: Vec<ActionPayload>, owner: Option<&str>) -> Vec<BatchStep> {
+    actions
+        .into_iter()
+        .map(|payload| {
+            let action = payload.name();
+            let (command, step_owner) = payload.into_command();
+            BatchStep {
+                action,
+                command,
+                owner: step_owner.or_else(|| owner.map(str::to_string)),
+            }
+        })
+        .collect()
+}
+
+pub fn run_request(data: RunBatchAction) -> CommandRequest {
+    let steps = steps(data.commands, data.owner.as_deref());
+    CommandRequest::from_action(
+        "run-batch",
+        VideohubCommand::RunBatch {
+            batch: data.batch,
+            steps,
+        },
+    )
+    .owned_by(data.owner)
+}
+
+pub async fn add_actions(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    add_command_action(
+        target,
+        display_names,
+        "Run Batch",
+        "run-batch",
+        command_tx,
+        run_request,
+    )
+    .await;
+}
+
+// Start a batch from the device task; its steps are checked as they are sent
+pub fn execute(command: &VideohubCommand, scope: &CommandScope<'_>) -> Result<()> {
+    let VideohubCommand::RunBatch { batch, steps } = command else {
+        bail!("not a batch command: {command:?}");
+    };
+    if steps.is_empty() {
+        bail!("batch {batch} has no commands");
+    }
+    log::info!("Running batch {batch} of {} steps", steps.len());
+    spawn_batch(
+        batch.clone(),
+        steps.clone(),
+        scope.command_tx.clone(),
+        scope.event_tx.clone(),
+    );
+    Ok(())
+}
+
+// Run a batch on its own task. Steps go through the command channel as the actions they are, so
+// they are checked (ownership, claims, frozen inputs) and reported like any other action.
+pub fn spawn_batch(
+    batch: String,
+    steps: Vec<BatchStep>,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+) {
+    tokio::spawn(async move {
+        let total = steps.len();
+        let mut last_action = "";
+        for (
+            step,
+            BatchStep {
+                action,
+                command,
+                owner,
+            },
+        ) in steps.into_iter().enumerate()
+        {
+            last_action = action;
+            send_progress(&event_tx, &batch, step, total, action, "running", None).await;
+
+            let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+            let request = CommandRequest::from_action(action, command)
+                .owned_by(owner)
+                .reporting_to(done_tx);
+            let outcome = if command_tx.send(request).await.is_err() {
+                Err("the service is shutting down".to_string())
+            } else {
+                match tokio::time::timeout(BATCH_STEP_TIMEOUT, done_rx.recv()).await {
+                    Ok(Some(outcome)) => outcome,
+                    Ok(None) => Err("the step was dropped".to_string()),
+                    Err(_) => Err(format!("not confirmed within {BATCH_STEP_TIMEOUT:?}")),
+                }
+            };
+            if let Err(error) = outcome {
+                log::warn!(
+                    "Batch {batch} stopped at step {} ({action}): {error}",
+                    step + 1
+                );
+                send_progress(
+                    &event_tx,
+                    &batch,
+                    step,
+                    total,
+                    action,
+                    "failed",
+                    Some(error),
+                )
+                .await;
+                return;
+            }
+        }
+        log::info!("Batch {batch} completed {total} steps");
+        send_progress(
+            &event_tx,
+            &batch,
+            total - 1,
+            total,
+            last_action,
+            "completed",
+            None,
+        )
+        .await;
+    });
+}
+
+async fn send_progress(
+    event_tx: &EventSender,
+    batch: &str,
+    step: usize,
+    total: usize,
+    action: &'static str,
+    status: &'static str,
+    error: Option<String>,
+) {
+    if let Err(e) = event_tx
+        .send(VideohubEvent::BatchProgress {
+            batch: batch.to_string(),
+            step: step as u32,
+            total: total as u32,
+            action,
+            status,
+            error,
+        })
+        .await
+    {
+        log::error!("Failed to send batch progress event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_act_for_the_batch_owner_unless_they_name_one() {
+        let data: RunBatchAction = serde_json::from_str(
+            r#"{"batch": "cue 1", "owner": "lighting", "commands": [
+                {"action": "set-route", "data": {"output": 2, "input": 5}},
+                {"action": "set-output-label", "data": {"output": 2, "label": "PGM", "owner": "video"}},
+                {"action": "set-output-lock", "data": {"output": 2, "locked": true}}
+            ]}"#,
+        )
+        .unwrap();
+        let request = run_request(data);
+        assert_eq!(
+            request.invocation.unwrap().owner.as_deref(),
+            Some("lighting")
+        );
+        let VideohubCommand::RunBatch { batch, steps } = request.command else {
+            panic!("not a batch");
+        };
+        assert_eq!(batch, "cue 1");
+
+        let actions: Vec<_> = steps.iter().map(|step| step.action).collect();
+        assert_eq!(
+            actions,
+            vec!["set-route", "set-output-label", "set-output-lock"]
+        );
+        assert!(matches!(
+            steps[0].command,
+            VideohubCommand::Route {
+                output: 1,
+                input: 4
+            }
+        ));
+        let owners: Vec<_> = steps.iter().map(|step| step.owner.as_deref()).collect();
+        assert_eq!(
+            owners,
+            vec![Some("lighting"), Some("video"), Some("lighting")]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_a_batch_without_commands() {
+        let state = crate::client::VideohubState::default();
+        let (command_tx, _command_rx) = mpsc::channel(1);
+        let (event_tx, _event_rx) = mpsc::channel(1);
+        let event_tx = EventSender(event_tx);
+        let request = run_request(RunBatchAction {
+            batch: "cue 2".into(),
+            commands: Vec::new(),
+            owner: None,
+        });
+        let mut invocation = request.invocation;
+        let scope = CommandScope {
+            source: request.source,
+            invocation: &mut invocation,
+            state: &state,
+            ownership: None,
+            command_tx: &command_tx,
+            event_tx: &event_tx,
+        };
+        let e = execute(&request.command, &scope).unwrap_err();
+        assert_eq!(e.to_string(), "batch cue 2 has no commands");
+    }
+}
@@ -0,0 +1,50 @@
+//! Emitter pulses held back while rship is unreachable
+
+use std::collections::VecDeque;
+
+// Bounded in-memory buffer; the oldest entries are dropped once it is full
+#[derive(Debug)]
+pub struct PulseBuffer<T> {
+    entries: VecDeque<(Option<String>, T)>,
+    capacity: usize,
+    // Keep only the latest entry per key
+    coalesce: bool,
+    dropped: u64,
+}
+
+impl<T> PulseBuffer<T> {
+    pub fn new(capacity: usize, coalesce: bool) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            coalesce,
+            dropped: 0,
+        }
+    }
+
+    // Buffer an entry. Entries without a key are history and never coalesced.
+    pub fn push(&mut self, key: Option<String>, entry: T) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.coalesce && key.is_some() {
+            self.entries.retain(|(existing, _)| *existing != key);
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back((key, entry));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Take the buffered entries in arrival order, with the number dropped since the last drain
+    pub fn drain(&mut self) -> (Vec<T>, u64) {
+        let entries = self.entries.drain(..).map(|(_, entry)| entry).collect();
+        (entries, std::mem::take(&mut self.dropped))
+    }
+}
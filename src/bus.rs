@@ -0,0 +1,247 @@
+//! The command bus in front of the device session. rship actions, the HTTP and OSC listeners,
+//! scripts and the executor's own features all queue commands on one channel to the single
+//! device connection; the bus takes them per source and hands them out round-robin, so a burst
+//! from one source cannot starve the others. Per-source counts and queue waits are reported on
+//! `GET /health`. Commands lost with the connection go ahead of every source once it is back.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::service::CommandRequest;
+
+// Commands taken off the channel ahead of the device; the rest wait in the channel, so its
+// capacity still holds producers back
+pub const COMMAND_BUS_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SourceStatistics {
+    // Commands received and handed to the device session so far
+    pub received: u64,
+    pub executed: u64,
+    // Commands of the source waiting in the bus
+    pub queued: usize,
+    // Time commands waited in the bus for their turn
+    pub mean_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct SourceQueue {
+    requests: VecDeque<(Instant, CommandRequest)>,
+    statistics: SourceStatistics,
+    total_wait_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CommandBus {
+    sources: BTreeMap<&'static str, SourceQueue>,
+    // Source served last; the next turn goes to the source after it
+    last_served: Option<&'static str>,
+    // Commands lost with the connection, sent again before any other
+    retries: VecDeque<CommandRequest>,
+    queued: usize,
+}
+
+impl CommandBus {
+    pub fn is_empty(&self) -> bool {
+        self.queued == 0
+    }
+
+    pub fn push(&mut self, request: CommandRequest) {
+        let source = self.sources.entry(request.source).or_default();
+        source.statistics.received += 1;
+        source.requests.push_back((Instant::now(), request));
+        self.queued += 1;
+    }
+
+    // Queue commands lost with the connection ahead of everything else, in their original order.
+    // Commands lost longer than `max_age` ago, or made obsolete by a newer command for the same
+    // port, are not sent again; they are returned with the reason so their callers can be told.
+    pub fn requeue(
+        &mut self,
+        requests: Vec<CommandRequest>,
+        max_age: Duration,
+    ) -> Vec<(CommandRequest, &'static str)> {
+        let mut dropped = Vec::new();
+        for (index, request) in requests.iter().enumerate() {
+            let superseded = requests[index + 1..]
+                .iter()
+                .chain(
+                    self.sources
+                        .values()
+                        .flat_map(|queue| queue.requests.iter().map(|(_, request)| request)),
+                )
+                .any(|newer| newer.command.supersedes(&request.command));
+            if superseded {
+                dropped.push((request.clone(), "superseded by a newer command"));
+            } else if request
+                .lost_at
+                .is_some_and(|lost_at| lost_at.elapsed() > max_age)
+            {
+                dropped.push((request.clone(), "lost with the connection too long ago"));
+            } else {
+                self.retries.push_back(request.clone());
+                self.queued += 1;
+            }
+        }
+        dropped
+    }
+
+    // The oldest command of the next source in turn that has one waiting
+    pub fn pop(&mut self) -> Option<CommandRequest> {
+        if let Some(request) = self.retries.pop_front() {
+            self.queued -= 1;
+            return Some(request);
+        }
+        let next = match self.last_served {
+            Some(last) => self
+                .sources
+                .range(last..)
+                .skip(1)
+                .chain(self.sources.range(..=last))
+                .find(|(_, queue)| !queue.requests.is_empty()),
+            None => self
+                .sources
+                .iter()
+                .find(|(_, queue)| !queue.requests.is_empty()),
+        }
+        .map(|(name, _)| *name)?;
+
+        let source = self.sources.get_mut(next)?;
+        let (queued_at, request) = source.requests.pop_front()?;
+        let waited = queued_at.elapsed().as_millis() as u64;
+        source.statistics.executed += 1;
+        source.total_wait_ms += waited;
+        source.statistics.mean_wait_ms = source.total_wait_ms / source.statistics.executed;
+        source.statistics.max_wait_ms = source.statistics.max_wait_ms.max(waited);
+        self.last_served = Some(next);
+        self.queued -= 1;
+        Some(request)
+    }
+
+    // Wait for the next command in turn, taking whatever else has arrived on the channel first.
+    // None once the channel is closed and the bus is empty.
+    pub async fn next(
+        &mut self,
+        command_rx: &mut mpsc::Receiver<CommandRequest>,
+    ) -> Option<CommandRequest> {
+        if self.is_empty() {
+            let request = command_rx.recv().await?;
+            self.push(request);
+        }
+        self.fill(command_rx);
+        self.pop()
+    }
+
+    // Take the commands already waiting on the channel, up to the capacity
+    pub fn fill(&mut self, command_rx: &mut mpsc::Receiver<CommandRequest>) {
+        while self.queued < COMMAND_BUS_CAPACITY {
+            match command_rx.try_recv() {
+                Ok(request) => self.push(request),
+                Err(_) => break,
+            }
+        }
+    }
+
+    pub fn statistics(&self) -> BTreeMap<&'static str, SourceStatistics> {
+        self.sources
+            .iter()
+            .map(|(name, source)| {
+                let statistics = SourceStatistics {
+                    queued: source.requests.len(),
+                    ..source.statistics.clone()
+                };
+                (*name, statistics)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::VideohubCommand;
+
+    fn route(source: &'static str, output: u32) -> CommandRequest {
+        CommandRequest::internal(VideohubCommand::Route { output, input: 0 }).via(source)
+    }
+
+    #[test]
+    fn serves_sources_in_turn() {
+        let mut bus = CommandBus::default();
+        for output in 0..3 {
+            bus.push(route("http", output));
+        }
+        bus.push(route("rship", 10));
+        bus.push(route("script", 20));
+
+        let served: Vec<_> = std::iter::from_fn(|| bus.pop())
+            .map(|request| match request.command {
+                VideohubCommand::Route { output, .. } => (request.source, output),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            served,
+            vec![
+                ("http", 0),
+                ("rship", 10),
+                ("script", 20),
+                ("http", 1),
+                ("http", 2)
+            ]
+        );
+        assert!(bus.is_empty());
+
+        let statistics = bus.statistics();
+        assert_eq!(statistics["http"].received, 3);
+        assert_eq!(statistics["http"].executed, 3);
+        assert_eq!(statistics["rship"].queued, 0);
+    }
+
+    #[test]
+    fn resends_lost_commands_first_unless_obsolete() {
+        let mut bus = CommandBus::default();
+        bus.push(route("http", 1));
+        let lost = |output, lost_at| CommandRequest {
+            lost_at: Some(lost_at),
+            retries: 1,
+            ..route("rship", output)
+        };
+        let now = Instant::now();
+        let dropped = bus.requeue(
+            vec![
+                lost(0, now),
+                lost(1, now),
+                lost(2, now - Duration::from_secs(60)),
+                lost(3, now),
+                lost(0, now),
+            ],
+            Duration::from_secs(10),
+        );
+        let dropped: Vec<_> = dropped
+            .into_iter()
+            .map(|(request, reason)| (request.command.outputs(), reason))
+            .collect();
+        assert_eq!(
+            dropped,
+            vec![
+                (vec![0], "superseded by a newer command"),
+                (vec![1], "superseded by a newer command"),
+                (vec![2], "lost with the connection too long ago"),
+            ]
+        );
+
+        let served: Vec<_> = std::iter::from_fn(|| bus.pop())
+            .map(|request| (request.source, request.command.outputs()))
+            .collect();
+        assert_eq!(
+            served,
+            vec![("rship", vec![3]), ("rship", vec![0]), ("http", vec![1])]
+        );
+        assert!(bus.is_empty());
+    }
+}
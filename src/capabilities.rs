@@ -0,0 +1,87 @@
+//! What each port can carry. The device reports the interface of each port (VIDEO INPUT STATUS
+//! and VIDEO OUTPUT STATUS blocks); the SDI level is only known from the model, e.g. every port of
+//! a Smart Videohub 12G is 12G-SDI. Formats are not reported at all, so routes are checked against
+//! the `format` of the ports in the port metadata instead.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::client::VideohubState;
+use crate::metadata::{Metadata, PortMetadata};
+
+// Port metadata key holding the video format a port is configured for, e.g. "2160p50"
+pub const FORMAT_KEY: &str = "format";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PortCapabilities {
+    // "BNC", "Optical", ... or "None" for an empty SFP cage; None if the device doesn't report it
+    pub interface: Option<String>,
+    // Highest SDI level the port supports, e.g. "12G-SDI"; None if the model doesn't tell
+    pub max_sdi_level: Option<String>,
+}
+
+// Capabilities of a port by type ("input" or "output"); None if nothing is known about them
+pub fn port_capabilities(
+    state: &VideohubState,
+    port_type: &str,
+    port: u32,
+) -> Option<PortCapabilities> {
+    let status = match port_type {
+        "input" => &state.input_status,
+        _ => &state.output_status,
+    };
+    let capabilities = PortCapabilities {
+        interface: status.get(&port).cloned(),
+        max_sdi_level: state
+            .device_info
+            .as_ref()
+            .and_then(|info| info.model_name.as_deref())
+            .and_then(max_sdi_level)
+            .map(str::to_string),
+    };
+    (capabilities.interface.is_some() || capabilities.max_sdi_level.is_some())
+        .then_some(capabilities)
+}
+
+fn max_sdi_level(model_name: &str) -> Option<&'static str> {
+    model_name.contains("12G").then_some("12G-SDI")
+}
+
+// The formats of input and output, if both are configured and differ
+pub fn format_mismatch(
+    metadata: &PortMetadata,
+    output: u32,
+    input: u32,
+) -> Option<(String, String)> {
+    let format = |metadata: Option<Metadata>| metadata?.remove(FORMAT_KEY);
+    let input_format = format(metadata.input(input))?;
+    let output_format = format(metadata.output(output))?;
+    (!input_format.eq_ignore_ascii_case(&output_format)).then_some((input_format, output_format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_interface_and_the_sdi_level_of_12g_models() {
+        let mut state = VideohubState::default();
+        assert_eq!(port_capabilities(&state, "input", 0), None);
+
+        state.input_status.insert(0, "Optical".into());
+        assert_eq!(
+            port_capabilities(&state, "input", 0),
+            Some(PortCapabilities {
+                interface: Some("Optical".into()),
+                max_sdi_level: None,
+            })
+        );
+        assert_eq!(port_capabilities(&state, "output", 0), None);
+
+        assert_eq!(
+            max_sdi_level("Blackmagic Smart Videohub 12G 12x12"),
+            Some("12G-SDI")
+        );
+        assert_eq!(max_sdi_level("Blackmagic Micro Videohub"), None);
+    }
+}
@@ -0,0 +1,263 @@
+//! Labels the device can show. Videohub firmware garbles or rejects some Unicode in labels, so with
+//! LABEL_CHARSET=ascii labels are transliterated before they are sent ("Kamera Ž1" becomes
+//! "Kamera Z1"). The original is kept on the executor and pulsed along with the device's label.
+
+use std::collections::HashMap;
+
+use crate::pending::ExpectedChange;
+use crate::service::{VideohubCommand, VideohubEvent};
+
+// Stands in for characters without a transliteration
+const REPLACEMENT: &str = "?";
+
+// Latin letters with diacritics, and the ASCII letter each is shown as
+const LETTERS: [(&str, &str); 42] = [
+    ("ÀÁÂÃÄÅĀĂĄ", "A"),
+    ("àáâãäåāăą", "a"),
+    ("ÇĆĈĊČ", "C"),
+    ("çćĉċč", "c"),
+    ("ĎĐÐ", "D"),
+    ("ďđð", "d"),
+    ("ÈÉÊËĒĔĖĘĚ", "E"),
+    ("èéêëēĕėęě", "e"),
+    ("ĜĞĠĢ", "G"),
+    ("ĝğġģ", "g"),
+    ("ĤĦ", "H"),
+    ("ĥħ", "h"),
+    ("ÌÍÎÏĨĪĬĮİ", "I"),
+    ("ìíîïĩīĭįı", "i"),
+    ("Ĵ", "J"),
+    ("ĵ", "j"),
+    ("Ķ", "K"),
+    ("ķ", "k"),
+    ("ĹĻĽĿŁ", "L"),
+    ("ĺļľŀł", "l"),
+    ("ÑŃŅŇ", "N"),
+    ("ñńņň", "n"),
+    ("ÒÓÔÕÖØŌŎŐ", "O"),
+    ("òóôõöøōŏő", "o"),
+    ("ŔŖŘ", "R"),
+    ("ŕŗř", "r"),
+    ("ŚŜŞŠȘ", "S"),
+    ("śŝşšș", "s"),
+    ("ŢŤŦȚ", "T"),
+    ("ţťŧț", "t"),
+    ("ÙÚÛÜŨŪŬŮŰŲ", "U"),
+    ("ùúûüũūŭůűų", "u"),
+    ("Ŵ", "W"),
+    ("ŵ", "w"),
+    ("ÝŸŶ", "Y"),
+    ("ýÿŷ", "y"),
+    ("ŹŻŽ", "Z"),
+    ("źżž", "z"),
+    ("Æ", "AE"),
+    ("æ", "ae"),
+    ("Œ", "OE"),
+    ("œ", "oe"),
+];
+
+// Other characters common in labels
+const SYMBOLS: [(char, &str); 16] = [
+    ('ß', "ss"),
+    ('Þ', "Th"),
+    ('þ', "th"),
+    ('\u{a0}', " "),
+    ('‘', "'"),
+    ('’', "'"),
+    ('‚', "'"),
+    ('′', "'"),
+    ('“', "\""),
+    ('”', "\""),
+    ('„', "\""),
+    ('″', "\""),
+    ('–', "-"),
+    ('—', "-"),
+    ('…', "..."),
+    ('€', "EUR"),
+];
+
+// Transliteration to printable ASCII, with the configured replacements taking precedence
+#[derive(Debug, Clone, Default)]
+pub struct LabelCharset {
+    transliterations: HashMap<char, String>,
+}
+
+impl LabelCharset {
+    pub fn new(transliterations: HashMap<char, String>) -> Self {
+        Self { transliterations }
+    }
+
+    // One LABEL_TRANSLITERATIONS entry, e.g. "ø=oe"
+    pub fn parse_transliteration(entry: &str) -> Option<(char, String)> {
+        let (from, to) = entry.split_once('=')?;
+        let mut chars = from.trim().chars();
+        let from = chars.next()?;
+        chars
+            .next()
+            .is_none()
+            .then(|| (from, to.trim().to_string()))
+    }
+
+    // The label as the device can show it
+    pub fn transliterate(&self, label: &str) -> String {
+        let mut ascii = String::with_capacity(label.len());
+        for c in label.chars() {
+            if let Some(replacement) = self.transliterations.get(&c) {
+                ascii.push_str(replacement);
+            } else if c.is_ascii_control() {
+                // Line breaks would end the protocol block
+                ascii.push(' ');
+            } else if c.is_ascii() {
+                ascii.push(c);
+            } else if let Some((_, letter)) =
+                LETTERS.iter().find(|(letters, _)| letters.contains(c))
+            {
+                ascii.push_str(letter);
+            } else if let Some((_, symbol)) = SYMBOLS.iter().find(|(symbol, _)| *symbol == c) {
+                ascii.push_str(symbol);
+            } else {
+                ascii.push_str(REPLACEMENT);
+            }
+        }
+        ascii
+    }
+}
+
+// Labels as they were requested, for the ports whose label had to be transliterated
+#[derive(Debug)]
+pub struct LabelTransliteration {
+    charset: LabelCharset,
+    inputs: HashMap<u32, String>,
+    outputs: HashMap<u32, String>,
+}
+
+impl LabelTransliteration {
+    pub fn new(charset: LabelCharset) -> Self {
+        Self {
+            charset,
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+        }
+    }
+
+    // Transliterate the labels a command sets, remembering the originals
+    pub fn apply(&mut self, command: &mut VideohubCommand) {
+        match command {
+            VideohubCommand::InputLabel { input, label } => self.label("input", *input, label),
+            VideohubCommand::OutputLabel { output, label } => self.label("output", *output, label),
+            VideohubCommand::WriteChanges { changes } => {
+                for change in changes {
+                    match change {
+                        ExpectedChange::InputLabel { input, label } => {
+                            self.label("input", *input, label)
+                        }
+                        ExpectedChange::OutputLabel { output, label } => {
+                            self.label("output", *output, label)
+                        }
+                        ExpectedChange::Route { .. } => {}
+                    }
+                }
+            }
+            VideohubCommand::AddToTransaction { command, .. } => self.apply(command),
+            _ => {}
+        }
+    }
+
+    fn label(&mut self, port_type: &str, port: u32, label: &mut String) {
+        let ascii = self.charset.transliterate(label);
+        let originals = self.originals_mut(port_type);
+        if ascii == *label {
+            originals.remove(&port);
+            return;
+        }
+        log::info!(
+            "Sending {port_type} {} label \"{label}\" as \"{ascii}\"",
+            port + 1
+        );
+        originals.insert(port, std::mem::replace(label, ascii));
+    }
+
+    fn originals_mut(&mut self, port_type: &str) -> &mut HashMap<u32, String> {
+        match port_type {
+            "input" => &mut self.inputs,
+            _ => &mut self.outputs,
+        }
+    }
+
+    // The label requested for a port, if the device shows it transliterated
+    pub fn original(&self, port_type: &str, port: u32, label: &str) -> Option<String> {
+        let originals = match port_type {
+            "input" => &self.inputs,
+            _ => &self.outputs,
+        };
+        originals
+            .get(&port)
+            .filter(|original| self.charset.transliterate(original) == label)
+            .cloned()
+    }
+
+    // Originals of the labels the device shows transliterated, by port type
+    pub fn originals(
+        &self,
+        port_type: &str,
+        labels: &HashMap<u32, String>,
+    ) -> HashMap<u32, String> {
+        labels
+            .iter()
+            .filter_map(|(port, label)| Some((*port, self.original(port_type, *port, label)?)))
+            .collect()
+    }
+
+    // Fill in the original of a label event
+    pub fn annotate(&self, event: &mut VideohubEvent) {
+        if let VideohubEvent::Label {
+            port_type,
+            port,
+            label,
+            original,
+            ..
+        } = event
+        {
+            *original = self.original(port_type, *port, label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliterates_labels_and_keeps_the_originals() {
+        let charset = LabelCharset::new(
+            [LabelCharset::parse_transliteration("ø=oe").unwrap()]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(charset.transliterate("Kamera Ž1"), "Kamera Z1");
+        assert_eq!(
+            charset.transliterate("Støre\nStraße 漢"),
+            "Stoere Strasse ?"
+        );
+
+        let mut transliteration = LabelTransliteration::new(charset);
+        let mut command = VideohubCommand::OutputLabel {
+            output: 2,
+            label: "Kamera Ž1".into(),
+        };
+        transliteration.apply(&mut command);
+        let VideohubCommand::OutputLabel { label, .. } = &command else {
+            panic!("command changed kind");
+        };
+        assert_eq!(label, "Kamera Z1");
+        assert_eq!(
+            transliteration
+                .original("output", 2, "Kamera Z1")
+                .as_deref(),
+            Some("Kamera Ž1")
+        );
+        // Relabelled by someone else since
+        assert_eq!(transliteration.original("output", 2, "Kamera 2"), None);
+        assert_eq!(transliteration.original("input", 2, "Kamera Z1"), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Operator claims of the device, coordinating several rship operators during rehearsals. While an
+//! operator holds a claim, actions acting for anyone else (their `owner`) and commands from the
+//! control listeners and scripts are rejected, or only logged with CLAIMS_ADVISORY. A claim lapses
+//! after its TTL unless it is claimed again.
+
+use anyhow::{Result, bail};
+use tokio::time::{Duration, Instant};
+
+use crate::middleware::CommandContext;
+use crate::service::VideohubCommand;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    pub operator: String,
+    pub expires_at: Instant,
+}
+
+impl Claim {
+    pub fn expires_in(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ControlClaim {
+    claim: Option<Claim>,
+}
+
+impl ControlClaim {
+    // Claim the device, or extend the operator's own claim
+    pub fn claim(&mut self, operator: &str, ttl: Duration) -> Result<&Claim> {
+        if let Some(holder) = self.holder()
+            && holder.operator != operator
+        {
+            bail!("the device is claimed by {}", holder.operator);
+        }
+        Ok(self.claim.insert(Claim {
+            operator: operator.to_string(),
+            expires_at: Instant::now() + ttl,
+        }))
+    }
+
+    pub fn release(&mut self, operator: &str) -> Result<()> {
+        match self.holder() {
+            Some(holder) if holder.operator != operator => {
+                bail!("the device is claimed by {}", holder.operator)
+            }
+            Some(_) => {
+                self.claim = None;
+                Ok(())
+            }
+            None => bail!("the device is not claimed"),
+        }
+    }
+
+    // The current claim, unless it has lapsed
+    pub fn holder(&self) -> Option<&Claim> {
+        self.claim
+            .as_ref()
+            .filter(|claim| claim.expires_at > Instant::now())
+    }
+
+    // Drop a lapsed claim; returns it so its end can be reported
+    pub fn expire(&mut self) -> Option<Claim> {
+        self.claim
+            .take_if(|claim| claim.expires_at <= Instant::now())
+    }
+
+    // The operator holding the claim, if it is not the one acting
+    pub fn check(&self, owner: Option<&str>) -> Result<(), String> {
+        match self.holder() {
+            Some(holder) if owner != Some(holder.operator.as_str()) => Err(holder.operator.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    // The check for a command in the pipeline, whichever way it came in: rship actions, the
+    // control listeners and scripts are held back unless they act for the claimant, which only
+    // actions can. Queries, probe signal reports, claims and releases, and commands the executor
+    // issues itself go through.
+    pub fn check_request(
+        &self,
+        command: &VideohubCommand,
+        context: &CommandContext,
+    ) -> Result<(), String> {
+        if context.source == "internal"
+            || command.is_query()
+            || matches!(
+                command,
+                VideohubCommand::ClaimControl { .. }
+                    | VideohubCommand::ReleaseControl { .. }
+                    | VideohubCommand::SignalStatus { .. }
+            )
+        {
+            return Ok(());
+        }
+        self.check(
+            context
+                .invocation
+                .and_then(|invocation| invocation.owner.as_deref()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::CommandRequest;
+
+    #[test]
+    fn only_the_claimant_acts_until_the_claim_lapses() {
+        let mut claims = ControlClaim::default();
+        assert_eq!(claims.check(None), Ok(()));
+
+        claims.claim("alex", Duration::from_secs(60)).unwrap();
+        assert_eq!(claims.check(Some("alex")), Ok(()));
+        assert_eq!(claims.check(Some("sam")), Err("alex".to_string()));
+        assert_eq!(claims.check(None), Err("alex".to_string()));
+        assert!(claims.claim("sam", Duration::from_secs(60)).is_err());
+        assert!(claims.release("sam").is_err());
+        assert!(claims.release("alex").is_ok());
+
+        claims.claim("sam", Duration::ZERO).unwrap();
+        assert_eq!(claims.check(Some("alex")), Ok(()));
+        assert_eq!(claims.expire().unwrap().operator, "sam");
+        assert_eq!(claims.expire(), None);
+    }
+
+    #[test]
+    fn holds_back_control_listeners_while_claimed() {
+        let mut claims = ControlClaim::default();
+        claims.claim("alex", Duration::from_secs(60)).unwrap();
+        let route = VideohubCommand::Route {
+            output: 2,
+            input: 0,
+        };
+
+        for source in ["http", "osc", "script"] {
+            let request = CommandRequest::internal(route.clone()).via(source);
+            let context = CommandContext {
+                source: request.source,
+                invocation: request.invocation.as_ref(),
+            };
+            assert_eq!(
+                claims.check_request(&request.command, &context),
+                Err("alex".to_string())
+            );
+        }
+
+        let request =
+            CommandRequest::from_action("set-route", route.clone()).owned_by(Some("alex".into()));
+        let context = CommandContext {
+            source: request.source,
+            invocation: request.invocation.as_ref(),
+        };
+        assert_eq!(claims.check_request(&request.command, &context), Ok(()));
+        let context = CommandContext {
+            source: "internal",
+            invocation: None,
+        };
+        assert_eq!(claims.check_request(&route, &context), Ok(()));
+    }
+}
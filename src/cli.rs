@@ -0,0 +1,344 @@
+//! Command line flags and the TOML config file given with `--config`. Each setting is taken from
+//! the command line first, then from its environment variable, then from the config file, and
+//! otherwise has its default; so a deployment can declare its devices, rship endpoint, logging
+//! and feature toggles in one file and still override single settings per host.
+
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::config::ServiceConfig;
+use crate::hubs::{DEFAULT_VIDEOHUB_PORT, Hub};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "rship executor for Blackmagic Videohub routers")]
+pub struct Cli {
+    #[arg(
+        long,
+        help = "TOML config file; environment variables take precedence over it"
+    )]
+    pub config: Option<PathBuf>,
+    #[arg(long, help = "Videohub address [env: VIDEOHUB_ADDRESS]")]
+    pub videohub_address: Option<String>,
+    #[arg(long, help = "Videohub port [env: VIDEOHUB_PORT] [default: 9990]")]
+    pub videohub_port: Option<u16>,
+    #[arg(long, help = "rship server address [env: RSHIP_ADDRESS]")]
+    pub rship_address: Option<String>,
+    #[arg(long, help = "rship server port [env: RSHIP_PORT]")]
+    pub rship_port: Option<u16>,
+    #[arg(
+        long,
+        help = "Log filter, e.g. info or debug [env: RUST_LOG] [default: error]"
+    )]
+    pub log_level: Option<String>,
+    #[arg(long, help = "Draw a continuously updated summary on the terminal")]
+    pub status_line: bool,
+    #[arg(long, help = "Profile switched to on start [env: PROFILE]")]
+    pub profile: Option<String>,
+    // A one-shot subcommand; without one the executor runs as an rship service
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// One-shot subcommands, run against the Videohub (the first hub of VIDEOHUBS) unless noted
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Export outputs, current inputs, labels and locks to a CSV file")]
+    ExportCsv { path: PathBuf },
+    #[command(about = "Apply the labels of a CSV file, and its routes with --routes")]
+    ImportCsv {
+        path: PathBuf,
+        #[arg(long, help = "Apply the routes in the file as well")]
+        routes: bool,
+    },
+    #[command(about = "Save routes, labels and locks to a JSON show file")]
+    SaveShow { path: PathBuf },
+    #[command(about = "Compare the device against a show file; exits with 1 if anything differs")]
+    Diff { path: PathBuf },
+    #[command(about = "Write a commissioning report, as Markdown if the file ends in .md")]
+    Report { path: PathBuf },
+    #[command(about = "Print the full device state as JSON")]
+    DumpState,
+    #[command(
+        about = "Convert the usage last saved to USAGE_FILE, as CSV if the file ends in .csv"
+    )]
+    UsageReport { path: PathBuf },
+    #[command(about = "Convert a Videohub Setup settings export to a show file; needs no device")]
+    MigrateSetup {
+        export: PathBuf,
+        path: PathBuf,
+        #[arg(long, help = "The export numbers ports from 0, like the protocol")]
+        zero_based: bool,
+    },
+    #[command(
+        about = "List the Videohubs announced on the LAN (discovery feature); needs no device"
+    )]
+    Discover,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Endpoint {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HubEntry {
+    pub name: String,
+    pub address: String,
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Logging {
+    // Log filter as in RUST_LOG
+    pub level: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub videohub: Endpoint,
+    // Several hubs instead of [videohub], as with VIDEOHUBS
+    #[serde(default)]
+    pub hubs: Vec<HubEntry>,
+    #[serde(default)]
+    pub rship: Endpoint,
+    #[serde(default)]
+    pub logging: Logging,
+    // Any other setting by its environment variable name, e.g. LABELS_FILE = "labels.json"
+    #[serde(default)]
+    pub settings: BTreeMap<String, toml::Value>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    // A setting as its environment variable would be written; lists are joined with commas
+    fn setting(&self, name: &str) -> Option<String> {
+        fn text(value: &toml::Value) -> String {
+            match value {
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Array(items) => items.iter().map(text).collect::<Vec<_>>().join(","),
+                value => value.to_string(),
+            }
+        }
+        self.settings.get(name).map(text)
+    }
+}
+
+// The command line with the config file it names
+#[derive(Debug)]
+pub struct Settings {
+    pub cli: Cli,
+    file: ConfigFile,
+}
+
+impl Settings {
+    pub fn load(cli: Cli) -> Result<Self> {
+        let file = match &cli.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        Ok(Self { cli, file })
+    }
+
+    // A setting by its environment variable name: the environment first, then the config file
+    pub fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok().or_else(|| self.file.setting(name))
+    }
+
+    pub fn log_level(&self) -> Option<String> {
+        self.cli
+            .log_level
+            .clone()
+            .or_else(|| self.var("RUST_LOG"))
+            .or_else(|| self.file.logging.level.clone())
+    }
+
+    // The hubs of VIDEOHUBS or [[hubs]]; None for a single device
+    pub fn hubs(&self) -> Result<Option<Vec<Hub>>> {
+        if self.cli.videohub_address.is_some() {
+            return Ok(None);
+        }
+        if let Some(list) = self.var("VIDEOHUBS") {
+            return Hub::parse_list(&list)
+                .context("Failed to parse VIDEOHUBS")
+                .map(Some);
+        }
+        if self.file.hubs.is_empty() {
+            return Ok(None);
+        }
+        let hubs = self
+            .file
+            .hubs
+            .iter()
+            .map(|hub| Hub {
+                name: hub.name.clone(),
+                host: hub.address.clone(),
+                port: hub.port.unwrap_or(DEFAULT_VIDEOHUB_PORT),
+            })
+            .collect();
+        Hub::checked(hubs)
+            .context("Failed to parse [[hubs]] in the config file")
+            .map(Some)
+    }
+
+    pub fn has_videohub_address(&self) -> bool {
+        self.cli.videohub_address.is_some()
+            || self.var("VIDEOHUB_ADDRESS").is_some()
+            || self.file.videohub.address.is_some()
+    }
+
+    pub fn videohub(&self) -> Result<(String, u16)> {
+        let address = self
+            .cli
+            .videohub_address
+            .clone()
+            .or_else(|| self.var("VIDEOHUB_ADDRESS"))
+            .or_else(|| self.file.videohub.address.clone())
+            .ok_or_else(|| {
+                anyhow!("No Videohub address: use --videohub-address, VIDEOHUB_ADDRESS, [videohub] address in the config file or VIDEOHUB_DISCOVERY=auto")
+            })?;
+        let port = match self.cli.videohub_port {
+            Some(port) => port,
+            None => self
+                .port("VIDEOHUB_PORT")?
+                .or(self.file.videohub.port)
+                .unwrap_or(DEFAULT_VIDEOHUB_PORT),
+        };
+        Ok((address, port))
+    }
+
+    pub fn rship(&self) -> Result<(String, u16)> {
+        let address = self
+            .cli
+            .rship_address
+            .clone()
+            .or_else(|| self.var("RSHIP_ADDRESS"))
+            .or_else(|| self.file.rship.address.clone())
+            .ok_or_else(|| {
+                anyhow!("No rship address: use --rship-address, RSHIP_ADDRESS or [rship] address in the config file")
+            })?;
+        let port = match self.cli.rship_port {
+            Some(port) => Some(port),
+            None => self.port("RSHIP_PORT")?.or(self.file.rship.port),
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "No rship port: use --rship-port, RSHIP_PORT or [rship] port in the config file"
+            )
+        })?;
+        Ok((address, port))
+    }
+
+    fn port(&self, name: &str) -> Result<Option<u16>> {
+        self.var(name)
+            .map(|port| {
+                port.trim()
+                    .parse()
+                    .with_context(|| format!("Failed to parse {name} \"{port}\""))
+            })
+            .transpose()
+    }
+
+    // The optional service settings, with the command line flags applied
+    pub fn service_config(&self) -> ServiceConfig {
+        let config = ServiceConfig::from_lookup(|name| self.var(name));
+        ServiceConfig {
+            status_line: self.cli.status_line,
+            profile: self.cli.profile.clone().or(config.profile.clone()),
+            ..config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_devices_and_settings_from_the_config_file() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+                [rship]
+                address = "rship.local"
+                port = 5155
+
+                [[hubs]]
+                name = "stage"
+                address = "10.0.0.20"
+
+                [logging]
+                level = "info"
+
+                [settings]
+                TEST_CLI_RAW_BLOCKS = true
+                TEST_CLI_FALLBACK_ENDPOINTS = ["a:5155", "b:5155"]
+            "#,
+        )
+        .unwrap();
+        let settings = Settings {
+            cli: Cli::parse_from(["rship-blackmagic-videohub", "--rship-port", "6000"]),
+            file,
+        };
+
+        assert_eq!(settings.rship().unwrap(), ("rship.local".to_string(), 6000));
+        let hubs = settings.hubs().unwrap().unwrap();
+        assert_eq!(hubs[0].port, DEFAULT_VIDEOHUB_PORT);
+        assert_eq!(settings.var("TEST_CLI_RAW_BLOCKS").as_deref(), Some("true"));
+        assert_eq!(
+            settings.var("TEST_CLI_FALLBACK_ENDPOINTS").as_deref(),
+            Some("a:5155,b:5155")
+        );
+        assert!(toml::from_str::<ConfigFile>("[videohub]\nhost = \"x\"").is_err());
+    }
+
+    #[test]
+    fn parses_subcommands_with_their_flags() {
+        let cli = Cli::parse_from([
+            "rship-blackmagic-videohub",
+            "--videohub-address",
+            "10.0.0.20",
+            "import-csv",
+            "routing.csv",
+            "--routes",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::ImportCsv { routes: true, .. })
+        ));
+        let cli = Cli::parse_from([
+            "rship-blackmagic-videohub",
+            "migrate-setup",
+            "settings.json",
+            "show.json",
+            "--zero-based",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::MigrateSetup {
+                zero_based: true,
+                ..
+            })
+        ));
+        assert!(
+            Cli::try_parse_from(["rship-blackmagic-videohub", "load-show", "show.json"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["rship-blackmagic-videohub", "export-csv", "--routes"]).is_err()
+        );
+    }
+}
@@ -1,415 +0,0 @@
-use anyhow::{Result, anyhow};
-use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
-use tokio::net::TcpStream;
-use tokio_util::codec::Framed;
-use videohub::{DeviceInfo, Label, Route, VideohubCodec, VideohubMessage};
-
-#[derive(Debug, Clone, PartialEq)]
-pub struct NetworkInterface {
-    pub id: u32,
-    pub name: String,
-    pub priority: Option<u32>,
-    pub mac_address: Option<String>,
-    pub dynamic_ip: Option<bool>,
-    pub current_addresses: Option<String>,
-    pub current_gateway: Option<String>,
-    pub static_addresses: Option<String>,
-    pub static_gateway: Option<String>,
-}
-
-// Represents the current state of a Videohub device
-#[derive(Debug, Clone, Default)]
-pub struct VideohubState {
-    pub device_info: Option<DeviceInfo>,
-    pub input_labels: HashMap<u32, String>,
-    pub output_labels: HashMap<u32, String>,
-    pub video_output_routing: HashMap<u32, u32>, // output -> input
-    pub take_mode: HashMap<u32, bool>,           // output -> take_mode_enabled
-    pub output_locks: HashMap<u32, bool>,        // output -> locked
-    pub protocol_version: Option<String>,
-    pub network_interfaces: Vec<NetworkInterface>,
-    pub connected: bool,
-    pub reconnected: bool, // Flag to indicate if we just reconnected and need to send full state
-}
-
-// Client for communicating with a Blackmagic Videohub device
-pub struct VideohubClient {
-    host: String,
-    port: u16,
-    state: VideohubState,
-    connection: Option<Framed<TcpStream, VideohubCodec>>,
-    initial_state_received: bool, // Track if we've received initial state after connection
-}
-
-impl VideohubClient {
-    pub fn new(host: String, port: u16) -> Self {
-        Self {
-            host,
-            port,
-            state: VideohubState::default(),
-            connection: None,
-            initial_state_received: false,
-        }
-    }
-
-    // Connect to the videohub device
-    pub async fn connect(&mut self) -> Result<()> {
-        log::debug!("Connecting to videohub at {}:{}", self.host, self.port);
-
-        let stream = TcpStream::connect(format!("{}:{}", self.host, self.port)).await?;
-        let framed = Framed::new(stream, VideohubCodec);
-
-        self.connection = Some(framed);
-        self.state.connected = true;
-        self.state.reconnected = !self.initial_state_received; // Mark as reconnected if not first connection
-        self.initial_state_received = false; // Reset flag to track new connection state
-
-        log::debug!("Connected to videohub successfully");
-        Ok(())
-    }
-
-    // Disconnect from the videohub device
-    #[allow(dead_code)]
-    pub async fn disconnect(&mut self) {
-        if let Some(mut conn) = self.connection.take() {
-            let _ = conn.close().await.map_err(|e| {
-                log::warn!("Error closing videohub connection: {e}");
-            });
-        }
-        self.state.connected = false;
-        self.initial_state_received = false; // Reset on disconnect
-        log::info!("Disconnected from videohub");
-    }
-
-    // Check if connected to the videohub
-    #[allow(dead_code)]
-    pub fn is_connected(&self) -> bool {
-        self.state.connected && self.connection.is_some()
-    }
-
-    // Get the current videohub state
-    pub fn state(&self) -> &VideohubState {
-        &self.state
-    }
-
-    // Send a message to the videohub
-    pub async fn send_message(&mut self, message: VideohubMessage) -> Result<()> {
-        if let Some(conn) = &mut self.connection {
-            conn.send(message)
-                .await
-                .map_err(|e| anyhow!("Failed to send message: {}", e))?;
-            Ok(())
-        } else {
-            Err(anyhow!("Not connected to videohub"))
-        }
-    }
-
-    // Receive the next message from the videohub
-    pub async fn receive_message(&mut self) -> Result<Option<VideohubMessage>> {
-        if let Some(conn) = &mut self.connection {
-            match conn.next().await {
-                Some(Ok(message)) => {
-                    self.handle_message(&message);
-                    Ok(Some(message))
-                }
-                Some(Err(e)) => Err(anyhow!("Failed to receive message: {}", e)),
-                None => {
-                    // Connection closed
-                    self.state.connected = false;
-                    Ok(None)
-                }
-            }
-        } else {
-            Err(anyhow!("Not connected to videohub"))
-        }
-    }
-
-    // Handle incoming messages and update state
-    fn handle_message(&mut self, message: &VideohubMessage) {
-        match message {
-            VideohubMessage::DeviceInfo(info) => {
-                log::info!(
-                    "Device connected: {} | Inputs: {} | Outputs: {} | ID: {}",
-                    info.model_name.as_deref().unwrap_or("Unknown"),
-                    info.video_inputs.unwrap_or(0),
-                    info.video_outputs.unwrap_or(0),
-                    info.unique_id.as_deref().unwrap_or("Unknown")
-                );
-                self.state.device_info = Some(info.clone());
-            }
-            VideohubMessage::InputLabels(labels) => {
-                log::debug!("Received input labels: {} labels", labels.len());
-                self.state.input_labels.clear();
-                for label in labels {
-                    self.state.input_labels.insert(label.id, label.name.clone());
-                }
-            }
-            VideohubMessage::OutputLabels(labels) => {
-                log::debug!("Received output labels: {} labels", labels.len());
-                self.state.output_labels.clear();
-                for label in labels {
-                    self.state
-                        .output_labels
-                        .insert(label.id, label.name.clone());
-                }
-            }
-            VideohubMessage::VideoOutputRouting(routes) => {
-                log::debug!("Received video output routing: {} routes", routes.len());
-                self.state.video_output_routing.clear();
-                for route in routes {
-                    self.state
-                        .video_output_routing
-                        .insert(route.to_output, route.from_input);
-                }
-            }
-            VideohubMessage::ACK => {
-                log::debug!("Received ACK");
-            }
-            VideohubMessage::NAK => {
-                log::warn!("Received NAK");
-            }
-            VideohubMessage::Ping => {
-                log::debug!("Received ping");
-            }
-            VideohubMessage::Configuration(settings) => {
-                log::debug!("Received configuration: {} settings", settings.len());
-                for setting in settings {
-                    log::debug!(
-                        "Configuration setting: {} = {}",
-                        setting.setting,
-                        setting.value
-                    );
-                }
-            }
-            VideohubMessage::EndPrelude => {
-                log::debug!("Received end of prelude - device initialization complete");
-                self.initial_state_received = true;
-                self.state.reconnected = false; // Clear reconnected flag after receiving full state
-            }
-            VideohubMessage::Preamble(preamble) => {
-                log::debug!("Received protocol preamble: version {}", preamble.version);
-                self.state.protocol_version = Some(preamble.version.clone());
-            }
-            VideohubMessage::VideoOutputLocks(locks) => {
-                log::debug!("Received video output locks: {} locks", locks.len());
-                self.state.output_locks.clear();
-                for lock in locks {
-                    let is_locked = matches!(lock.state, videohub::LockState::Locked);
-                    self.state.output_locks.insert(lock.id, is_locked);
-                    log::debug!(
-                        "Output {} lock state: {}",
-                        lock.id,
-                        if is_locked { "locked" } else { "unlocked" }
-                    );
-                }
-            }
-            VideohubMessage::UnknownMessage(header, body) => {
-                let header_str = String::from_utf8_lossy(header);
-                let body_str = String::from_utf8_lossy(body);
-                log::debug!(
-                    "Received unknown message: {} with body: {}",
-                    header_str.trim(),
-                    body_str.trim()
-                );
-
-                // Handle specific unknown messages that we can parse
-                match header_str.trim() {
-                    "TAKE MODE:" => {
-                        log::debug!("Processing take mode configuration");
-                        self.handle_take_mode(&body_str);
-                    }
-                    "NETWORK:" => {
-                        log::debug!("Processing network configuration");
-                        self.handle_network_config(&body_str);
-                    }
-                    header if header.starts_with("NETWORK INTERFACE ") => {
-                        if let Some(interface_id) = header
-                            .strip_prefix("NETWORK INTERFACE ")
-                            .and_then(|s| s.strip_suffix(":"))
-                            .and_then(|s| s.parse::<u32>().ok())
-                        {
-                            log::debug!(
-                                "Processing network interface {interface_id} configuration"
-                            );
-                            self.handle_network_interface(interface_id, &body_str);
-                        }
-                    }
-                    _ => {
-                        log::debug!("Unhandled unknown message: {}", header_str.trim());
-                    }
-                }
-            }
-            _ => {
-                log::debug!("Received unhandled message: {message:?}");
-            }
-        }
-    }
-
-    // Set a video output route
-    pub async fn set_route(&mut self, output: u32, input: u32) -> Result<()> {
-        log::info!("Setting route: output {output} -> input {input}");
-
-        let route = Route {
-            to_output: output,
-            from_input: input,
-        };
-
-        let message = VideohubMessage::VideoOutputRouting(vec![route]);
-        self.send_message(message).await?;
-
-        Ok(())
-    }
-
-    // Check if this client just reconnected and needs to send full state
-    pub fn just_reconnected(&self) -> bool {
-        self.state.reconnected
-    }
-
-    // Clear the reconnected flag (called after full state has been processed)
-    pub fn clear_reconnected_flag(&mut self) {
-        self.state.reconnected = false;
-    }
-
-    // Force a full state refresh (called when rship SDK reconnects)
-    pub fn force_full_state_refresh(&mut self) {
-        self.state.reconnected = true;
-        log::info!("Forced full state refresh - will emit all state on next messages");
-    }
-
-    // Set an input label
-    pub async fn set_input_label(&mut self, input: u32, label: String) -> Result<()> {
-        log::info!("Setting input {input} label to: {label}");
-
-        let label_msg = Label {
-            id: input,
-            name: label,
-        };
-
-        let message = VideohubMessage::InputLabels(vec![label_msg]);
-        self.send_message(message).await?;
-
-        Ok(())
-    }
-
-    // Set an output label
-    pub async fn set_output_label(&mut self, output: u32, label: String) -> Result<()> {
-        log::info!("Setting output {output} label to: {label}");
-
-        let label_msg = Label {
-            id: output,
-            name: label,
-        };
-
-        let message = VideohubMessage::OutputLabels(vec![label_msg]);
-        self.send_message(message).await?;
-
-        Ok(())
-    }
-
-    // Request device information
-    #[allow(dead_code)]
-    pub async fn request_device_info(&mut self) -> Result<()> {
-        log::debug!("Requesting device info");
-        // Videohub protocol sends device info automatically on connection
-        // We can send a ping to trigger a response
-        let message = VideohubMessage::Ping;
-        self.send_message(message).await?;
-        Ok(())
-    }
-
-    // Handle take mode configuration from unknown message
-    fn handle_take_mode(&mut self, body: &str) {
-        self.state.take_mode.clear();
-
-        for line in body.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 2 {
-                continue;
-            }
-            if let Ok(output_id) = parts[0].parse::<u32>() {
-                let take_mode_enabled = parts[1] == "true";
-                self.state.take_mode.insert(output_id, take_mode_enabled);
-                log::debug!("Take mode for output {output_id}: {take_mode_enabled}");
-            }
-        }
-
-        log::info!(
-            "Updated take mode configuration for {} outputs",
-            self.state.take_mode.len()
-        );
-    }
-
-    // Handle network configuration from unknown message
-    fn handle_network_config(&mut self, body: &str) {
-        log::debug!("Processing network configuration");
-        for line in body.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            if let Some((key, value)) = line.split_once(": ") {
-                log::debug!("Network config: {key} = {value}");
-            }
-        }
-    }
-
-    // Handle network interface configuration from unknown message
-    fn handle_network_interface(&mut self, interface_id: u32, body: &str) {
-        let mut interface = NetworkInterface {
-            id: interface_id,
-            name: String::new(),
-            priority: None,
-            mac_address: None,
-            dynamic_ip: None,
-            current_addresses: None,
-            current_gateway: None,
-            static_addresses: None,
-            static_gateway: None,
-        };
-
-        for line in body.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            if let Some((key, value)) = line.split_once(": ") {
-                match key {
-                    "Name" => interface.name = value.to_string(),
-                    "Priority" => interface.priority = value.parse().ok(),
-                    "MAC Address" => interface.mac_address = Some(value.to_string()),
-                    "Dynamic IP" => interface.dynamic_ip = Some(value == "true"),
-                    "Current Addresses" => interface.current_addresses = Some(value.to_string()),
-                    "Current Gateway" => interface.current_gateway = Some(value.to_string()),
-                    "Static Addresses" => interface.static_addresses = Some(value.to_string()),
-                    "Static Gateway" => interface.static_gateway = Some(value.to_string()),
-                    _ => log::debug!("Unknown network interface field: {key} = {value}"),
-                }
-            }
-        }
-
-        // Update or add the interface
-        if let Some(existing) = self
-            .state
-            .network_interfaces
-            .iter_mut()
-            .find(|iface| iface.id == interface_id)
-        {
-            *existing = interface.clone();
-        } else {
-            self.state.network_interfaces.push(interface.clone());
-        }
-
-        log::debug!(
-            "Updated network interface {interface_id}: {}",
-            interface.name
-        );
-    }
-}
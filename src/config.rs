@@ -0,0 +1,310 @@
+//! Optional service settings, read from environment variables or a config file
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::PathBuf;
+
+use crate::charset::LabelCharset;
+use crate::filter::EmitterFilter;
+use crate::watchdog::WatchdogProbe;
+
+// How routes are emitted while the input labels are not known yet, e.g. early in the prelude
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteLabelWarming {
+    // Hold routes back until their input label arrives or the prelude ends
+    Defer,
+    // Emit routes right away and again once their input label arrives
+    Backfill,
+}
+
+// What the executor does with the Videohubs it finds on the LAN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideohubDiscovery {
+    // Pulse them on discovered-videohubs
+    Report,
+    // Also connect to the ones found on start when no Videohub is configured
+    Auto,
+}
+
+// What the executor does with the device state once it has first received it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupPolicy {
+    // Adopt whatever the device shows
+    Observe,
+    // Write the routes and labels of the show file <name>.json in SNAPSHOTS_DIR
+    ApplySnapshot(String),
+    // Write whatever differs from SHOW_FILE
+    ReconcileShowFile,
+}
+
+impl StartupPolicy {
+    // "observe", "apply-snapshot:<name>" or "reconcile-show-file"
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy.trim() {
+            "observe" => Some(Self::Observe),
+            "reconcile-show-file" => Some(Self::ReconcileShowFile),
+            policy => policy
+                .strip_prefix("apply-snapshot:")
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| Self::ApplySnapshot(name.to_string())),
+        }
+    }
+}
+
+// Optional behaviour of the service; everything defaults to the original behaviour
+#[derive(Debug, Clone, Default)]
+pub struct ServiceConfig {
+    // Emitter ids that should not pulse the device echo of changes made by this executor
+    pub suppress_own_echo: HashSet<String>,
+    // JSON file the port labels are persisted to
+    pub labels_file: Option<PathBuf>,
+    // Transliterate labels to ASCII before sending them; labels are sent as given if unset
+    pub label_charset: Option<LabelCharset>,
+    // Push the persisted labels to the device on connect instead of adopting the device's labels
+    pub labels_authoritative: bool,
+    // Route outputs locked by this service by unlocking, routing and re-locking them in one write
+    pub relock_routes: bool,
+    // JSON file mapping events to external commands
+    pub hooks_file: Option<PathBuf>,
+    // JSON file of alarm notifications (email, Slack, Telegram)
+    pub notifications_file: Option<PathBuf>,
+    // Rhai script reacting to events (requires the `scripting` feature)
+    pub script_file: Option<PathBuf>,
+    // Log every command that is about to be executed
+    pub audit_commands: bool,
+    // JSON file defining named rotation groups
+    pub rotations_file: Option<PathBuf>,
+    // JSON file of per-output failover rules
+    pub failover_file: Option<PathBuf>,
+    // Port of the HTTP control listener
+    pub control_http_port: Option<u16>,
+    // UDP port of the OSC control listener
+    pub control_osc_port: Option<u16>,
+    // Hold bulk changes (CSV import, show load) until apply-staged-changes is triggered
+    pub stage_bulk_changes: bool,
+    // How long staged changes wait for an apply before they are discarded
+    pub staged_changes_timeout_ms: Option<u64>,
+    // JSON file of free-form metadata per port
+    pub port_metadata_file: Option<PathBuf>,
+    // Warn when a route connects ports with a different "format" in the port metadata
+    pub warn_format_mismatch: bool,
+    // JSON file of category, name prefix and order per output subtarget
+    pub output_targets_file: Option<PathBuf>,
+    // JSON file translating action and emitter display names
+    pub translations_file: Option<PathBuf>,
+    // JSON file assigning outputs to named owners
+    pub ownership_file: Option<PathBuf>,
+    // Only log actions of other operators while the device is claimed instead of rejecting them
+    pub claims_advisory: bool,
+    // JSON file the per-owner usage is saved to periodically and resumed from on start
+    pub usage_file: Option<PathBuf>,
+    // JSON file journaling changes until the device confirms them, checked on start
+    pub journal_file: Option<PathBuf>,
+    // Write the missing changes of operations a crash interrupted instead of only reporting them
+    pub complete_interrupted: bool,
+    // rship servers to fall back to, in priority order ("host:port" or a full ws:// URL)
+    pub rship_fallback_endpoints: Vec<String>,
+    // How long the active rship server may stay down before switching to the next one
+    pub rship_failover_after_ms: Option<u64>,
+    // How long registering an output subtarget with rship may take before it is tried again
+    pub registration_timeout_ms: Option<u64>,
+    // Emitters turned off or sampled, by emitter id
+    pub emitter_filters: HashMap<String, EmitterFilter>,
+    // Number of pulses held back while rship is unreachable; no buffering if unset
+    pub rship_buffer_size: Option<usize>,
+    // Only keep the latest buffered pulse of each piece of state
+    pub rship_buffer_coalesce: bool,
+    // Maximum acceptable p95 command-to-confirmation latency
+    pub latency_budget_ms: Option<u64>,
+    // Consecutive short-lived device connections before reconnects back off
+    pub reconnect_flap_threshold: Option<u32>,
+    // How long a device connection must last to count as stable
+    pub reconnect_stability_window_ms: Option<u64>,
+    // Longest delay between reconnect attempts while backing off
+    pub reconnect_max_delay_ms: Option<u64>,
+    // Avoid route pulses without an input label; routes are emitted as they arrive if unset
+    pub route_label_warming: Option<RouteLabelWarming>,
+    // How often the matrix summary is pulsed when nothing changes
+    pub matrix_summary_interval_ms: Option<u64>,
+    // Window of the change summary, pulsed at the end of each window; no summaries if unset
+    pub change_summary_interval_ms: Option<u64>,
+    // Browse for Videohubs over mDNS; no discovery if unset
+    pub videohub_discovery: Option<VideohubDiscovery>,
+    // Probe sent periodically to check that the device acts on commands; no watchdog if unset
+    pub watchdog: Option<WatchdogProbe>,
+    pub watchdog_interval_ms: Option<u64>,
+    // How long a probe may go unanswered before the watchdog alarms
+    pub watchdog_timeout_ms: Option<u64>,
+    // JSON file of named profiles; no profiles if unset
+    pub profiles_file: Option<PathBuf>,
+    // Profile switched to on start (PROFILE, or --profile)
+    pub profile: Option<String>,
+    // Only observe the device and compare it with the intent posted by a legacy controller
+    pub shadow_mode: bool,
+    // Offer send-raw-block, which writes protocol blocks past ownership and pending-change tracking
+    pub raw_blocks: bool,
+    // Offer identify-device and reboot-device, which write settings outside the documented protocol
+    pub device_functions: bool,
+    // Port counts used instead of the device's, for firmware that under-reports them
+    pub video_inputs_override: Option<u32>,
+    pub video_outputs_override: Option<u32>,
+    // "host:port" of the other executor of an active/standby pair; no redundancy if unset
+    pub redundancy_peer: Option<String>,
+    // UDP port the redundancy heartbeats are received on
+    pub redundancy_port: Option<u16>,
+    // The higher priority executor leads when both start together
+    pub redundancy_priority: u32,
+    // Routing restored after the first prelude and after the device restarted; observe if unset
+    pub startup_policy: Option<StartupPolicy>,
+    // Directory of the show files apply-snapshot refers to by name
+    pub snapshots_dir: Option<PathBuf>,
+    // Show file reconcile-show-file brings the device in line with
+    pub show_file: Option<PathBuf>,
+    // JSON file the named salvos of save-salvo are persisted to; salvos are disabled if unset
+    pub salvos_file: Option<PathBuf>,
+    // Draw a continuously updated summary on the terminal (--status-line)
+    pub status_line: bool,
+    // Name of the hub when several are controlled (VIDEOHUBS); names its rship instance
+    pub hub_name: Option<String>,
+}
+
+impl ServiceConfig {
+    // Read the optional settings from the environment
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    // Read the optional settings by their environment variable names from any source, e.g. the
+    // environment with a config file as fallback
+    pub fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            suppress_own_echo: parse_list(var("SUPPRESS_OWN_ECHO")).into_iter().collect(),
+            labels_file: var("LABELS_FILE").map(PathBuf::from),
+            label_charset: var("LABEL_CHARSET")
+                .filter(|charset| charset.trim().eq_ignore_ascii_case("ascii"))
+                .map(|_| {
+                    LabelCharset::new(
+                        parse_list(var("LABEL_TRANSLITERATIONS"))
+                            .iter()
+                            .filter_map(|entry| LabelCharset::parse_transliteration(entry))
+                            .collect(),
+                    )
+                }),
+            labels_authoritative: parse_flag(var("LABELS_AUTHORITATIVE")),
+            relock_routes: parse_flag(var("RELOCK_ROUTES")),
+            hooks_file: var("HOOKS_FILE").map(PathBuf::from),
+            notifications_file: var("NOTIFICATIONS_FILE").map(PathBuf::from),
+            script_file: var("SCRIPT_FILE").map(PathBuf::from),
+            audit_commands: parse_flag(var("AUDIT_COMMANDS")),
+            rotations_file: var("ROTATIONS_FILE").map(PathBuf::from),
+            failover_file: var("FAILOVER_FILE").map(PathBuf::from),
+            control_http_port: var("CONTROL_HTTP_PORT").and_then(|port| port.parse().ok()),
+            control_osc_port: var("CONTROL_OSC_PORT").and_then(|port| port.parse().ok()),
+            stage_bulk_changes: parse_flag(var("STAGE_BULK_CHANGES")),
+            staged_changes_timeout_ms: var("STAGED_CHANGES_TIMEOUT_MS")
+                .and_then(|ms| ms.parse().ok()),
+            port_metadata_file: var("PORT_METADATA_FILE").map(PathBuf::from),
+            warn_format_mismatch: parse_flag(var("WARN_FORMAT_MISMATCH")),
+            output_targets_file: var("OUTPUT_TARGETS_FILE").map(PathBuf::from),
+            translations_file: var("TRANSLATIONS_FILE").map(PathBuf::from),
+            ownership_file: var("OWNERSHIP_FILE").map(PathBuf::from),
+            claims_advisory: parse_flag(var("CLAIMS_ADVISORY")),
+            usage_file: var("USAGE_FILE").map(PathBuf::from),
+            journal_file: var("JOURNAL_FILE").map(PathBuf::from),
+            complete_interrupted: parse_flag(var("COMPLETE_INTERRUPTED_OPERATIONS")),
+            rship_fallback_endpoints: parse_list(var("RSHIP_FALLBACK_ENDPOINTS")),
+            rship_failover_after_ms: var("RSHIP_FAILOVER_AFTER_MS").and_then(|ms| ms.parse().ok()),
+            registration_timeout_ms: var("RSHIP_REGISTRATION_TIMEOUT_MS")
+                .and_then(|ms| ms.parse().ok()),
+            emitter_filters: parse_list(var("EMITTER_FILTERS"))
+                .iter()
+                .filter_map(|entry| {
+                    let (emitter, filter) = entry.split_once('=')?;
+                    Some((emitter.trim().to_string(), EmitterFilter::parse(filter)?))
+                })
+                .collect(),
+            rship_buffer_size: var("RSHIP_BUFFER_SIZE").and_then(|size| size.parse().ok()),
+            rship_buffer_coalesce: parse_flag(var("RSHIP_BUFFER_COALESCE")),
+            latency_budget_ms: var("LATENCY_BUDGET_MS").and_then(|ms| ms.parse().ok()),
+            reconnect_flap_threshold: var("RECONNECT_FLAP_THRESHOLD")
+                .and_then(|count| count.parse().ok()),
+            reconnect_stability_window_ms: var("RECONNECT_STABILITY_WINDOW_MS")
+                .and_then(|ms| ms.parse().ok()),
+            reconnect_max_delay_ms: var("RECONNECT_MAX_DELAY_MS").and_then(|ms| ms.parse().ok()),
+            route_label_warming: var("ROUTE_LABEL_WARMING").and_then(|mode| {
+                match mode.trim().to_lowercase().as_str() {
+                    "defer" => Some(RouteLabelWarming::Defer),
+                    "backfill" => Some(RouteLabelWarming::Backfill),
+                    _ => None,
+                }
+            }),
+            videohub_discovery: var("VIDEOHUB_DISCOVERY").and_then(|mode| {
+                match mode.trim().to_lowercase().as_str() {
+                    "report" => Some(VideohubDiscovery::Report),
+                    "auto" => Some(VideohubDiscovery::Auto),
+                    _ => None,
+                }
+            }),
+            matrix_summary_interval_ms: var("MATRIX_SUMMARY_INTERVAL_MS")
+                .and_then(|ms| ms.parse().ok()),
+            change_summary_interval_ms: var("CHANGE_SUMMARY_INTERVAL_MS")
+                .and_then(|ms| ms.parse().ok())
+                .filter(|ms| *ms > 0),
+            watchdog: var("WATCHDOG").and_then(|probe| WatchdogProbe::parse(&probe)),
+            watchdog_interval_ms: var("WATCHDOG_INTERVAL_MS").and_then(|ms| ms.parse().ok()),
+            watchdog_timeout_ms: var("WATCHDOG_TIMEOUT_MS").and_then(|ms| ms.parse().ok()),
+            profiles_file: var("PROFILES_FILE").map(PathBuf::from),
+            profile: var("PROFILE"),
+            shadow_mode: parse_flag(var("SHADOW_MODE")),
+            raw_blocks: parse_flag(var("RAW_BLOCKS")),
+            device_functions: parse_flag(var("DEVICE_FUNCTIONS")),
+            video_inputs_override: var("VIDEO_INPUTS_OVERRIDE")
+                .and_then(|count| count.parse().ok()),
+            video_outputs_override: var("VIDEO_OUTPUTS_OVERRIDE")
+                .and_then(|count| count.parse().ok()),
+            redundancy_peer: var("REDUNDANCY_PEER"),
+            redundancy_port: var("REDUNDANCY_PORT").and_then(|port| port.parse().ok()),
+            redundancy_priority: var("REDUNDANCY_PRIORITY")
+                .and_then(|priority| priority.parse().ok())
+                .unwrap_or(0),
+            startup_policy: var("STARTUP_POLICY").and_then(|policy| {
+                let parsed = StartupPolicy::parse(&policy);
+                if parsed.is_none() {
+                    log::error!("Unknown STARTUP_POLICY {policy:?}, observing the device instead");
+                }
+                parsed
+            }),
+            snapshots_dir: var("SNAPSHOTS_DIR").map(PathBuf::from),
+            show_file: var("SHOW_FILE").map(PathBuf::from),
+            salvos_file: var("SALVOS_FILE").map(PathBuf::from),
+            status_line: false,
+            hub_name: None,
+        }
+    }
+
+    // Whether the given emitter should skip changes initiated by this executor
+    pub fn suppresses_own_echo(&self, emitter_id: &str) -> bool {
+        self.suppress_own_echo.contains(emitter_id)
+    }
+}
+
+// Parse a comma-separated setting, ignoring empty entries
+fn parse_list(value: Option<String>) -> Vec<String> {
+    value
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Parse a boolean setting, treating "1", "true" and "yes" as enabled
+fn parse_flag(value: Option<String>) -> bool {
+    value
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
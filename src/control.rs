@@ -0,0 +1,304 @@
+//! Minimal HTTP and OSC listeners for triggering commands from outside rship
+//!
+//! Both protocols share the same paths (ports are 1-indexed); OSC addresses are prefixed
+//! with `/videohub`, e.g. HTTP `POST /failover/3` is OSC `/videohub/failover/3`.
+//! Signal probes report inputs with `/signal/<input>/ok` and `/signal/<input>/lost`.
+//! Over HTTP, `GET /state` answers with the current device state as JSON, `GET /health` with
+//! channel depths and task heartbeats, and `POST /simulate` with what the action in the JSON
+//! body would change, without sending it to the device. In shadow mode a legacy controller posts
+//! the routes it intends with `PUT /intent`, as a JSON object of output to input, e.g. `{"1": 3}`.
+//!
+//! The listeners are built with the `http` and `osc` features respectively.
+
+#[cfg(feature = "http")]
+use anyhow::bail;
+use anyhow::{Context, Result};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::sync::Arc;
+#[cfg(feature = "http")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "http")]
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+#[cfg(feature = "http")]
+use crate::health::Health;
+#[cfg(feature = "http")]
+use crate::service;
+use crate::service::{CommandRequest, VideohubCommand};
+#[cfg(feature = "http")]
+use crate::simulation::ActionPayload;
+
+#[cfg(feature = "osc")]
+const OSC_PREFIX: &str = "/videohub";
+#[cfg(feature = "http")]
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+// Map a control path to a command
+fn command_for_path(path: &str) -> Option<VideohubCommand> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let port = |segment: &str| {
+        segment
+            .parse::<u32>()
+            .ok()
+            .map(|port| port.clamp(1, u32::MAX) - 1)
+    };
+
+    match segments.as_slice() {
+        ["failover", output] => Some(VideohubCommand::TriggerFailover {
+            output: port(output)?,
+        }),
+        ["failover", output, "revert"] => Some(VideohubCommand::RevertFailover {
+            output: port(output)?,
+        }),
+        ["signal", input, status @ ("ok" | "lost")] => Some(VideohubCommand::SignalStatus {
+            input: port(input)?,
+            present: *status == "ok",
+        }),
+        _ => None,
+    }
+}
+
+async fn queue(
+    command_tx: &mpsc::Sender<CommandRequest>,
+    command: VideohubCommand,
+    source: &'static str,
+) -> Result<()> {
+    command_tx
+        .send(CommandRequest::internal(command).via(source))
+        .await
+        .context("service is shutting down")
+}
+
+// Accept HTTP control requests until the listener fails
+#[cfg(feature = "http")]
+pub async fn serve_http(
+    port: u16,
+    command_tx: mpsc::Sender<CommandRequest>,
+    health: Arc<Health>,
+) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to bind HTTP control port {port}"))?;
+    log::info!("HTTP control listening on port {port}");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let command_tx = command_tx.clone();
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_http(stream, &command_tx, &health).await {
+                log::warn!("HTTP control request from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(feature = "http")]
+async fn handle_http(
+    mut stream: TcpStream,
+    command_tx: &mpsc::Sender<CommandRequest>,
+    health: &Health,
+) -> Result<()> {
+    let mut buffer = Vec::new();
+    let (method, path, body_start, content_length) = loop {
+        let mut chunk = [0u8; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("connection closed before the request was complete");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_SIZE {
+            bail!("request too large");
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        if let httparse::Status::Complete(body_start) = request.parse(&buffer)? {
+            let content_length = request
+                .headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+                .and_then(|header| std::str::from_utf8(header.value).ok()?.trim().parse().ok())
+                .unwrap_or(0usize);
+            break (
+                request.method.unwrap_or_default().to_string(),
+                request.path.unwrap_or_default().to_string(),
+                body_start,
+                content_length,
+            );
+        }
+    };
+
+    // Read the rest of the body, if one was sent
+    if body_start + content_length > MAX_REQUEST_SIZE {
+        bail!("request too large");
+    }
+    while buffer.len() < body_start + content_length {
+        let mut chunk = [0u8; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("connection closed before the body was complete");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = &buffer[body_start..body_start + content_length];
+
+    if path.trim_end_matches('/') == "/simulate" {
+        let (status, content_type, body) = match method.as_str() {
+            "POST" => match serde_json::from_slice::<ActionPayload>(body) {
+                Ok(action) => match service::simulate(command_tx, action).await {
+                    Ok(prediction) => (
+                        "200 OK",
+                        "application/json",
+                        serde_json::to_string_pretty(&prediction)? + "\n",
+                    ),
+                    Err(_) => (
+                        "503 Service Unavailable",
+                        "text/plain",
+                        "unavailable\n".into(),
+                    ),
+                },
+                Err(e) => ("400 Bad Request", "text/plain", format!("{e}\n")),
+            },
+            _ => ("405 Method Not Allowed", "text/plain", "use POST\n".into()),
+        };
+        return respond(&mut stream, status, content_type, &body).await;
+    }
+
+    if path.trim_end_matches('/') == "/intent" {
+        let (status, body) = match method.as_str() {
+            "PUT" => match intent_routes(body) {
+                Ok(routes) => {
+                    match queue(command_tx, VideohubCommand::SetIntent { routes }, "http").await {
+                        Ok(()) => ("202 Accepted", "queued\n".to_string()),
+                        Err(_) => ("503 Service Unavailable", "unavailable\n".into()),
+                    }
+                }
+                Err(e) => ("400 Bad Request", format!("{e}\n")),
+            },
+            _ => ("405 Method Not Allowed", "use PUT\n".into()),
+        };
+        return respond(&mut stream, status, "text/plain", &body).await;
+    }
+
+    if path.trim_end_matches('/') == "/health" {
+        let (status, content_type, body) = match method.as_str() {
+            "GET" => {
+                let report = health.report();
+                // Monitoring sees a stuck task without parsing the body
+                let status = if report.healthy {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                };
+                (
+                    status,
+                    "application/json",
+                    serde_json::to_string_pretty(&report)? + "\n",
+                )
+            }
+            _ => ("405 Method Not Allowed", "text/plain", "use GET\n".into()),
+        };
+        return respond(&mut stream, status, content_type, &body).await;
+    }
+
+    if path.trim_end_matches('/') == "/state" {
+        let (status, content_type, body) = match method.as_str() {
+            "GET" => match service::snapshot(command_tx).await {
+                Ok(snapshot) => (
+                    "200 OK",
+                    "application/json",
+                    serde_json::to_string_pretty(&snapshot)? + "\n",
+                ),
+                Err(_) => (
+                    "503 Service Unavailable",
+                    "text/plain",
+                    "unavailable\n".into(),
+                ),
+            },
+            _ => ("405 Method Not Allowed", "text/plain", "use GET\n".into()),
+        };
+        return respond(&mut stream, status, content_type, &body).await;
+    }
+
+    let (status, body) = match (method.as_str(), command_for_path(&path)) {
+        ("POST", Some(command)) => {
+            log::info!("HTTP control: {method} {path}");
+            match queue(command_tx, command, "http").await {
+                Ok(()) => ("202 Accepted", "queued\n"),
+                Err(_) => ("503 Service Unavailable", "unavailable\n"),
+            }
+        }
+        (_, Some(_)) => ("405 Method Not Allowed", "use POST\n"),
+        (_, None) => ("404 Not Found", "not found\n"),
+    };
+    respond(&mut stream, status, "text/plain", body).await
+}
+
+// Intended routes from 1-indexed output and input numbers
+#[cfg(feature = "http")]
+fn intent_routes(body: &[u8]) -> Result<HashMap<u32, u32>> {
+    let routes: HashMap<u32, u32> = serde_json::from_slice(body)?;
+    routes
+        .into_iter()
+        .map(
+            |(output, input)| match (output.checked_sub(1), input.checked_sub(1)) {
+                (Some(output), Some(input)) => Ok((output, input)),
+                _ => bail!("ports are numbered from 1"),
+            },
+        )
+        .collect()
+}
+
+#[cfg(feature = "http")]
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+// Receive OSC control messages until the socket fails
+#[cfg(feature = "osc")]
+pub async fn serve_osc(port: u16, command_tx: mpsc::Sender<CommandRequest>) -> Result<()> {
+    let socket = tokio::net::UdpSocket::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to bind OSC control port {port}"))?;
+    log::info!("OSC control listening on port {port}");
+
+    let mut packet = [0u8; 1536];
+    loop {
+        let (len, peer) = socket.recv_from(&mut packet).await?;
+        let Some(address) = osc_address(&packet[..len]) else {
+            log::debug!("Ignoring malformed OSC packet from {peer}");
+            continue;
+        };
+        let Some(command) = address.strip_prefix(OSC_PREFIX).and_then(command_for_path) else {
+            log::debug!("Ignoring OSC message {address} from {peer}");
+            continue;
+        };
+
+        log::info!("OSC control: {address}");
+        queue(&command_tx, command, "osc").await?;
+    }
+}
+
+// Address pattern of an OSC message; bundles and arguments are not supported
+#[cfg(feature = "osc")]
+fn osc_address(packet: &[u8]) -> Option<&str> {
+    if packet.first() != Some(&b'/') {
+        return None;
+    }
+    let end = packet.iter().position(|&byte| byte == 0)?;
+    std::str::from_utf8(&packet[..end]).ok()
+}
@@ -0,0 +1,180 @@
+//! Change detection for the parts of the device state that the client parses itself
+//! (take mode, network interfaces and configuration) rather than passing through as typed messages
+
+use std::collections::HashMap;
+
+use crate::client::{NetworkInterface, VideohubState};
+use crate::service::VideohubEvent;
+
+// The last take mode, network interfaces and configuration that were emitted
+#[derive(Debug, Default)]
+pub struct StateDiff {
+    take_mode: HashMap<u32, bool>,
+    network_interfaces: HashMap<u32, NetworkInterface>,
+    configuration: HashMap<String, String>,
+}
+
+impl StateDiff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Forget what was emitted, so the next call returns everything, e.g. after reconnecting
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // Compare the client state with what was emitted before and return the events for what changed
+    pub fn changes(&mut self, state: &VideohubState) -> Vec<VideohubEvent> {
+        let mut events = Vec::new();
+
+        let mut take_mode: Vec<_> = state.take_mode.iter().collect();
+        take_mode.sort_unstable();
+        for (&output, &enabled) in take_mode {
+            if self.take_mode.insert(output, enabled) != Some(enabled) {
+                events.push(VideohubEvent::TakeMode { output, enabled });
+            }
+        }
+
+        for interface in &state.network_interfaces {
+            let previous = self
+                .network_interfaces
+                .insert(interface.id, interface.clone());
+            if previous.as_ref() != Some(interface) {
+                events.push(VideohubEvent::NetworkInterface {
+                    interface: interface.clone(),
+                });
+            }
+        }
+
+        let mut configuration: Vec<_> = state.configuration.iter().collect();
+        configuration.sort_unstable();
+        for (setting, value) in configuration {
+            if self.configuration.get(setting) != Some(value) {
+                self.configuration.insert(setting.clone(), value.clone());
+                events.push(VideohubEvent::Configuration {
+                    setting: setting.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(id: u32, address: &str) -> NetworkInterface {
+        NetworkInterface {
+            id,
+            name: format!("eth{id}"),
+            priority: None,
+            mac_address: None,
+            dynamic_ip: Some(false),
+            current_addresses: Some(address.to_string()),
+            current_gateway: None,
+            static_addresses: None,
+            static_gateway: None,
+        }
+    }
+
+    fn take_mode_events(events: &[VideohubEvent]) -> Vec<(u32, bool)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                VideohubEvent::TakeMode { output, enabled } => Some((*output, *enabled)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn emits_take_mode_changes_only() {
+        let mut diff = StateDiff::new();
+        let mut state = VideohubState::default();
+        state.take_mode.insert(0, false);
+        state.take_mode.insert(1, true);
+
+        assert_eq!(
+            take_mode_events(&diff.changes(&state)),
+            vec![(0, false), (1, true)]
+        );
+        assert!(diff.changes(&state).is_empty());
+
+        state.take_mode.insert(1, false);
+        assert_eq!(take_mode_events(&diff.changes(&state)), vec![(1, false)]);
+    }
+
+    #[test]
+    fn detects_changes_regardless_of_the_triggering_message() {
+        // The take mode block can arrive just before a typed message (e.g. routing); the
+        // diff only depends on the state, so the change is seen whichever message follows
+        let mut diff = StateDiff::new();
+        let mut state = VideohubState::default();
+        diff.changes(&state);
+
+        state.take_mode.insert(3, true);
+        state
+            .network_interfaces
+            .push(interface(0, "10.0.0.2/255.255.255.0"));
+        state.video_output_routing.insert(3, 7);
+
+        let events = diff.changes(&state);
+        assert_eq!(take_mode_events(&events), vec![(3, true)]);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            VideohubEvent::NetworkInterface { interface } if interface.id == 0
+        )));
+    }
+
+    #[test]
+    fn emits_network_interface_changes() {
+        let mut diff = StateDiff::new();
+        let mut state = VideohubState::default();
+        state
+            .network_interfaces
+            .push(interface(0, "10.0.0.2/255.255.255.0"));
+        assert_eq!(diff.changes(&state).len(), 1);
+        assert!(diff.changes(&state).is_empty());
+
+        state.network_interfaces[0] = interface(0, "10.0.0.3/255.255.255.0");
+        assert_eq!(diff.changes(&state).len(), 1);
+    }
+
+    #[test]
+    fn emits_configuration_changes() {
+        let mut diff = StateDiff::new();
+        let mut state = VideohubState::default();
+        state
+            .configuration
+            .insert("Take Mode".to_string(), "false".to_string());
+        assert_eq!(diff.changes(&state).len(), 1);
+        assert!(diff.changes(&state).is_empty());
+
+        state
+            .configuration
+            .insert("Take Mode".to_string(), "true".to_string());
+        let events = diff.changes(&state);
+        assert!(matches!(
+            events.as_slice(),
+            [VideohubEvent::Configuration { setting, value }] if setting == "Take Mode" && value == "true"
+        ));
+    }
+
+    #[test]
+    fn clear_re_emits_unchanged_state() {
+        let mut diff = StateDiff::new();
+        let mut state = VideohubState::default();
+        state.take_mode.insert(0, true);
+        state
+            .network_interfaces
+            .push(interface(0, "10.0.0.2/255.255.255.0"));
+        diff.changes(&state);
+
+        diff.clear();
+        assert_eq!(diff.changes(&state).len(), 2);
+    }
+}
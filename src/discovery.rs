@@ -0,0 +1,146 @@
+//! Discovery of Videohubs on the LAN. Blackmagic devices announce themselves over mDNS (Bonjour)
+//! as `_blackmagic._tcp`; with VIDEOHUB_DISCOVERY the executor browses for them, pulses what it
+//! found on `discovered-videohubs`, and with `auto` connects to them on start instead of a
+//! configured VIDEOHUB_ADDRESS, so venues where DHCP hands out changing addresses need no fixed one.
+
+use anyhow::{Context, Result};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use std::collections::BTreeMap;
+use tokio::time::{Duration, Instant};
+
+use crate::emitters::DiscoveredVideohub;
+use crate::hubs::{DEFAULT_VIDEOHUB_PORT, Hub};
+use crate::service::{EventSender, VideohubEvent};
+
+// Service type Blackmagic devices announce
+pub const SERVICE_TYPE: &str = "_blackmagic._tcp.local.";
+
+// How long one browse collects answers
+pub const DISCOVERY_WINDOW: Duration = Duration::from_secs(3);
+
+// Time between browses while the service runs
+pub const DISCOVERY_INTERVAL: Duration = Duration::from_secs(60);
+
+// A device from its announcement, if it is a Videohub. `property` looks up TXT record entries,
+// e.g. "class" ("Videohub"), "name" (the model) and "unique id".
+pub fn videohub(
+    instance: &str,
+    addresses: &[String],
+    property: impl Fn(&str) -> Option<String>,
+) -> Option<DiscoveredVideohub> {
+    let is_videohub = property("class")
+        .map(|class| class.to_lowercase().contains("videohub"))
+        .unwrap_or_else(|| instance.to_lowercase().contains("videohub"));
+    if !is_videohub {
+        return None;
+    }
+    Some(DiscoveredVideohub {
+        name: instance.to_string(),
+        model: property("name"),
+        unique_id: property("unique id"),
+        address: addresses.first()?.clone(),
+        port: DEFAULT_VIDEOHUB_PORT,
+    })
+}
+
+// Browse for Videohubs for the given time; devices are listed once, by name
+pub async fn browse(window: Duration) -> Result<Vec<DiscoveredVideohub>> {
+    let daemon = ServiceDaemon::new().context("Failed to start mDNS discovery")?;
+    let receiver = daemon
+        .browse(SERVICE_TYPE)
+        .context("Failed to browse for Videohubs")?;
+
+    let mut found = BTreeMap::new();
+    let deadline = Instant::now() + window;
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, receiver.recv_async()).await {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let instance = info
+            .get_fullname()
+            .trim_end_matches(SERVICE_TYPE)
+            .trim_end_matches('.')
+            .to_string();
+        // IPv4 first, as the devices are usually reached over it
+        let mut addresses: Vec<_> = info.get_addresses().iter().copied().collect();
+        addresses.sort_by_key(|address| !address.is_ipv4());
+        let addresses: Vec<_> = addresses.iter().map(ToString::to_string).collect();
+        let property = |key: &str| info.get_property_val_str(key).map(str::to_string);
+        if let Some(device) = videohub(&instance, &addresses, property) {
+            found.insert(device.name.clone(), device);
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(found.into_values().collect())
+}
+
+// Hubs for the devices found, named after them ("Smart Videohub 12G" becomes
+// "Smart-Videohub-12G")
+pub fn hubs(devices: &[DiscoveredVideohub]) -> Result<Vec<Hub>> {
+    let hubs = devices
+        .iter()
+        .map(|device| Hub {
+            name: device
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect(),
+            host: device.address.clone(),
+            port: device.port,
+        })
+        .collect();
+    Hub::checked(hubs).context("No usable Videohubs were discovered")
+}
+
+// Browse periodically and pulse what was found
+pub async fn run(event_tx: EventSender) {
+    loop {
+        match browse(DISCOVERY_WINDOW).await {
+            Ok(devices) => {
+                log::debug!("Discovered {} Videohubs", devices.len());
+                if let Err(e) = event_tx
+                    .send(VideohubEvent::DiscoveredVideohubs { devices })
+                    .await
+                {
+                    log::error!("Failed to send discovered Videohubs: {e}");
+                    return;
+                }
+            }
+            Err(e) => log::warn!("Videohub discovery failed: {e:#}"),
+        }
+        tokio::time::sleep(DISCOVERY_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_videohubs_and_names_their_hubs() {
+        let txt = |class: &'static str| {
+            move |key: &str| match key {
+                "class" => Some(class.to_string()),
+                "name" => Some("Smart Videohub 12G 40x40".to_string()),
+                _ => None,
+            }
+        };
+        let addresses = vec!["10.0.0.20".to_string()];
+        let device = videohub("Smart Videohub 12G (Stage)", &addresses, txt("Videohub")).unwrap();
+        assert_eq!(device.model.as_deref(), Some("Smart Videohub 12G 40x40"));
+        assert_eq!(device.port, DEFAULT_VIDEOHUB_PORT);
+        assert!(videohub("HyperDeck Studio", &addresses, txt("HyperDeck")).is_none());
+        assert!(videohub("Smart Videohub", &[], txt("Videohub")).is_none());
+
+        let named = hubs(&[device]).unwrap();
+        assert_eq!(named[0].name, "Smart-Videohub-12G--Stage-");
+        assert_eq!(named[0].host, "10.0.0.20");
+        assert!(hubs(&[]).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Drift between the show plan and the device. With SHOW_FILE (or the snapshot STARTUP_POLICY
+//! applies) as the desired state, the device state is compared with it after every change it
+//! reports; the number of mismatching crosspoints, labels and locks is pulsed on `drift` and
+//! reported on `GET /health`, so dashboards show at a glance whether the router matches the plan.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::client::VideohubState;
+use crate::service::VideohubEvent;
+use crate::show::ShowFile;
+
+// Mismatches with the plan, by kind; `score` is their sum
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DriftScore {
+    pub score: u32,
+    pub routes: u32,
+    pub input_labels: u32,
+    pub output_labels: u32,
+    pub locks: u32,
+}
+
+// The plan and the score last reported
+#[derive(Debug)]
+pub struct DriftMonitor {
+    plan: ShowFile,
+    reported: Option<DriftScore>,
+}
+
+impl DriftMonitor {
+    pub fn new(plan: ShowFile) -> Self {
+        Self {
+            plan,
+            reported: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::new(ShowFile::load(path)?))
+    }
+
+    // Crosspoints, labels and locks the plan sets
+    pub fn planned(&self) -> u32 {
+        (self.plan.routes.len()
+            + self.plan.input_labels.len()
+            + self.plan.output_labels.len()
+            + self.plan.output_locks.len()) as u32
+    }
+
+    pub fn score(&self, state: &VideohubState) -> DriftScore {
+        let mut drift = DriftScore::default();
+        for difference in self.plan.diff(&ShowFile::from_state(state)) {
+            match difference.kind {
+                "route" => drift.routes += 1,
+                "input-label" => drift.input_labels += 1,
+                "output-label" => drift.output_labels += 1,
+                _ => drift.locks += 1,
+            }
+            drift.score += 1;
+        }
+        drift
+    }
+
+    // The drift, if it moved since the last report
+    pub fn changes(&mut self, state: &VideohubState) -> Option<VideohubEvent> {
+        let drift = self.score(state);
+        if self.reported == Some(drift) {
+            return None;
+        }
+        self.reported = Some(drift);
+        Some(VideohubEvent::Drift {
+            drift,
+            planned: self.planned(),
+        })
+    }
+
+    // Report the drift again after a reconnect, even if it did not move
+    pub fn reset(&mut self) {
+        self.reported = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn counts_mismatches_with_the_plan_and_reports_changes() {
+        let plan = ShowFile {
+            routes: BTreeMap::from([(1, 1), (2, 2)]),
+            input_labels: BTreeMap::from([(1, "CAM 1".to_string())]),
+            output_locks: BTreeMap::from([(1, true)]),
+            ..Default::default()
+        };
+        let mut monitor = DriftMonitor::new(plan);
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 0);
+        state.video_output_routing.insert(1, 5);
+        state.input_labels.insert(0, "CAM 1".to_string());
+
+        let Some(VideohubEvent::Drift { drift, planned }) = monitor.changes(&state) else {
+            panic!("no drift reported");
+        };
+        assert_eq!(planned, 4);
+        assert_eq!(
+            drift,
+            DriftScore {
+                score: 2,
+                routes: 1,
+                input_labels: 0,
+                output_labels: 0,
+                locks: 1,
+            }
+        );
+        assert!(monitor.changes(&state).is_none());
+
+        state.video_output_routing.insert(1, 1);
+        let Some(VideohubEvent::Drift { drift, .. }) = monitor.changes(&state) else {
+            panic!("no drift reported");
+        };
+        assert_eq!(drift.score, 1);
+    }
+}
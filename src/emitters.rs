@@ -1,5 +1,15 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::capabilities::PortCapabilities;
+use crate::metadata::Metadata;
+use crate::simulation::PredictedChange;
+use crate::staging::StagedChange;
+
+// Every emitter payload ends with the same three fields: `sequence`, the pulse number of its
+// emitter, increasing by one per pulse, and `timestamp` and `monotonic_ms`, when the event was
+// produced (ISO-8601, and on a monotonic clock).
 
 // DEVICE-LEVEL EMITTERS (for main device target - include output fields)
 
@@ -14,6 +24,12 @@ pub struct RouteChangedEmitter {
     pub output_label: Option<String>,
     // Optional input label
     pub input_label: Option<String>,
+    // Configured metadata of the output and input
+    pub output_metadata: Option<Metadata>,
+    pub input_metadata: Option<Metadata>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for device status
@@ -21,12 +37,17 @@ pub struct RouteChangedEmitter {
 pub struct DeviceStatusEmitter {
     // Whether the device is connected
     pub connected: bool,
+    // Whether the device keeps dropping connections right after accepting them
+    pub unstable: bool,
     // Device model name (if available)
     pub model_name: Option<String>,
     // Number of video inputs
     pub video_inputs: Option<u32>,
     // Number of video outputs
     pub video_outputs: Option<u32>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for label changes
@@ -38,6 +59,13 @@ pub struct LabelChangedEmitter {
     pub port: u32,
     // New label
     pub label: String,
+    // Label as requested, if the device shows it transliterated (LABEL_CHARSET)
+    pub original_label: Option<String>,
+    // False while the change is only expected, true once reported by the device
+    pub confirmed: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for output lock changes
@@ -49,6 +77,9 @@ pub struct OutputLockChangedEmitter {
     pub locked: bool,
     // Optional output label
     pub output_label: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for take mode changes
@@ -60,6 +91,9 @@ pub struct TakeModeChangedEmitter {
     pub enabled: bool,
     // Optional output label
     pub output_label: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for network interface status
@@ -77,6 +111,575 @@ pub struct NetworkInterfaceEmitter {
     pub current_gateway: Option<String>,
     // Whether using dynamic IP
     pub dynamic_ip: Option<bool>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for raw protocol blocks (only pulsed while protocol tracing is enabled)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProtocolTraceEmitter {
+    // Block direction ("sent" or "received")
+    pub direction: String,
+    // Raw protocol block text
+    pub block: String,
+    // Time the block was sent or received, in milliseconds since the UNIX epoch
+    pub timestamp_ms: u64,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the progress of a command issued by an rship action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ActionFeedbackEmitter {
+    // Action id (e.g. "set-route")
+    pub action: String,
+    // Identifies one invocation of the action
+    pub correlation_id: u64,
+    // "queued", "sent", "confirmed", "retrying" or "failed"
+    pub status: String,
+    // Reason for a failure
+    pub error: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the progress of an input sequence
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SequenceProgressEmitter {
+    pub output: u32,
+    // Position in the sequence (1-indexed)
+    pub step: u32,
+    pub total: u32,
+    pub input: u32,
+    // False once the sequence has finished or was stopped
+    pub running: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the progress of a test-signal sweep
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SweepProgressEmitter {
+    pub input: u32,
+    // Output showing the test input
+    pub output: u32,
+    // Position in the sweep (1-indexed)
+    pub step: u32,
+    pub total: u32,
+    // False once the sweep has finished or was stopped, with every route restored
+    pub running: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data describing the device as a whole, for asset management
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceInventoryEmitter {
+    pub model_name: Option<String>,
+    pub friendly_name: Option<String>,
+    pub unique_id: Option<String>,
+    // "true", "false" or "needs_update"
+    pub present: Option<String>,
+    pub protocol_version: Option<String>,
+    pub video_inputs: Option<u32>,
+    pub video_outputs: Option<u32>,
+    pub video_monitoring_outputs: Option<u32>,
+    pub video_processing_units: Option<u32>,
+    pub serial_ports: Option<u32>,
+    pub network_interfaces: Vec<NetworkInterfaceEmitter>,
+    // Optional features the device has reported, e.g. "take-mode", "output-locks"
+    pub capabilities: Vec<String>,
+    // Configured port metadata (1-indexed)
+    pub input_metadata: BTreeMap<u32, Metadata>,
+    pub output_metadata: BTreeMap<u32, Metadata>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the command-to-confirmation latency crossing its configured budget
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PerformanceDegradedEmitter {
+    // True when the p95 latency exceeds the budget, false once it is back within it
+    pub degraded: bool,
+    pub p95_ms: u64,
+    pub budget_ms: u64,
+    // Number of recent confirmations the p95 was computed over
+    pub samples: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a setting of the device's CONFIGURATION block
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceConfigurationEmitter {
+    // Setting name as reported by the device (e.g. "Take Mode")
+    pub setting: String,
+    pub value: String,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a command the device rejected with a NAK
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandRejectedEmitter {
+    // Kind of command the NAK most likely refers to: "route", "input-label", "output-label",
+    // "lock", "take-mode", "raw", "ping", "other" or "unknown"
+    pub category: String,
+    // NAKs received for this category since the service started
+    pub rejected_total: u64,
+    // Optimistically emitted changes that were rolled back
+    pub rolled_back: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data answering get-port-info
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PortInfoEmitter {
+    // "input" or "output"
+    pub port_type: String,
+    pub port: u32,
+    pub label: Option<String>,
+    // Input currently routed to an output
+    pub routed_input: Option<u32>,
+    pub metadata: Option<Metadata>,
+    // Interface and SDI level, as far as the device reports them
+    pub capabilities: Option<PortCapabilities>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the rship server this executor is connected to
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RshipEndpointEmitter {
+    pub endpoint: String,
+    // 1 for the primary server, 2 for the first fallback, ...
+    pub priority: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the Videohub this executor controls, pulsed on start and after set-device-address
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceAddressEmitter {
+    pub host: String,
+    pub port: u16,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for bulk changes awaiting review, and what became of them
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StagedChangesEmitter {
+    // Operation that produced the changes, e.g. "import-csv"
+    pub source: String,
+    // "staged", "applied", "discarded" or "expired"
+    pub status: String,
+    pub changes: Vec<StagedChange>,
+    // Time left to apply the changes while staged
+    pub expires_in_ms: Option<u64>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a device restart noticed on reconnecting
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceRebootDetectedEmitter {
+    // Connection epoch, counting connections since the executor started
+    pub epoch: u64,
+    // How long the connection was down
+    pub disconnected_ms: u64,
+    // Uptime the device reports, on firmware that does
+    pub device_uptime_s: Option<u64>,
+    // What shows the restart, e.g. locks other controllers held being gone
+    pub evidence: Vec<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for an operation a crash interrupted, found in the journal on start
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InterruptedOperationEmitter {
+    // Action that issued the operation, or "internal"
+    pub source: String,
+    // When it was written to the device (ISO-8601)
+    pub written_at: String,
+    // Changes the device shows, and those it doesn't
+    pub applied: Vec<StagedChange>,
+    pub missing: Vec<StagedChange>,
+    // Whether the missing changes are being written now (COMPLETE_INTERRUPTED_OPERATIONS)
+    pub completed: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the routes preset for the next take
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PendingRoutesEmitter {
+    // "pending", "taken" or "cleared"
+    pub status: String,
+    pub routes: Vec<PendingRoute>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PendingRoute {
+    // 1-indexed output and input
+    pub output: u32,
+    pub input: u32,
+    pub input_label: Option<String>,
+}
+
+// Emitter data for the saved salvos, pulsed on connect and whenever one is saved or deleted
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SalvosEmitter {
+    pub salvos: Vec<SalvoSummary>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SalvoSummary {
+    pub name: String,
+    // Number of outputs the salvo routes
+    pub routes: u32,
+    // When the salvo was saved (ISO-8601)
+    pub saved_at: String,
+}
+
+// Emitter data for the progress of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TransactionStatusEmitter {
+    pub transaction: String,
+    // "open", "committing", "committed", "failed", "rolled-back" or "expired"
+    pub status: String,
+    // Number of changes in the transaction
+    pub changes: u32,
+    // Changes the device did not apply, when the commit failed and the rest was reverted
+    pub failed: Vec<StagedChange>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the progress of a batch run with run-batch
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchProgressEmitter {
+    pub batch: String,
+    // Position of the step in the batch (1-indexed), and the action it runs
+    pub step: u32,
+    pub total: u32,
+    pub action: String,
+    // "running" as each step is sent, then "completed", or "failed" at the step that failed
+    pub status: String,
+    pub error: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a failover applied because an input lost its signal
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AutoFailoverEmitter {
+    pub output: u32,
+    // Input reported without signal
+    pub primary: u32,
+    pub backup: u32,
+    // Reason the backup could not be routed
+    pub error: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a protocol block the executor does not understand, e.g. from newer firmware
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnknownBlockEmitter {
+    // Block header as sent by the device (e.g. "ALARM STATUS:")
+    pub header: String,
+    // First lines of the latest block with this header
+    pub sample: String,
+    // Blocks received with this header, and unknown blocks of any header, since the service started
+    pub count: u64,
+    pub total: u64,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the device's answer to a send-raw-block action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RawResponseEmitter {
+    // Header of the block sent
+    pub header: String,
+    // "ack", "nak", or "none" if the device did not answer in time
+    pub reply: String,
+    // Blocks the device sent back under the same header, as received
+    pub blocks: Vec<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data summarising the whole matrix for dashboards
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatrixSummaryEmitter {
+    // Outputs locked by this or another client
+    pub locked_outputs: u32,
+    // Outputs with take mode enabled
+    pub take_mode_outputs: u32,
+    // When a route, lock or take mode last changed (ISO-8601)
+    pub last_change: Option<String>,
+    // Up to five most recently changed outputs, most recent first
+    pub recent_outputs: Vec<u32>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data counting the changes over one CHANGE_SUMMARY_INTERVAL_MS window
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChangeSummaryEmitter {
+    // Length of the window
+    pub window_ms: u64,
+    pub route_changes: u64,
+    pub label_changes: u64,
+    pub lock_changes: u64,
+    // Up to five outputs with the most route and lock changes, busiest first
+    pub busiest_outputs: Vec<BusyOutput>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BusyOutput {
+    // 1-indexed output number
+    pub output: u32,
+    pub changes: u64,
+}
+
+// A Videohub announced on the LAN
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DiscoveredVideohub {
+    // mDNS instance name, e.g. "Smart Videohub 12G 40x40 (Stage)"
+    pub name: String,
+    // Model and unique id from the announcement, if given
+    pub model: Option<String>,
+    pub unique_id: Option<String>,
+    // Address and control port to connect to
+    pub address: String,
+    pub port: u16,
+}
+
+// Emitter data for the Videohubs found by the last browse (VIDEOHUB_DISCOVERY)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiscoveredVideohubsEmitter {
+    pub devices: Vec<DiscoveredVideohub>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for a configured port count (VIDEO_INPUTS_OVERRIDE, VIDEO_OUTPUTS_OVERRIDE) that
+// differs from what the device reports
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PortCountMismatchEmitter {
+    // "input" or "output"
+    pub port_type: String,
+    // Count the device reports, if any
+    pub reported: Option<u32>,
+    // Count used instead
+    pub configured: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the watchdog probe going unanswered, and being answered again
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WatchdogEmitter {
+    // True while the device does not answer the probe, false once it does again
+    pub alarm: bool,
+    // "ping" or "route:<output>:<input>"
+    pub probe: String,
+    // How long the probe had gone unanswered when the alarm was raised
+    pub unanswered_ms: Option<u64>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for this executor taking over or handing back control of the device
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LeadershipChangedEmitter {
+    // True if this executor now sends commands, false if it only observes
+    pub leader: bool,
+    // "leader" or "standby"
+    pub role: String,
+    // Whether the other executor of the pair is sending heartbeats
+    pub peer_alive: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the two executors of a redundant pair disagreeing about the device state
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StateDivergenceEmitter {
+    // True while the observed states differ, false once they match again
+    pub alarm: bool,
+    // Checksums of the state observed by this executor and by its peer (hex)
+    pub local_checksum: String,
+    pub peer_checksum: String,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the drift between the device and the show plan (SHOW_FILE)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DriftEmitter {
+    // Mismatching crosspoints, labels and locks in all, and by kind
+    pub score: u32,
+    pub routes: u32,
+    pub input_labels: u32,
+    pub output_labels: u32,
+    pub locks: u32,
+    // Crosspoints, labels and locks the plan sets
+    pub planned: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data comparing the device with the routes a legacy controller intends (shadow mode)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IntentDivergenceEmitter {
+    // True once every intended output is routed as intended
+    pub aligned: bool,
+    // Outputs the intent covers
+    pub intended_outputs: u32,
+    pub diverged_outputs: Vec<DivergedOutput>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DivergedOutput {
+    // 1-indexed output and inputs
+    pub output: u32,
+    pub expected_input: u32,
+    // None if the device hasn't reported the output's route
+    pub actual_input: Option<u32>,
+}
+
+// Emitter data for the profile in effect, pulsed whenever it is switched
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileEmitter {
+    pub profile: String,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the predicted effect of a simulate-command action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SimulationResultEmitter {
+    // Action that was simulated (e.g. "set-route")
+    pub action: String,
+    // Invocation of simulate-command this answers
+    pub correlation_id: Option<u64>,
+    // Whether the action would be sent and accepted, and if not, why
+    pub allowed: bool,
+    pub blocked_by: Vec<String>,
+    pub changes: Vec<PredictedChange>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for an action rejected because the output belongs to another owner
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OwnershipViolationEmitter {
+    pub action: String,
+    pub correlation_id: u64,
+    // Owner named by the action, if any
+    pub owner: Option<String>,
+    pub output: u32,
+    pub output_owner: String,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the output subtargets registered with rship, pulsed after each registration pass
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RegistrationStatusEmitter {
+    // False while some subtargets could not be registered
+    pub complete: bool,
+    pub registered: u32,
+    // Subtargets still missing, e.g. "output 12"; registered again once rship reconnects
+    pub missing: Vec<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FreezeViolationEmitter {
+    // Action that issued the command, None for the executor's own commands
+    pub action: Option<String>,
+    pub correlation_id: Option<u64>,
+    pub output: u32,
+    pub frozen_input: u32,
+    // Input the command tried to route instead
+    pub input: u32,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for the operator claim of the device
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ControlClaimEmitter {
+    pub claimed: bool,
+    pub operator: Option<String>,
+    pub expires_in_ms: Option<u64>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for outputs handed over to another owner
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OwnershipChangedEmitter {
+    pub outputs: Vec<u32>,
+    pub to_owner: String,
+    // Previous owner per output; outputs that had none are left out
+    pub previous_owners: BTreeMap<u32, String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // OUTPUT-LEVEL EMITTERS (for output subtargets - NO output fields, output is implicit)
@@ -88,6 +691,21 @@ pub struct InputChangedEmitter {
     pub input: u32,
     // Optional input label
     pub input_label: Option<String>,
+    // Name to show for the input: the `alias` in its metadata, otherwise its label
+    pub input_alias: Option<String>,
+    // The `group` in the input's metadata, e.g. "Cameras"
+    pub input_group: Option<String>,
+    // Configured metadata of the input
+    pub input_metadata: Option<Metadata>,
+    // False while the change is only expected, true once reported by the device
+    pub confirmed: bool,
+    // Origin of the last route change on this output (action name, "internal" or "device") and
+    // when it was confirmed (ISO-8601); unknown for routes set before the executor connected
+    pub changed_by: Option<String>,
+    pub changed_at: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for lock changes on this output (output is implicit from target)
@@ -95,6 +713,9 @@ pub struct InputChangedEmitter {
 pub struct LockChangedEmitter {
     // Whether the output is locked
     pub locked: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
 
 // Emitter data for take mode changes on this output (output is implicit from target)
@@ -102,4 +723,40 @@ pub struct LockChangedEmitter {
 pub struct TakeModeOnThisOutputEmitter {
     // Whether take mode is enabled
     pub enabled: bool,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// Emitter data for everything about this output in one payload, pulsed when any of it changes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OutputStateEmitter {
+    // 1-indexed input routed to the output, and its label
+    pub input: Option<u32>,
+    pub input_label: Option<String>,
+    pub label: Option<String>,
+    // "unlocked", "owned" (locked by this executor) or "locked" (by another controller)
+    pub lock: String,
+    pub take_mode: bool,
+    // 1-indexed input preset for the next take
+    pub pending_input: Option<u32>,
+    // Protections: a route hold, a frozen input and the owner from OWNERSHIP_FILE
+    pub held: bool,
+    pub frozen: bool,
+    pub owner: Option<String>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
+}
+
+// INPUT-LEVEL EMITTERS (for input subtargets - input is implicit from target)
+
+// Emitter data for the outputs this input feeds, pulsed whenever one of them changes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoutedOutputsEmitter {
+    // Outputs currently showing this input (1-indexed), lowest first
+    pub outputs: Vec<u32>,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub monotonic_ms: u64,
 }
@@ -0,0 +1,171 @@
+//! Per-output failover from a primary input to a backup input
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Failover file; ports are 1-indexed like the rship actions
+#[derive(Debug, Clone, Deserialize)]
+struct FailoverFile {
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RuleEntry {
+    // Name for set-rule-enabled; "failover-<output>" if not given
+    id: Option<String>,
+    #[serde(flatten)]
+    rule: FailoverRule,
+}
+
+// Switch `output` from `primary` to `backup` when failover is triggered
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FailoverRule {
+    pub output: u32,
+    pub primary: u32,
+    pub backup: u32,
+}
+
+// Configured rules and the outputs currently failed over (ports are 0-indexed in memory)
+#[derive(Debug, Default)]
+pub struct Failover {
+    rules: Vec<FailoverRule>,
+    // Id of each rule; several rules may share one
+    ids: Vec<String>,
+    // output -> rule that was applied
+    active: HashMap<u32, FailoverRule>,
+    // Rules that don't fail over automatically, and when they do again
+    disabled: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+impl Failover {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read failover file {}", path.display()))?;
+        let file: FailoverFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse failover file {}", path.display()))?;
+        log::info!("Loaded {} failover rules", file.rules.len());
+
+        let (ids, rules) = file
+            .rules
+            .into_iter()
+            .map(|entry| {
+                let rule = entry.rule;
+                let id = entry
+                    .id
+                    .unwrap_or_else(|| format!("failover-{}", rule.output));
+                let rule = FailoverRule {
+                    output: rule.output.clamp(1, u32::MAX) - 1,
+                    primary: rule.primary.clamp(1, u32::MAX) - 1,
+                    backup: rule.backup.clamp(1, u32::MAX) - 1,
+                };
+                (id, rule)
+            })
+            .unzip();
+        Ok(Self {
+            rules,
+            ids,
+            active: HashMap::new(),
+            disabled: HashMap::new(),
+        })
+    }
+
+    // Suspend or resume automatic failover for the rules with an id; a suspended rule can still
+    // be triggered with trigger-failover. `until` resumes it at that time.
+    pub fn set_enabled(
+        &mut self,
+        id: &str,
+        enabled: bool,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        if !self.ids.iter().any(|rule_id| rule_id == id) {
+            bail!("no failover rule {id}");
+        }
+        match (enabled, until) {
+            (true, Some(_)) => bail!("until only applies when disabling a rule"),
+            (true, None) => {
+                self.disabled.remove(id);
+            }
+            (false, Some(until)) if until <= Utc::now() => bail!("until {until} has passed"),
+            (false, until) => {
+                self.disabled.insert(id.to_string(), until);
+            }
+        }
+        Ok(())
+    }
+
+    // Resume rules whose suspension has run out; returns their ids
+    pub fn take_reenabled(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let reenabled: Vec<String> = self
+            .disabled
+            .iter()
+            .filter(|(_, until)| until.is_some_and(|until| until <= now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &reenabled {
+            self.disabled.remove(id);
+        }
+        reenabled
+    }
+
+    // Rule to apply to an output currently showing `current_input`.
+    // Returns None if the output is already failed over.
+    pub fn rule_for(
+        &self,
+        output: u32,
+        current_input: Option<u32>,
+    ) -> Result<Option<FailoverRule>> {
+        if self.active.contains_key(&output) {
+            return Ok(None);
+        }
+
+        let mut rules = self.rules.iter().filter(|rule| rule.output == output);
+        let Some(first) = rules.next() else {
+            bail!("no failover configured for output {}", output + 1);
+        };
+        // With several primaries per output, fail over whichever one is on air
+        let rule = std::iter::once(first)
+            .chain(rules)
+            .find(|rule| Some(rule.primary) == current_input)
+            .unwrap_or(first);
+        Ok(Some(*rule))
+    }
+
+    // Rules applying to outputs currently showing an input that has lost its signal
+    pub fn rules_for_lost_input(
+        &self,
+        input: u32,
+        routes: &HashMap<u32, u32>,
+    ) -> Vec<FailoverRule> {
+        self.rules
+            .iter()
+            .zip(&self.ids)
+            .filter(|(_, id)| !self.disabled.contains_key(*id))
+            .map(|(rule, _)| rule)
+            .filter(|rule| rule.primary == input)
+            .filter(|rule| routes.get(&rule.output) == Some(&input))
+            .filter(|rule| !self.active.contains_key(&rule.output))
+            .copied()
+            .collect()
+    }
+
+    // Rule applied to a failed over output
+    pub fn active_rule(&self, output: u32) -> Result<FailoverRule> {
+        match self.active.get(&output) {
+            Some(rule) => Ok(*rule),
+            None => bail!("output {} is not failed over", output + 1),
+        }
+    }
+
+    // Record that a rule's backup is on air, so it can be reverted
+    pub fn activate(&mut self, rule: FailoverRule) {
+        self.active.insert(rule.output, rule);
+    }
+
+    pub fn deactivate(&mut self, output: u32) {
+        self.active.remove(&output);
+    }
+}
@@ -0,0 +1,104 @@
+//! Per-emitter filtering for constrained deployments. Emitters nobody looks at can be turned off
+//! and chatty ones sampled, so they don't use up the rship history. Applied in the emission task
+//! just before the pulse, after the QoS queues.
+
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+
+// The output state, the subtarget registration and the active profile are always pulsed
+pub const UNFILTERED: [&str; 8] = [
+    "input-changed",
+    "output-state",
+    "label-changed",
+    "lock-changed",
+    "take-mode-changed",
+    "registration-status",
+    "rship-endpoint",
+    "profile",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitterFilter {
+    Off,
+    // At most one pulse per interval; the pulses in between are dropped
+    Sample(Duration),
+}
+
+impl EmitterFilter {
+    // "off", or the sampling interval in milliseconds
+    pub fn parse(filter: &str) -> Option<Self> {
+        match filter.trim() {
+            "off" => Some(Self::Off),
+            ms => ms
+                .parse()
+                .ok()
+                .filter(|ms| *ms > 0)
+                .map(|ms| Self::Sample(Duration::from_millis(ms))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EmitterFilters {
+    filters: HashMap<String, EmitterFilter>,
+    last_pulse: HashMap<String, Instant>,
+}
+
+impl EmitterFilters {
+    pub fn new(mut filters: HashMap<String, EmitterFilter>) -> Self {
+        filters.retain(|emitter, _| {
+            let filterable = !UNFILTERED.contains(&emitter.as_str());
+            if !filterable {
+                log::warn!("The {emitter} emitter is always pulsed; ignoring its filter");
+            }
+            filterable
+        });
+        Self {
+            filters,
+            last_pulse: HashMap::new(),
+        }
+    }
+
+    // Whether a pulse of the emitter goes out now
+    pub fn allows(&mut self, emitter: &str) -> bool {
+        match self.filters.get(emitter) {
+            None => true,
+            Some(EmitterFilter::Off) => false,
+            Some(EmitterFilter::Sample(interval)) => {
+                let now = Instant::now();
+                if let Some(last) = self.last_pulse.get(emitter)
+                    && now.duration_since(*last) < *interval
+                {
+                    return false;
+                }
+                self.last_pulse.insert(emitter.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_disabled_and_oversampled_pulses() {
+        let filters = [
+            ("network-interface", "off"),
+            ("matrix-summary", "60000"),
+            ("input-changed", "off"),
+        ]
+        .into_iter()
+        .map(|(emitter, filter)| (emitter.to_string(), EmitterFilter::parse(filter).unwrap()))
+        .collect();
+        let mut filters = EmitterFilters::new(filters);
+
+        assert!(!filters.allows("network-interface"));
+        assert!(filters.allows("matrix-summary"));
+        assert!(!filters.allows("matrix-summary"));
+        assert!(filters.allows("input-changed"));
+        assert!(filters.allows("device-status"));
+        assert_eq!(EmitterFilter::parse("0"), None);
+    }
+}
@@ -0,0 +1,74 @@
+//! Reconnect back-off for devices that accept connections and then drop them right away
+//! (common during firmware updates)
+
+use tokio::time::{Duration, Instant};
+
+// Delay between reconnect attempts while the device is stable
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+pub const DEFAULT_FLAP_THRESHOLD: u32 = 3;
+pub const DEFAULT_STABILITY_WINDOW: Duration = Duration::from_secs(60);
+pub const DEFAULT_MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub struct FlapDetector {
+    // Consecutive short-lived connections before the device counts as unstable
+    threshold: u32,
+    // How long a connection must last to count as stable again
+    stability_window: Duration,
+    max_delay: Duration,
+    connected_at: Option<Instant>,
+    flaps: u32,
+}
+
+impl FlapDetector {
+    pub fn new(threshold: u32, stability_window: Duration, max_delay: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            stability_window,
+            max_delay,
+            connected_at: None,
+            flaps: 0,
+        }
+    }
+
+    pub fn connected(&mut self) {
+        self.connected_at = Some(Instant::now());
+    }
+
+    // The connection was lost; returns how long to wait before reconnecting
+    pub fn disconnected(&mut self) -> Duration {
+        match self.connected_at.take() {
+            Some(since) if since.elapsed() >= self.stability_window => self.flaps = 0,
+            Some(_) => self.flaps += 1,
+            // A failed connection attempt keeps the current cadence
+            None => {}
+        }
+        self.reconnect_delay()
+    }
+
+    pub fn is_unstable(&self) -> bool {
+        self.flaps >= self.threshold
+    }
+
+    // Called periodically; returns true once an unstable device has stayed connected
+    // for the stability window
+    pub fn recovered(&mut self) -> bool {
+        let stable = self
+            .connected_at
+            .is_some_and(|since| since.elapsed() >= self.stability_window);
+        if self.is_unstable() && stable {
+            self.flaps = 0;
+            return true;
+        }
+        false
+    }
+
+    // Doubles with every flap past the threshold
+    fn reconnect_delay(&self) -> Duration {
+        if !self.is_unstable() {
+            return RECONNECT_DELAY;
+        }
+        let doublings = (self.flaps - self.threshold + 1).min(16);
+        (RECONNECT_DELAY * 2u32.pow(doublings)).min(self.max_delay)
+    }
+}
@@ -0,0 +1,125 @@
+//! Executor-side input freezes. The protocol only locks outputs; freezing an input protects the
+//! outputs it currently feeds, e.g. a live program source during transmission, by rejecting any
+//! command that would route a different input onto them. Like ownership, this only holds for
+//! commands that go through this executor.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::pending::ExpectedChange;
+use crate::service::VideohubCommand;
+
+// A route that would replace a frozen input
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreezeViolation {
+    pub output: u32,
+    pub frozen_input: u32,
+    pub input: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct InputFreezes {
+    frozen: BTreeSet<u32>,
+}
+
+impl InputFreezes {
+    // Returns false if the input was already frozen
+    pub fn freeze(&mut self, input: u32) -> bool {
+        self.frozen.insert(input)
+    }
+
+    // Returns false if the input was not frozen
+    pub fn unfreeze(&mut self, input: u32) -> bool {
+        self.frozen.remove(&input)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frozen.is_empty()
+    }
+
+    pub fn is_frozen(&self, input: u32) -> bool {
+        self.frozen.contains(&input)
+    }
+
+    // Outputs currently fed by the input, which a freeze protects
+    pub fn protected_outputs(input: u32, routing: &HashMap<u32, u32>) -> BTreeSet<u32> {
+        routing
+            .iter()
+            .filter(|(_, routed)| **routed == input)
+            .map(|(output, _)| *output)
+            .collect()
+    }
+
+    // The first route of the command that would take a frozen input off an output
+    pub fn check(
+        &self,
+        command: &VideohubCommand,
+        routing: &HashMap<u32, u32>,
+    ) -> Result<(), FreezeViolation> {
+        let routes: Vec<ExpectedChange> = match command {
+            VideohubCommand::WriteChanges { changes } => changes.clone(),
+            command => command.expected_change().into_iter().collect(),
+        };
+        for route in routes {
+            let ExpectedChange::Route { output, input } = route else {
+                continue;
+            };
+            if let Some(&frozen_input) = routing.get(&output)
+                && frozen_input != input
+                && self.frozen.contains(&frozen_input)
+            {
+                return Err(FreezeViolation {
+                    output,
+                    frozen_input,
+                    input,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protects_the_outputs_of_a_frozen_input() {
+        let routing = HashMap::from([(0, 5), (1, 5), (2, 3)]);
+        let mut freezes = InputFreezes::default();
+        assert!(freezes.freeze(5));
+        assert_eq!(
+            InputFreezes::protected_outputs(5, &routing),
+            BTreeSet::from([0, 1])
+        );
+
+        let route = |output, input| VideohubCommand::Route { output, input };
+        assert_eq!(
+            freezes.check(&route(1, 2), &routing),
+            Err(FreezeViolation {
+                output: 1,
+                frozen_input: 5,
+                input: 2
+            })
+        );
+        // Re-taking the frozen input, or routing it somewhere else, is fine
+        assert_eq!(freezes.check(&route(1, 5), &routing), Ok(()));
+        assert_eq!(freezes.check(&route(2, 5), &routing), Ok(()));
+
+        let bulk = VideohubCommand::WriteChanges {
+            changes: vec![
+                ExpectedChange::Route {
+                    output: 2,
+                    input: 0,
+                },
+                ExpectedChange::Route {
+                    output: 0,
+                    input: 0,
+                },
+            ],
+        };
+        assert!(freezes.check(&bulk, &routing).is_err());
+
+        assert!(freezes.unfreeze(5));
+        assert_eq!(freezes.check(&bulk, &routing), Ok(()));
+    }
+}
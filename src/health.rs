@@ -0,0 +1,188 @@
+//! Live health of the service for `GET /health`: how full the channels between the tasks are,
+//! how late the state task runs its periodic work, and when each long-running task last showed
+//! progress. Backpressure and stuck tasks show up here before they affect the show.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::bus::SourceStatistics;
+use crate::drift::DriftScore;
+
+// A task that has been busy this long without a heartbeat is reported as stuck
+pub const STUCK_AFTER: Duration = Duration::from_secs(10);
+
+type Depth = Box<dyn Fn() -> Option<ChannelDepth> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChannelDepth {
+    pub queued: usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskHealth {
+    pub heartbeat_age_ms: u64,
+    // Waiting for work (or for the next reconnect attempt), so an old heartbeat is expected
+    pub idle: bool,
+    pub stuck: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    // False while any task is stuck
+    pub healthy: bool,
+    pub channels: BTreeMap<&'static str, ChannelDepth>,
+    // Pulses in the emission task's QoS queues, waiting for rship
+    pub pulses_queued: usize,
+    // How late the state task ran its last periodic tick, and the worst so far
+    pub event_loop_lag_ms: u64,
+    pub max_event_loop_lag_ms: u64,
+    pub tasks: BTreeMap<&'static str, TaskHealth>,
+    // Commands per source sharing the device session
+    pub command_sources: BTreeMap<&'static str, SourceStatistics>,
+    // Mismatches with the show plan; None without one
+    pub drift: Option<DriftScore>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Heartbeat {
+    at: Instant,
+    idle: bool,
+}
+
+#[derive(Default)]
+pub struct Health {
+    channels: Mutex<Vec<(&'static str, Depth)>>,
+    heartbeats: Mutex<BTreeMap<&'static str, Heartbeat>>,
+    // Latest and largest tick lag
+    lag: Mutex<(Duration, Duration)>,
+    pulses_queued: AtomicUsize,
+    command_sources: Mutex<BTreeMap<&'static str, SourceStatistics>>,
+    drift: Mutex<Option<DriftScore>>,
+}
+
+impl Health {
+    // Report the occupancy of a channel; it is not kept open by this
+    pub fn watch_channel<T: Send + 'static>(&self, name: &'static str, sender: &mpsc::Sender<T>) {
+        let sender = sender.downgrade();
+        let depth: Depth = Box::new(move || {
+            let sender = sender.upgrade()?;
+            Some(ChannelDepth {
+                queued: sender.max_capacity() - sender.capacity(),
+                capacity: sender.max_capacity(),
+            })
+        });
+        lock(&self.channels).push((name, depth));
+    }
+
+    // The task made progress
+    pub fn beat(&self, task: &'static str) {
+        self.record(task, false);
+    }
+
+    // The task is about to wait for work
+    pub fn idle(&self, task: &'static str) {
+        self.record(task, true);
+    }
+
+    fn record(&self, task: &'static str, idle: bool) {
+        let heartbeat = Heartbeat {
+            at: Instant::now(),
+            idle,
+        };
+        lock(&self.heartbeats).insert(task, heartbeat);
+    }
+
+    // A periodic tick of the state task ran this late
+    pub fn event_loop_lag(&self, lag: Duration) {
+        let mut recorded = lock(&self.lag);
+        *recorded = (lag, recorded.1.max(lag));
+    }
+
+    pub fn set_pulses_queued(&self, queued: usize) {
+        self.pulses_queued.store(queued, Ordering::Relaxed);
+    }
+
+    pub fn set_command_sources(&self, sources: BTreeMap<&'static str, SourceStatistics>) {
+        *lock(&self.command_sources) = sources;
+    }
+
+    pub fn set_drift(&self, drift: DriftScore) {
+        *lock(&self.drift) = Some(drift);
+    }
+
+    pub fn report(&self) -> HealthReport {
+        let channels = lock(&self.channels)
+            .iter()
+            .filter_map(|(name, depth)| Some((*name, depth()?)))
+            .collect();
+        let tasks: BTreeMap<_, _> = lock(&self.heartbeats)
+            .iter()
+            .map(|(task, heartbeat)| {
+                let age = heartbeat.at.elapsed();
+                let health = TaskHealth {
+                    heartbeat_age_ms: age.as_millis() as u64,
+                    idle: heartbeat.idle,
+                    stuck: !heartbeat.idle && age >= STUCK_AFTER,
+                };
+                (*task, health)
+            })
+            .collect();
+        let (lag, max_lag) = *lock(&self.lag);
+        HealthReport {
+            healthy: tasks.values().all(|task| !task.stuck),
+            channels,
+            pulses_queued: self.pulses_queued.load(Ordering::Relaxed),
+            event_loop_lag_ms: lag.as_millis() as u64,
+            max_event_loop_lag_ms: max_lag.as_millis() as u64,
+            tasks,
+            command_sources: lock(&self.command_sources).clone(),
+            drift: *lock(&self.drift),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_channel_depths_and_busy_tasks() {
+        let health = Health::default();
+        let (tx, _rx) = mpsc::channel::<u32>(4);
+        health.watch_channel("commands", &tx);
+        tx.send(1).await.unwrap();
+        health.beat("state");
+        health.idle("emission");
+        health.event_loop_lag(Duration::from_millis(30));
+        health.event_loop_lag(Duration::from_millis(5));
+
+        let report = health.report();
+        assert!(report.healthy);
+        assert_eq!(
+            report.channels["commands"],
+            ChannelDepth {
+                queued: 1,
+                capacity: 4
+            }
+        );
+        assert_eq!(report.event_loop_lag_ms, 5);
+        assert_eq!(report.max_event_loop_lag_ms, 30);
+        assert!(!report.tasks["state"].idle);
+        assert!(report.tasks["emission"].idle);
+
+        // A closed channel is left out
+        drop(tx);
+        assert!(health.report().channels.is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+//! External commands run when selected events occur
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::time::{Duration, timeout};
+
+use crate::observer::VideohubObserver;
+use crate::service::VideohubEvent;
+
+fn default_max_concurrent() -> usize {
+    4
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+// Hook configuration file
+#[derive(Debug, Clone, Deserialize)]
+pub struct HooksConfig {
+    // Maximum number of hook commands running at the same time; further events wait
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    // Commands still running after this long are killed
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+// A command run for one kind of event
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hook {
+    // "device-connected", "device-disconnected", "route-changed", "label-changed" or "lock-changed"
+    pub event: String,
+    // Only run for these outputs (1-indexed); all outputs if omitted
+    pub outputs: Option<Vec<u32>>,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+// Runs configured hooks with the event JSON on stdin
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    hooks: Arc<Vec<Hook>>,
+    permits: Arc<Semaphore>,
+    timeout: Duration,
+}
+
+impl HookRunner {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read hooks file {}", path.display()))?;
+        let config: HooksConfig = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse hooks file {}", path.display()))?;
+        log::info!("Loaded {} event hooks", config.hooks.len());
+
+        Ok(Self {
+            hooks: Arc::new(config.hooks),
+            permits: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+}
+
+impl VideohubObserver for HookRunner {
+    // Start the hooks matching an event without waiting for them
+    fn on_event(&self, event: &VideohubEvent) {
+        let Some((name, output, payload)) = describe_event(event) else {
+            return;
+        };
+
+        for hook in self.hooks.iter().filter(|hook| hook.event == name) {
+            if let (Some(outputs), Some(output)) = (&hook.outputs, output)
+                && !outputs.contains(&output)
+            {
+                continue;
+            }
+
+            let hook = hook.clone();
+            let payload = payload.clone();
+            let permits = self.permits.clone();
+            let hook_timeout = self.timeout;
+            tokio::spawn(async move {
+                let Ok(_permit) = permits.acquire_owned().await else {
+                    return;
+                };
+                match timeout(hook_timeout, run_hook(&hook, &payload)).await {
+                    Ok(Ok(())) => log::debug!("Hook {} for {} finished", hook.command, hook.event),
+                    Ok(Err(e)) => {
+                        log::error!("Hook {} for {} failed: {e}", hook.command, hook.event)
+                    }
+                    Err(_) => log::error!(
+                        "Hook {} for {} timed out after {hook_timeout:?}",
+                        hook.command,
+                        hook.event
+                    ),
+                }
+            });
+        }
+    }
+}
+
+// Spawn the hook command and feed it the event
+async fn run_hook(hook: &Hook, payload: &str) -> Result<()> {
+    let mut child = Command::new(&hook.command)
+        .args(&hook.args)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", hook.command))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.as_bytes()).await?;
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        anyhow::bail!("exited with {status}");
+    }
+    Ok(())
+}
+
+// Hook event name, affected output (1-indexed) and JSON payload for events that can trigger hooks.
+// Optimistic (unconfirmed) changes never trigger hooks.
+fn describe_event(event: &VideohubEvent) -> Option<(&'static str, Option<u32>, String)> {
+    let (name, output, mut payload) = match event {
+        VideohubEvent::DeviceStatus {
+            connected,
+            model_name,
+            ..
+        } => (
+            if *connected {
+                "device-connected"
+            } else {
+                "device-disconnected"
+            },
+            None,
+            serde_json::json!({ "connected": connected, "model_name": model_name }),
+        ),
+        VideohubEvent::Route {
+            output,
+            input,
+            input_label,
+            confirmed: true,
+            ..
+        } => (
+            "route-changed",
+            Some(output + 1),
+            serde_json::json!({ "output": output + 1, "input": input + 1, "input_label": input_label }),
+        ),
+        VideohubEvent::Label {
+            port_type,
+            port,
+            label,
+            confirmed: true,
+            ..
+        } => (
+            "label-changed",
+            (port_type == "output").then_some(port + 1),
+            serde_json::json!({ "port_type": port_type, "port": port + 1, "label": label }),
+        ),
+        VideohubEvent::OutputLock { output, locked } => (
+            "lock-changed",
+            Some(output + 1),
+            serde_json::json!({ "output": output + 1, "locked": locked }),
+        ),
+        _ => return None,
+    };
+
+    payload["event"] = serde_json::Value::from(name);
+    Some((name, output, payload.to_string()))
+}
@@ -0,0 +1,166 @@
+//! Several Videohubs controlled from one process (VIDEOHUBS). Each hub runs as its own service
+//! with its own rship instance, device connection, reconnects and state, as if one copy of the
+//! executor ran per router.
+
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+
+use crate::config::ServiceConfig;
+
+// Port of the Videohub control protocol
+pub const DEFAULT_VIDEOHUB_PORT: u16 = 9990;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hub {
+    // Names the hub's rship instance; letters, digits, '-' and '_'
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Hub {
+    // Comma-separated "name=host" or "name=host:port" entries,
+    // e.g. "stage=10.0.0.20,truck=10.0.0.21:9990"
+    pub fn parse_list(list: &str) -> Result<Vec<Hub>> {
+        let mut entries = Vec::new();
+        for entry in list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let Some((name, address)) = entry.split_once('=') else {
+                bail!("\"{entry}\" is not name=host[:port]");
+            };
+            let (host, port) = match address.trim().rsplit_once(':') {
+                Some((host, port)) => match port.parse() {
+                    Ok(port) => (host, port),
+                    Err(_) => bail!("\"{port}\" is not a valid port for hub {}", name.trim()),
+                },
+                None => (address.trim(), DEFAULT_VIDEOHUB_PORT),
+            };
+            entries.push(Hub {
+                name: name.trim().to_string(),
+                host: host.to_string(),
+                port,
+            });
+        }
+        Self::checked(entries)
+    }
+
+    // The hubs, if there are any and each has a valid, unique name and a host
+    pub fn checked(hubs: Vec<Hub>) -> Result<Vec<Hub>> {
+        for (index, hub) in hubs.iter().enumerate() {
+            let name = &hub.name;
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                bail!("\"{name}\" is not a valid hub name (letters, digits, '-' and '_')");
+            }
+            if hubs[..index].iter().any(|other| other.name == *name) {
+                bail!("the hub name {name} is used twice");
+            }
+            if hub.host.is_empty() {
+                bail!("hub {name} has no host");
+            }
+        }
+        if hubs.is_empty() {
+            bail!("no hubs are listed");
+        }
+        Ok(hubs)
+    }
+
+    // Settings that cannot be shared by several hubs. An active/standby pair elects one leader
+    // per process, which cannot stand for the leadership of each hub.
+    pub fn check_config(config: &ServiceConfig) -> Result<()> {
+        if config.redundancy_peer.is_some() {
+            bail!("REDUNDANCY_PEER cannot be combined with several hubs; run one executor per hub");
+        }
+        Ok(())
+    }
+
+    // The settings of this hub. Files the service reads and writes get the hub name, so hubs keep
+    // their labels, ownership, usage, journal and salvos apart; the control listeners, discovery
+    // and the status line belong to the process and stay with the first hub.
+    pub fn config(&self, config: &ServiceConfig, first: bool) -> ServiceConfig {
+        let per_hub = |path: &Option<PathBuf>| path.as_deref().map(|path| self.file(path));
+        let mut config = ServiceConfig {
+            hub_name: Some(self.name.clone()),
+            labels_file: per_hub(&config.labels_file),
+            ownership_file: per_hub(&config.ownership_file),
+            usage_file: per_hub(&config.usage_file),
+            journal_file: per_hub(&config.journal_file),
+            salvos_file: per_hub(&config.salvos_file),
+            ..config.clone()
+        };
+        if !first {
+            config.control_http_port = None;
+            config.control_osc_port = None;
+            config.videohub_discovery = None;
+            config.status_line = false;
+        }
+        config
+    }
+
+    // "labels.json" becomes "labels-stage.json"
+    fn file(&self, path: &Path) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(extension) => format!("{stem}-{}.{}", self.name, extension.to_string_lossy()),
+            None => format!("{stem}-{}", self.name),
+        };
+        path.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hubs_and_keeps_their_files_apart() {
+        let hubs = Hub::parse_list("stage=10.0.0.20, truck=10.0.0.21:9991").unwrap();
+        assert_eq!(
+            hubs,
+            vec![
+                Hub {
+                    name: "stage".into(),
+                    host: "10.0.0.20".into(),
+                    port: DEFAULT_VIDEOHUB_PORT
+                },
+                Hub {
+                    name: "truck".into(),
+                    host: "10.0.0.21".into(),
+                    port: 9991
+                },
+            ]
+        );
+        assert!(Hub::parse_list("stage=10.0.0.20,stage=10.0.0.21").is_err());
+        assert!(Hub::parse_list("main stage=10.0.0.20").is_err());
+        assert!(Hub::parse_list("10.0.0.20").is_err());
+
+        let config = ServiceConfig {
+            labels_file: Some("/var/lib/videohub/labels.json".into()),
+            ownership_file: Some("ownership.json".into()),
+            control_http_port: Some(8080),
+            ..Default::default()
+        };
+        assert!(Hub::check_config(&config).is_ok());
+        let truck = hubs[1].config(&config, false);
+        assert_eq!(truck.hub_name.as_deref(), Some("truck"));
+        assert_eq!(
+            truck.labels_file,
+            Some("/var/lib/videohub/labels-truck.json".into())
+        );
+        assert_eq!(truck.ownership_file, Some("ownership-truck.json".into()));
+        assert_eq!(truck.control_http_port, None);
+        assert_eq!(hubs[0].config(&config, true).control_http_port, Some(8080));
+
+        let config = ServiceConfig {
+            redundancy_peer: Some("10.0.0.31:9910".into()),
+            ..config
+        };
+        assert!(Hub::check_config(&config).is_err());
+    }
+}
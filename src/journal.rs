@@ -0,0 +1,158 @@
+//! Journal of changes written to the device but not yet confirmed by it, so an operation cut
+//! short by a crash (e.g. halfway through a salvo) is noticed on the next start instead of
+//! leaving the router in an intermediate state silently
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::time::Instant;
+
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+use crate::pending::{ExpectedChange, PENDING_CHANGE_TIMEOUT};
+use crate::staging::StagedChange;
+
+// One command or salvo as it was sent (ports are 1-indexed like the rship actions)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // Action that issued the changes, or "internal"
+    pub source: String,
+    pub written_at: String,
+    pub changes: Vec<StagedChange>,
+    #[serde(skip, default = "Instant::now")]
+    recorded: Instant,
+}
+
+impl JournalEntry {
+    // Split the changes into those the device state shows and those it doesn't
+    pub fn check(&self, state: &VideohubState) -> (Vec<ExpectedChange>, Vec<ExpectedChange>) {
+        self.changes
+            .iter()
+            .filter_map(|change| ExpectedChange::try_from(change).ok())
+            .partition(|change| change.current_state(state).as_ref() == Some(change))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    entries: Vec<JournalEntry>,
+}
+
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    // Left by the previous run, until they have been checked against the device
+    interrupted: Vec<JournalEntry>,
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    // Open the journal, keeping whatever the previous run did not see confirmed
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let file: JournalFile = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read journal {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse journal {}", path.display()))?
+        } else {
+            JournalFile::default()
+        };
+        if !file.entries.is_empty() {
+            log::warn!(
+                "Journal {} holds {} operations the previous run did not see confirmed",
+                path.display(),
+                file.entries.len()
+            );
+        }
+        Ok(Self {
+            path,
+            interrupted: file.entries,
+            entries: Vec::new(),
+        })
+    }
+
+    // Operations left by the previous run; returned once, to be checked against the device
+    pub fn take_interrupted(&mut self) -> Vec<JournalEntry> {
+        let interrupted = std::mem::take(&mut self.interrupted);
+        if !interrupted.is_empty() {
+            self.save_or_log();
+        }
+        interrupted
+    }
+
+    // Record changes before they are written to the device
+    pub fn record(&mut self, source: &str, changes: &[ExpectedChange]) {
+        self.entries.push(JournalEntry {
+            source: source.to_string(),
+            written_at: EventTime::now().timestamp,
+            changes: changes.iter().map(StagedChange::from).collect(),
+            recorded: Instant::now(),
+        });
+        self.save_or_log();
+    }
+
+    // Drop entries the device has confirmed, and those this run already handled as failed
+    // (rejected or never confirmed; reported through the usual feedback)
+    pub fn settle(&mut self, state: &VideohubState) {
+        let before = self.entries.len();
+        self.entries.retain(|entry| {
+            let (_, missing) = entry.check(state);
+            !missing.is_empty() && entry.recorded.elapsed() < PENDING_CHANGE_TIMEOUT
+        });
+        if self.entries.len() != before {
+            self.save_or_log();
+        }
+    }
+
+    fn save_or_log(&self) {
+        if let Err(e) = self.save() {
+            log::error!("Failed to write journal: {e}");
+        }
+    }
+
+    // Written to a temporary file and renamed, so a crash mid-write leaves the old journal intact
+    fn save(&self) -> Result<()> {
+        let file = JournalFile {
+            entries: self
+                .interrupted
+                .iter()
+                .chain(&self.entries)
+                .cloned()
+                .collect(),
+        };
+        let temporary = self.path.with_extension("tmp");
+        std::fs::write(&temporary, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        std::fs::rename(&temporary, &self.path)
+            .with_context(|| format!("Failed to replace journal {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settles_confirmed_operations_and_keeps_the_rest_for_the_next_run() {
+        let path = std::env::temp_dir().join(format!("journal-{}.json", std::process::id()));
+        let mut journal = Journal::open(path.clone()).unwrap();
+        let route = |output, input| ExpectedChange::Route { output, input };
+        journal.record("set-destinations", &[route(0, 1), route(1, 1)]);
+
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 1);
+        journal.settle(&state);
+        assert_eq!(journal.entries.len(), 1);
+
+        // A restart finds the operation with output 2 missing
+        let mut restarted = Journal::open(path.clone()).unwrap();
+        let interrupted = restarted.take_interrupted();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(
+            interrupted[0].check(&state),
+            (vec![route(0, 1)], vec![route(1, 1)])
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Local persistence of port labels
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+// On-disk format; port numbers are 1-indexed like the rship actions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LabelFile {
+    #[serde(default)]
+    inputs: BTreeMap<u32, String>,
+    #[serde(default)]
+    outputs: BTreeMap<u32, String>,
+}
+
+// Label set persisted to a JSON file (ports are 0-indexed in memory)
+#[derive(Debug)]
+pub struct LabelStore {
+    path: PathBuf,
+    pub input_labels: HashMap<u32, String>,
+    pub output_labels: HashMap<u32, String>,
+}
+
+impl LabelStore {
+    // Load the label set, starting empty if the file does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let file: LabelFile = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read label file {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse label file {}", path.display()))?
+        } else {
+            LabelFile::default()
+        };
+
+        let from_file = |labels: BTreeMap<u32, String>| {
+            labels
+                .into_iter()
+                .filter(|(port, _)| *port > 0)
+                .map(|(port, label)| (port - 1, label))
+                .collect()
+        };
+
+        Ok(Self {
+            input_labels: from_file(file.inputs),
+            output_labels: from_file(file.outputs),
+            path,
+        })
+    }
+
+    // Write the label set back to disk
+    pub fn save(&self) -> Result<()> {
+        let to_file = |labels: &HashMap<u32, String>| {
+            labels
+                .iter()
+                .map(|(port, label)| (port + 1, label.clone()))
+                .collect()
+        };
+        let file = LabelFile {
+            inputs: to_file(&self.input_labels),
+            outputs: to_file(&self.output_labels),
+        };
+
+        let contents = serde_json::to_string_pretty(&file)?;
+        std::fs::write(&self.path, contents)
+            .with_context(|| format!("Failed to write label file {}", self.path.display()))
+    }
+
+    // Labels that differ between the store and the device, as (port, stored label) pairs.
+    // Ports the store does not know yet are adopted from the device instead.
+    pub fn reconcile(
+        stored: &mut HashMap<u32, String>,
+        device: &HashMap<u32, String>,
+    ) -> Vec<(u32, String)> {
+        let mut corrections = Vec::new();
+        for (port, device_label) in device {
+            match stored.get(port) {
+                Some(label) if label != device_label => corrections.push((*port, label.clone())),
+                Some(_) => {}
+                None => {
+                    stored.insert(*port, device_label.clone());
+                }
+            }
+        }
+        corrections.sort_by_key(|(port, _)| *port);
+        corrections
+    }
+}
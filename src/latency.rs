@@ -0,0 +1,66 @@
+//! Rolling command-to-confirmation latency, checked against a configured budget
+
+use std::collections::VecDeque;
+use tokio::time::Duration;
+
+// Number of most recent confirmations the percentile is computed over
+const LATENCY_WINDOW: usize = 100;
+// Confirmations needed before the budget is checked at all
+const MIN_SAMPLES: usize = 20;
+
+// A change in whether the latency budget is met
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyReport {
+    pub degraded: bool,
+    pub p95: Duration,
+    pub budget: Duration,
+    pub samples: usize,
+}
+
+#[derive(Debug)]
+pub struct LatencyMonitor {
+    budget: Duration,
+    samples: VecDeque<Duration>,
+    degraded: bool,
+}
+
+impl LatencyMonitor {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            samples: VecDeque::with_capacity(LATENCY_WINDOW),
+            degraded: false,
+        }
+    }
+
+    // Record a confirmation latency. Returns a report when the p95 crosses the budget either way.
+    pub fn record(&mut self, latency: Duration) -> Option<LatencyReport> {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+        if self.samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        let p95 = self.p95();
+        let degraded = p95 > self.budget;
+        if degraded == self.degraded {
+            return None;
+        }
+        self.degraded = degraded;
+        Some(LatencyReport {
+            degraded,
+            p95,
+            budget: self.budget,
+            samples: self.samples.len(),
+        })
+    }
+
+    fn p95(&self) -> Duration {
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let index = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+        sorted[index]
+    }
+}
@@ -5,19 +5,108 @@
 //! This crate provides functionality to connect to and control Blackmagic Videohub video routing devices
 //! with [rship](https://docs.rship.io).
 
+// The device client and state live in videohub-executor-core and are re-exported here
+pub use videohub_executor_core::{client, clock, snapshot, unknown};
+
 pub mod actions;
-pub mod client;
+pub mod activity;
+pub mod batch;
+pub mod buffer;
+pub mod bus;
+pub mod capabilities;
+pub mod charset;
+pub mod claim;
+pub mod cli;
+pub mod config;
+#[cfg(any(feature = "http", feature = "osc"))]
+pub mod control;
+pub mod diff;
+#[cfg(feature = "discovery")]
+pub mod discovery;
+pub mod drift;
 pub mod emitters;
+pub mod failover;
+pub mod filter;
+pub mod flap;
+pub mod freeze;
+pub mod health;
+pub mod hooks;
+pub mod hubs;
+pub mod journal;
+pub mod labels;
+pub mod latency;
+pub mod logging;
+pub mod metadata;
+pub mod middleware;
+pub mod migrate;
+pub mod notifications;
+pub mod observer;
+pub mod outbox;
+pub mod output_status;
+pub mod ownership;
+pub mod pending;
+pub mod profile;
+pub mod qos;
+pub mod raw;
+pub mod reboot;
+pub mod redundancy;
+pub mod registration;
+pub mod report;
+pub mod salvo;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sequence;
 pub mod service;
+pub mod shadow;
+pub mod sheet;
+pub mod show;
+pub mod simulation;
+pub mod staging;
+pub mod status;
+pub mod summary;
+pub mod sweep;
+pub mod take;
+pub mod targets;
+pub mod transactions;
+pub mod translations;
+pub mod usage;
+pub mod watchdog;
 
 // Re-export the main service and commonly used types
 pub use actions::{
-    SetInputAction, SetInputLabelAction, SetLabelAction, SetLockAction, SetOutputLabelAction,
-    SetOutputLockAction, SetRouteAction, SetTakeModeAction, SetTakeModeOnThisOutputAction,
+    AddToTransactionAction, ApplyStagedChangesAction, BeginTransactionAction, ClaimControlAction,
+    ClearPendingAction, CommitTransactionAction, DeleteSalvoAction, DiscardStagedChangesAction,
+    DurationMs, ExportUsageReportAction, FailoverAction, FreezeInputAction, GetInventoryAction,
+    GetPortInfoAction, GetRoutingTableAction, HoldRouteAction, IdentifyDeviceAction,
+    LabelOutputRangeAction, LoadShowAction, LockOutputRangeAction, PortRange, PortRef,
+    PresetRouteAction, RebootDeviceAction, RecallSalvoAction, ReleaseControlAction,
+    ReleaseHoldAction, RollbackTransactionAction, RotationAction, RouteOutputRangeAction,
+    RouteToOutputAction, RunBatchAction, SaveSalvoAction, SendRawBlockAction, SequenceInputsAction,
+    SetDestinationsAction, SetDeviceAddressAction, SetDeviceConfigurationAction, SetInputAction,
+    SetInputLabelAction, SetLabelAction, SetLockAction, SetLogLevelAction, SetOutputLabelAction,
+    SetOutputLockAction, SetProfileAction, SetProtocolTraceAction, SetRouteAction,
+    SetRuleEnabledAction, SetTakeModeAction, SetTakeModeOnThisOutputAction, SimulateCommandAction,
+    StopSequenceAction, SweepTestAction, TakeAction, TransferOutputOwnershipAction,
+    UnfreezeInputAction,
 };
+pub use config::ServiceConfig;
 pub use emitters::{
-    DeviceStatusEmitter, InputChangedEmitter, LabelChangedEmitter, LockChangedEmitter,
-    NetworkInterfaceEmitter, OutputLockChangedEmitter, RouteChangedEmitter, TakeModeChangedEmitter,
-    TakeModeOnThisOutputEmitter,
+    ActionFeedbackEmitter, AutoFailoverEmitter, BatchProgressEmitter, BusyOutput,
+    ChangeSummaryEmitter, CommandRejectedEmitter, ControlClaimEmitter, DeviceAddressEmitter,
+    DeviceConfigurationEmitter, DeviceInventoryEmitter, DeviceRebootDetectedEmitter,
+    DeviceStatusEmitter, DiscoveredVideohub, DiscoveredVideohubsEmitter, DivergedOutput,
+    DriftEmitter, FreezeViolationEmitter, InputChangedEmitter, IntentDivergenceEmitter,
+    InterruptedOperationEmitter, LabelChangedEmitter, LeadershipChangedEmitter, LockChangedEmitter,
+    MatrixSummaryEmitter, NetworkInterfaceEmitter, OutputLockChangedEmitter, OutputStateEmitter,
+    OwnershipChangedEmitter, OwnershipViolationEmitter, PendingRoute, PendingRoutesEmitter,
+    PerformanceDegradedEmitter, PortCountMismatchEmitter, PortInfoEmitter, ProfileEmitter,
+    ProtocolTraceEmitter, RawResponseEmitter, RegistrationStatusEmitter, RouteChangedEmitter,
+    RoutedOutputsEmitter, RshipEndpointEmitter, SalvoSummary, SalvosEmitter,
+    SequenceProgressEmitter, SimulationResultEmitter, StagedChangesEmitter, StateDivergenceEmitter,
+    SweepProgressEmitter, TakeModeChangedEmitter, TakeModeOnThisOutputEmitter,
+    TransactionStatusEmitter, UnknownBlockEmitter, WatchdogEmitter,
 };
+pub use logging::LogControl;
+pub use middleware::CommandMiddleware;
+pub use observer::VideohubObserver;
 pub use service::VideohubService;
@@ -0,0 +1,75 @@
+//! Logging setup with a filter that can be changed at runtime
+
+use anyhow::{Result, anyhow};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+
+const LOG_LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "off"];
+
+// Handle for changing the log filter of the running process
+#[derive(Debug, Clone)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogControl {
+    // Install the global logger with the given initial filter (from --log-level, RUST_LOG or the
+    // config file), or "error" when there is none or it does not parse.
+    // Records from the `log` macros are forwarded to the same subscriber.
+    pub fn init(filter: Option<&str>) -> Self {
+        let filter = filter
+            .and_then(|filter| EnvFilter::try_new(filter).ok())
+            .unwrap_or_else(|| EnvFilter::new("error"));
+        let (filter, handle) = reload::Layer::new(filter);
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer())
+            .init();
+
+        Self { handle }
+    }
+
+    // Set the level for one module, or the default level when no module is given.
+    // Module-specific levels set earlier are kept when changing the default.
+    pub fn set_level(&self, level: &str, module: Option<&str>) -> Result<()> {
+        let level = level.trim().to_lowercase();
+        if !LOG_LEVELS.contains(&level.as_str()) {
+            return Err(anyhow!("Unknown log level: {level}"));
+        }
+
+        let current = self
+            .handle
+            .with_current(|filter| filter.to_string())
+            .map_err(|e| anyhow!("Failed to read log filter: {e}"))?;
+
+        let mut directives: Vec<String> = current
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        match module {
+            Some(module) => {
+                let prefix = format!("{module}=");
+                directives.retain(|directive| !directive.starts_with(&prefix));
+                directives.push(format!("{module}={level}"));
+            }
+            None => {
+                directives.retain(|directive| directive.contains('='));
+                directives.insert(0, level);
+            }
+        }
+
+        let spec = directives.join(",");
+        let filter =
+            EnvFilter::try_new(&spec).map_err(|e| anyhow!("Invalid log filter {spec}: {e}"))?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| anyhow!("Failed to apply log filter: {e}"))?;
+
+        log::info!("Log filter changed to: {spec}");
+        Ok(())
+    }
+}
@@ -1,43 +1,296 @@
 use anyhow::Result;
-use std::env;
+use clap::Parser;
 
 mod actions;
-mod client;
+mod activity;
+mod batch;
+mod buffer;
+mod bus;
+mod capabilities;
+mod charset;
+mod claim;
+mod cli;
+mod config;
+#[cfg(any(feature = "http", feature = "osc"))]
+mod control;
+mod diff;
+#[cfg(feature = "discovery")]
+mod discovery;
+mod drift;
 mod emitters;
+mod failover;
+mod filter;
+mod flap;
+mod freeze;
+mod health;
+mod hooks;
+mod hubs;
+mod journal;
+mod labels;
+mod latency;
+mod logging;
+mod metadata;
+mod middleware;
+mod migrate;
+mod notifications;
+mod observer;
+mod outbox;
+mod output_status;
+mod ownership;
+mod pending;
+mod profile;
+mod qos;
+mod raw;
+mod reboot;
+mod redundancy;
+mod registration;
+mod report;
+mod salvo;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod sequence;
 mod service;
+mod shadow;
+mod sheet;
+mod show;
+mod simulation;
+mod staging;
+mod status;
+mod summary;
+mod sweep;
+mod take;
+mod targets;
+mod transactions;
+mod translations;
+mod usage;
+mod watchdog;
 
+use videohub_executor_core::{client, clock, snapshot, unknown};
+
+use cli::{Cli, Command, Settings};
+use client::VideohubClient;
+use config::{ServiceConfig, VideohubDiscovery};
+use hubs::Hub;
+use logging::LogControl;
+use ownership::Ownership;
+use report::CommissioningReport;
 use service::VideohubService;
+use show::ShowFile;
+use usage::UsageReport;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load environment variables from .env file
     dotenv::dotenv().ok();
 
-    // Initialize logger
-    env_logger::init();
+    // Command line flags, then environment variables, then the --config file
+    let settings = Settings::load(Cli::parse())?;
+
+    // Initialize logger (the filter can be changed later via the set-log-level action)
+    let log_control = LogControl::init(settings.log_level().as_deref());
+
+    match &settings.cli.command {
+        // Converting a Videohub Setup export needs no device
+        Some(Command::MigrateSetup {
+            export,
+            path,
+            zero_based,
+        }) => {
+            let show = migrate::from_setup_export(export, *zero_based)?;
+            show.save(path)?;
+            println!(
+                "Wrote {} routes, {} input labels and {} output labels from {} to {}",
+                show.routes.len(),
+                show.input_labels.len(),
+                show.output_labels.len(),
+                export.display(),
+                path.display()
+            );
+            return Ok(());
+        }
+        // Listing the Videohubs on the LAN needs no configured device either
+        Some(Command::Discover) => return discover().await,
+        _ => {}
+    }
 
-    // Get configuration from environment variables
-    let videohub_address = env::var("VIDEOHUB_ADDRESS").expect("VIDEOHUB_ADDRESS must be set");
-    let videohub_port: u16 = env::var("VIDEOHUB_PORT")
-        .expect("VIDEOHUB_PORT must be set")
-        .parse()
-        .expect("Failed to parse VIDEOHUB_PORT");
+    let config = settings.service_config();
 
-    let rship_address = env::var("RSHIP_ADDRESS").expect("RSHIP_ADDRESS must be set");
-    let rship_port: u16 = env::var("RSHIP_PORT")
-        .expect("RSHIP_PORT must be set")
-        .parse()
-        .expect("Failed to parse RSHIP_PORT");
+    // VIDEOHUBS or [[hubs]] lists several hubs instead of one; with VIDEOHUB_DISCOVERY=auto and no
+    // Videohub configured, the ones found on the LAN are used
+    let mut hubs = settings.hubs()?;
+    if hubs.is_none()
+        && !settings.has_videohub_address()
+        && config.videohub_discovery == Some(VideohubDiscovery::Auto)
+    {
+        hubs = Some(discovered_hubs().await?);
+    }
+    let (videohub_address, videohub_port) = match hubs.as_ref().and_then(|hubs| hubs.first()) {
+        Some(hub) => (hub.host.clone(), hub.port),
+        None => settings.videohub()?,
+    };
+
+    // One-shot subcommands only need the videohub connection (the first hub of VIDEOHUBS)
+    if let Some(command) = &settings.cli.command {
+        return run_subcommand(command, videohub_address, videohub_port, &config).await;
+    }
+
+    let (rship_address, rship_port) = settings.rship()?;
 
     log::info!("Starting rship-blackmagic-videohub service");
-    log::info!("Videohub: {videohub_address}:{videohub_port}");
     log::info!("Rship: {rship_address}:{rship_port}");
 
-    // Create and start the service
-    let service =
-        VideohubService::new(videohub_address, videohub_port, rship_address, rship_port).await?;
+    let Some(hubs) = hubs else {
+        log::info!("Videohub: {videohub_address}:{videohub_port}");
+
+        // Create and start the service
+        let service =
+            VideohubService::new(videohub_address, videohub_port, rship_address, rship_port)
+                .await?
+                .with_config(config)
+                .with_log_control(log_control);
+
+        service.start().await?;
+
+        return Ok(());
+    };
+
+    // One service per hub, each with its own rship instance, connection and state
+    Hub::check_config(&config)?;
+    let mut services = Vec::with_capacity(hubs.len());
+    for (index, hub) in hubs.iter().enumerate() {
+        log::info!("Videohub {}: {}:{}", hub.name, hub.host, hub.port);
+        let service = VideohubService::new(
+            hub.host.clone(),
+            hub.port,
+            rship_address.clone(),
+            rship_port,
+        )
+        .await?
+        .with_config(hub.config(&config, index == 0))
+        .with_log_control(log_control.clone());
+        services.push(service);
+    }
+    futures_util::future::try_join_all(services.iter().map(|service| service.start())).await?;
+
+    Ok(())
+}
+
+// The Videohubs on the LAN, as one hub each
+#[cfg(feature = "discovery")]
+async fn discovered_hubs() -> Result<Vec<Hub>> {
+    let devices = discovery::browse(discovery::DISCOVERY_WINDOW).await?;
+    for device in &devices {
+        log::info!("Discovered {} at {}", device.name, device.address);
+    }
+    discovery::hubs(&devices)
+}
+
+#[cfg(not(feature = "discovery"))]
+async fn discovered_hubs() -> Result<Vec<Hub>> {
+    anyhow::bail!("VIDEOHUB_DISCOVERY=auto needs a build with the discovery feature")
+}
+
+// Print the Videohubs on the LAN, one per line
+#[cfg(feature = "discovery")]
+async fn discover() -> Result<()> {
+    for device in discovery::browse(discovery::DISCOVERY_WINDOW).await? {
+        println!(
+            "{}\t{}:{}\t{}",
+            device.name,
+            device.address,
+            device.port,
+            device.model.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "discovery"))]
+async fn discover() -> Result<()> {
+    anyhow::bail!("discover needs a build with the discovery feature")
+}
+
+// Run a one-shot subcommand against the videohub and exit
+async fn run_subcommand(
+    command: &Command,
+    videohub_address: String,
+    videohub_port: u16,
+    config: &ServiceConfig,
+) -> Result<()> {
+    let mut client = VideohubClient::new(videohub_address, videohub_port);
+
+    match command {
+        Command::ExportCsv { path } => {
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            let rows = sheet::rows_from_state(client.state());
+            sheet::write_csv(path, &rows)?;
+            println!("Exported {} outputs to {}", rows.len(), path.display());
+        }
+        Command::SaveShow { path } => {
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            ShowFile::from_state(client.state()).save(path)?;
+            println!("Saved device state to {}", path.display());
+        }
+        Command::Report { path } => {
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            CommissioningReport::from_state(client.state()).write(path)?;
+            println!("Wrote commissioning report to {}", path.display());
+        }
+        Command::DumpState => {
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            let mut snapshot = client.snapshot();
+            if let Some(path) = &config.ownership_file {
+                snapshot.output_owners = Ownership::load(path)?.owners();
+            }
+            println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        }
+        Command::Diff { path } => {
+            let expected = ShowFile::load(path)?;
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            let differences = expected.diff(&ShowFile::from_state(client.state()));
+            client.disconnect().await;
 
-    service.start().await?;
+            if differences.is_empty() {
+                println!("Device matches {}", path.display());
+                return Ok(());
+            }
+            for difference in &differences {
+                println!("{difference}");
+            }
+            println!("{} differences from {}", differences.len(), path.display());
+            std::process::exit(1);
+        }
+        Command::UsageReport { path } => {
+            // Usage is collected by the running service; this converts what it last saved
+            let Some(usage_file) = &config.usage_file else {
+                anyhow::bail!("USAGE_FILE must be set (or in the config file)");
+            };
+            let report = UsageReport::load(usage_file)?;
+            report.write(path)?;
+            println!(
+                "Exported usage of {} owners since {} to {}",
+                report.owners.len(),
+                report.since,
+                path.display()
+            );
+            return Ok(());
+        }
+        Command::ImportCsv { path, routes } => {
+            let rows = sheet::read_csv(path)?;
+            client.connect().await?;
+            client.receive_initial_state().await?;
+            sheet::apply(&mut client, &rows, *routes).await?;
+            println!("Applied {} rows from {}", rows.len(), path.display());
+        }
+        // Run in main before a device is needed
+        Command::MigrateSetup { .. } | Command::Discover => unreachable!(),
+    }
 
+    client.disconnect().await;
     Ok(())
 }
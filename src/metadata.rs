@@ -0,0 +1,97 @@
+//! Free-form notes attached to ports in config (location, contact, downstream device, ...)
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// Metadata of one port, e.g. {"location": "Rack 3", "feeds": "Stage left monitor"}
+pub type Metadata = BTreeMap<String, String>;
+
+// Port metadata keys naming a source for overlays (e.g. multiviewer UMDs): the name shown instead
+// of the device label, and the group it belongs to, e.g. "Cameras"
+pub const ALIAS_KEY: &str = "alias";
+pub const GROUP_KEY: &str = "group";
+
+// On-disk format; port numbers are 1-indexed like the rship actions
+#[derive(Debug, Default, Deserialize)]
+struct MetadataFile {
+    #[serde(default)]
+    inputs: BTreeMap<u32, Metadata>,
+    #[serde(default)]
+    outputs: BTreeMap<u32, Metadata>,
+}
+
+// Port metadata (ports are 0-indexed in memory)
+#[derive(Debug, Clone, Default)]
+pub struct PortMetadata {
+    inputs: HashMap<u32, Metadata>,
+    outputs: HashMap<u32, Metadata>,
+}
+
+impl PortMetadata {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read port metadata file {}", path.display()))?;
+        let file: MetadataFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse port metadata file {}", path.display()))?;
+
+        let from_file = |ports: BTreeMap<u32, Metadata>| {
+            ports
+                .into_iter()
+                .filter(|(port, _)| *port > 0)
+                .map(|(port, metadata)| (port - 1, metadata))
+                .collect()
+        };
+        Ok(Self {
+            inputs: from_file(file.inputs),
+            outputs: from_file(file.outputs),
+        })
+    }
+
+    pub fn input(&self, input: u32) -> Option<Metadata> {
+        self.inputs.get(&input).cloned()
+    }
+
+    pub fn output(&self, output: u32) -> Option<Metadata> {
+        self.outputs.get(&output).cloned()
+    }
+
+    // The name to show for an input: its alias, otherwise its device label
+    pub fn input_alias(&self, input: u32, label: Option<&str>) -> Option<String> {
+        self.inputs
+            .get(&input)
+            .and_then(|metadata| metadata.get(ALIAS_KEY))
+            .map(String::as_str)
+            .or(label)
+            .map(str::to_string)
+    }
+
+    pub fn input_group(&self, input: u32) -> Option<String> {
+        self.inputs.get(&input)?.get(GROUP_KEY).cloned()
+    }
+
+    // All input metadata, 1-indexed
+    pub fn inputs(&self) -> BTreeMap<u32, Metadata> {
+        self.inputs
+            .iter()
+            .map(|(input, metadata)| (input + 1, metadata.clone()))
+            .collect()
+    }
+
+    // All output metadata, 1-indexed
+    pub fn outputs(&self) -> BTreeMap<u32, Metadata> {
+        self.outputs
+            .iter()
+            .map(|(output, metadata)| (output + 1, metadata.clone()))
+            .collect()
+    }
+
+    // Metadata of a port by type ("input" or "output")
+    pub fn port(&self, port_type: &str, port: u32) -> Option<Metadata> {
+        match port_type {
+            "input" => self.input(port),
+            _ => self.output(port),
+        }
+    }
+}
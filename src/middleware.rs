@@ -0,0 +1,148 @@
+//! Middleware applied to every command before it reaches the device
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::actions::PortRef;
+use crate::client::VideohubState;
+use crate::service::{ActionInvocation, VideohubCommand};
+
+// Who issued a command: the source it was queued from and the rship action, if any
+#[derive(Debug, Clone, Copy)]
+pub struct CommandContext<'a> {
+    pub source: &'static str,
+    pub invocation: Option<&'a ActionInvocation>,
+}
+
+// A step in the command pipeline. Middleware may rewrite the command in place,
+// or return an error to reject it; rejected commands are reported to observers.
+// Runs on the videohub task, so it must not block.
+pub trait CommandMiddleware: Send + Sync {
+    fn process(&self, command: &mut VideohubCommand, state: &VideohubState) -> Result<()>;
+
+    // Like `process`, for middleware that depends on who issued the command
+    fn process_request(
+        &self,
+        command: &mut VideohubCommand,
+        _context: &CommandContext,
+        state: &VideohubState,
+    ) -> Result<()> {
+        self.process(command, state)
+    }
+}
+
+// Turns routes with ports given by label into plain routes, using the labels the device reported
+pub struct ResolveLabels;
+
+impl CommandMiddleware for ResolveLabels {
+    fn process(&self, command: &mut VideohubCommand, state: &VideohubState) -> Result<()> {
+        if let VideohubCommand::AddToTransaction { command, .. } = command {
+            return self.process(command, state);
+        }
+        if let VideohubCommand::RouteByLabel {
+            output,
+            input,
+            set_input,
+        } = command
+        {
+            let output = resolve_port(output, &state.output_labels, "output")?;
+            let input = resolve_port(input, &state.input_labels, "input")?;
+            *command = if *set_input {
+                VideohubCommand::SetInput { output, input }
+            } else {
+                VideohubCommand::Route { output, input }
+            };
+        }
+        Ok(())
+    }
+}
+
+// The 0-indexed port with the given label. A label no port has may still be a port number ("3").
+fn resolve_port(port: &PortRef, labels: &HashMap<u32, String>, port_type: &str) -> Result<u32> {
+    let label = match port {
+        PortRef::Number(port) => return Ok(*port),
+        PortRef::Label(label) => label.trim(),
+    };
+    let mut matches = labels
+        .iter()
+        .filter(|(_, name)| name.as_str() == label)
+        .map(|(port, _)| *port);
+    match (matches.next(), matches.next()) {
+        (Some(port), None) => Ok(port),
+        (Some(_), Some(_)) => bail!("several {port_type}s are labelled \"{label}\""),
+        (None, _) => match label.parse::<u32>() {
+            Ok(port) => Ok(port.clamp(1, u32::MAX) - 1),
+            Err(_) => bail!("no {port_type} is labelled \"{label}\""),
+        },
+    }
+}
+
+// Rejects commands addressing ports the device does not have
+pub struct ValidatePorts;
+
+impl CommandMiddleware for ValidatePorts {
+    fn process(&self, command: &mut VideohubCommand, state: &VideohubState) -> Result<()> {
+        let Some(info) = &state.device_info else {
+            // Port counts are unknown until the device has identified itself
+            return Ok(());
+        };
+
+        let (input, output) = match command {
+            VideohubCommand::Route { output, input }
+            | VideohubCommand::SetInput { output, input }
+            | VideohubCommand::HoldRoute { output, input, .. }
+            | VideohubCommand::PresetRoute { output, input } => (Some(*input), Some(*output)),
+            VideohubCommand::InputLabel { input, .. }
+            | VideohubCommand::SignalStatus { input, .. }
+            | VideohubCommand::FreezeInput { input }
+            | VideohubCommand::UnfreezeInput { input } => (Some(*input), None),
+            VideohubCommand::GetPortInfo { port_type, port } if port_type == "input" => {
+                (Some(*port), None)
+            }
+            VideohubCommand::GetPortInfo { port, .. } => (None, Some(*port)),
+            VideohubCommand::OutputLabel { output, .. }
+            | VideohubCommand::OutputLock { output, .. }
+            | VideohubCommand::TakeMode { output, .. }
+            | VideohubCommand::ReleaseHold { output, .. }
+            | VideohubCommand::StopSequence { output }
+            | VideohubCommand::TriggerFailover { output }
+            | VideohubCommand::RevertFailover { output } => (None, Some(*output)),
+            VideohubCommand::StartSequence { output, inputs, .. } => {
+                (inputs.iter().max().copied(), Some(*output))
+            }
+            VideohubCommand::WriteChanges { changes } => {
+                for change in changes.iter() {
+                    self.process(&mut change.to_command(), state)?;
+                }
+                return Ok(());
+            }
+            VideohubCommand::OutputLocks { outputs, .. } => (None, outputs.iter().max().copied()),
+            VideohubCommand::AddToTransaction { command, .. } => {
+                return self.process(command, state);
+            }
+            _ => (None, None),
+        };
+
+        if let (Some(input), Some(inputs)) = (input, info.video_inputs)
+            && input >= inputs
+        {
+            bail!("input {} out of range (device has {inputs})", input + 1);
+        }
+        if let (Some(output), Some(outputs)) = (output, info.video_outputs)
+            && output >= outputs
+        {
+            bail!("output {} out of range (device has {outputs})", output + 1);
+        }
+        Ok(())
+    }
+}
+
+// Logs every command that passes the preceding middleware
+pub struct AuditLog;
+
+impl CommandMiddleware for AuditLog {
+    fn process(&self, command: &mut VideohubCommand, _state: &VideohubState) -> Result<()> {
+        log::info!("Audit: {command:?}");
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+//! Migration from Blackmagic Videohub Setup. The `migrate-setup` subcommand reads a settings export
+//! of the Setup utility (XML, or JSON from tools that convert it) and writes its labels, routes
+//! and locks as a show file, ready for `diff` or `load-show`.
+//!
+//! Exports differ between Setup versions, so the file is read loosely: any element named like an
+//! input or output (`Input`, `VideoOutput`, `outputs`, ...) with a port number (`id`, `index`,
+//! `number` or `port`) gives its `label` (or `name`), outputs also their routed `input` (or
+//! `source`) and `locked` state, and `Route` elements their `output` and `input`. Values may be
+//! attributes or child elements. These names are not checked against exports of every Setup
+//! version.
+//!
+//! Ports are 1-indexed like in Setup. Exports from tools that number them like the protocol, from
+//! 0, are read with `--zero-based`; the numbering is never guessed, since a 1-indexed export
+//! without port 1 would otherwise be read off by one. Port 0 in a 1-indexed export is an error.
+
+use anyhow::{Context, Result, bail};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::show::ShowFile;
+
+const PORT_KEYS: [&str; 4] = ["id", "index", "number", "port"];
+const LABEL_KEYS: [&str; 2] = ["label", "name"];
+const SOURCE_KEYS: [&str; 2] = ["input", "source"];
+const LOCK_KEYS: [&str; 2] = ["locked", "lock"];
+
+// An element of the export with its values (attributes and text-only children) and other children
+#[derive(Debug, Default)]
+struct Node {
+    name: String,
+    values: BTreeMap<String, String>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn value(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .find_map(|key| self.values.get(*key))
+            .map(|value| value.trim())
+    }
+
+    fn number(&self, keys: &[&str]) -> Option<u32> {
+        self.value(keys)?.parse().ok()
+    }
+}
+
+// "VideoOutputs" and "video_output" are both "output"
+fn normalized(name: &str) -> String {
+    let name = name.to_lowercase().replace(['_', '-'], "");
+    let name = name.strip_prefix("video").unwrap_or(&name);
+    name.strip_suffix('s').unwrap_or(name).to_string()
+}
+
+// Read a Videohub Setup export (JSON if the file ends in .json, XML otherwise) as a show file.
+// `zero_based` tells that the export numbers ports from 0.
+pub fn from_setup_export(path: &Path, zero_based: bool) -> Result<ShowFile> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read Videohub Setup export {}", path.display()))?;
+    let root = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        from_json("root", &value)
+    } else {
+        from_xml(&contents).with_context(|| format!("Failed to parse {}", path.display()))?
+    };
+    let show = show_file(&root, zero_based)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if show == ShowFile::default() {
+        bail!(
+            "{} has no input or output labels, routes or locks",
+            path.display()
+        );
+    }
+    Ok(show)
+}
+
+fn from_json(name: &str, value: &serde_json::Value) -> Node {
+    let mut node = Node {
+        name: normalized(name),
+        ..Default::default()
+    };
+    let mut add = |key: &str, value: &serde_json::Value| match value {
+        serde_json::Value::Object(_) => node.children.push(from_json(key, value)),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                node.children.push(from_json(key, item));
+            }
+        }
+        serde_json::Value::String(text) => {
+            node.values.insert(normalized(key), text.clone());
+        }
+        serde_json::Value::Null => {}
+        value => {
+            node.values.insert(normalized(key), value.to_string());
+        }
+    };
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, value) in fields {
+                add(key, value);
+            }
+        }
+        value => add(name, value),
+    }
+    node
+}
+
+fn from_xml(contents: &str) -> Result<Node> {
+    fn start(element: &BytesStart) -> Result<Node> {
+        let mut node = Node {
+            name: normalized(&String::from_utf8_lossy(element.local_name().as_ref())),
+            ..Default::default()
+        };
+        for attribute in element.attributes() {
+            let attribute = attribute?;
+            let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string();
+            node.values
+                .insert(normalized(&key), attribute.unescape_value()?.to_string());
+        }
+        Ok(node)
+    }
+
+    let mut reader = Reader::from_str(contents);
+    reader.config_mut().trim_text(true);
+    // Open elements; the root collects the top-level ones
+    let mut stack = vec![Node::default()];
+    let mut text = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                stack.push(start(&element)?);
+                text.clear();
+            }
+            Event::Empty(element) => {
+                let node = start(&element)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+            }
+            Event::Text(content) => text.push_str(&content.unescape()?),
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content)),
+            Event::End(_) => {
+                let node = stack.pop().context("unbalanced end tag")?;
+                let Some(parent) = stack.last_mut() else {
+                    bail!("unbalanced end tag");
+                };
+                // <Label>CAM 1</Label> is a value of its parent
+                if node.children.is_empty() && node.values.is_empty() && !text.is_empty() {
+                    parent.values.insert(node.name, std::mem::take(&mut text));
+                } else {
+                    parent.children.push(node);
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if stack.len() != 1 {
+        bail!("the document ends inside an element");
+    }
+    Ok(stack.remove(0))
+}
+
+fn show_file(root: &Node, zero_based: bool) -> Result<ShowFile> {
+    let mut show = ShowFile::default();
+    collect(root, &mut show);
+
+    let uses_port_zero = show.input_labels.contains_key(&0)
+        || show.output_labels.contains_key(&0)
+        || show.output_locks.contains_key(&0)
+        || show
+            .routes
+            .iter()
+            .any(|(output, input)| *output == 0 || *input == 0);
+    if !zero_based {
+        if uses_port_zero {
+            bail!(
+                "the export has port 0, but Setup numbers ports from 1; use --zero-based if it numbers them from 0"
+            );
+        }
+        return Ok(show);
+    }
+
+    // Show files number ports from 1 like Setup
+    fn shift<T>(ports: &mut BTreeMap<u32, T>) {
+        *ports = std::mem::take(ports)
+            .into_iter()
+            .map(|(port, value)| (port + 1, value))
+            .collect();
+    }
+    shift(&mut show.input_labels);
+    shift(&mut show.output_labels);
+    shift(&mut show.output_locks);
+    show.routes = std::mem::take(&mut show.routes)
+        .into_iter()
+        .map(|(output, input)| (output + 1, input + 1))
+        .collect();
+    Ok(show)
+}
+
+fn collect(node: &Node, show: &mut ShowFile) {
+    match node.name.as_str() {
+        "input" => {
+            if let (Some(input), Some(label)) = (node.number(&PORT_KEYS), node.value(&LABEL_KEYS)) {
+                show.input_labels.insert(input, label.to_string());
+            }
+        }
+        "output" => {
+            if let Some(output) = node.number(&PORT_KEYS) {
+                if let Some(label) = node.value(&LABEL_KEYS) {
+                    show.output_labels.insert(output, label.to_string());
+                }
+                if let Some(input) = node.number(&SOURCE_KEYS) {
+                    show.routes.insert(output, input);
+                }
+                if let Some(locked) = node.value(&LOCK_KEYS).and_then(parse_lock) {
+                    show.output_locks.insert(output, locked);
+                }
+            }
+        }
+        "route" | "routing" => {
+            if let (Some(output), Some(input)) = (
+                node.number(&["output", "destination"]),
+                node.number(&SOURCE_KEYS),
+            ) {
+                show.routes.insert(output, input);
+            }
+        }
+        _ => {}
+    }
+    for child in &node.children {
+        collect(child, show);
+    }
+}
+
+// "true"/"false" and the protocol's lock states ("O" and "L" are locked, "U" is not)
+fn parse_lock(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "o" | "l" => Some(true),
+        "false" | "0" | "no" | "u" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_labels_routes_and_locks_from_xml_and_json() {
+        let xml = from_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <VideohubSettings>
+              <VideoInputs>
+                <Input id="1" label="CAM 1"/>
+                <Input id="2"><Label>CAM &amp; 2</Label></Input>
+              </VideoInputs>
+              <VideoOutputs>
+                <Output id="1" label="PGM" input="2" locked="L"/>
+              </VideoOutputs>
+              <Routing><Route output="2" input="1"/></Routing>
+            </VideohubSettings>"#,
+        )
+        .unwrap();
+        let show = show_file(&xml, false).unwrap();
+        assert_eq!(show.input_labels[&2], "CAM & 2");
+        assert_eq!(show.output_labels[&1], "PGM");
+        assert_eq!(show.routes, BTreeMap::from([(1, 2), (2, 1)]));
+        assert!(show.output_locks[&1]);
+
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{"inputs": [{"index": 0, "name": "CAM 1"}],
+                "outputs": [{"index": 0, "name": "PGM", "source": 0}]}"#,
+        )
+        .unwrap();
+        let json = from_json("root", &json);
+        let show = show_file(&json, true).unwrap();
+        assert_eq!(show.input_labels[&1], "CAM 1");
+        assert_eq!(show.routes, BTreeMap::from([(1, 1)]));
+        // Port 0 only exists when the export is read as zero-based
+        assert!(show_file(&json, false).is_err());
+    }
+
+    #[test]
+    fn keeps_one_based_ports_without_port_one() {
+        // Only outputs 2 and 3 listed; nothing may shift them
+        let xml = from_xml(
+            r#"<VideohubSettings>
+                 <Output id="2" label="MON 2" input="4"/>
+                 <Output id="3" label="MON 3" input="5"/>
+               </VideohubSettings>"#,
+        )
+        .unwrap();
+        let show = show_file(&xml, false).unwrap();
+        assert_eq!(show.routes, BTreeMap::from([(2, 4), (3, 5)]));
+        assert_eq!(show.output_labels[&2], "MON 2");
+        let show = show_file(&xml, true).unwrap();
+        assert_eq!(show.routes, BTreeMap::from([(3, 5), (4, 6)]));
+    }
+}
@@ -0,0 +1,428 @@
+//! Notifications to on-call engineers (email, Slack, Telegram) for critical alarms, with rate
+//! limiting and quiet hours. Delivery goes through `sendmail` and `curl` on the executor host.
+
+use anyhow::{Context, bail};
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{Duration, Instant, timeout};
+
+use crate::observer::VideohubObserver;
+use crate::service::VideohubEvent;
+
+// Delivery attempts still running after this long are killed
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// Header of the block some models report power supply and fan alarms in
+const ALARM_STATUS_HEADER: &str = "ALARM STATUS:";
+
+fn default_min_interval_ms() -> u64 {
+    300_000
+}
+
+fn default_offline_after_ms() -> u64 {
+    60_000
+}
+
+// Where notifications are delivered
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Sink {
+    // Sent with `sendmail -t`
+    Email {
+        to: Vec<String>,
+        from: Option<String>,
+    },
+    // Slack incoming webhook
+    Slack {
+        webhook_url: String,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+    },
+}
+
+// Which alarm is sent where
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlarmRule {
+    // "device-offline", "psu" or "critical-output"
+    pub class: String,
+    pub sinks: Vec<String>,
+    // device-offline: how long the device must stay offline
+    #[serde(default = "default_offline_after_ms")]
+    pub after_ms: u64,
+    // critical-output: outputs (1-indexed) whose route changes are alarms
+    #[serde(default)]
+    pub outputs: Vec<u32>,
+    // At most one notification per interval; the ones held back are counted in the next
+    #[serde(default = "default_min_interval_ms")]
+    pub min_interval_ms: u64,
+    // Deliver even during quiet hours
+    #[serde(default)]
+    pub ignore_quiet_hours: bool,
+}
+
+// Local time range in which only alarms with ignore_quiet_hours are delivered, e.g. 23:00-07:00
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuietHours {
+    #[serde(deserialize_with = "time_of_day")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "time_of_day")]
+    pub end: NaiveTime,
+}
+
+// "HH:MM" in local time
+fn time_of_day<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(serde::de::Error::custom)
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+// Notifications configuration file
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationsConfig {
+    pub sinks: BTreeMap<String, Sink>,
+    #[serde(default)]
+    pub alarms: Vec<AlarmRule>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    last_sent: Option<Instant>,
+    suppressed: u64,
+}
+
+#[derive(Debug, Default)]
+struct NotifierState {
+    rules: HashMap<usize, RuleState>,
+    device: Option<String>,
+    // Last known input of each output, so only changes of critical outputs alarm
+    routes: HashMap<u32, u32>,
+    offline: bool,
+    offline_notified: bool,
+}
+
+// Observer turning alarm events into notifications
+#[derive(Debug)]
+pub struct Notifier {
+    config: Arc<NotificationsConfig>,
+    state: Arc<Mutex<NotifierState>>,
+    // Bumped on every connect and disconnect, cancelling pending offline alarms
+    connection_generation: Arc<AtomicU64>,
+}
+
+impl Notifier {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read notifications file {}", path.display()))?;
+        let config: NotificationsConfig = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse notifications file {}", path.display()))?;
+        for rule in &config.alarms {
+            if let Some(sink) = rule
+                .sinks
+                .iter()
+                .find(|sink| !config.sinks.contains_key(*sink))
+            {
+                bail!("alarm {} uses unknown sink {sink}", rule.class);
+            }
+        }
+        log::info!(
+            "Loaded {} alarm notifications to {} sinks",
+            config.alarms.len(),
+            config.sinks.len()
+        );
+
+        Ok(Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(NotifierState::default())),
+            connection_generation: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn rules(&self, class: &str) -> impl Iterator<Item = (usize, &AlarmRule)> {
+        self.config
+            .alarms
+            .iter()
+            .enumerate()
+            .filter(move |(_, rule)| rule.class == class)
+    }
+
+    fn schedule_offline_alarms(&self) {
+        let generation = self.connection_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        for (index, rule) in self.rules("device-offline") {
+            let after = Duration::from_millis(rule.after_ms);
+            let config = self.config.clone();
+            let state = self.state.clone();
+            let connection_generation = self.connection_generation.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(after).await;
+                if connection_generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                lock(&state).offline_notified = true;
+                let message = format!("offline for more than {}s", after.as_secs());
+                notify(&config, &state, index, &message, true);
+            });
+        }
+    }
+}
+
+impl VideohubObserver for Notifier {
+    fn on_event(&self, event: &VideohubEvent) {
+        match event {
+            VideohubEvent::DeviceStatus {
+                connected,
+                model_name,
+                ..
+            } => {
+                if *connected {
+                    self.connection_generation.fetch_add(1, Ordering::SeqCst);
+                    let mut state = lock(&self.state);
+                    state.offline = false;
+                    if model_name.is_some() {
+                        state.device = model_name.clone();
+                    }
+                    if std::mem::take(&mut state.offline_notified) {
+                        drop(state);
+                        for (index, _) in self.rules("device-offline") {
+                            // Recoveries always go out, even right after the alarm
+                            notify(&self.config, &self.state, index, "back online", false);
+                        }
+                    }
+                } else if !std::mem::replace(&mut lock(&self.state).offline, true) {
+                    // Every failed reconnect reports the device offline again; time from the first
+                    self.schedule_offline_alarms();
+                }
+            }
+            VideohubEvent::Route {
+                output,
+                input,
+                input_label,
+                confirmed: true,
+                ..
+            } => {
+                let previous = lock(&self.state).routes.insert(*output, *input);
+                if previous.is_none_or(|previous| previous == *input) {
+                    return;
+                }
+                let label = input_label
+                    .as_deref()
+                    .map(|label| format!(" ({label})"))
+                    .unwrap_or_default();
+                let message = format!(
+                    "critical output {} changed to input {}{label}",
+                    output + 1,
+                    input + 1
+                );
+                for (index, rule) in self.rules("critical-output") {
+                    if rule.outputs.contains(&(output + 1)) {
+                        notify(&self.config, &self.state, index, &message, true);
+                    }
+                }
+            }
+            VideohubEvent::UnknownBlock { header, sample, .. } if header == ALARM_STATUS_HEADER => {
+                let raised = raised_alarms(sample);
+                if raised.is_empty() {
+                    return;
+                }
+                let message = format!("hardware alarm: {}", raised.join(", "));
+                for (index, _) in self.rules("psu") {
+                    notify(&self.config, &self.state, index, &message, true);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Entries of an ALARM STATUS block that are not in their normal state, e.g. "PSU 2: failed"
+fn raised_alarms(body: &str) -> Vec<String> {
+    body.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(_, value)| {
+            !matches!(
+                value.trim().to_lowercase().as_str(),
+                "ok" | "normal" | "false" | "none" | ""
+            )
+        })
+        .map(|(name, value)| format!("{}: {}", name.trim(), value.trim()))
+        .collect()
+}
+
+fn lock(state: &Mutex<NotifierState>) -> std::sync::MutexGuard<'_, NotifierState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Send a notification for a rule, unless it is rate limited or falls into quiet hours
+fn notify(
+    config: &Arc<NotificationsConfig>,
+    state: &Mutex<NotifierState>,
+    index: usize,
+    message: &str,
+    rate_limited: bool,
+) {
+    let rule = &config.alarms[index];
+    if !rule.ignore_quiet_hours
+        && let Some(quiet_hours) = &config.quiet_hours
+        && quiet_hours.contains(Local::now().time())
+    {
+        log::info!("Notification held back during quiet hours: {message}");
+        return;
+    }
+
+    let mut state = lock(state);
+    let device = state.device.clone().unwrap_or_else(|| "Videohub".into());
+    let rule_state = state.rules.entry(index).or_default();
+    let min_interval = Duration::from_millis(rule.min_interval_ms);
+    if rate_limited
+        && rule_state
+            .last_sent
+            .is_some_and(|last_sent| last_sent.elapsed() < min_interval)
+    {
+        rule_state.suppressed += 1;
+        log::debug!("Notification rate limited: {message}");
+        return;
+    }
+    rule_state.last_sent = Some(Instant::now());
+    let suppressed = std::mem::take(&mut rule_state.suppressed);
+    drop(state);
+
+    let mut text = format!("[{}] {device}: {message}", rule.class);
+    if suppressed > 0 {
+        text.push_str(&format!(" ({suppressed} more held back by rate limiting)"));
+    }
+    for name in &rule.sinks {
+        let Some(sink) = config.sinks.get(name).cloned() else {
+            continue;
+        };
+        let name = name.clone();
+        let text = text.clone();
+        tokio::spawn(async move {
+            match timeout(DELIVERY_TIMEOUT, deliver(&sink, &text)).await {
+                Ok(Ok(())) => log::info!("Notified {name}: {text}"),
+                Ok(Err(e)) => log::error!("Failed to notify {name}: {e}"),
+                Err(_) => log::error!("Notifying {name} timed out after {DELIVERY_TIMEOUT:?}"),
+            }
+        });
+    }
+}
+
+async fn deliver(sink: &Sink, text: &str) -> anyhow::Result<()> {
+    match sink {
+        Sink::Email { to, from } => {
+            let mut mail = String::new();
+            if let Some(from) = from {
+                mail.push_str(&format!("From: {from}\n"));
+            }
+            // A line break in the alarm text would end the header and start another one
+            let subject: String = text
+                .chars()
+                .map(|c| if c == '\r' || c == '\n' { ' ' } else { c })
+                .collect();
+            mail.push_str(&format!(
+                "To: {}\nSubject: {subject}\n\n{text}\n",
+                to.join(", ")
+            ));
+            run("sendmail", &["-t"], &mail).await
+        }
+        Sink::Slack { webhook_url } => {
+            let body = serde_json::json!({ "text": text }).to_string();
+            post_json(webhook_url, &body).await
+        }
+        Sink::Telegram { bot_token, chat_id } => {
+            let url = format!("https://api.telegram.org/bot{bot_token}/sendMessage");
+            let body = serde_json::json!({ "chat_id": chat_id, "text": text }).to_string();
+            post_json(&url, &body).await
+        }
+    }
+}
+
+// The URL is handed to curl in a config on stdin rather than as an argument, since webhook URLs
+// and bot tokens are secrets and arguments are visible to every user of the host
+async fn post_json(url: &str, body: &str) -> anyhow::Result<()> {
+    run(
+        "curl",
+        &["--silent", "--show-error", "--fail", "--config", "-"],
+        &curl_config(url, body),
+    )
+    .await
+}
+
+fn curl_config(url: &str, body: &str) -> String {
+    let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!(
+        "url = \"{}\"\nheader = \"Content-Type: application/json\"\ndata-raw = \"{}\"\n",
+        quote(url),
+        quote(body)
+    )
+}
+
+// Run a delivery command with the given input on stdin
+async fn run(command: &str, args: &[&str], input: &str) -> anyhow::Result<()> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {command}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!("{command} exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_hours_wrap_around_midnight() {
+        let quiet_hours = QuietHours {
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        };
+        assert!(quiet_hours.contains(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+        assert!(quiet_hours.contains(NaiveTime::from_hms_opt(6, 59, 0).unwrap()));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn quotes_the_url_and_body_for_curl() {
+        let body = serde_json::json!({ "text": "Input \"CAM 1\" lost\nC:\\feeds" }).to_string();
+        let config = curl_config("https://api.telegram.org/bot123:ABC/sendMessage", &body);
+        assert_eq!(
+            config,
+            concat!(
+                "url = \"https://api.telegram.org/bot123:ABC/sendMessage\"\n",
+                "header = \"Content-Type: application/json\"\n",
+                r#"data-raw = "{\"text\":\"Input \\\"CAM 1\\\" lost\\nC:\\\\feeds\"}""#,
+                "\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Extension point for code embedding the service
+
+use anyhow::Result;
+
+use crate::service::{VideohubCommand, VideohubEvent};
+
+// Receives events and command results from a running VideohubService.
+// Callbacks run on the service's tasks, so they must return quickly; spawn for slow work.
+pub trait VideohubObserver: Send + Sync {
+    // Called for every event before it is pulsed to rship
+    fn on_event(&self, _event: &VideohubEvent) {}
+
+    // Called after a command has been executed, or failed to execute
+    fn on_command_result(&self, _command: &VideohubCommand, _result: &Result<()>) {}
+}
@@ -0,0 +1,158 @@
+//! Events on their way from the device task to the emission task. The device task hands its
+//! events to the outbox task over a bounded channel and goes back to its commands; the outbox task
+//! holds them until the emission task has room, so rship being slow to take pulses does not hold
+//! back commands, only the events themselves.
+
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::mpsc;
+
+use crate::clock::EventTime;
+use crate::qos::QosClass;
+use crate::service::VideohubEvent;
+
+// Events the device task can hand over before the outbox task takes them
+pub const OUTBOX_CHANNEL_CAPACITY: usize = 1024;
+// Events held for the emission task; beyond this the outbox task stops taking events, the
+// channel fills and the device task waits for room again
+pub const MAX_HELD_EVENTS: usize = 10_000;
+
+type Entry = (EventTime, VideohubEvent);
+
+// Held events in the order they were produced. A held bulk or background event is replaced in
+// place by a newer one for the same state; realtime events are all kept, as in the QoS queues.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    events: VecDeque<Entry>,
+    // Sequence number of the front event, and of the held event reporting each state
+    front: u64,
+    keyed: HashMap<String, u64>,
+}
+
+impl Outbox {
+    pub fn push(&mut self, time: EventTime, event: VideohubEvent) {
+        let key = event
+            .buffer_key()
+            .filter(|_| event.qos_class() != QosClass::Realtime);
+        if let Some(key) = key {
+            if let Some(&sequence) = self.keyed.get(&key) {
+                self.events[(sequence - self.front) as usize] = (time, event);
+                return;
+            }
+            self.keyed
+                .insert(key, self.front + self.events.len() as u64);
+        }
+        self.events.push_back((time, event));
+    }
+
+    pub fn pop(&mut self) -> Option<Entry> {
+        let entry = self.events.pop_front()?;
+        if let Some(key) = entry.1.buffer_key()
+            && self.keyed.get(&key) == Some(&self.front)
+        {
+            self.keyed.remove(&key);
+        }
+        self.front += 1;
+        Some(entry)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+}
+
+// Take the device task's events and pass them on to the emission task in order, until the device
+// task has stopped and everything held was passed on
+pub async fn run(mut events_rx: mpsc::Receiver<Entry>, emission_tx: mpsc::Sender<Entry>) {
+    let mut outbox = Outbox::default();
+    let mut open = true;
+    while open || !outbox.is_empty() {
+        tokio::select! {
+            event = events_rx.recv(), if open && outbox.len() < MAX_HELD_EVENTS => match event {
+                Some((time, event)) => outbox.push(time, event),
+                None => open = false,
+            },
+            permit = emission_tx.reserve(), if !outbox.is_empty() => match permit {
+                Ok(permit) => {
+                    if let Some(entry) = outbox.pop() {
+                        permit.send(entry);
+                    }
+                }
+                Err(_) => {
+                    log::debug!("Emission task gone, dropping {} held events", outbox.len());
+                    return;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(port: u32, label: &str) -> VideohubEvent {
+        VideohubEvent::Label {
+            port_type: "output".into(),
+            port,
+            label: label.into(),
+            original: None,
+            confirmed: true,
+        }
+    }
+
+    fn route(output: u32, input: u32) -> VideohubEvent {
+        VideohubEvent::Route {
+            output,
+            input,
+            input_label: None,
+            confirmed: true,
+            last_change: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn takes_events_while_the_emission_task_is_busy() {
+        // The emission queue is full and nobody takes from it
+        let (emission_tx, mut emission_rx) = mpsc::channel(1);
+        emission_tx
+            .send((EventTime::now(), route(9, 9)))
+            .await
+            .unwrap();
+        let (events_tx, events_rx) = mpsc::channel(4);
+        tokio::spawn(run(events_rx, emission_tx));
+
+        let events = [
+            route(0, 1),
+            label(0, "REC 1"),
+            route(0, 2),
+            label(1, "REC 2"),
+            label(0, "REC 1B"),
+        ];
+        for event in events.into_iter().cycle().take(100) {
+            tokio::time::timeout(
+                std::time::Duration::from_secs(1),
+                events_tx.send((EventTime::now(), event)),
+            )
+            .await
+            .expect("the device task waited for rship")
+            .unwrap();
+        }
+        drop(events_tx);
+
+        let mut received = Vec::new();
+        while let Some((_, event)) = emission_rx.recv().await {
+            received.push(event);
+        }
+        // Every route change arrives in order; each label only with its latest value, in the
+        // place of the first one held
+        assert_eq!(received.len(), 1 + 40 + 2);
+        assert!(matches!(received[1], VideohubEvent::Route { input: 1, .. }));
+        assert!(matches!(&received[2], VideohubEvent::Label { label, .. } if label == "REC 1B"));
+        assert!(matches!(received[3], VideohubEvent::Route { input: 2, .. }));
+        assert!(matches!(&received[4], VideohubEvent::Label { port: 1, .. }));
+    }
+}
@@ -0,0 +1,155 @@
+//! Per-output status composite. The route, labels, lock and take mode of an output, its route
+//! pending for the next take, and the executor's protections of it (route hold, frozen input,
+//! owner) in one `output-state` pulse on the output subtarget, sent whenever any of them changes,
+//! so UI builders subscribe to one emitter per output instead of joining four.
+
+use std::collections::HashMap;
+
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+use crate::emitters::OutputStateEmitter;
+use crate::freeze::InputFreezes;
+use crate::ownership::Ownership;
+use crate::service::VideohubEvent;
+use crate::take::PendingRoutes;
+
+// What is known about one output; ports are 0-indexed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputStatus {
+    pub input: Option<u32>,
+    pub input_label: Option<String>,
+    pub label: Option<String>,
+    // "unlocked", "owned" (locked by this executor) or "locked" (by another controller)
+    pub lock: &'static str,
+    pub take_mode: bool,
+    pub pending_input: Option<u32>,
+    pub held: bool,
+    pub frozen: bool,
+    pub owner: Option<String>,
+}
+
+impl OutputStatus {
+    pub fn data(&self, sequence: u64, time: &EventTime) -> OutputStateEmitter {
+        OutputStateEmitter {
+            input: self.input.map(|input| input + 1),
+            input_label: self.input_label.clone(),
+            label: self.label.clone(),
+            lock: self.lock.to_string(),
+            take_mode: self.take_mode,
+            pending_input: self.pending_input.map(|input| input + 1),
+            held: self.held,
+            frozen: self.frozen,
+            owner: self.owner.clone(),
+            sequence,
+            timestamp: time.timestamp.clone(),
+            monotonic_ms: time.monotonic_ms,
+        }
+    }
+}
+
+// The status last reported per output
+#[derive(Debug, Default)]
+pub struct OutputStatusMonitor {
+    reported: HashMap<u32, OutputStatus>,
+}
+
+impl OutputStatusMonitor {
+    // The outputs whose status moved since the last report. `held` tells whether a route hold
+    // keeps the output on its input.
+    pub fn changes(
+        &mut self,
+        state: &VideohubState,
+        pending: &PendingRoutes,
+        freezes: &InputFreezes,
+        ownership: Option<&Ownership>,
+        held: impl Fn(u32) -> bool,
+    ) -> Vec<VideohubEvent> {
+        let outputs = state
+            .device_info
+            .as_ref()
+            .and_then(|info| info.video_outputs)
+            .unwrap_or(state.video_output_routing.len() as u32);
+
+        let mut events = Vec::new();
+        for output in 0..outputs {
+            let input = state.video_output_routing.get(&output).copied();
+            let lock = if state.owned_locks.contains(&output) {
+                "owned"
+            } else if state.output_locks.get(&output).copied().unwrap_or(false) {
+                "locked"
+            } else {
+                "unlocked"
+            };
+            let status = OutputStatus {
+                input,
+                input_label: input.and_then(|input| state.input_labels.get(&input).cloned()),
+                label: state.output_labels.get(&output).cloned(),
+                lock,
+                take_mode: state.take_mode.get(&output).copied().unwrap_or(false),
+                pending_input: pending.input(output),
+                held: held(output),
+                frozen: input.is_some_and(|input| freezes.is_frozen(input)),
+                owner: ownership
+                    .and_then(|ownership| ownership.owner(output))
+                    .map(str::to_string),
+            };
+            if self.reported.get(&output) == Some(&status) {
+                continue;
+            }
+            self.reported.insert(output, status.clone());
+            events.push(VideohubEvent::OutputState { output, status });
+        }
+        events
+    }
+
+    // Report every output again after a reconnect, even if it did not change
+    pub fn reset(&mut self) {
+        self.reported.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_outputs_whose_composite_status_changed() {
+        let mut monitor = OutputStatusMonitor::default();
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 2);
+        state.video_output_routing.insert(1, 3);
+        state.input_labels.insert(2, "CAM 3".to_string());
+        state.output_locks.insert(1, true);
+        let mut pending = PendingRoutes::default();
+        let mut freezes = InputFreezes::default();
+
+        let events = monitor.changes(&state, &pending, &freezes, None, |_| false);
+        assert_eq!(events.len(), 2);
+        let VideohubEvent::OutputState { output, status } = &events[0] else {
+            panic!("not an output state event");
+        };
+        assert_eq!(*output, 0);
+        assert_eq!(status.input_label.as_deref(), Some("CAM 3"));
+        assert_eq!(status.lock, "unlocked");
+        assert!(
+            monitor
+                .changes(&state, &pending, &freezes, None, |_| false)
+                .is_empty()
+        );
+
+        pending.preset(0, 5);
+        freezes.freeze(2);
+        let events = monitor.changes(&state, &pending, &freezes, None, |output| output == 0);
+        assert_eq!(events.len(), 1);
+        let VideohubEvent::OutputState { status, .. } = &events[0] else {
+            panic!("not an output state event");
+        };
+        assert_eq!(status.pending_input, Some(5));
+        assert!(status.held);
+        assert!(status.frozen);
+
+        let data = status.data(1, &EventTime::now());
+        assert_eq!(data.input, Some(3));
+        assert_eq!(data.pending_input, Some(6));
+    }
+}
@@ -0,0 +1,311 @@
+//! Outputs assigned to named owners (productions, departments), so one executor can serve
+//! several teams without them changing each other's outputs
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::client::VideohubState;
+use crate::middleware::{CommandContext, CommandMiddleware};
+use crate::pending::ExpectedChange;
+use crate::service::VideohubCommand;
+
+// On-disk format; output numbers are 1-indexed like the rship actions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OwnershipFile {
+    // Owner name -> outputs it owns
+    #[serde(default)]
+    owners: BTreeMap<String, Vec<u32>>,
+    // Owner names allowed to change any output
+    #[serde(default)]
+    admins: Vec<String>,
+}
+
+// An action changing an output that belongs to someone else
+#[derive(Debug, Clone)]
+pub struct OwnershipViolation {
+    pub output: u32,
+    pub output_owner: String,
+}
+
+impl std::fmt::Display for OwnershipViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "output {} belongs to {}",
+            self.output + 1,
+            self.output_owner
+        )
+    }
+}
+
+impl std::error::Error for OwnershipViolation {}
+
+// Output owners, persisted to a JSON file (outputs are 0-indexed in memory)
+#[derive(Debug, Clone)]
+pub struct Ownership {
+    path: PathBuf,
+    owners: HashMap<u32, String>,
+    admins: HashSet<String>,
+}
+
+impl Ownership {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ownership file {}", path.display()))?;
+        let file: OwnershipFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse ownership file {}", path.display()))?;
+
+        let mut owners = HashMap::new();
+        for (owner, outputs) in file.owners {
+            for output in outputs.into_iter().filter(|output| *output > 0) {
+                if let Some(previous) = owners.insert(output - 1, owner.clone()) {
+                    log::warn!(
+                        "Output {output} is assigned to both {previous} and {owner}, using {owner}"
+                    );
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            owners,
+            admins: file.admins.into_iter().collect(),
+        })
+    }
+
+    // Write the current owners back to disk, so handovers survive a restart. Written to a
+    // temporary file and renamed, so a crash mid-write cannot leave a truncated ownership file
+    // that fails to load on the next start.
+    pub fn save(&self) -> Result<()> {
+        let mut owners: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for (output, owner) in &self.owners {
+            owners.entry(owner.clone()).or_default().push(output + 1);
+        }
+        owners
+            .values_mut()
+            .for_each(|outputs| outputs.sort_unstable());
+        let mut admins: Vec<String> = self.admins.iter().cloned().collect();
+        admins.sort();
+
+        let contents = serde_json::to_string_pretty(&OwnershipFile { owners, admins })?;
+        let temporary = self.path.with_extension("tmp");
+        std::fs::write(&temporary, contents)
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        std::fs::rename(&temporary, &self.path)
+            .with_context(|| format!("Failed to replace ownership file {}", self.path.display()))
+    }
+
+    // Hand outputs over to another owner. Returns the outputs with their previous owner.
+    pub fn transfer(&mut self, outputs: &[u32], to_owner: &str) -> Vec<(u32, Option<String>)> {
+        outputs
+            .iter()
+            .map(|&output| (output, self.owners.insert(output, to_owner.to_string())))
+            .collect()
+    }
+
+    // Owner of every assigned output
+    pub fn owners(&self) -> BTreeMap<u32, String> {
+        self.owners
+            .iter()
+            .map(|(output, owner)| (*output, owner.clone()))
+            .collect()
+    }
+
+    pub fn owner(&self, output: u32) -> Option<&str> {
+        self.owners.get(&output).map(String::as_str)
+    }
+
+    // The first of the outputs the caller may not change. Outputs without an owner are open to
+    // everyone; admins may change any output.
+    pub fn check(&self, outputs: &[u32], caller: Option<&str>) -> Result<(), OwnershipViolation> {
+        if caller.is_some_and(|caller| self.admins.contains(caller)) {
+            return Ok(());
+        }
+        for &output in outputs {
+            if let Some(owner) = self.owner(output)
+                && caller != Some(owner)
+            {
+                return Err(OwnershipViolation {
+                    output,
+                    output_owner: owner.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // The check for a command in the pipeline. Commands without an action (scripts, control
+    // listeners) and changes the executor writes for an action that was already checked are let
+    // through.
+    pub fn check_request(
+        &self,
+        outputs: &[u32],
+        context: &CommandContext,
+    ) -> Result<(), OwnershipViolation> {
+        match context.invocation {
+            Some(invocation) if context.source != "internal" => {
+                self.check(outputs, invocation.owner.as_deref())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Check the changes an action works out only once it runs (a salvo recall, a show file, a CSV
+    // import, staged changes), which the pipeline saw as the action's command without outputs
+    pub fn check_changes(
+        &self,
+        changes: &[ExpectedChange],
+        context: &CommandContext,
+    ) -> Result<(), OwnershipViolation> {
+        let outputs: Vec<u32> = changes.iter().filter_map(ExpectedChange::output).collect();
+        self.check_request(&outputs, context)
+    }
+}
+
+// Actions may only change outputs of the owner they act for
+impl CommandMiddleware for Ownership {
+    fn process(&self, _command: &mut VideohubCommand, _state: &VideohubState) -> Result<()> {
+        Ok(())
+    }
+
+    fn process_request(
+        &self,
+        command: &mut VideohubCommand,
+        context: &CommandContext,
+        _state: &VideohubState,
+    ) -> Result<()> {
+        Ok(self.check_request(&command.outputs(), context)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::salvo::SalvoStore;
+    use crate::service::CommandRequest;
+
+    fn ownership(name: &str) -> Ownership {
+        let path =
+            std::env::temp_dir().join(format!("ownership-{name}-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"owners": {"news": [1, 2], "sport": [3]}, "admins": ["mcr"]}"#,
+        )
+        .unwrap();
+        let ownership = Ownership::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        ownership
+    }
+
+    #[test]
+    fn only_owners_and_admins_change_owned_outputs() {
+        let ownership = ownership("check");
+        assert!(ownership.check(&[0, 1], Some("news")).is_ok());
+        assert!(ownership.check(&[0, 2], Some("mcr")).is_ok());
+        // Outputs without an owner are open to everyone
+        assert!(ownership.check(&[5], None).is_ok());
+        assert!(ownership.check(&[5], Some("sport")).is_ok());
+
+        let violation = ownership.check(&[5, 2, 0], Some("news")).unwrap_err();
+        assert_eq!(violation.output, 2);
+        assert_eq!(violation.output_owner, "sport");
+        // An action without an owner may not change owned outputs
+        let violation = ownership.check(&[0], None).unwrap_err();
+        assert_eq!(violation.to_string(), "output 1 belongs to news");
+    }
+
+    #[test]
+    fn saves_handovers_in_place_of_the_file() {
+        let path = std::env::temp_dir().join(format!("ownership-save-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"owners": {"news": [1, 2]}}"#).unwrap();
+        let mut ownership = Ownership::load(&path).unwrap();
+        ownership.transfer(&[1], "sport");
+        ownership.save().unwrap();
+
+        assert!(!path.with_extension("tmp").exists());
+        let ownership = Ownership::load(&path).unwrap();
+        assert_eq!(ownership.owner(0), Some("news"));
+        assert_eq!(ownership.owner(1), Some("sport"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn checks_only_actions_in_the_pipeline() {
+        let ownership = ownership("middleware");
+        let state = VideohubState::default();
+        let request = CommandRequest::from_action(
+            "set-input",
+            VideohubCommand::Route {
+                output: 2,
+                input: 0,
+            },
+        );
+        let mut command = request.command.clone();
+        let context = CommandContext {
+            source: request.source,
+            invocation: request.invocation.as_ref(),
+        };
+        let e = ownership
+            .process_request(&mut command, &context, &state)
+            .unwrap_err();
+        assert_eq!(e.downcast_ref::<OwnershipViolation>().unwrap().output, 2);
+
+        for context in [
+            CommandContext {
+                source: "internal",
+                invocation: request.invocation.as_ref(),
+            },
+            CommandContext {
+                source: "http",
+                invocation: None,
+            },
+        ] {
+            assert!(
+                ownership
+                    .process_request(&mut command, &context, &state)
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn checks_the_routes_of_a_recalled_salvo() {
+        let ownership = ownership("salvo");
+        let path = std::env::temp_dir().join(format!("salvos-owned-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 4);
+        state.video_output_routing.insert(2, 5);
+        let mut store = SalvoStore::load(path.clone()).unwrap();
+        store.capture("show", &state).unwrap();
+        state.video_output_routing.insert(0, 0);
+        state.video_output_routing.insert(2, 0);
+        let changes = store.recall("show", &state).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // The recall itself changes no outputs; the salvo's routes do
+        let request = CommandRequest::from_action(
+            "recall-salvo",
+            VideohubCommand::RecallSalvo {
+                name: "show".into(),
+            },
+        )
+        .owned_by(Some("news".into()));
+        assert!(request.command.outputs().is_empty());
+        let context = CommandContext {
+            source: request.source,
+            invocation: request.invocation.as_ref(),
+        };
+        let violation = ownership.check_changes(&changes, &context).unwrap_err();
+        assert_eq!(violation.output, 2);
+        assert_eq!(violation.output_owner, "sport");
+
+        // Changes the executor writes on its own are not checked
+        let context = CommandContext {
+            source: "internal",
+            invocation: None,
+        };
+        assert!(ownership.check_changes(&changes, &context).is_ok());
+    }
+}
@@ -0,0 +1,608 @@
+//! Tracking of commands whose effect has been emitted optimistically but not yet confirmed by the device
+
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+
+use anyhow::Result;
+use videohub::{Label, Route, VideohubMessage};
+
+use crate::client::{VideohubClient, VideohubState};
+use crate::service::{ActionInvocation, VideohubCommand, VideohubEvent};
+
+// How long to wait for the device to echo a change before rolling it back
+pub const PENDING_CHANGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Whether an ACK or NAK for a command of this category (see client::command_category) can refer
+// to pending changes
+pub fn replies_to_pending(category: &str) -> bool {
+    matches!(
+        category,
+        "route" | "input-label" | "output-label" | "unknown"
+    )
+}
+
+// A state change we expect the device to echo back after a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpectedChange {
+    Route { output: u32, input: u32 },
+    InputLabel { input: u32, label: String },
+    OutputLabel { output: u32, label: String },
+}
+
+impl ExpectedChange {
+    // Output the change affects; input labels affect none
+    pub fn output(&self) -> Option<u32> {
+        match self {
+            Self::Route { output, .. } | Self::OutputLabel { output, .. } => Some(*output),
+            Self::InputLabel { .. } => None,
+        }
+    }
+
+    // Whether both changes affect the same port and property
+    pub fn same_port(&self, other: &ExpectedChange) -> bool {
+        match (self, other) {
+            (Self::Route { output: a, .. }, Self::Route { output: b, .. }) => a == b,
+            (Self::InputLabel { input: a, .. }, Self::InputLabel { input: b, .. }) => a == b,
+            (Self::OutputLabel { output: a, .. }, Self::OutputLabel { output: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+
+    // The state the device last reported for the port this change affects
+    pub fn current_state(&self, state: &VideohubState) -> Option<ExpectedChange> {
+        match self {
+            Self::Route { output, .. } => {
+                state
+                    .video_output_routing
+                    .get(output)
+                    .map(|&input| Self::Route {
+                        output: *output,
+                        input,
+                    })
+            }
+            Self::InputLabel { input, .. } => {
+                state.input_labels.get(input).map(|label| Self::InputLabel {
+                    input: *input,
+                    label: label.clone(),
+                })
+            }
+            Self::OutputLabel { output, .. } => {
+                state
+                    .output_labels
+                    .get(output)
+                    .map(|label| Self::OutputLabel {
+                        output: *output,
+                        label: label.clone(),
+                    })
+            }
+        }
+    }
+
+    // Command that makes this change
+    pub fn to_command(&self) -> VideohubCommand {
+        match self.clone() {
+            Self::Route { output, input } => VideohubCommand::Route { output, input },
+            Self::InputLabel { input, label } => VideohubCommand::InputLabel { input, label },
+            Self::OutputLabel { output, label } => VideohubCommand::OutputLabel { output, label },
+        }
+    }
+
+    // Write changes to the device directly, bypassing the command pipeline
+    pub async fn send_all(client: &mut VideohubClient, changes: Vec<ExpectedChange>) -> Result<()> {
+        let (blocks, _) = into_changed_blocks(changes, client.state());
+        let messages = blocks.into_iter().map(|block| block.message).collect();
+        client.send_pipelined(messages).await
+    }
+
+    // Build the event describing this change
+    pub fn to_event(&self, confirmed: bool, input_labels: &HashMap<u32, String>) -> VideohubEvent {
+        match self {
+            Self::Route { output, input } => VideohubEvent::Route {
+                output: *output,
+                input: *input,
+                input_label: input_labels.get(input).cloned(),
+                confirmed,
+                last_change: None,
+            },
+            Self::InputLabel { input, label } => VideohubEvent::Label {
+                port_type: "input".to_string(),
+                port: *input,
+                label: label.clone(),
+                original: None,
+                confirmed,
+            },
+            Self::OutputLabel { output, label } => VideohubEvent::Label {
+                port_type: "output".to_string(),
+                port: *output,
+                label: label.clone(),
+                original: None,
+                confirmed,
+            },
+        }
+    }
+}
+
+// Changes of one kind written as a single protocol block, which the device acknowledges once
+#[derive(Debug)]
+pub struct ChangeBlock {
+    pub changes: Vec<ExpectedChange>,
+    pub message: VideohubMessage,
+}
+
+// Group changes into one block per kind (routes, input labels, output labels), leaving out
+// labels the device already has: label imports are mostly unchanged, and the device processes
+// (and echoes) every entry it is sent. Also returns how many labels were left out.
+pub fn into_changed_blocks(
+    changes: Vec<ExpectedChange>,
+    state: &VideohubState,
+) -> (Vec<ChangeBlock>, usize) {
+    let mut kinds = group(changes);
+    let mut unchanged = 0;
+    for labels in &mut kinds[1..] {
+        let before = labels.len();
+        labels.retain(|change| change.current_state(state).as_ref() != Some(change));
+        unchanged += before - labels.len();
+    }
+    (to_blocks(kinds), unchanged)
+}
+
+// When a port appears more than once, the last change wins
+fn group(changes: Vec<ExpectedChange>) -> [Vec<ExpectedChange>; 3] {
+    let mut kinds: [Vec<ExpectedChange>; 3] = Default::default();
+    for change in changes {
+        let kind = match change {
+            ExpectedChange::Route { .. } => &mut kinds[0],
+            ExpectedChange::InputLabel { .. } => &mut kinds[1],
+            ExpectedChange::OutputLabel { .. } => &mut kinds[2],
+        };
+        kind.retain(|existing| !existing.same_port(&change));
+        kind.push(change);
+    }
+    kinds
+}
+
+fn to_blocks(kinds: [Vec<ExpectedChange>; 3]) -> Vec<ChangeBlock> {
+    kinds
+        .into_iter()
+        .filter(|changes| !changes.is_empty())
+        .map(|changes| {
+            let message = match &changes[0] {
+                ExpectedChange::Route { .. } => VideohubMessage::VideoOutputRouting(
+                    changes
+                        .iter()
+                        .filter_map(|change| match change {
+                            ExpectedChange::Route { output, input } => Some(Route {
+                                to_output: *output,
+                                from_input: *input,
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                ExpectedChange::InputLabel { .. } => VideohubMessage::InputLabels(
+                    changes
+                        .iter()
+                        .filter_map(|change| match change {
+                            ExpectedChange::InputLabel { input, label } => Some(Label {
+                                id: *input,
+                                name: label.clone(),
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                ExpectedChange::OutputLabel { .. } => VideohubMessage::OutputLabels(
+                    changes
+                        .iter()
+                        .filter_map(|change| match change {
+                            ExpectedChange::OutputLabel { output, label } => Some(Label {
+                                id: *output,
+                                name: label.clone(),
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+            };
+            ChangeBlock { changes, message }
+        })
+        .collect()
+}
+
+// A command that has been sent and optimistically emitted
+#[derive(Debug, Clone)]
+pub struct PendingChange {
+    pub expected: ExpectedChange,
+    // Last state the device reported before the command, used for rollback
+    pub previous: Option<ExpectedChange>,
+    // The rship action that issued the command, for feedback
+    pub invocation: Option<ActionInvocation>,
+    // Protocol block the change was written in; the device acknowledges whole blocks
+    block: u64,
+    // Write of several changes for one action, see PendingChanges::push_write
+    write: Option<u64>,
+    acked: bool,
+    sent_at: Instant,
+    deadline: Instant,
+}
+
+impl PendingChange {
+    // Time since the command was sent
+    pub fn latency(&self) -> Duration {
+        self.sent_at.elapsed()
+    }
+}
+
+// The pending changes a report from the device resolved: the one it echoed, which is confirmed,
+// and the ones it overrode, which failed
+#[derive(Debug, Default)]
+pub struct Resolved {
+    pub echoed: Option<PendingChange>,
+    pub overridden: Vec<PendingChange>,
+}
+
+impl Resolved {
+    pub fn is_empty(&self) -> bool {
+        self.echoed.is_none() && self.overridden.is_empty()
+    }
+}
+
+// Changes written for one action, e.g. a salvo recall. The action is confirmed once all of
+// them are, or fails with the first of them that fails.
+#[derive(Debug)]
+struct Write {
+    invocation: ActionInvocation,
+    remaining: usize,
+    error: Option<String>,
+}
+
+// Pending changes in the order their commands were sent
+#[derive(Debug)]
+pub struct PendingChanges {
+    timeout: Duration,
+    changes: Vec<PendingChange>,
+    next_block: u64,
+    writes: HashMap<u64, Write>,
+    next_write: u64,
+}
+
+impl PendingChanges {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            changes: Vec::new(),
+            next_block: 0,
+            writes: HashMap::new(),
+            next_write: 0,
+        }
+    }
+
+    // Record a change that was just sent to the device
+    pub fn push(
+        &mut self,
+        expected: ExpectedChange,
+        previous: Option<ExpectedChange>,
+        invocation: Option<ActionInvocation>,
+    ) {
+        self.push_block(vec![(expected, previous, invocation)]);
+    }
+
+    // Record changes that were just sent to the device in a single protocol block
+    pub fn push_block(
+        &mut self,
+        changes: Vec<(
+            ExpectedChange,
+            Option<ExpectedChange>,
+            Option<ActionInvocation>,
+        )>,
+    ) {
+        self.push_changes(changes, None);
+    }
+
+    // Record the changes an action just wrote in one or more protocol blocks, each change with
+    // the state it replaces. The action's feedback is sent through settle once all of them
+    // are resolved.
+    pub fn push_write(
+        &mut self,
+        blocks: Vec<Vec<(ExpectedChange, Option<ExpectedChange>)>>,
+        invocation: ActionInvocation,
+    ) {
+        let write = self.next_write;
+        self.next_write += 1;
+        self.writes.insert(
+            write,
+            Write {
+                invocation,
+                remaining: blocks.iter().map(Vec::len).sum(),
+                error: None,
+            },
+        );
+        for block in blocks {
+            let changes = block
+                .into_iter()
+                .map(|(expected, previous)| (expected, previous, None))
+                .collect();
+            self.push_changes(changes, Some(write));
+        }
+    }
+
+    fn push_changes(
+        &mut self,
+        changes: Vec<(
+            ExpectedChange,
+            Option<ExpectedChange>,
+            Option<ActionInvocation>,
+        )>,
+        write: Option<u64>,
+    ) {
+        let block = self.next_block;
+        self.next_block += 1;
+        let now = Instant::now();
+        for (expected, previous, invocation) in changes {
+            self.changes.push(PendingChange {
+                expected,
+                previous,
+                invocation,
+                block,
+                write,
+                acked: false,
+                sent_at: now,
+                deadline: now + self.timeout,
+            });
+        }
+    }
+
+    // Count a resolved change (failed if there is an error) towards the write it belongs to.
+    // Returns the action and its outcome once the last change of the write is resolved.
+    pub fn settle(
+        &mut self,
+        change: &PendingChange,
+        error: Option<String>,
+    ) -> Option<(ActionInvocation, Option<String>)> {
+        let id = change.write?;
+        let write = self.writes.get_mut(&id)?;
+        write.remaining = write.remaining.saturating_sub(1);
+        if write.error.is_none() {
+            write.error = error;
+        }
+        if write.remaining > 0 {
+            return None;
+        }
+        self.writes
+            .remove(&id)
+            .map(|write| (write.invocation, write.error))
+    }
+
+    // Block of the oldest unacknowledged change
+    fn oldest_unacked_block(&self) -> Option<u64> {
+        self.changes
+            .iter()
+            .find(|change| !change.acked)
+            .map(|change| change.block)
+    }
+
+    // The device acknowledges blocks in order, so an ACK belongs to the oldest unacknowledged block
+    pub fn ack(&mut self) {
+        if let Some(block) = self.oldest_unacked_block() {
+            for change in self
+                .changes
+                .iter_mut()
+                .filter(|change| change.block == block)
+            {
+                change.acked = true;
+            }
+        }
+    }
+
+    // Remove and return the changes of the oldest unacknowledged block, which the NAK refers to
+    pub fn nak(&mut self) -> Vec<PendingChange> {
+        let Some(block) = self.oldest_unacked_block() else {
+            return Vec::new();
+        };
+        let (rejected, remaining) = self
+            .changes
+            .drain(..)
+            .partition(|change| change.block == block);
+        self.changes = remaining;
+        rejected
+    }
+
+    // The device reported new state for a port. The oldest pending change with the reported
+    // value is the one the device echoed; the changes for the port sent before it were
+    // overridden. Changes sent after it stay pending, their echoes follow. When no pending
+    // change has the reported value, every change for the port was overridden.
+    pub fn confirm(&mut self, reported: &ExpectedChange) -> Resolved {
+        let echoed = self
+            .changes
+            .iter()
+            .position(|change| change.expected == *reported);
+        let mut resolved = Resolved::default();
+        let mut index = 0;
+        self.changes.retain(|change| {
+            let position = index;
+            index += 1;
+            if !change.expected.same_port(reported)
+                || echoed.is_some_and(|echoed| position > echoed)
+            {
+                return true;
+            }
+            if Some(position) == echoed {
+                resolved.echoed = Some(change.clone());
+            } else {
+                resolved.overridden.push(change.clone());
+            }
+            false
+        });
+        resolved
+    }
+
+    // Like confirm, but only builds the reported change when something is pending, which
+    // during a prelude is usually nothing
+    pub fn confirm_with(&mut self, reported: impl FnOnce() -> ExpectedChange) -> Resolved {
+        if self.changes.is_empty() {
+            return Resolved::default();
+        }
+        self.confirm(&reported())
+    }
+
+    // Remove and return all changes that were not confirmed in time
+    pub fn take_expired(&mut self) -> Vec<PendingChange> {
+        let now = Instant::now();
+        let (expired, remaining): (Vec<_>, Vec<_>) = self
+            .changes
+            .drain(..)
+            .partition(|change| change.deadline <= now);
+        self.changes = remaining;
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // Drop everything, e.g. after the connection was lost. The writes are settled through the
+    // returned changes.
+    pub fn clear(&mut self) -> Vec<PendingChange> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_out_labels_the_device_already_has() {
+        let mut state = VideohubState::default();
+        state.input_labels.insert(0, "CAM 1".into());
+        state.input_labels.insert(1, "CAM 2".into());
+        state.output_labels.insert(0, "PGM".into());
+        let input_label = |input, label: &str| ExpectedChange::InputLabel {
+            input,
+            label: label.into(),
+        };
+
+        let (blocks, unchanged) = into_changed_blocks(
+            vec![
+                input_label(0, "CAM 1"),
+                // The last change for a port wins before comparing
+                input_label(1, "CAM 1"),
+                input_label(1, "CAM 2"),
+                input_label(2, "CAM 3"),
+                ExpectedChange::OutputLabel {
+                    output: 0,
+                    label: "PGM".into(),
+                },
+                ExpectedChange::Route {
+                    output: 0,
+                    input: 0,
+                },
+            ],
+            &state,
+        );
+        assert_eq!(unchanged, 3);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].changes, vec![input_label(2, "CAM 3")]);
+    }
+
+    fn route(output: u32, input: u32) -> ExpectedChange {
+        ExpectedChange::Route { output, input }
+    }
+
+    #[test]
+    fn resolves_every_pending_change_for_a_reported_port() {
+        let mut pending = PendingChanges::new(PENDING_CHANGE_TIMEOUT);
+        pending.push(route(0, 1), None, None);
+        pending.push(route(0, 2), None, None);
+        pending.push(route(0, 3), None, None);
+        pending.push(route(1, 1), None, None);
+
+        // The echo of the second write overrides the first; the third is still on its way
+        let resolved = pending.confirm(&route(0, 2));
+        assert_eq!(resolved.echoed.unwrap().expected, route(0, 2));
+        assert_eq!(resolved.overridden.len(), 1);
+        assert_eq!(resolved.overridden[0].expected, route(0, 1));
+        assert_eq!(pending.len(), 2);
+
+        // Another controller's route overrides everything still pending for the port
+        let resolved = pending.confirm_with(|| route(0, 9));
+        assert!(resolved.echoed.is_none());
+        assert_eq!(resolved.overridden[0].expected, route(0, 3));
+        assert_eq!(pending.len(), 1);
+        assert!(pending.confirm(&route(2, 0)).is_empty());
+    }
+
+    #[test]
+    fn acknowledges_and_rejects_blocks_in_order() {
+        let mut pending = PendingChanges::new(PENDING_CHANGE_TIMEOUT);
+        pending.push_block(vec![(route(0, 1), None, None), (route(1, 1), None, None)]);
+        pending.push(route(2, 1), None, None);
+        pending.push(route(3, 1), None, None);
+
+        // The ACK belongs to the first block, so the NAK rejects the second
+        pending.ack();
+        let rejected = pending.nak();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].expected, route(2, 1));
+        assert_eq!(pending.len(), 3);
+
+        pending.ack();
+        assert!(pending.nak().is_empty());
+        assert_eq!(pending.len(), 3);
+    }
+
+    #[test]
+    fn takes_changes_that_were_not_confirmed_in_time() {
+        let mut pending = PendingChanges::new(PENDING_CHANGE_TIMEOUT);
+        pending.push(route(0, 1), None, None);
+        assert!(pending.take_expired().is_empty());
+
+        let mut pending = PendingChanges::new(Duration::ZERO);
+        pending.push(route(0, 1), None, None);
+        let expired = pending.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn settles_a_write_once_all_its_changes_are_resolved() {
+        let invocation = ActionInvocation {
+            action: "recall-salvo".into(),
+            correlation_id: 1,
+            owner: None,
+            done: None,
+        };
+        let label = ExpectedChange::OutputLabel {
+            output: 0,
+            label: "PGM".into(),
+        };
+        let mut pending = PendingChanges::new(PENDING_CHANGE_TIMEOUT);
+        pending.push_write(
+            vec![
+                vec![(route(0, 1), None), (route(1, 1), None)],
+                vec![(label.clone(), None)],
+            ],
+            invocation,
+        );
+        assert_eq!(pending.len(), 3);
+
+        let echoed = pending.confirm(&route(0, 1)).echoed.unwrap();
+        assert!(pending.settle(&echoed, None).is_none());
+        let rejected = pending.nak();
+        assert_eq!(rejected[0].expected, route(1, 1));
+        assert!(
+            pending
+                .settle(&rejected[0], Some("rejected".into()))
+                .is_none()
+        );
+        let echoed = pending.confirm(&label).echoed.unwrap();
+        let (invocation, error) = pending.settle(&echoed, None).unwrap();
+        assert_eq!(invocation.action, "recall-salvo");
+        assert_eq!(error.as_deref(), Some("rejected"));
+        assert!(pending.is_empty());
+    }
+}
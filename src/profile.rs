@@ -0,0 +1,151 @@
+//! Named profiles (e.g. `rehearsal`, `show`, `maintenance`) bundling the protections, failover
+//! suspensions and emitter filters that suit one phase of an event, loaded from PROFILES_FILE.
+//! Switching profiles undoes what the previous profile set before applying the next one.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::failover::Failover;
+use crate::filter::EmitterFilter;
+use crate::freeze::InputFreezes;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    // Overrides CLAIMS_ADVISORY while the profile is active
+    #[serde(default)]
+    pub claims_advisory: Option<bool>,
+    // Inputs frozen while the profile is active (1-indexed)
+    #[serde(default)]
+    pub frozen_inputs: Vec<u32>,
+    // Failover rules whose automatic failover is suspended while the profile is active
+    #[serde(default)]
+    pub suspended_failover_rules: Vec<String>,
+    // EMITTER_FILTERS entries added to, or replacing, the configured ones
+    #[serde(default)]
+    pub emitter_filters: HashMap<String, String>,
+}
+
+impl Profile {
+    fn frozen_inputs(&self) -> impl Iterator<Item = u32> + '_ {
+        self.frozen_inputs
+            .iter()
+            .filter(|input| **input > 0)
+            .map(|input| input - 1)
+    }
+
+    // The emitter filters of the profile; invalid ones are skipped
+    pub fn emitter_filters(&self) -> HashMap<String, EmitterFilter> {
+        self.emitter_filters
+            .iter()
+            .filter_map(|(emitter, filter)| match EmitterFilter::parse(filter) {
+                Some(filter) => Some((emitter.clone(), filter)),
+                None => {
+                    log::warn!("Ignoring invalid filter \"{filter}\" for the {emitter} emitter");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+// The profiles by name, and the one active
+#[derive(Debug, Default)]
+pub struct Profiles {
+    profiles: BTreeMap<String, Profile>,
+    active: Option<String>,
+}
+
+impl Profiles {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read profiles file {}", path.display()))?;
+        let profiles = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse profiles file {}", path.display()))?;
+        Ok(Self {
+            profiles,
+            active: None,
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    // Switch to a profile: lift the freezes and suspensions of the active one, then apply the new
+    // profile's. Returns the new profile.
+    pub fn activate(
+        &mut self,
+        name: &str,
+        freezes: &mut InputFreezes,
+        failover: &mut Failover,
+    ) -> Result<&Profile> {
+        let next = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("no profile {name} (profiles: {})", self.names().join(", ")))?;
+        if let Some(previous) = self
+            .active
+            .as_ref()
+            .and_then(|active| self.profiles.get(active))
+        {
+            for input in previous.frozen_inputs() {
+                freezes.unfreeze(input);
+            }
+            for rule in &previous.suspended_failover_rules {
+                failover.set_enabled(rule, true, None)?;
+            }
+        }
+        for input in next.frozen_inputs() {
+            freezes.freeze(input);
+        }
+        for rule in &next.suspended_failover_rules {
+            failover.set_enabled(rule, false, None)?;
+        }
+        self.active = Some(name.to_string());
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_lifts_the_previous_profiles_freezes() {
+        let mut profiles = Profiles {
+            profiles: serde_json::from_str(
+                r#"{
+                    "rehearsal": { "claims_advisory": true },
+                    "show": { "frozen_inputs": [1, 2], "emitter_filters": { "matrix-summary": "off" } }
+                }"#,
+            )
+            .unwrap(),
+            active: None,
+        };
+        let mut freezes = InputFreezes::default();
+        let mut failover = Failover::default();
+
+        let show = profiles
+            .activate("show", &mut freezes, &mut failover)
+            .unwrap();
+        assert_eq!(
+            show.emitter_filters().get("matrix-summary"),
+            Some(&EmitterFilter::Off)
+        );
+        assert!(!freezes.freeze(0));
+
+        let rehearsal = profiles
+            .activate("rehearsal", &mut freezes, &mut failover)
+            .unwrap();
+        assert_eq!(rehearsal.claims_advisory, Some(true));
+        assert!(freezes.is_empty());
+        assert!(
+            profiles
+                .activate("maintenance", &mut freezes, &mut failover)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Priority classes for emitter pulses. While rship takes pulses slower than the device produces
+//! them, they queue up in the emission task and are sent most urgent class first, so e.g. a
+//! label import never delays a route change. Within a class pulses keep their order.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QosClass {
+    // Routes, locks, device status and replies to actions
+    Realtime,
+    // Labels, inventory and other state that arrives in large blocks
+    Bulk,
+    // Statistics and diagnostics
+    Background,
+}
+
+// One queue per class. A queued bulk or background pulse is replaced by a newer one for the same
+// state; realtime pulses are all delivered, since each route change matters.
+#[derive(Debug)]
+pub struct QosQueues<T> {
+    queues: [VecDeque<(Option<String>, T)>; 3],
+}
+
+impl<T> Default for QosQueues<T> {
+    fn default() -> Self {
+        Self {
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+        }
+    }
+}
+
+impl<T> QosQueues<T> {
+    // Queue a pulse; the key identifies the state it reports, None for one-off pulses
+    pub fn push(&mut self, class: QosClass, key: Option<String>, entry: T) {
+        let queue = &mut self.queues[class as usize];
+        if class != QosClass::Realtime && key.is_some() {
+            queue.retain(|(existing, _)| *existing != key);
+        }
+        queue.push_back((key, entry));
+    }
+
+    // The oldest pulse of the most urgent class that has any
+    pub fn pop(&mut self) -> Option<T> {
+        self.queues
+            .iter_mut()
+            .find_map(VecDeque::pop_front)
+            .map(|(_, entry)| entry)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_overtake_a_label_import() {
+        let mut queues = QosQueues::default();
+        let label = |port: u32| {
+            (
+                Some(format!("label:output:{port}")),
+                format!("label {port}"),
+            )
+        };
+        for port in 0..3 {
+            let (key, entry) = label(port);
+            queues.push(QosClass::Bulk, key, entry);
+        }
+        queues.push(QosClass::Background, None, "summary".to_string());
+        // A second label for port 1 replaces the queued one; both routes are kept
+        let (key, _) = label(1);
+        queues.push(QosClass::Bulk, key, "label 1 again".to_string());
+        queues.push(QosClass::Realtime, Some("route:0".into()), "route 0".into());
+        queues.push(
+            QosClass::Realtime,
+            Some("route:0".into()),
+            "route 0 again".into(),
+        );
+
+        let order: Vec<String> = std::iter::from_fn(|| queues.pop()).collect();
+        assert_eq!(
+            order,
+            vec![
+                "route 0",
+                "route 0 again",
+                "label 0",
+                "label 2",
+                "label 1 again",
+                "summary"
+            ]
+        );
+        assert!(queues.is_empty());
+    }
+}
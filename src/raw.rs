@@ -0,0 +1,169 @@
+//! Raw protocol passthrough for firmware features the executor does not model yet. With
+//! RAW_BLOCKS the send-raw-block action writes a protocol block as given and pulses the device's
+//! answer: its ACK or NAK, and any blocks it sends back under the same header.
+
+use anyhow::{Result, bail};
+use tokio::time::{Duration, Instant};
+use videohub::VideohubMessage;
+
+use crate::service::VideohubEvent;
+
+// How long blocks sent back under the header are collected after a raw block
+pub const RAW_RESPONSE_WINDOW: Duration = Duration::from_secs(1);
+
+// Longest raw block accepted
+const MAX_RAW_BLOCK_LEN: usize = 16 * 1024;
+
+// Blocks the executor models; they are changed through their own actions so ownership, claims,
+// pending changes and the emitters keep track of them
+const MODELLED_HEADERS: [&str; 13] = [
+    "PROTOCOL PREAMBLE:",
+    "VIDEOHUB DEVICE:",
+    "INPUT LABELS:",
+    "OUTPUT LABELS:",
+    "VIDEO OUTPUT ROUTING:",
+    "VIDEO OUTPUT LOCKS:",
+    "TAKE MODE:",
+    "VIDEO INPUT STATUS:",
+    "VIDEO OUTPUT STATUS:",
+    "CONFIGURATION:",
+    "NETWORK:",
+    "END PRELUDE:",
+    "PING:",
+];
+
+// A protocol block checked to be safe to write: one block, printable ASCII, not modelled
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawBlock {
+    pub header: String,
+    // Body lines, each ending in a newline
+    pub body: String,
+}
+
+impl RawBlock {
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("the block is empty");
+        }
+        if text.len() > MAX_RAW_BLOCK_LEN {
+            bail!("the block is longer than {MAX_RAW_BLOCK_LEN} bytes");
+        }
+        if let Some(c) = text
+            .chars()
+            .find(|c| *c != '\n' && !(c.is_ascii_graphic() || *c == ' '))
+        {
+            bail!("the block contains {c:?}; only printable ASCII is allowed");
+        }
+
+        let mut lines = text.lines();
+        let header = lines.next().unwrap_or_default().trim().to_string();
+        if !header.ends_with(':') {
+            bail!("the header \"{header}\" does not end in ':'");
+        }
+        if MODELLED_HEADERS.contains(&header.as_str()) || header.starts_with("NETWORK INTERFACE ") {
+            bail!("{header} blocks are handled by the executor; use their actions instead");
+        }
+        let mut body = String::new();
+        for line in lines {
+            // A blank line would end the block early and start another one
+            if line.trim().is_empty() {
+                bail!("the block contains a blank line; send one block at a time");
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        Ok(Self { header, body })
+    }
+
+    pub fn message(&self) -> VideohubMessage {
+        VideohubMessage::UnknownMessage(
+            self.header.as_bytes().to_vec().into(),
+            self.body.as_bytes().to_vec().into(),
+        )
+    }
+}
+
+// The device's answer to a raw block, collected until it is complete or the window ends
+#[derive(Debug)]
+pub struct RawExchange {
+    header: String,
+    reply: Option<&'static str>,
+    blocks: Vec<String>,
+    deadline: Instant,
+}
+
+impl RawExchange {
+    pub fn new(header: String) -> Self {
+        Self {
+            header,
+            reply: None,
+            blocks: Vec::new(),
+            deadline: Instant::now() + RAW_RESPONSE_WINDOW,
+        }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    // Record a block from the device, given the kind of command its ACK or NAK refers to.
+    // Returns whether the answer is complete: a NAK, or an ACK and a block under the header.
+    pub fn record(&mut self, message: &VideohubMessage, reply_category: Option<&str>) -> bool {
+        match message {
+            VideohubMessage::ACK if reply_category == Some("raw") && self.reply.is_none() => {
+                self.reply = Some("ack");
+            }
+            VideohubMessage::NAK if reply_category == Some("raw") && self.reply.is_none() => {
+                self.reply = Some("nak");
+            }
+            VideohubMessage::UnknownMessage(header, body)
+                if String::from_utf8_lossy(header).trim() == self.header =>
+            {
+                self.blocks.push(format!(
+                    "{}\n{}",
+                    self.header,
+                    String::from_utf8_lossy(body).trim_end()
+                ));
+            }
+            _ => return false,
+        }
+        self.reply == Some("nak") || (self.reply.is_some() && !self.blocks.is_empty())
+    }
+
+    pub fn finish(self) -> VideohubEvent {
+        VideohubEvent::RawResponse {
+            header: self.header,
+            reply: self.reply.unwrap_or("none"),
+            blocks: self.blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_blocks_and_collects_the_answer() {
+        let block = RawBlock::parse("MONITORING OUTPUT ROUTING:\r\n0 3\r\n1 4\r\n").unwrap();
+        assert_eq!(block.header, "MONITORING OUTPUT ROUTING:");
+        assert_eq!(block.body, "0 3\n1 4\n");
+        assert!(RawBlock::parse("VIDEO OUTPUT ROUTING:\n0 1").is_err());
+        assert!(RawBlock::parse("NETWORK INTERFACE 0:\nDynamic IP: true").is_err());
+        assert!(RawBlock::parse("SERIAL PORT ROUTING:\n0 1\n\nPING:").is_err());
+        assert!(RawBlock::parse("NO COLON\n0 1").is_err());
+        assert!(RawBlock::parse("FRAME LABELS:\n0 Ž").is_err());
+
+        let mut exchange = RawExchange::new(block.header.clone());
+        assert!(!exchange.record(&VideohubMessage::ACK, Some("route")));
+        assert!(!exchange.record(&VideohubMessage::ACK, Some("raw")));
+        assert!(exchange.record(&block.message(), None));
+        let VideohubEvent::RawResponse { reply, blocks, .. } = exchange.finish() else {
+            panic!("not a raw response");
+        };
+        assert_eq!(reply, "ack");
+        assert_eq!(blocks, vec!["MONITORING OUTPUT ROUTING:\n0 3\n1 4"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Telling a power-cycled router from a network blip. Each connection to the device is an epoch;
+//! when the prelude of a new epoch shows that the device restarted in between (the locks other
+//! controllers held are all gone, or the device reports an uptime shorter than the disconnect),
+//! it is reported, since a restarted router may have lost routes that were not saved.
+
+use tokio::time::{Duration, Instant};
+
+use crate::client::VideohubState;
+
+// CONFIGURATION setting with the device uptime in seconds, on firmware that reports one
+pub const UPTIME_SETTING: &str = "Uptime";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReboot {
+    // Connection epoch that found the device restarted
+    pub epoch: u64,
+    pub disconnected_for: Duration,
+    pub device_uptime: Option<Duration>,
+    // What shows the restart, e.g. "2 outputs locked by other controllers were unlocked"
+    pub evidence: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct RebootDetector {
+    epoch: u64,
+    // When the connection was lost, and how many outputs other controllers had locked then
+    lost: Option<(Instant, usize)>,
+}
+
+impl RebootDetector {
+    // A connection was established; returns its epoch, counting from 1
+    pub fn connected(&mut self) -> u64 {
+        self.epoch += 1;
+        self.epoch
+    }
+
+    // The connection was lost. Failed reconnects call this again; the first loss is kept.
+    pub fn disconnected(&mut self, state: &VideohubState) {
+        if self.lost.is_none() {
+            self.lost = Some((Instant::now(), foreign_locks(state)));
+        }
+    }
+
+    // Compare the full state of a new epoch with the one before the disconnect
+    pub fn check(&mut self, state: &VideohubState) -> Option<DeviceReboot> {
+        let (lost_at, locks_before) = self.lost.take()?;
+        let disconnected_for = lost_at.elapsed();
+        let device_uptime = device_uptime(state);
+
+        let mut evidence = Vec::new();
+        // Locks are released when the controller holding them disconnects, and other
+        // controllers stay connected through our network blip
+        if locks_before > 0 && foreign_locks(state) == 0 {
+            evidence.push(format!(
+                "{locks_before} outputs locked by other controllers were unlocked"
+            ));
+        }
+        if let Some(uptime) = device_uptime
+            && uptime <= disconnected_for
+        {
+            evidence.push(format!(
+                "device uptime {}s is shorter than the {}s disconnect",
+                uptime.as_secs(),
+                disconnected_for.as_secs()
+            ));
+        }
+
+        (!evidence.is_empty()).then_some(DeviceReboot {
+            epoch: self.epoch,
+            disconnected_for,
+            device_uptime,
+            evidence,
+        })
+    }
+}
+
+// Device uptime, if the firmware reports it
+pub fn device_uptime(state: &VideohubState) -> Option<Duration> {
+    let value = state.configuration.get(UPTIME_SETTING)?;
+    let seconds = value.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+fn foreign_locks(state: &VideohubState) -> usize {
+    state
+        .output_locks
+        .values()
+        .filter(|locked| **locked)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_foreign_locks_mean_a_restart() {
+        let mut detector = RebootDetector::default();
+        let mut state = VideohubState::default();
+        state.output_locks.insert(0, true);
+        state.output_locks.insert(1, false);
+        assert_eq!(detector.connected(), 1);
+
+        // A blip: the other controller still holds its lock
+        detector.disconnected(&state);
+        detector.connected();
+        assert_eq!(detector.check(&state), None);
+
+        detector.disconnected(&state);
+        state.output_locks.insert(0, false);
+        assert_eq!(detector.connected(), 3);
+        let reboot = detector.check(&state).unwrap();
+        assert_eq!(reboot.epoch, 3);
+        assert_eq!(
+            reboot.evidence,
+            vec!["1 outputs locked by other controllers were unlocked"]
+        );
+        // Only once per disconnect
+        assert_eq!(detector.check(&state), None);
+    }
+}
@@ -0,0 +1,327 @@
+//! Active/standby pairs of executors on the same device: the two exchange UDP heartbeats and
+//! only the elected leader sends commands, while both keep observing the device. Heartbeats
+//! also carry a checksum of the observed state, so a standby with a stale view is noticed
+//! before it has to take over.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, interval};
+
+use crate::clock::EventTime;
+use crate::service::{self, CommandRequest, EventSender, VideohubCommand, VideohubEvent};
+use crate::snapshot::VideohubStateSnapshot;
+
+// UDP port heartbeats are received on if REDUNDANCY_PORT is unset
+pub const DEFAULT_REDUNDANCY_PORT: u16 = 9910;
+// How often each executor announces itself to its peer
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+// How long the peer may stay silent before it is considered gone
+pub const DEFAULT_PEER_TIMEOUT: Duration = Duration::from_secs(3);
+// How often the observed state is checksummed and compared with the peer's
+pub const DEFAULT_CHECKSUM_INTERVAL: Duration = Duration::from_secs(5);
+// How long the checksums may differ before alarming; changes reach the two executors at
+// slightly different times
+pub const DEFAULT_DIVERGENCE_GRACE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Heartbeat {
+    // Distinguishes the two executors when their priorities are equal
+    instance: String,
+    priority: u32,
+    leader: bool,
+    // Checksum of the observed device state, while connected
+    #[serde(default)]
+    state_checksum: Option<u64>,
+}
+
+// Leadership as seen by this executor
+#[derive(Debug)]
+pub struct Election {
+    instance: String,
+    priority: u32,
+    timeout: Duration,
+    started: Instant,
+    leader: bool,
+    // Latest heartbeat of the peer and when it arrived
+    peer: Option<(Heartbeat, Instant)>,
+    // Whether the role was reported yet; the first decision is reported even if it is standby
+    announced: bool,
+}
+
+impl Election {
+    pub fn new(instance: String, priority: u32, timeout: Duration) -> Self {
+        Self {
+            instance,
+            priority,
+            timeout,
+            started: Instant::now(),
+            leader: false,
+            peer: None,
+            announced: false,
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+
+    pub fn peer_alive(&self) -> bool {
+        self.peer
+            .as_ref()
+            .is_some_and(|(_, received)| received.elapsed() < self.timeout)
+    }
+
+    // Checksum in the peer's latest heartbeat, if it is alive and connected to the device
+    fn peer_checksum(&self) -> Option<u64> {
+        self.peer
+            .as_ref()
+            .filter(|_| self.peer_alive())
+            .and_then(|(peer, _)| peer.state_checksum)
+    }
+
+    fn received(&mut self, heartbeat: Heartbeat) {
+        self.peer = Some((heartbeat, Instant::now()));
+    }
+
+    // Whether this executor beats the peer when both or neither claim leadership
+    fn outranks(&self, peer: &Heartbeat) -> bool {
+        (self.priority, &peer.instance) > (peer.priority, &self.instance)
+    }
+
+    // Re-evaluate leadership; returns the new role if it changed, and the first role once the
+    // peer was heard or the initial wait is over. A running leader is never preempted by a
+    // returning peer, so a flapping peer doesn't bounce control back and forth.
+    pub fn decide(&mut self) -> Option<bool> {
+        let leader = match &self.peer {
+            Some((peer, _)) if self.peer_alive() => match (self.leader, peer.leader) {
+                (true, true) | (false, false) => self.outranks(peer),
+                (leader, _) => leader,
+            },
+            // Wait one timeout after starting, so a restarted executor doesn't take over from a
+            // leader it hasn't heard yet
+            _ => self.leader || self.started.elapsed() >= self.timeout,
+        };
+        let settled = self.peer_alive() || self.started.elapsed() >= self.timeout;
+        let announce = !self.announced && settled;
+        (leader != self.leader || announce).then(|| {
+            self.leader = leader;
+            self.announced = true;
+            leader
+        })
+    }
+}
+
+// Checksum of the state both executors should agree on: routes, labels, locks and take mode.
+// Locks are compared regardless of which executor holds them.
+pub fn state_checksum(snapshot: &VideohubStateSnapshot) -> u64 {
+    #[derive(Serialize)]
+    struct Observed<'a> {
+        routes: &'a BTreeMap<u32, u32>,
+        input_labels: &'a BTreeMap<u32, String>,
+        output_labels: &'a BTreeMap<u32, String>,
+        locked_outputs: BTreeSet<u32>,
+        take_mode: &'a BTreeMap<u32, bool>,
+    }
+    let observed = Observed {
+        routes: &snapshot.routes,
+        input_labels: &snapshot.input_labels,
+        output_labels: &snapshot.output_labels,
+        locked_outputs: snapshot
+            .output_locks
+            .iter()
+            .filter(|(_, locked)| **locked)
+            .map(|(output, _)| *output)
+            .chain(snapshot.owned_locks.iter().copied())
+            .collect(),
+        take_mode: &snapshot.take_mode,
+    };
+    // FNV-1a, so both executors agree regardless of how they were built
+    serde_json::to_vec(&observed)
+        .unwrap_or_default()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// Tracks how long the two executors have disagreed about the device state
+#[derive(Debug)]
+pub struct Divergence {
+    grace: Duration,
+    since: Option<Instant>,
+    alarmed: bool,
+}
+
+impl Divergence {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            since: None,
+            alarmed: false,
+        }
+    }
+
+    // Compare the latest checksums; returns the alarm state when an alarm starts or ends
+    pub fn compare(&mut self, local: u64, peer: u64) -> Option<bool> {
+        if local == peer {
+            self.since = None;
+            return std::mem::take(&mut self.alarmed).then_some(false);
+        }
+        let since = *self.since.get_or_insert_with(Instant::now);
+        if self.alarmed || since.elapsed() < self.grace {
+            return None;
+        }
+        self.alarmed = true;
+        Some(true)
+    }
+}
+
+// Exchange heartbeats with the peer and queue a leadership command whenever the role changes
+pub async fn run(
+    port: u16,
+    peer: String,
+    priority: u32,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+) -> Result<()> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to bind redundancy port {port}"))?;
+    log::info!("Redundancy: heartbeats on port {port} with peer {peer}, priority {priority}");
+
+    let instance = format!("{}-{}", std::process::id(), EventTime::now().timestamp);
+    let mut election = Election::new(instance.clone(), priority, DEFAULT_PEER_TIMEOUT);
+    let mut heartbeat_tick = interval(DEFAULT_HEARTBEAT_INTERVAL);
+    let mut checksum_tick = interval(DEFAULT_CHECKSUM_INTERVAL);
+    let mut local_checksum = None;
+    let mut divergence = Divergence::new(DEFAULT_DIVERGENCE_GRACE);
+    let mut packet = [0u8; 1024];
+
+    loop {
+        tokio::select! {
+            _ = heartbeat_tick.tick() => {
+                let heartbeat = Heartbeat {
+                    instance: instance.clone(),
+                    priority,
+                    leader: election.is_leader(),
+                    state_checksum: local_checksum,
+                };
+                if let Err(e) = socket.send_to(&serde_json::to_vec(&heartbeat)?, &peer).await {
+                    log::debug!("Failed to send heartbeat to {peer}: {e}");
+                }
+            }
+            _ = checksum_tick.tick() => {
+                local_checksum = service::snapshot(&command_tx)
+                    .await
+                    .ok()
+                    .filter(|snapshot| snapshot.connected)
+                    .map(|snapshot| state_checksum(&snapshot));
+                let peer_checksum = election.peer_checksum();
+                if let (Some(local), Some(peer)) = (local_checksum, peer_checksum)
+                    && let Some(alarm) = divergence.compare(local, peer)
+                {
+                    if alarm {
+                        log::warn!("Redundancy: observed state differs from the peer's ({local:016x} vs {peer:016x})");
+                    } else {
+                        log::info!("Redundancy: observed state matches the peer's again");
+                    }
+                    if let Err(e) = event_tx
+                        .send(VideohubEvent::StateDivergence {
+                            alarm,
+                            local_checksum: format!("{local:016x}"),
+                            peer_checksum: format!("{peer:016x}"),
+                        })
+                        .await
+                    {
+                        log::error!("Failed to send state divergence event: {e}");
+                    }
+                }
+            }
+            received = socket.recv_from(&mut packet) => {
+                // Errors here are per packet, e.g. an ICMP unreachable for an earlier heartbeat
+                // while the peer is down, and must not end the election
+                let (len, from) = match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        log::debug!("Failed to receive heartbeat: {e}");
+                        continue;
+                    }
+                };
+                match serde_json::from_slice::<Heartbeat>(&packet[..len]) {
+                    Ok(heartbeat) => election.received(heartbeat),
+                    Err(e) => log::debug!("Ignoring malformed heartbeat from {from}: {e}"),
+                }
+            }
+        }
+
+        if let Some(leader) = election.decide() {
+            log::info!(
+                "Redundancy: now {} (peer {})",
+                if leader { "leader" } else { "standby" },
+                if election.peer_alive() {
+                    "alive"
+                } else {
+                    "silent"
+                }
+            );
+            command_tx
+                .send(CommandRequest::internal(VideohubCommand::SetLeadership {
+                    leader,
+                    peer_alive: election.peer_alive(),
+                }))
+                .await
+                .context("service is shutting down")?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(priority: u32, leader: bool) -> Heartbeat {
+        Heartbeat {
+            instance: "peer".into(),
+            priority,
+            leader,
+            state_checksum: None,
+        }
+    }
+
+    #[test]
+    fn higher_priority_wins_and_the_leader_is_not_preempted() {
+        let mut election = Election::new("self".into(), 1, DEFAULT_PEER_TIMEOUT);
+        election.received(heartbeat(0, false));
+        assert_eq!(election.decide(), Some(true));
+
+        // A higher priority peer coming back finds a leader and stays on standby
+        election.received(heartbeat(5, false));
+        assert_eq!(election.decide(), None);
+
+        // Both claiming leadership (e.g. after a network split) resolves by priority
+        election.received(heartbeat(5, true));
+        assert_eq!(election.decide(), Some(false));
+        assert_eq!(election.decide(), None);
+    }
+
+    #[test]
+    fn reports_standby_once_a_leading_peer_is_heard() {
+        let mut election = Election::new("self".into(), 5, DEFAULT_PEER_TIMEOUT);
+        assert_eq!(election.decide(), None);
+        election.received(heartbeat(0, true));
+        assert_eq!(election.decide(), Some(false));
+        assert_eq!(election.decide(), None);
+    }
+
+    #[test]
+    fn alarms_once_on_divergence_and_clears_on_agreement() {
+        let mut divergence = Divergence::new(Duration::ZERO);
+        assert_eq!(divergence.compare(1, 1), None);
+        assert_eq!(divergence.compare(1, 2), Some(true));
+        assert_eq!(divergence.compare(1, 3), None);
+        assert_eq!(divergence.compare(3, 3), Some(false));
+    }
+}
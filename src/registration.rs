@@ -0,0 +1,97 @@
+//! Registration of output and input subtargets with rship. The SDK calls wait for the server
+//! rather than fail, so a server restarting mid-registration would stall the setup and leave
+//! subtargets missing. Each subtarget gets a timeout and a few attempts; those that still could
+//! not be registered are reported on `registration-status` and tried again once rship reconnects.
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use tokio::time::Duration;
+
+pub const DEFAULT_REGISTRATION_TIMEOUT: Duration = Duration::from_secs(10);
+// Attempts per registration pass, each waiting up to the timeout
+const REGISTRATION_ATTEMPTS: u32 = 3;
+
+#[derive(Debug)]
+pub struct Registration {
+    timeout: Duration,
+    registered: BTreeSet<String>,
+    missing: BTreeSet<String>,
+}
+
+impl Registration {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            registered: BTreeSet::new(),
+            missing: BTreeSet::new(),
+        }
+    }
+
+    // Run the registration of one part of the target tree; None if every attempt timed out
+    pub async fn register<T, F, Fut>(&mut self, name: &str, mut attempt: F) -> Option<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        for number in 1..=REGISTRATION_ATTEMPTS {
+            match tokio::time::timeout(self.timeout, attempt()).await {
+                Ok(registered) => {
+                    self.missing.remove(name);
+                    self.registered.insert(name.to_string());
+                    return Some(registered);
+                }
+                Err(_) => log::warn!(
+                    "Registering {name} with rship timed out after {:?} (attempt {number} of {REGISTRATION_ATTEMPTS})",
+                    self.timeout
+                ),
+            }
+        }
+        log::error!("Could not register {name} with rship; trying again once it reconnects");
+        self.missing.insert(name.to_string());
+        None
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn registered(&self) -> usize {
+        self.registered.len()
+    }
+
+    pub fn missing(&self) -> Vec<String> {
+        self.missing.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn retries_a_hanging_registration() {
+        let mut registration = Registration::new(Duration::from_millis(10));
+        let mut calls = 0;
+        let registered = registration
+            .register("output 1", || {
+                calls += 1;
+                let call = calls;
+                async move {
+                    if call == 1 {
+                        std::future::pending::<()>().await;
+                    }
+                    call
+                }
+            })
+            .await;
+        assert_eq!(registered, Some(2));
+
+        let never = registration
+            .register("output 2", || std::future::pending::<()>())
+            .await;
+        assert_eq!(never, None);
+        assert!(!registration.is_complete());
+        assert_eq!(registration.registered(), 1);
+        assert_eq!(registration.missing(), vec!["output 2"]);
+    }
+}
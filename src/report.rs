@@ -0,0 +1,268 @@
+//! Commissioning report: device identity, port counts, labels, routing, locks and network settings
+//! as one document, for the show's technical documentation. Written as Markdown if the path ends
+//! in .md, as JSON otherwise.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+
+// Ports are 1-indexed like the rship actions
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportInput {
+    pub input: u32,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportOutput {
+    pub output: u32,
+    pub label: String,
+    pub input: Option<u32>,
+    pub input_label: String,
+    // "unlocked", "owned" (by this executor) or "locked" (by another client)
+    pub lock: &'static str,
+    pub take_mode: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportInterface {
+    pub name: String,
+    pub mac_address: Option<String>,
+    pub dynamic_ip: Option<bool>,
+    pub current_addresses: Option<String>,
+    pub current_gateway: Option<String>,
+    pub static_addresses: Option<String>,
+    pub static_gateway: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommissioningReport {
+    // When the report was captured (ISO-8601)
+    pub generated: String,
+    pub model_name: Option<String>,
+    pub friendly_name: Option<String>,
+    pub unique_id: Option<String>,
+    // Protocol version the firmware speaks
+    pub protocol_version: Option<String>,
+    pub video_inputs: Option<u32>,
+    pub video_outputs: Option<u32>,
+    pub video_monitoring_outputs: Option<u32>,
+    pub video_processing_units: Option<u32>,
+    pub serial_ports: Option<u32>,
+    pub inputs: Vec<ReportInput>,
+    pub outputs: Vec<ReportOutput>,
+    pub network_interfaces: Vec<ReportInterface>,
+    // CONFIGURATION block settings
+    pub configuration: BTreeMap<String, String>,
+}
+
+impl CommissioningReport {
+    pub fn from_state(state: &VideohubState) -> Self {
+        let info = state.device_info.clone().unwrap_or_default();
+        let num_inputs = info.video_inputs.unwrap_or(state.input_labels.len() as u32);
+        let num_outputs = info
+            .video_outputs
+            .unwrap_or(state.output_labels.len() as u32);
+        let label = |labels: &std::collections::HashMap<u32, String>, port: u32| {
+            labels.get(&port).cloned().unwrap_or_default()
+        };
+
+        Self {
+            generated: EventTime::now().timestamp,
+            model_name: info.model_name,
+            friendly_name: info.friendly_name,
+            unique_id: info.unique_id,
+            protocol_version: state.protocol_version.clone(),
+            video_inputs: info.video_inputs,
+            video_outputs: info.video_outputs,
+            video_monitoring_outputs: info.video_monitoring_outputs,
+            video_processing_units: info.video_processing_units,
+            serial_ports: info.serial_ports,
+            inputs: (0..num_inputs)
+                .map(|input| ReportInput {
+                    input: input + 1,
+                    label: label(&state.input_labels, input),
+                })
+                .collect(),
+            outputs: (0..num_outputs)
+                .map(|output| {
+                    let input = state.video_output_routing.get(&output).copied();
+                    ReportOutput {
+                        output: output + 1,
+                        label: label(&state.output_labels, output),
+                        input: input.map(|input| input + 1),
+                        input_label: input
+                            .map(|input| label(&state.input_labels, input))
+                            .unwrap_or_default(),
+                        lock: if state.owned_locks.contains(&output) {
+                            "owned"
+                        } else if state.output_locks.get(&output) == Some(&true) {
+                            "locked"
+                        } else {
+                            "unlocked"
+                        },
+                        take_mode: state.take_mode.get(&output).copied(),
+                    }
+                })
+                .collect(),
+            network_interfaces: state
+                .network_interfaces
+                .iter()
+                .map(|interface| ReportInterface {
+                    name: interface.name.clone(),
+                    mac_address: interface.mac_address.clone(),
+                    dynamic_ip: interface.dynamic_ip,
+                    current_addresses: interface.current_addresses.clone(),
+                    current_gateway: interface.current_gateway.clone(),
+                    static_addresses: interface.static_addresses.clone(),
+                    static_gateway: interface.static_gateway.clone(),
+                })
+                .collect(),
+            configuration: state
+                .configuration
+                .iter()
+                .map(|(setting, value)| (setting.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".into());
+        let count = |value: Option<u32>| value.map_or("-".into(), |value| value.to_string());
+        // Pipes would end the table cell
+        let cell = |value: &str| value.replace('|', "\\|");
+
+        let mut markdown = String::new();
+        let _ = writeln!(
+            markdown,
+            "# Commissioning report: {}\n",
+            cell(&text(&self.friendly_name))
+        );
+        let _ = writeln!(markdown, "Captured {}\n", self.generated);
+
+        let _ = writeln!(markdown, "## Device\n");
+        let _ = writeln!(markdown, "| | |\n|---|---|");
+        for (name, value) in [
+            ("Model", text(&self.model_name)),
+            ("Name", text(&self.friendly_name)),
+            ("Unique ID", text(&self.unique_id)),
+            ("Protocol version", text(&self.protocol_version)),
+            ("Video inputs", count(self.video_inputs)),
+            ("Video outputs", count(self.video_outputs)),
+            ("Monitoring outputs", count(self.video_monitoring_outputs)),
+            ("Processing units", count(self.video_processing_units)),
+            ("Serial ports", count(self.serial_ports)),
+        ] {
+            let _ = writeln!(markdown, "| {name} | {} |", cell(&value));
+        }
+
+        let _ = writeln!(markdown, "\n## Network\n");
+        if self.network_interfaces.is_empty() {
+            let _ = writeln!(markdown, "No network settings reported.");
+        } else {
+            let _ = writeln!(
+                markdown,
+                "| Interface | MAC address | DHCP | Addresses | Gateway | Static addresses | Static gateway |\n|---|---|---|---|---|---|---|"
+            );
+            for interface in &self.network_interfaces {
+                let _ = writeln!(
+                    markdown,
+                    "| {} | {} | {} | {} | {} | {} | {} |",
+                    cell(&interface.name),
+                    text(&interface.mac_address),
+                    interface
+                        .dynamic_ip
+                        .map_or("-", |dynamic| if dynamic { "yes" } else { "no" }),
+                    text(&interface.current_addresses),
+                    text(&interface.current_gateway),
+                    text(&interface.static_addresses),
+                    text(&interface.static_gateway),
+                );
+            }
+        }
+
+        let _ = writeln!(markdown, "\n## Inputs\n");
+        let _ = writeln!(markdown, "| Input | Label |\n|---|---|");
+        for input in &self.inputs {
+            let _ = writeln!(markdown, "| {} | {} |", input.input, cell(&input.label));
+        }
+
+        let _ = writeln!(markdown, "\n## Outputs\n");
+        let _ = writeln!(
+            markdown,
+            "| Output | Label | Input | Input label | Lock | Take mode |\n|---|---|---|---|---|---|"
+        );
+        for output in &self.outputs {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} | {} | {} |",
+                output.output,
+                cell(&output.label),
+                count(output.input),
+                cell(&output.input_label),
+                output.lock,
+                output
+                    .take_mode
+                    .map_or("-", |enabled| if enabled { "on" } else { "off" }),
+            );
+        }
+
+        if !self.configuration.is_empty() {
+            let _ = writeln!(markdown, "\n## Configuration\n");
+            let _ = writeln!(markdown, "| Setting | Value |\n|---|---|");
+            for (setting, value) in &self.configuration {
+                let _ = writeln!(markdown, "| {} | {} |", cell(setting), cell(value));
+            }
+        }
+        markdown
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let is_markdown = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("md"));
+        let contents = if is_markdown {
+            self.to_markdown()
+        } else {
+            serde_json::to_string_pretty(self)? + "\n"
+        };
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_routing_and_locks_per_output() {
+        let mut state = VideohubState::default();
+        state
+            .input_labels
+            .extend([(0, "Cam 1".into()), (1, "Cam|2".into())]);
+        state
+            .output_labels
+            .extend([(0, "PGM".into()), (1, "Mon".into())]);
+        state.video_output_routing.extend([(0, 1)]);
+        state.output_locks.extend([(0, true), (1, false)]);
+        state.owned_locks.insert(1);
+
+        let report = CommissioningReport::from_state(&state);
+        assert_eq!(report.inputs.len(), 2);
+        assert_eq!(report.outputs[0].input, Some(2));
+        assert_eq!(report.outputs[0].input_label, "Cam|2");
+        assert_eq!(report.outputs[0].lock, "locked");
+        assert_eq!(report.outputs[1].input, None);
+        assert_eq!(report.outputs[1].lock, "owned");
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| 1 | PGM | 2 | Cam\\|2 | locked | - |"));
+        assert!(markdown.contains("No network settings reported."));
+    }
+}
@@ -0,0 +1,301 @@
+//! Named salvos: routing configurations saved from the device with `save-salvo` and written back
+//! with `recall-salvo`, so operators jump between known setups (rehearsal, show, maintenance)
+//! without a show file per setup. Salvos are persisted to SALVOS_FILE and listed on `salvos`.
+
+use anyhow::{Context, Result, bail};
+use rship_sdk::TargetProxy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+use crate::actions::{DeleteSalvoAction, RecallSalvoAction, SaveSalvoAction, add_command_action};
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+use crate::emitters::SalvoSummary;
+use crate::pending::ExpectedChange;
+use crate::service::{CommandRequest, CommandScope, VideohubCommand, VideohubEvent};
+use crate::translations::DisplayNames;
+
+// A saved routing; outputs and inputs are 1-indexed like the rship actions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Salvo {
+    pub routes: BTreeMap<u32, u32>,
+    pub saved_at: String,
+}
+
+// Salvos by name, persisted to a JSON file
+#[derive(Debug)]
+pub struct SalvoStore {
+    path: PathBuf,
+    salvos: BTreeMap<String, Salvo>,
+}
+
+impl SalvoStore {
+    // Load the salvos, starting with none if the file does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let salvos = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read salvos file {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse salvos file {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, salvos })
+    }
+
+    fn save(&self) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.salvos)?;
+        std::fs::write(&self.path, contents)
+            .with_context(|| format!("Failed to write salvos file {}", self.path.display()))
+    }
+
+    // Save the current routing under the name, replacing a salvo of the same name; returns the
+    // number of routes saved
+    pub fn capture(&mut self, name: &str, state: &VideohubState) -> Result<usize> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("salvo name must not be empty");
+        }
+        let salvo = Salvo {
+            routes: state
+                .video_output_routing
+                .iter()
+                .map(|(&output, &input)| (output + 1, input + 1))
+                .collect(),
+            saved_at: EventTime::now().timestamp,
+        };
+        let routes = salvo.routes.len();
+        self.salvos.insert(name.to_string(), salvo);
+        self.save()?;
+        Ok(routes)
+    }
+
+    // The routes of the salvo that differ from the device, as changes to write
+    pub fn recall(&self, name: &str, state: &VideohubState) -> Result<Vec<ExpectedChange>> {
+        let salvo = self
+            .salvos
+            .get(name.trim())
+            .with_context(|| format!("no salvo named {name}"))?;
+        Ok(salvo
+            .routes
+            .iter()
+            .filter(|(output, _)| **output > 0)
+            .map(|(&output, &input)| (output - 1, input.saturating_sub(1)))
+            .filter(|(output, input)| state.video_output_routing.get(output) != Some(input))
+            .map(|(output, input)| ExpectedChange::Route { output, input })
+            .collect())
+    }
+
+    // Forget the salvo; returns whether there was one
+    pub fn delete(&mut self, name: &str) -> Result<bool> {
+        if self.salvos.remove(name.trim()).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn event(&self) -> VideohubEvent {
+        VideohubEvent::Salvos {
+            salvos: self
+                .salvos
+                .iter()
+                .map(|(name, salvo)| SalvoSummary {
+                    name: name.clone(),
+                    routes: salvo.routes.len() as u32,
+                    saved_at: salvo.saved_at.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn save_request(data: SaveSalvoAction) -> CommandRequest {
+    CommandRequest::from_action("save-salvo", VideohubCommand::SaveSalvo { name: data.name })
+}
+
+// A recall writes routes, so it acts for the owner named in the payload
+pub fn recall_request(data: RecallSalvoAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "recall-salvo",
+        VideohubCommand::RecallSalvo { name: data.name },
+    )
+    .owned_by(data.owner)
+}
+
+pub fn delete_request(data: DeleteSalvoAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "delete-salvo",
+        VideohubCommand::DeleteSalvo { name: data.name },
+    )
+}
+
+pub async fn add_actions(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    add_command_action(
+        target,
+        display_names,
+        "Save Salvo",
+        "save-salvo",
+        command_tx,
+        save_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Recall Salvo",
+        "recall-salvo",
+        command_tx,
+        recall_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Delete Salvo",
+        "delete-salvo",
+        command_tx,
+        delete_request,
+    )
+    .await;
+}
+
+// Run a salvo command on the device task. The routes of a recalled salvo get the ownership check
+// the recall itself could not get in the pipeline, and are confirmed once all of them are echoed.
+pub async fn execute(
+    store: Option<&mut SalvoStore>,
+    command: &VideohubCommand,
+    scope: &mut CommandScope<'_>,
+) -> Result<()> {
+    let Some(store) = store else {
+        bail!("SALVOS_FILE is not set");
+    };
+    match command {
+        VideohubCommand::SaveSalvo { name } => {
+            let routes = store.capture(name, scope.state)?;
+            log::info!("Saved {routes} routes as salvo {name}");
+            send_salvos(store, scope).await;
+        }
+        VideohubCommand::RecallSalvo { name } => {
+            let changes = store.recall(name, scope.state)?;
+            scope.check_changes(&changes).await?;
+            log::info!("Recalling salvo {name}: {} routes differ", changes.len());
+            scope.write_changes(changes);
+        }
+        VideohubCommand::DeleteSalvo { name } => {
+            if !store.delete(name)? {
+                bail!("no salvo named {name}");
+            }
+            log::info!("Deleted salvo {name}");
+            send_salvos(store, scope).await;
+        }
+        command => bail!("not a salvo command: {command:?}"),
+    }
+    Ok(())
+}
+
+async fn send_salvos(store: &SalvoStore, scope: &CommandScope<'_>) {
+    if let Err(e) = scope.event_tx.send(store.event()).await {
+        log::error!("Failed to send salvos event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ownership::Ownership;
+    use crate::service::EventSender;
+
+    #[test]
+    fn saves_and_recalls_salvos_across_restarts() {
+        let path = std::env::temp_dir().join(format!("salvos-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 2);
+        state.video_output_routing.insert(1, 3);
+
+        let mut store = SalvoStore::load(path.clone()).unwrap();
+        assert_eq!(store.capture("rehearsal", &state).unwrap(), 2);
+        assert!(store.capture(" ", &state).is_err());
+
+        state.video_output_routing.insert(1, 7);
+        let store = SalvoStore::load(path.clone()).unwrap();
+        assert_eq!(
+            store.recall("rehearsal", &state).unwrap(),
+            vec![ExpectedChange::Route {
+                output: 1,
+                input: 3
+            }]
+        );
+        assert!(store.recall("show", &state).is_err());
+        let VideohubEvent::Salvos { salvos } = store.event() else {
+            panic!("not a salvos event");
+        };
+        assert_eq!(salvos[0].name, "rehearsal");
+        assert_eq!(salvos[0].routes, 2);
+
+        let mut store = store;
+        assert!(store.delete("rehearsal").unwrap());
+        assert!(!store.delete("rehearsal").unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn recalls_only_salvos_the_caller_may_write() {
+        let path = std::env::temp_dir().join(format!("salvos-recall-{}.json", std::process::id()));
+        let owners = path.with_extension("owners.json");
+        let _ = std::fs::remove_file(&path);
+        std::fs::write(&owners, r#"{"owners": {"news": [1], "sport": [3]}}"#).unwrap();
+        let ownership = Ownership::load(&owners).unwrap();
+        let _ = std::fs::remove_file(&owners);
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 4);
+        state.video_output_routing.insert(2, 5);
+        let mut store = SalvoStore::load(path.clone()).unwrap();
+        store.capture("show", &state).unwrap();
+        state.video_output_routing.insert(0, 0);
+        state.video_output_routing.insert(2, 0);
+        let _ = std::fs::remove_file(&path);
+
+        let request = recall_request(RecallSalvoAction {
+            name: "show".into(),
+            owner: Some("news".into()),
+        });
+        assert_eq!(request.source, "rship");
+        let (command_tx, mut command_rx) = mpsc::channel(1);
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let event_tx = EventSender(event_tx);
+        let mut invocation = request.invocation;
+        let mut scope = CommandScope {
+            source: request.source,
+            invocation: &mut invocation,
+            state: &state,
+            ownership: Some(&ownership),
+            command_tx: &command_tx,
+            event_tx: &event_tx,
+        };
+
+        // Output 3 belongs to sport, so nothing of the salvo is written
+        let e = execute(Some(&mut store), &request.command, &mut scope)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "output 3 belongs to sport");
+        assert!(matches!(
+            event_rx.recv().await,
+            Some((_, VideohubEvent::OwnershipViolation { output: 2, .. }))
+        ));
+        assert!(command_rx.try_recv().is_err());
+
+        // Without SALVOS_FILE there is nothing to recall
+        let e = execute(None, &request.command, &mut scope)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "SALVOS_FILE is not set");
+    }
+}
@@ -0,0 +1,134 @@
+//! Rhai scripts reacting to events and issuing commands (enabled with the `scripting` feature)
+//!
+//! Scripts may define any of these handlers (ports are 1-indexed):
+//! `on_route(output, input)`, `on_label(port_type, port, label)`, `on_lock(output, locked)`,
+//! `on_device(connected)`. They can call `route(output, input)`, `set_input_label(input, label)`,
+//! `set_output_label(output, label)` and `print(message)`. Scripts have no file or network access.
+
+use anyhow::{Result, anyhow};
+use rhai::{AST, Dynamic, Engine, FuncArgs, INT, Scope};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use crate::observer::VideohubObserver;
+use crate::service::{CommandRequest, VideohubCommand, VideohubEvent};
+
+// Upper bound on the work a single handler invocation may do
+const MAX_OPERATIONS: u64 = 100_000;
+
+// A compiled user script and the engine it runs in
+pub struct ScriptHost {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHost {
+    // Compile a script whose commands are sent through the given channel
+    pub fn load(path: &Path, command_tx: mpsc::Sender<CommandRequest>) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(32);
+        engine.on_print(|message| log::info!("[script] {message}"));
+        engine.on_debug(|message, _, _| log::debug!("[script] {message}"));
+
+        let tx = command_tx.clone();
+        engine.register_fn("route", move |output: INT, input: INT| {
+            send_command(
+                &tx,
+                VideohubCommand::Route {
+                    output: to_port(output),
+                    input: to_port(input),
+                },
+            );
+        });
+        let tx = command_tx.clone();
+        engine.register_fn("set_input_label", move |input: INT, label: &str| {
+            send_command(
+                &tx,
+                VideohubCommand::InputLabel {
+                    input: to_port(input),
+                    label: label.to_string(),
+                },
+            );
+        });
+        let tx = command_tx;
+        engine.register_fn("set_output_label", move |output: INT, label: &str| {
+            send_command(
+                &tx,
+                VideohubCommand::OutputLabel {
+                    output: to_port(output),
+                    label: label.to_string(),
+                },
+            );
+        });
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("Failed to compile script {}: {e}", path.display()))?;
+        log::info!("Loaded script {}", path.display());
+
+        Ok(Self { engine, ast })
+    }
+
+    fn call(&self, handler: &str, args: impl FuncArgs) {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == handler)
+        {
+            return;
+        }
+        if let Err(e) = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, handler, args)
+        {
+            log::error!("Script handler {handler} failed: {e}");
+        }
+    }
+}
+
+impl VideohubObserver for ScriptHost {
+    // Run the script handler for an event, if the script defines one.
+    // Optimistic (unconfirmed) changes are not passed to scripts.
+    fn on_event(&self, event: &VideohubEvent) {
+        match event {
+            VideohubEvent::Route {
+                output,
+                input,
+                confirmed: true,
+                ..
+            } => self.call("on_route", (from_port(*output), from_port(*input))),
+            VideohubEvent::Label {
+                port_type,
+                port,
+                label,
+                confirmed: true,
+                ..
+            } => self.call(
+                "on_label",
+                (port_type.clone(), from_port(*port), label.clone()),
+            ),
+            VideohubEvent::OutputLock { output, locked } => {
+                self.call("on_lock", (from_port(*output), *locked))
+            }
+            VideohubEvent::DeviceStatus { connected, .. } => self.call("on_device", (*connected,)),
+            _ => {}
+        }
+    }
+}
+
+// Script ports are 1-indexed
+fn to_port(port: INT) -> u32 {
+    (port.clamp(1, u32::MAX as INT) - 1) as u32
+}
+
+fn from_port(port: u32) -> INT {
+    port as INT + 1
+}
+
+// Commands are queued without waiting so a handler never blocks event processing
+fn send_command(tx: &mpsc::Sender<CommandRequest>, command: VideohubCommand) {
+    if let Err(e) = tx.try_send(CommandRequest::internal(command).via("script")) {
+        log::error!("Failed to queue script command: {e}");
+    }
+}
@@ -0,0 +1,335 @@
+//! Cycling outputs through lists of inputs at a fixed dwell time
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::service::{CommandRequest, EventSender, VideohubCommand, VideohubEvent};
+
+// Shortest dwell of a sequence or rotation. Faster steps queue routes quicker than the device
+// echoes them, and nobody can watch an input for less.
+pub const MIN_DWELL: Duration = Duration::from_millis(100);
+
+// Parameters of a sequence (ports are 0-indexed)
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub output: u32,
+    pub inputs: Vec<u32>,
+    pub dwell: Duration,
+    pub repeat: bool,
+}
+
+// Rotation group file
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotationsConfig {
+    #[serde(default)]
+    pub groups: Vec<RotationGroup>,
+}
+
+// A set of outputs rotating through a shared pool of inputs (ports are 1-indexed)
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotationGroup {
+    pub name: String,
+    pub outputs: Vec<u32>,
+    pub inputs: Vec<u32>,
+    pub dwell_ms: u64,
+    // Start the rotation as soon as the device is connected
+    #[serde(default)]
+    pub autostart: bool,
+}
+
+impl Sequence {
+    pub fn new(output: u32, inputs: Vec<u32>, dwell: Duration, repeat: bool) -> Result<Self> {
+        if inputs.is_empty() {
+            anyhow::bail!("sequence for output {} has no inputs", output + 1);
+        }
+        check_dwell(dwell)?;
+        Ok(Self {
+            output,
+            inputs,
+            dwell,
+            repeat,
+        })
+    }
+}
+
+fn check_dwell(dwell: Duration) -> Result<()> {
+    if dwell < MIN_DWELL {
+        anyhow::bail!(
+            "dwell of {} ms is below the minimum of {} ms",
+            dwell.as_millis(),
+            MIN_DWELL.as_millis()
+        );
+    }
+    Ok(())
+}
+
+impl RotationsConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rotations file {}", path.display()))?;
+        let config: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse rotations file {}", path.display()))?;
+        for group in &config.groups {
+            check_dwell(Duration::from_millis(group.dwell_ms))
+                .with_context(|| format!("Invalid rotation group {}", group.name))?;
+        }
+        log::info!("Loaded {} rotation groups", config.groups.len());
+        Ok(config)
+    }
+}
+
+impl RotationGroup {
+    // One looping sequence per output. Each output starts further into the pool,
+    // so the outputs of a wall show different inputs at any time.
+    pub fn sequences(&self) -> Vec<Sequence> {
+        let inputs: Vec<u32> = self
+            .inputs
+            .iter()
+            .map(|&input| input.clamp(1, u32::MAX) - 1)
+            .collect();
+        if inputs.is_empty() {
+            return Vec::new();
+        }
+
+        self.outputs
+            .iter()
+            .enumerate()
+            .map(|(index, &output)| {
+                let mut offset_inputs = inputs.clone();
+                offset_inputs.rotate_left(index % inputs.len());
+                Sequence {
+                    output: output.clamp(1, u32::MAX) - 1,
+                    inputs: offset_inputs,
+                    dwell: Duration::from_millis(self.dwell_ms),
+                    repeat: true,
+                }
+            })
+            .collect()
+    }
+}
+
+// Running sequences, at most one per output, by the id of the run and its stop token.
+// A sequence that finishes removes itself.
+type Running = Arc<Mutex<HashMap<u32, (u64, CancellationToken)>>>;
+
+pub struct Sequencer {
+    running: Running,
+    next_id: u64,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+}
+
+impl Sequencer {
+    pub fn new(command_tx: mpsc::Sender<CommandRequest>, event_tx: EventSender) -> Self {
+        Self {
+            running: Running::default(),
+            next_id: 0,
+            command_tx,
+            event_tx,
+        }
+    }
+
+    // Start a sequence, replacing the one already running on its output
+    pub fn start(&mut self, sequence: Sequence) {
+        self.stop(sequence.output);
+        log::info!(
+            "Starting sequence of {} inputs on output {}",
+            sequence.inputs.len(),
+            sequence.output
+        );
+        let id = self.next_id;
+        self.next_id += 1;
+        let stop = CancellationToken::new();
+        lock(&self.running).insert(sequence.output, (id, stop.clone()));
+        spawn_sequence(
+            sequence,
+            id,
+            stop,
+            self.running.clone(),
+            self.command_tx.clone(),
+            self.event_tx.clone(),
+        );
+    }
+
+    // Stop the sequence on an output, returning whether one was running
+    pub fn stop(&mut self, output: u32) -> bool {
+        let Some((_, stop)) = lock(&self.running).remove(&output) else {
+            return false;
+        };
+        stop.cancel();
+        true
+    }
+
+    pub fn stop_all(&mut self) {
+        for (_, (_, stop)) in lock(&self.running).drain() {
+            stop.cancel();
+        }
+    }
+
+    pub fn is_running(&self, output: u32) -> bool {
+        lock(&self.running).contains_key(&output)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Run a sequence on its own task, routing through the command channel like any other client,
+// until the stop token is cancelled or a non-repeating sequence ends. The final progress event
+// is sent either way.
+fn spawn_sequence(
+    sequence: Sequence,
+    id: u64,
+    stopped: CancellationToken,
+    running: Running,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+) {
+    tokio::spawn(async move {
+        let mut step = 0;
+        loop {
+            let input = sequence.inputs[step];
+            let route = VideohubCommand::Route {
+                output: sequence.output,
+                input,
+            };
+            if command_tx
+                .send(CommandRequest::internal(route))
+                .await
+                .is_err()
+            {
+                break;
+            }
+            send_progress(&event_tx, &sequence, step, true).await;
+
+            tokio::select! {
+                _ = stopped.cancelled() => break,
+                _ = tokio::time::sleep(sequence.dwell) => {}
+            }
+
+            if step + 1 < sequence.inputs.len() {
+                step += 1;
+            } else if sequence.repeat {
+                step = 0;
+            } else {
+                break;
+            }
+        }
+        send_progress(&event_tx, &sequence, step, false).await;
+        // Unless a newer sequence has replaced this one on the output
+        let mut running = lock(&running);
+        if running
+            .get(&sequence.output)
+            .is_some_and(|(running_id, _)| *running_id == id)
+        {
+            running.remove(&sequence.output);
+        }
+    });
+}
+
+async fn send_progress(event_tx: &EventSender, sequence: &Sequence, step: usize, running: bool) {
+    if let Err(e) = event_tx
+        .send(VideohubEvent::SequenceProgress {
+            output: sequence.output,
+            step: step as u32,
+            total: sequence.inputs.len() as u32,
+            input: sequence.inputs[step],
+            running,
+        })
+        .await
+    {
+        log::error!("Failed to send sequence progress event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequencer() -> (
+        Sequencer,
+        mpsc::Receiver<CommandRequest>,
+        mpsc::Receiver<(crate::clock::EventTime, VideohubEvent)>,
+    ) {
+        let (command_tx, command_rx) = mpsc::channel(16);
+        let (event_tx, event_rx) = mpsc::channel(16);
+        (
+            Sequencer::new(command_tx, EventSender(event_tx)),
+            command_rx,
+            event_rx,
+        )
+    }
+
+    async fn routed_input(commands: &mut mpsc::Receiver<CommandRequest>) -> u32 {
+        match commands.recv().await.unwrap().command {
+            VideohubCommand::Route { input, .. } => input,
+            command => panic!("unexpected command {command:?}"),
+        }
+    }
+
+    // Wait for the final progress event, skipping those of the steps
+    async fn finished(events: &mut mpsc::Receiver<(crate::clock::EventTime, VideohubEvent)>) {
+        while let Some((_, event)) = events.recv().await {
+            if let VideohubEvent::SequenceProgress { running: false, .. } = event {
+                return;
+            }
+        }
+        panic!("the sequence never finished");
+    }
+
+    #[test]
+    fn rejects_dwell_below_the_minimum() {
+        assert!(Sequence::new(0, vec![1], Duration::from_millis(99), false).is_err());
+        assert!(Sequence::new(0, Vec::new(), MIN_DWELL, false).is_err());
+        assert!(Sequence::new(0, vec![1], MIN_DWELL, false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn repeats_until_stopped() {
+        let (mut sequencer, mut commands, mut events) = sequencer();
+        sequencer.start(Sequence {
+            output: 3,
+            inputs: vec![1, 2],
+            dwell: Duration::from_millis(5),
+            repeat: true,
+        });
+        let mut inputs = Vec::new();
+        for _ in 0..5 {
+            inputs.push(routed_input(&mut commands).await);
+        }
+        assert_eq!(inputs, vec![1, 2, 1, 2, 1]);
+
+        assert!(sequencer.stop(3));
+        assert!(!sequencer.is_running(3));
+        assert!(!sequencer.stop(3));
+        finished(&mut events).await;
+    }
+
+    #[tokio::test]
+    async fn removes_sequences_that_ran_to_the_end() {
+        let (mut sequencer, mut commands, mut events) = sequencer();
+        sequencer.start(Sequence {
+            output: 3,
+            inputs: vec![1, 2],
+            dwell: Duration::from_millis(5),
+            repeat: false,
+        });
+        assert!(sequencer.is_running(3));
+        assert_eq!(routed_input(&mut commands).await, 1);
+        assert_eq!(routed_input(&mut commands).await, 2);
+        finished(&mut events).await;
+        // The task removes itself right after the final progress event
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!sequencer.is_running(3));
+        assert!(!sequencer.stop(3));
+    }
+}
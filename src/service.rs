@@ -2,49 +2,603 @@
 
 use anyhow::Result;
 use rship_sdk::{ActionArgs, EmitterArgs, InstanceArgs, SdkClient, TargetArgs};
-use tokio::sync::mpsc;
-use tokio::time::{Duration, interval};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::sync::{Notify, mpsc, oneshot};
+use tokio::time::{Duration, interval, interval_at};
 use videohub::{DeviceInfo, VideohubMessage};
 
 use crate::actions::{
-    SetInputAction, SetInputLabelAction, SetLabelAction, SetLockAction, SetOutputLabelAction,
-    SetOutputLockAction, SetRouteAction, SetTakeModeAction, SetTakeModeOnThisOutputAction,
+    ClaimControlAction, ExportCsvAction, ExportUsageReportAction, FailoverAction,
+    FreezeInputAction, GenerateReportAction, GetInventoryAction, GetPortInfoAction,
+    GetRoutingTableAction, HoldRouteAction, IdentifyDeviceAction, ImportCsvAction,
+    LabelOutputRangeAction, LoadShowAction, LockOutputRangeAction, PortRef, RebootDeviceAction,
+    ReleaseControlAction, ReleaseHoldAction, RotationAction, RouteOutputRangeAction,
+    RouteToOutputAction, SendRawBlockAction, SequenceInputsAction, SetDestinationsAction,
+    SetDeviceAddressAction, SetDeviceConfigurationAction, SetInputAction, SetInputLabelAction,
+    SetLabelAction, SetLockAction, SetLogLevelAction, SetOutputLabelAction, SetOutputLockAction,
+    SetProfileAction, SetProtocolTraceAction, SetRouteAction, SetRuleEnabledAction,
+    SetTakeModeAction, SetTakeModeOnThisOutputAction, SimulateCommandAction, StopSequenceAction,
+    SweepTestAction, TransferOutputOwnershipAction, UnfreezeInputAction,
 };
-use crate::client::{NetworkInterface, VideohubClient};
+use crate::activity::ChangeWindow;
+use crate::batch::{self, BatchStep};
+use crate::buffer::PulseBuffer;
+use crate::bus::CommandBus;
+use crate::capabilities::{PortCapabilities, format_mismatch, port_capabilities};
+use crate::charset::LabelTransliteration;
+use crate::claim::ControlClaim;
+use crate::client::{
+    IDENTIFY_SETTING, NetworkInterface, PRELUDE_QUIET, ProtocolTraceEntry, REBOOT_SETTING,
+    VideohubClient, VideohubState, is_connection_lost,
+};
+use crate::clock::EventTime;
+use crate::config::{RouteLabelWarming, ServiceConfig, StartupPolicy};
+#[cfg(any(feature = "http", feature = "osc"))]
+use crate::control;
+use crate::diff::StateDiff;
+use crate::drift::{DriftMonitor, DriftScore};
 use crate::emitters::{
-    DeviceStatusEmitter, InputChangedEmitter, LabelChangedEmitter, LockChangedEmitter,
-    NetworkInterfaceEmitter, TakeModeOnThisOutputEmitter,
+    ActionFeedbackEmitter, AutoFailoverEmitter, BatchProgressEmitter, BusyOutput,
+    ChangeSummaryEmitter, CommandRejectedEmitter, ControlClaimEmitter, DeviceAddressEmitter,
+    DeviceConfigurationEmitter, DeviceInventoryEmitter, DeviceRebootDetectedEmitter,
+    DeviceStatusEmitter, DiscoveredVideohub, DiscoveredVideohubsEmitter, DivergedOutput,
+    DriftEmitter, FreezeViolationEmitter, InputChangedEmitter, IntentDivergenceEmitter,
+    InterruptedOperationEmitter, LabelChangedEmitter, LeadershipChangedEmitter, LockChangedEmitter,
+    MatrixSummaryEmitter, NetworkInterfaceEmitter, OutputStateEmitter, OwnershipChangedEmitter,
+    OwnershipViolationEmitter, PendingRoute, PendingRoutesEmitter, PerformanceDegradedEmitter,
+    PortCountMismatchEmitter, PortInfoEmitter, ProfileEmitter, ProtocolTraceEmitter,
+    RawResponseEmitter, RegistrationStatusEmitter, RoutedOutputsEmitter, RshipEndpointEmitter,
+    SalvoSummary, SalvosEmitter, SequenceProgressEmitter, SimulationResultEmitter,
+    StagedChangesEmitter, StateDivergenceEmitter, SweepProgressEmitter,
+    TakeModeOnThisOutputEmitter, TransactionStatusEmitter, UnknownBlockEmitter, WatchdogEmitter,
+};
+use crate::failover::Failover;
+use crate::filter::{EmitterFilter, EmitterFilters};
+use crate::flap::{
+    DEFAULT_FLAP_THRESHOLD, DEFAULT_MAX_RECONNECT_DELAY, DEFAULT_STABILITY_WINDOW, FlapDetector,
+};
+use crate::freeze::InputFreezes;
+use crate::health::Health;
+use crate::hooks::HookRunner;
+use crate::journal::Journal;
+use crate::labels::LabelStore;
+use crate::latency::LatencyMonitor;
+use crate::logging::LogControl;
+use crate::metadata::PortMetadata;
+use crate::middleware::{
+    AuditLog, CommandContext, CommandMiddleware, ResolveLabels, ValidatePorts,
+};
+use crate::notifications::Notifier;
+use crate::observer::VideohubObserver;
+use crate::outbox::{self, OUTBOX_CHANNEL_CAPACITY};
+use crate::output_status::{OutputStatus, OutputStatusMonitor};
+use crate::ownership::{Ownership, OwnershipViolation};
+use crate::pending::{
+    self, ExpectedChange, PENDING_CHANGE_TIMEOUT, PendingChange, PendingChanges, Resolved,
+    replies_to_pending,
 };
+use crate::profile::Profiles;
+use crate::qos::{QosClass, QosQueues};
+use crate::raw::{RawBlock, RawExchange};
+use crate::reboot::RebootDetector;
+use crate::redundancy::{self, DEFAULT_REDUNDANCY_PORT};
+use crate::registration::{DEFAULT_REGISTRATION_TIMEOUT, Registration};
+use crate::report::CommissioningReport;
+use crate::salvo::{self, SalvoStore};
+use crate::sequence::{RotationsConfig, Sequence, Sequencer};
+use crate::shadow::{Intent, IntentMismatch};
+use crate::sheet;
+use crate::show::ShowFile;
+use crate::simulation::{self, ActionPayload, Prediction};
+use crate::snapshot::{RouteChange, VideohubStateSnapshot};
+use crate::staging::{
+    self, DEFAULT_STAGED_CHANGES_TIMEOUT, StagedChange, StagedChanges, queue_changes,
+    queue_changes_for,
+};
+use crate::status::StatusLine;
+use crate::summary::{DEFAULT_MATRIX_SUMMARY_INTERVAL, MatrixSummary};
+use crate::sweep::{Sweep, Sweeper};
+use crate::take::{self, PendingRoutes};
+use crate::targets::OutputTargets;
+use crate::transactions::{self, DEFAULT_TRANSACTION_TIMEOUT, Outcome, Transactions};
+use crate::translations::DisplayNames;
+use crate::unknown::{UNKNOWN_BLOCK_REPORT_INTERVAL, UnknownBlockThrottle};
+use crate::usage::{DEFAULT_USAGE_SAVE_INTERVAL, UsageReport, UsageTracker};
+use crate::watchdog::{DEFAULT_WATCHDOG_INTERVAL, DEFAULT_WATCHDOG_TIMEOUT, Watchdog};
+
+// How long the active rship server may stay down before switching to the next one
+const DEFAULT_RSHIP_FAILOVER_AFTER: Duration = Duration::from_secs(15);
+// Times a command lost with the connection is sent again after reconnecting
+const MAX_SEND_RETRIES: u32 = 2;
+// How long after it was lost with the connection a command is still sent again
+const MAX_RESEND_AGE: Duration = Duration::from_secs(10);
+// How long set-device-address waits for the new device to accept a connection
+const DEVICE_SWITCH_TIMEOUT: Duration = Duration::from_secs(5);
+// Category of the input subtargets, alongside the outputs' default "video"
+const INPUT_TARGET_CATEGORY: &str = "video";
 
 // Commands sent to the videohub client task
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum VideohubCommand {
-    Route { output: u32, input: u32 },
-    SetInput { output: u32, input: u32 }, // For output subtargets - output is implicit
-    InputLabel { input: u32, label: String },
-    OutputLabel { output: u32, label: String },
-    OutputLock { output: u32, locked: bool },
-    TakeMode { output: u32, enabled: bool },
+    Route {
+        output: u32,
+        input: u32,
+    },
+    SetInput {
+        output: u32,
+        input: u32,
+    }, // For output subtargets - output is implicit
+    // Route or SetInput with ports given by label, resolved against the device labels
+    // by the first middleware
+    RouteByLabel {
+        output: PortRef,
+        input: PortRef,
+        set_input: bool,
+    },
+    InputLabel {
+        input: u32,
+        label: String,
+    },
+    OutputLabel {
+        output: u32,
+        label: String,
+    },
+    OutputLock {
+        output: u32,
+        locked: bool,
+    },
+    // Several outputs locked or unlocked in one protocol block
+    OutputLocks {
+        outputs: Vec<u32>,
+        locked: bool,
+    },
+    TakeMode {
+        output: u32,
+        enabled: bool,
+    },
+    SetProtocolTrace {
+        enabled: bool,
+    },
+    ExportCsv {
+        path: String,
+    },
+    GenerateReport {
+        path: String,
+    },
+    SendRawBlock {
+        text: String,
+    },
+    ImportCsv {
+        path: String,
+        apply_routes: bool,
+    },
+    HoldRoute {
+        output: u32,
+        input: u32,
+        duration_ms: u64,
+    },
+    ReleaseHold {
+        output: u32,
+        hold_id: Option<u64>,
+    }, // None releases whatever hold is active
+    StartSequence {
+        output: u32,
+        inputs: Vec<u32>,
+        dwell_ms: u64,
+        repeat: bool,
+    },
+    StopSequence {
+        output: u32,
+    },
+    StartSweep {
+        input: u32,
+        outputs: Vec<u32>,
+        dwell_ms: u64,
+    },
+    StartRotation {
+        group: String,
+    },
+    StopRotation {
+        group: String,
+    },
+    TriggerFailover {
+        output: u32,
+    },
+    RevertFailover {
+        output: u32,
+    },
+    SetRuleEnabled {
+        rule_id: String,
+        enabled: bool,
+        until: Option<String>, // RFC 3339
+    },
+    FreezeInput {
+        input: u32,
+    },
+    UnfreezeInput {
+        input: u32,
+    },
+    SignalStatus {
+        input: u32,
+        present: bool,
+    }, // Reported by an external signal probe
+    LoadShow {
+        path: String,
+    },
+    ApplyStagedChanges,
+    DiscardStagedChanges,
+    // Routes held for the next take, written together by Take
+    PresetRoute {
+        output: u32,
+        input: u32,
+    },
+    Take,
+    ClearPending,
+    // Named routing configurations kept in SALVOS_FILE
+    SaveSalvo {
+        name: String,
+    },
+    RecallSalvo {
+        name: String,
+    },
+    DeleteSalvo {
+        name: String,
+    },
+    GetPortInfo {
+        port_type: String,
+        port: u32,
+    },
+    GetInventory,
+    GetRoutingTable,
+    IdentifyDevice,
+    RebootDevice,
+    // Disconnect and continue with the Videohub at another address
+    SetDeviceAddress {
+        host: String,
+        port: u16,
+    },
+    SetConfiguration {
+        setting: String,
+        value: String,
+    },
+    // Routes and labels written as one protocol block per kind, e.g. a snapshot recall
+    WriteChanges {
+        changes: Vec<ExpectedChange>,
+    },
+    // Copy of the current device state, e.g. for the HTTP control listener
+    Snapshot {
+        reply: SnapshotReply,
+    },
+    // Routing and label changes collected under an id and written as one salvo on commit
+    BeginTransaction {
+        id: String,
+    },
+    AddToTransaction {
+        id: String,
+        command: Box<VideohubCommand>,
+    },
+    CommitTransaction {
+        id: String,
+    },
+    RunBatch {
+        batch: String,
+        steps: Vec<BatchStep>,
+    },
+    RollbackTransaction {
+        id: String,
+    },
+    // Predicted effect of an action; answered through the reply if given, emitted otherwise
+    Simulate {
+        action: ActionPayload,
+        reply: Option<Reply<Prediction>>,
+    },
+    // Reserve the device for one operator's actions until the TTL lapses
+    ClaimControl {
+        operator: String,
+        ttl_ms: u64,
+    },
+    ReleaseControl {
+        operator: String,
+    },
+    // Hand outputs over to another owner
+    TransferOwnership {
+        outputs: Vec<u32>,
+        to_owner: String,
+    },
+    ExportUsageReport {
+        path: String,
+        reset: bool,
+    },
+    // Decided by the redundancy heartbeats: whether this executor may send commands
+    SetLeadership {
+        leader: bool,
+        peer_alive: bool,
+    },
+    // Switch to a named profile from PROFILES_FILE
+    SetProfile {
+        profile: String,
+    },
+    // Routes a legacy controller intends (output -> input), compared with the device in shadow mode
+    SetIntent {
+        routes: std::collections::HashMap<u32, u32>,
+    },
 }
 
-// Events emitted from the videohub client task
+// Where to deliver the answer to a request made through the command queue
 #[derive(Debug)]
+pub struct Reply<T>(Arc<std::sync::Mutex<Option<oneshot::Sender<T>>>>);
+
+pub type SnapshotReply = Reply<VideohubStateSnapshot>;
+
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Reply<T> {
+    pub fn new() -> (Self, oneshot::Receiver<T>) {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        (
+            Self(Arc::new(std::sync::Mutex::new(Some(reply_tx)))),
+            reply_rx,
+        )
+    }
+
+    fn send(&self, answer: T) {
+        let reply_tx = self.0.lock().ok().and_then(|mut reply_tx| reply_tx.take());
+        if let Some(reply_tx) = reply_tx {
+            // The requester may have given up waiting
+            let _ = reply_tx.send(answer);
+        }
+    }
+}
+
+// Ask the videohub task for a copy of the current device state
+pub async fn snapshot(command_tx: &mpsc::Sender<CommandRequest>) -> Result<VideohubStateSnapshot> {
+    let (reply, reply_rx) = SnapshotReply::new();
+    command_tx
+        .send(CommandRequest::internal(VideohubCommand::Snapshot {
+            reply,
+        }))
+        .await
+        .map_err(|_| anyhow::anyhow!("service is shutting down"))?;
+    reply_rx
+        .await
+        .map_err(|_| anyhow::anyhow!("snapshot request was rejected"))
+}
+
+// Ask the videohub task what an action would change, without sending it to the device
+pub async fn simulate(
+    command_tx: &mpsc::Sender<CommandRequest>,
+    action: ActionPayload,
+) -> Result<Prediction> {
+    let (reply, reply_rx) = Reply::new();
+    command_tx
+        .send(CommandRequest::internal(VideohubCommand::Simulate {
+            action,
+            reply: Some(reply),
+        }))
+        .await
+        .map_err(|_| anyhow::anyhow!("service is shutting down"))?;
+    reply_rx
+        .await
+        .map_err(|_| anyhow::anyhow!("simulation request was rejected"))
+}
+
+// Command routing an action's ports; ports given by label are resolved on the videohub task
+pub(crate) fn route_command(output: PortRef, input: PortRef, set_input: bool) -> VideohubCommand {
+    match (output, input) {
+        (PortRef::Number(output), PortRef::Number(input)) if set_input => {
+            VideohubCommand::SetInput { output, input }
+        }
+        (PortRef::Number(output), PortRef::Number(input)) => {
+            VideohubCommand::Route { output, input }
+        }
+        (output, input) => VideohubCommand::RouteByLabel {
+            output,
+            input,
+            set_input,
+        },
+    }
+}
+
+static NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(1);
+
+// Identifies the rship action invocation a command came from
+#[derive(Debug, Clone)]
+pub struct ActionInvocation {
+    pub action: String,
+    pub correlation_id: u64,
+    // Owner named in the action payload, checked against the output owners
+    pub owner: Option<String>,
+    // Told whether the command was confirmed or failed, for callers waiting on it (batches)
+    pub done: Option<mpsc::UnboundedSender<Result<(), String>>>,
+}
+
+// A command queued for the videohub task, with the action that issued it (if any)
+#[derive(Debug, Clone)]
+pub struct CommandRequest {
+    pub command: VideohubCommand,
+    pub invocation: Option<ActionInvocation>,
+    // Where the command came from ("rship", "http", "osc", "script" or "internal"); the command
+    // bus takes turns between sources
+    pub source: &'static str,
+    // Times the command was lost with the connection and queued again, and when it was first lost
+    pub retries: u32,
+    pub lost_at: Option<tokio::time::Instant>,
+}
+
+impl CommandRequest {
+    // A command issued by the executor itself (scripts, imports, ...)
+    pub fn internal(command: VideohubCommand) -> Self {
+        Self {
+            command,
+            invocation: None,
+            source: "internal",
+            retries: 0,
+            lost_at: None,
+        }
+    }
+
+    // Changes the executor writes for an action that was already checked (a take, a salvo
+    // recall, ...), so the action's feedback follows the write
+    pub fn on_behalf_of(command: VideohubCommand, invocation: Option<ActionInvocation>) -> Self {
+        Self {
+            invocation,
+            ..Self::internal(command)
+        }
+    }
+
+    // A command issued by an rship action; its progress is pulsed on the action feedback emitter
+    pub fn from_action(action: &str, command: VideohubCommand) -> Self {
+        Self {
+            command,
+            invocation: Some(ActionInvocation {
+                action: action.to_string(),
+                correlation_id: NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed),
+                owner: None,
+                done: None,
+            }),
+            source: "rship",
+            retries: 0,
+            lost_at: None,
+        }
+    }
+
+    // Attribute the command to another source, e.g. a control listener
+    pub fn via(mut self, source: &'static str) -> Self {
+        self.source = source;
+        self
+    }
+
+    // Attribute an action's command to the owner named in its payload
+    pub fn owned_by(mut self, owner: Option<String>) -> Self {
+        if let Some(invocation) = &mut self.invocation {
+            invocation.owner = owner;
+        }
+        self
+    }
+
+    // Report whether the command was confirmed or failed on `done` as well
+    pub fn reporting_to(mut self, done: mpsc::UnboundedSender<Result<(), String>>) -> Self {
+        if let Some(invocation) = &mut self.invocation {
+            invocation.done = Some(done);
+        }
+        self
+    }
+}
+
+impl VideohubCommand {
+    // Outputs the command changes, for the ownership check
+    pub fn outputs(&self) -> Vec<u32> {
+        match self {
+            Self::Route { output, .. }
+            | Self::SetInput { output, .. }
+            | Self::OutputLabel { output, .. }
+            | Self::OutputLock { output, .. }
+            | Self::TakeMode { output, .. }
+            | Self::HoldRoute { output, .. }
+            | Self::ReleaseHold { output, .. }
+            | Self::StartSequence { output, .. }
+            | Self::PresetRoute { output, .. }
+            | Self::StopSequence { output }
+            | Self::TriggerFailover { output }
+            | Self::RevertFailover { output } => vec![*output],
+            Self::OutputLocks { outputs, .. }
+            | Self::TransferOwnership { outputs, .. }
+            | Self::StartSweep { outputs, .. } => outputs.clone(),
+            Self::AddToTransaction { command, .. } => command.outputs(),
+            Self::WriteChanges { changes } => {
+                changes.iter().filter_map(ExpectedChange::output).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Whether this command makes an older one obsolete, as both set the same property of the same
+    // ports
+    pub fn supersedes(&self, older: &VideohubCommand) -> bool {
+        match (self.expected_change(), older.expected_change()) {
+            (Some(change), Some(older)) => change.same_port(&older),
+            _ => {
+                std::mem::discriminant(self) == std::mem::discriminant(older)
+                    && !self.outputs().is_empty()
+                    && self.outputs() == older.outputs()
+            }
+        }
+    }
+
+    // Commands that only report state, which an operator claim does not hold back
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self,
+            Self::GetPortInfo { .. }
+                | Self::GetInventory
+                | Self::GetRoutingTable
+                | Self::Snapshot { .. }
+                | Self::Simulate { .. }
+                | Self::ExportCsv { .. }
+                | Self::GenerateReport { .. }
+                | Self::SetIntent { .. }
+        )
+    }
+
+    // State change the device is expected to report once this command is applied
+    pub fn expected_change(&self) -> Option<ExpectedChange> {
+        match self {
+            Self::Route { output, input }
+            | Self::SetInput { output, input }
+            | Self::HoldRoute { output, input, .. } => Some(ExpectedChange::Route {
+                output: *output,
+                input: *input,
+            }),
+            Self::InputLabel { input, label } => Some(ExpectedChange::InputLabel {
+                input: *input,
+                label: label.clone(),
+            }),
+            Self::OutputLabel { output, label } => Some(ExpectedChange::OutputLabel {
+                output: *output,
+                label: label.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Events emitted from the videohub client task
+#[derive(Debug, Clone)]
 pub enum VideohubEvent {
     Route {
         output: u32,
         input: u32,
         input_label: Option<String>,
+        confirmed: bool,
+        // Set when the device confirmed a change made after the initial state
+        last_change: Option<RouteChange>,
     },
     DeviceStatus {
         connected: bool,
+        // The device keeps dropping connections right after accepting them
+        unstable: bool,
         model_name: Option<String>,
         video_inputs: Option<u32>,
         video_outputs: Option<u32>,
     },
+    // The device finished sending its initial state; the output and input subtargets are created from it
+    PreludeComplete {
+        video_inputs: Option<u32>,
+        video_outputs: Option<u32>,
+    },
     Label {
         port_type: String,
         port: u32,
         label: String,
+        // Label as requested, if the device shows it transliterated (LABEL_CHARSET)
+        original: Option<String>,
+        confirmed: bool,
     },
     OutputLock {
         output: u32,
@@ -54,98 +608,1188 @@ pub enum VideohubEvent {
         output: u32,
         enabled: bool,
     },
+    // Route, labels, lock, take mode, pending route and protections of an output together
+    OutputState {
+        output: u32,
+        status: OutputStatus,
+    },
     NetworkInterface {
         interface: NetworkInterface,
     },
+    ActionFeedback {
+        action: String,
+        correlation_id: u64,
+        status: &'static str, // "queued", "sent", "confirmed" or "failed"
+        error: Option<String>,
+    },
+    SequenceProgress {
+        output: u32,
+        step: u32,
+        total: u32,
+        input: u32,
+        running: bool,
+    },
+    SweepProgress {
+        input: u32,
+        output: u32,
+        step: u32,
+        total: u32,
+        running: bool,
+    },
+    AutoFailover {
+        output: u32,
+        primary: u32,
+        backup: u32,
+        error: Option<String>,
+    },
+    Inventory {
+        device_info: Option<DeviceInfo>,
+        protocol_version: Option<String>,
+        network_interfaces: Vec<NetworkInterface>,
+        capabilities: Vec<String>,
+    },
+    PortInfo {
+        port_type: String,
+        port: u32,
+        label: Option<String>,
+        routed_input: Option<u32>,
+        capabilities: Option<PortCapabilities>,
+    },
+    PendingRoutes {
+        routes: Vec<PendingRoute>,
+        status: &'static str, // "pending", "taken" or "cleared"
+    },
+    Salvos {
+        salvos: Vec<SalvoSummary>,
+    },
+    StagedChanges {
+        source: String,
+        status: &'static str, // "staged", "applied", "discarded" or "expired"
+        changes: Vec<StagedChange>,
+        expires_in_ms: Option<u64>,
+    },
+    Configuration {
+        setting: String,
+        value: String,
+    },
+    CommandRejected {
+        category: &'static str,
+        rejected_total: u64,
+        rolled_back: usize,
+    },
+    PerformanceDegraded {
+        degraded: bool,
+        p95_ms: u64,
+        budget_ms: u64,
+        samples: usize,
+    },
+    // The Videohub this executor controls
+    DeviceAddress {
+        host: String,
+        port: u16,
+    },
+    RshipEndpoint {
+        url: String,
+        // Index into the endpoint list, 0 being the primary server
+        priority: usize,
+    },
+    UnknownBlock {
+        header: String,
+        count: u64,
+        total: u64,
+        sample: String,
+    },
+    RawResponse {
+        header: String,
+        reply: &'static str, // "ack", "nak" or "none"
+        blocks: Vec<String>,
+    },
+    MatrixSummary {
+        locked_outputs: usize,
+        take_mode_outputs: usize,
+        // When a route, lock or take mode last changed
+        last_change: Option<EventTime>,
+        // Most recently changed outputs first
+        recent_outputs: Vec<u32>,
+    },
+    ChangeSummary {
+        window_ms: u64,
+        route_changes: u64,
+        label_changes: u64,
+        lock_changes: u64,
+        // (output, changes), busiest first
+        busiest_outputs: Vec<(u32, u64)>,
+    },
+    Watchdog {
+        alarm: bool,
+        probe: String,
+        unanswered_ms: Option<u64>,
+    },
+    PortCountMismatch {
+        port_type: &'static str,
+        reported: Option<u32>,
+        configured: u32,
+    },
+    DiscoveredVideohubs {
+        devices: Vec<DiscoveredVideohub>,
+    },
+    OwnershipViolation {
+        action: String,
+        correlation_id: u64,
+        owner: Option<String>,
+        output: u32,
+        output_owner: String,
+    },
+    OwnershipChanged {
+        outputs: Vec<u32>,
+        to_owner: String,
+        previous_owners: BTreeMap<u32, String>,
+    },
+    // Operator holding the device, None once released or lapsed
+    ControlClaim {
+        operator: Option<String>,
+        expires_in_ms: Option<u64>,
+    },
+    // The profile now active, with the emitter filters it selects
+    Profile {
+        profile: String,
+        emitter_filters: std::collections::HashMap<String, EmitterFilter>,
+    },
+    FreezeViolation {
+        // Action that issued the command, None for the executor's own commands
+        action: Option<String>,
+        correlation_id: Option<u64>,
+        output: u32,
+        frozen_input: u32,
+        input: u32,
+    },
+    LeadershipChanged {
+        leader: bool,
+        peer_alive: bool,
+    },
+    StateDivergence {
+        alarm: bool,
+        local_checksum: String,
+        peer_checksum: String,
+    },
+    // Mismatches between the device and the show plan
+    Drift {
+        drift: DriftScore,
+        planned: u32,
+    },
+    // Outputs routed differently from the legacy controller's intent, in shadow mode
+    IntentDivergence {
+        intended_outputs: usize,
+        diverged: Vec<IntentMismatch>,
+    },
+    // An operation the previous run wrote but never saw confirmed, found in the journal
+    InterruptedOperation {
+        source: String,
+        written_at: String,
+        applied: Vec<StagedChange>,
+        missing: Vec<StagedChange>,
+        completed: bool,
+    },
+    // The device restarted while the connection was down, rather than the network dropping out
+    DeviceRebootDetected {
+        epoch: u64,
+        disconnected_ms: u64,
+        device_uptime_s: Option<u64>,
+        evidence: Vec<String>,
+    },
+    TransactionStatus {
+        transaction: String,
+        status: &'static str, // "open", "committing", "committed", "failed", "rolled-back" or "expired"
+        changes: usize,
+        failed: Vec<StagedChange>,
+    },
+    BatchProgress {
+        batch: String,
+        step: u32,
+        total: u32,
+        action: &'static str,
+        status: &'static str, // "running", "completed" or "failed"
+        error: Option<String>,
+    },
+    SimulationResult {
+        action: String,
+        correlation_id: Option<u64>,
+        prediction: Prediction,
+    },
 }
 
-// Main service for integrating Videohub with rship
-pub struct VideohubService {
-    sdk_client: SdkClient,
-    rship_address: String,
-    rship_port: u16,
-    videohub_host: String,
-    videohub_port: u16,
+impl VideohubEvent {
+    // The state an event reports, so a buffered pulse can be replaced by a newer one.
+    // None for one-off events that must all be delivered.
+    pub(crate) fn buffer_key(&self) -> Option<String> {
+        match self {
+            Self::Route { output, .. } => Some(format!("route:{output}")),
+            Self::DeviceStatus { .. } => Some("device-status".into()),
+            Self::PreludeComplete { .. } => Some("prelude-complete".into()),
+            Self::Label {
+                port_type, port, ..
+            } => Some(format!("label:{port_type}:{port}")),
+            Self::OutputLock { output, .. } => Some(format!("output-lock:{output}")),
+            Self::TakeMode { output, .. } => Some(format!("take-mode:{output}")),
+            Self::OutputState { output, .. } => Some(format!("output-state:{output}")),
+            Self::NetworkInterface { interface } => {
+                Some(format!("network-interface:{}", interface.id))
+            }
+            Self::SequenceProgress { output, .. } => Some(format!("sequence-progress:{output}")),
+            Self::SweepProgress { .. } => Some("sweep-progress".into()),
+            Self::Inventory { .. } => Some("inventory".into()),
+            Self::StagedChanges { .. } => Some("staged-changes".into()),
+            Self::PendingRoutes { .. } => Some("pending-routes".into()),
+            Self::Salvos { .. } => Some("salvos".into()),
+            Self::RshipEndpoint { .. } => Some("rship-endpoint".into()),
+            Self::DeviceAddress { .. } => Some("device-address".into()),
+            Self::Configuration { setting, .. } => Some(format!("configuration:{setting}")),
+            Self::PerformanceDegraded { .. } => Some("performance-degraded".into()),
+            Self::UnknownBlock { header, .. } => Some(format!("unknown-block:{header}")),
+            Self::MatrixSummary { .. } => Some("matrix-summary".into()),
+            Self::ChangeSummary { .. } => Some("change-summary".into()),
+            Self::Watchdog { .. } => Some("watchdog".into()),
+            Self::PortCountMismatch { port_type, .. } => {
+                Some(format!("port-count-mismatch:{port_type}"))
+            }
+            Self::DiscoveredVideohubs { .. } => Some("discovered-videohubs".into()),
+            Self::LeadershipChanged { .. } => Some("leadership-changed".into()),
+            Self::StateDivergence { .. } => Some("state-divergence".into()),
+            Self::IntentDivergence { .. } => Some("intent-divergence".into()),
+            Self::Drift { .. } => Some("drift".into()),
+            Self::ControlClaim { .. } => Some("control-claim".into()),
+            Self::Profile { .. } => Some("profile".into()),
+            Self::ActionFeedback { .. }
+            | Self::OwnershipViolation { .. }
+            | Self::OwnershipChanged { .. }
+            | Self::FreezeViolation { .. }
+            | Self::AutoFailover { .. }
+            | Self::PortInfo { .. }
+            | Self::SimulationResult { .. }
+            | Self::TransactionStatus { .. }
+            | Self::BatchProgress { .. }
+            | Self::InterruptedOperation { .. }
+            | Self::DeviceRebootDetected { .. }
+            | Self::RawResponse { .. }
+            | Self::CommandRejected { .. } => None,
+        }
+    }
+
+    // The emitter pulsed for the event, if EMITTER_FILTERS may drop it. None for the output state
+    // and the events driving the subtarget registration.
+    fn emitter_id(&self) -> Option<&'static str> {
+        match self {
+            Self::DeviceStatus { .. } => Some("device-status"),
+            Self::NetworkInterface { .. } => Some("network-interface"),
+            Self::ActionFeedback { .. } => Some("action-feedback"),
+            Self::SequenceProgress { .. } => Some("sequence-progress"),
+            Self::SweepProgress { .. } => Some("sweep-progress"),
+            Self::AutoFailover { .. } => Some("auto-failover"),
+            Self::Inventory { .. } => Some("device-inventory"),
+            Self::PortInfo { .. } => Some("port-info"),
+            Self::StagedChanges { .. } => Some("staged-changes"),
+            Self::PendingRoutes { .. } => Some("pending-routes"),
+            Self::Salvos { .. } => Some("salvos"),
+            Self::Configuration { .. } => Some("device-configuration"),
+            Self::CommandRejected { .. } => Some("command-rejected"),
+            Self::PerformanceDegraded { .. } => Some("performance-degraded"),
+            Self::DeviceAddress { .. } => Some("device-address"),
+            Self::UnknownBlock { .. } => Some("unknown-block"),
+            Self::RawResponse { .. } => Some("raw-response"),
+            Self::MatrixSummary { .. } => Some("matrix-summary"),
+            Self::ChangeSummary { .. } => Some("change-summary"),
+            Self::Watchdog { .. } => Some("watchdog"),
+            Self::PortCountMismatch { .. } => Some("port-count-mismatch"),
+            Self::DiscoveredVideohubs { .. } => Some("discovered-videohubs"),
+            Self::OwnershipViolation { .. } => Some("ownership-violation"),
+            Self::OwnershipChanged { .. } => Some("ownership-changed"),
+            Self::FreezeViolation { .. } => Some("freeze-violation"),
+            Self::ControlClaim { .. } => Some("control-claim"),
+            Self::Profile { .. } => Some("profile"),
+            Self::LeadershipChanged { .. } => Some("leadership-changed"),
+            Self::StateDivergence { .. } => Some("state-divergence"),
+            Self::IntentDivergence { .. } => Some("intent-divergence"),
+            Self::Drift { .. } => Some("drift"),
+            Self::InterruptedOperation { .. } => Some("interrupted-operation"),
+            Self::DeviceRebootDetected { .. } => Some("device-reboot-detected"),
+            Self::TransactionStatus { .. } => Some("transaction-status"),
+            Self::BatchProgress { .. } => Some("batch-progress"),
+            Self::SimulationResult { .. } => Some("simulation-result"),
+            Self::Route { .. }
+            | Self::Label { .. }
+            | Self::OutputLock { .. }
+            | Self::TakeMode { .. }
+            | Self::OutputState { .. }
+            | Self::PreludeComplete { .. }
+            | Self::RshipEndpoint { .. } => None,
+        }
+    }
+
+    // How urgently the event's pulse reaches rship when pulses queue up
+    pub(crate) fn qos_class(&self) -> QosClass {
+        match self {
+            Self::Route { .. }
+            | Self::DeviceStatus { .. }
+            | Self::DeviceAddress { .. }
+            | Self::OutputLock { .. }
+            | Self::TakeMode { .. }
+            | Self::OutputState { .. }
+            | Self::ActionFeedback { .. }
+            | Self::SequenceProgress { .. }
+            | Self::SweepProgress { .. }
+            | Self::AutoFailover { .. }
+            | Self::StagedChanges { .. }
+            | Self::PendingRoutes { .. }
+            | Self::Salvos { .. }
+            | Self::CommandRejected { .. }
+            | Self::Watchdog { .. }
+            | Self::PortCountMismatch { .. }
+            | Self::OwnershipViolation { .. }
+            | Self::OwnershipChanged { .. }
+            | Self::FreezeViolation { .. }
+            | Self::ControlClaim { .. }
+            | Self::Profile { .. }
+            | Self::LeadershipChanged { .. }
+            | Self::StateDivergence { .. }
+            | Self::IntentDivergence { .. }
+            | Self::InterruptedOperation { .. }
+            | Self::DeviceRebootDetected { .. }
+            | Self::TransactionStatus { .. }
+            | Self::BatchProgress { .. }
+            | Self::SimulationResult { .. }
+            | Self::RawResponse { .. } => QosClass::Realtime,
+            // The subtargets are created after the labels queued before them
+            Self::Label { .. }
+            | Self::PreludeComplete { .. }
+            | Self::NetworkInterface { .. }
+            | Self::Inventory { .. }
+            | Self::PortInfo { .. }
+            | Self::Configuration { .. } => QosClass::Bulk,
+            Self::PerformanceDegraded { .. }
+            | Self::RshipEndpoint { .. }
+            | Self::UnknownBlock { .. }
+            | Self::MatrixSummary { .. }
+            | Self::ChangeSummary { .. }
+            | Self::Drift { .. }
+            | Self::DiscoveredVideohubs { .. } => QosClass::Background,
+        }
+    }
 }
 
-impl VideohubService {
-    pub async fn new(
-        videohub_host: String,
-        videohub_port: u16,
-        rship_address: String,
-        rship_port: u16,
-    ) -> Result<Self> {
-        let sdk_client = SdkClient::init();
+// Sends events stamped with the time they were produced, so the emitters report when
+// something happened rather than when it reached rship
+#[derive(Debug, Clone)]
+pub struct EventSender(pub(crate) mpsc::Sender<(EventTime, VideohubEvent)>);
 
-        Ok(Self {
-            sdk_client,
-            rship_address,
-            rship_port,
-            videohub_host,
-            videohub_port,
-        })
+impl EventSender {
+    pub async fn send(
+        &self,
+        event: VideohubEvent,
+    ) -> Result<(), mpsc::error::SendError<(EventTime, VideohubEvent)>> {
+        self.0.send((EventTime::now(), event)).await
     }
 
-    pub async fn start(&self) -> Result<()> {
-        log::info!("Starting Videohub service");
+    // Events waiting for the emission task
+    pub fn queued(&self) -> usize {
+        self.0.max_capacity() - self.0.capacity()
+    }
+}
 
-        // First, establish connection to rship
-        self.setup_rship_connection().await?;
+// Record how long the device took to confirm a change, reporting when the latency budget
+// starts or stops being met
+async fn record_latency(
+    monitor: &mut Option<LatencyMonitor>,
+    latency: Duration,
+    client: &VideohubClient,
+    command_tx: &mpsc::Sender<CommandRequest>,
+    event_tx: &EventSender,
+    pending: usize,
+) {
+    let Some(report) = monitor.as_mut().and_then(|monitor| monitor.record(latency)) else {
+        return;
+    };
 
-        // Create the mpsc channels for command and event communication
-        let (command_tx, command_rx) = mpsc::channel::<VideohubCommand>(100);
-        let (event_tx, event_rx) = mpsc::channel::<VideohubEvent>(100);
-        let (rship_reconnect_tx, rship_reconnect_rx) = mpsc::channel::<()>(10);
+    if report.degraded {
+        log::warn!(
+            "Confirmation latency p95 {:?} exceeds budget {:?} over {} commands; socket: {}; queued commands: {}, queued events: {}, pending changes: {pending}",
+            report.p95,
+            report.budget,
+            report.samples,
+            client.socket_diagnostics(),
+            command_tx.max_capacity() - command_tx.capacity(),
+            event_tx.queued()
+        );
+    } else {
+        log::info!(
+            "Confirmation latency p95 {:?} back within budget {:?}",
+            report.p95,
+            report.budget
+        );
+    }
+    if let Err(e) = event_tx
+        .send(VideohubEvent::PerformanceDegraded {
+            degraded: report.degraded,
+            p95_ms: report.p95.as_millis() as u64,
+            budget_ms: report.budget.as_millis() as u64,
+            samples: report.samples,
+        })
+        .await
+    {
+        log::error!("Failed to send performance degraded event: {e}");
+    }
+}
 
-        // Setup the rship instance with both command and event handling
-        self.setup_rship_instance(command_tx, event_rx).await?;
+fn device_status_event(
+    device_info: Option<&DeviceInfo>,
+    connected: bool,
+    unstable: bool,
+) -> VideohubEvent {
+    VideohubEvent::DeviceStatus {
+        connected,
+        unstable,
+        model_name: device_info.and_then(|info| info.model_name.clone()),
+        video_inputs: device_info.and_then(|info| info.video_inputs),
+        video_outputs: device_info.and_then(|info| info.video_outputs),
+    }
+}
 
-        // Start the videohub task
-        self.start_videohub_task(command_rx, event_tx, rship_reconnect_rx)
-            .await?;
+// Emit routes that were waiting for their input label (see RouteLabelWarming), now that the label
+// is known. With `flush_all` the remaining ones are emitted without a label.
+async fn emit_unlabelled_routes(
+    unlabelled_routes: &mut BTreeSet<u32>,
+    state: &VideohubState,
+    flush_all: bool,
+    event_tx: &EventSender,
+) {
+    let routes = &state.video_output_routing;
+    let input_labels = &state.input_labels;
+    let ready: Vec<u32> = unlabelled_routes
+        .iter()
+        .copied()
+        .filter(|output| {
+            flush_all
+                || routes
+                    .get(output)
+                    .is_some_and(|input| input_labels.contains_key(input))
+        })
+        .collect();
+    for output in ready {
+        unlabelled_routes.remove(&output);
+        let Some(&input) = routes.get(&output) else {
+            continue;
+        };
+        if let Err(e) = event_tx
+            .send(VideohubEvent::Route {
+                output,
+                input,
+                input_label: input_labels.get(&input).cloned(),
+                confirmed: true,
+                last_change: None,
+            })
+            .await
+        {
+            log::error!("Failed to send route event for output {output}: {e}");
+        }
+    }
+}
 
-        // Start watching rship connection status for reconnections
-        self.start_connection_monitoring(rship_reconnect_tx).await?;
+// Fill in the requested label of a label pulse the device shows transliterated
+fn with_original_label(
+    mut event: VideohubEvent,
+    transliteration: Option<&LabelTransliteration>,
+) -> VideohubEvent {
+    if let Some(transliteration) = transliteration {
+        transliteration.annotate(&mut event);
+    }
+    event
+}
 
-        // Keep the service running indefinitely
-        log::info!("Service started successfully, running indefinitely...");
-        std::future::pending::<()>().await;
+// Describe the device for the device-inventory emitter
+fn inventory_event(state: &VideohubState) -> VideohubEvent {
+    let info = state.device_info.as_ref();
+    let has = |count: Option<u32>| count.is_some_and(|count| count > 0);
+    let capabilities = [
+        ("take-mode", !state.take_mode.is_empty()),
+        ("output-locks", !state.output_locks.is_empty()),
+        (
+            "monitoring-outputs",
+            has(info.and_then(|info| info.video_monitoring_outputs)),
+        ),
+        (
+            "processing-units",
+            has(info.and_then(|info| info.video_processing_units)),
+        ),
+        ("serial-ports", has(info.and_then(|info| info.serial_ports))),
+        ("identify", state.supports_setting(IDENTIFY_SETTING)),
+        ("reboot", state.supports_setting(REBOOT_SETTING)),
+    ]
+    .into_iter()
+    .filter(|(_, supported)| *supported)
+    .map(|(capability, _)| capability.to_string())
+    .collect();
 
-        Ok(())
+    VideohubEvent::Inventory {
+        device_info: state.device_info.clone(),
+        protocol_version: state.protocol_version.clone(),
+        network_interfaces: state.network_interfaces.clone(),
+        capabilities,
     }
+}
 
-    async fn setup_rship_connection(&self) -> Result<()> {
-        let url = format!("ws://{}:{}/myko", self.rship_address, self.rship_port);
-        log::debug!("Connecting to rship at: {url}");
+// Report the outputs whose composite status changed
+async fn send_output_status(event_tx: &EventSender, changes: Vec<VideohubEvent>) {
+    for event in changes {
+        if let Err(e) = event_tx.send(event).await {
+            log::error!("Failed to send output state event: {e}");
+        }
+    }
+}
 
-        self.sdk_client.set_address(Some(url));
-        self.sdk_client.await_connection().await;
+// Queue the changes that bring the device in line with the startup policy's show file
+fn apply_startup_show(
+    path: &std::path::Path,
+    state: &VideohubState,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    match ShowFile::load(path) {
+        Ok(show) => {
+            for difference in show.diff(&ShowFile::from_state(state)) {
+                log::info!("Startup policy: {difference}");
+            }
+            let changes = show.changes(state);
+            log::info!(
+                "Startup policy: writing {} changes from {}",
+                changes.len(),
+                path.display()
+            );
+            queue_changes(command_tx, changes);
+        }
+        Err(e) => log::error!("Startup policy not applied: {e:#}"),
+    }
+}
 
-        log::debug!("Connected to rship successfully");
-        Ok(())
+// What the device task hands a feature module running one of its commands (salvos, staging,
+// take, transactions, batches): where the command came from, the device state, and the channels
+// to queue writes and pulse events on
+pub struct CommandScope<'a> {
+    pub source: &'static str,
+    pub invocation: &'a mut Option<ActionInvocation>,
+    pub state: &'a VideohubState,
+    pub ownership: Option<&'a Ownership>,
+    pub command_tx: &'a mpsc::Sender<CommandRequest>,
+    pub event_tx: &'a EventSender,
+}
+
+impl CommandScope<'_> {
+    // Check the changes the command works out only now, as the pipeline checked the command
+    pub async fn check_changes(&self, changes: &[ExpectedChange]) -> anyhow::Result<()> {
+        let context = CommandContext {
+            source: self.source,
+            invocation: self.invocation.as_ref(),
+        };
+        check_owned_changes(self.event_tx, self.ownership, changes, &context).await
     }
 
-    async fn setup_rship_instance(
-        &self,
-        command_tx: mpsc::Sender<VideohubCommand>,
-        mut event_rx: mpsc::Receiver<VideohubEvent>,
-    ) -> Result<()> {
-        // We'll need to create output subtargets dynamically once we know device capabilities
-        let command_tx_for_subtargets = command_tx.clone();
-        // Create the main instance
-        let instance = self
+    // Write the changes for the command's action, which is then confirmed once the device has
+    // echoed all of them
+    pub fn write_changes(&mut self, changes: Vec<ExpectedChange>) {
+        queue_changes_for(self.command_tx, changes, self.invocation.take());
+    }
+}
+
+// Changes an action works out only once it runs get the ownership check the pipeline gave the
+// action itself; a violation is pulsed like one found in the pipeline
+async fn check_owned_changes(
+    event_tx: &EventSender,
+    ownership: Option<&Ownership>,
+    changes: &[ExpectedChange],
+    context: &CommandContext<'_>,
+) -> anyhow::Result<()> {
+    let Some(Err(violation)) = ownership.map(|ownership| ownership.check_changes(changes, context))
+    else {
+        return Ok(());
+    };
+    if let Some(invocation) = context.invocation {
+        send_ownership_violation(event_tx, invocation, &violation).await;
+    }
+    Err(violation.into())
+}
+
+async fn send_ownership_violation(
+    event_tx: &EventSender,
+    invocation: &ActionInvocation,
+    violation: &OwnershipViolation,
+) {
+    if let Err(e) = event_tx
+        .send(VideohubEvent::OwnershipViolation {
+            action: invocation.action.clone(),
+            correlation_id: invocation.correlation_id,
+            owner: invocation.owner.clone(),
+            output: violation.output,
+            output_owner: violation.output_owner.clone(),
+        })
+        .await
+    {
+        log::error!("Failed to send ownership violation event: {e}");
+    }
+}
+
+// Report a command rejected before it reached the device on its action feedback and to the
+// observers
+async fn reject_command(
+    event_tx: &EventSender,
+    observers: &[Arc<dyn VideohubObserver>],
+    command: &VideohubCommand,
+    invocation: Option<&ActionInvocation>,
+    error: anyhow::Error,
+) {
+    log::warn!("Rejected {command:?}: {error}");
+    send_feedback(event_tx, invocation, "failed", Some(error.to_string())).await;
+    let result = Err(error);
+    for observer in observers {
+        observer.on_command_result(command, &result);
+    }
+}
+
+// Report the progress of a command issued by an rship action
+async fn send_feedback(
+    event_tx: &EventSender,
+    invocation: Option<&ActionInvocation>,
+    status: &'static str,
+    error: Option<String>,
+) {
+    let Some(invocation) = invocation else {
+        return;
+    };
+    if let Some(done) = &invocation.done {
+        match status {
+            "confirmed" => _ = done.send(Ok(())),
+            "failed" => _ = done.send(Err(error.clone().unwrap_or_default())),
+            _ => {}
+        }
+    }
+    if let Err(e) = event_tx
+        .send(VideohubEvent::ActionFeedback {
+            action: invocation.action.clone(),
+            correlation_id: invocation.correlation_id,
+            status,
+            error,
+        })
+        .await
+    {
+        log::error!("Failed to send action feedback event: {e}");
+    }
+}
+
+// Who made a route change the device reported: the action (and its owner) behind our own write,
+// "internal" for the executor's own writes, or "device" for other controllers and the front panel
+fn route_change_origin(echoed: Option<&PendingChange>) -> String {
+    match echoed {
+        Some(change) => match &change.invocation {
+            Some(ActionInvocation {
+                action,
+                owner: Some(owner),
+                ..
+            }) => format!("{action} ({owner})"),
+            Some(invocation) => invocation.action.clone(),
+            None => "internal".to_string(),
+        },
+        None => "device".to_string(),
+    }
+}
+
+// Report a resolved change, failed if there is an error, to the action that issued it, and to
+// the action whose write it completes
+async fn send_change_feedback(
+    event_tx: &EventSender,
+    pending_changes: &mut PendingChanges,
+    change: &PendingChange,
+    error: Option<String>,
+) {
+    let status = |error: &Option<String>| {
+        if error.is_some() {
+            "failed"
+        } else {
+            "confirmed"
+        }
+    };
+    send_feedback(
+        event_tx,
+        change.invocation.as_ref(),
+        status(&error),
+        error.clone(),
+    )
+    .await;
+    if let Some((invocation, error)) = pending_changes.settle(change, error) {
+        send_feedback(event_tx, Some(&invocation), status(&error), error).await;
+    }
+}
+
+// Confirm the change the device echoed and fail the ones it overrode
+async fn send_resolved_feedback(
+    event_tx: &EventSender,
+    pending_changes: &mut PendingChanges,
+    resolved: &Resolved,
+) {
+    if let Some(change) = &resolved.echoed {
+        send_change_feedback(event_tx, pending_changes, change, None).await;
+    }
+    for change in &resolved.overridden {
+        send_change_feedback(
+            event_tx,
+            pending_changes,
+            change,
+            Some("device reported a different value".into()),
+        )
+        .await;
+    }
+}
+
+// A momentary route that is reverted when released
+#[derive(Debug)]
+struct RouteHold {
+    id: u64,
+    input: u32,
+    previous_input: Option<u32>,
+}
+
+// Pulse counters per emitter id, so consumers can detect missed pulses
+#[derive(Debug, Default)]
+struct PulseSequences(std::collections::HashMap<String, u64>);
+
+impl PulseSequences {
+    fn next(&mut self, emitter_id: String) -> u64 {
+        let sequence = self.0.entry(emitter_id).or_default();
+        *sequence += 1;
+        *sequence
+    }
+}
+
+// Latest state pulsed for an output, kept so a subtarget created after it arrived starts with it
+#[derive(Debug, Default)]
+struct OutputState {
+    // Input, its label, and whether the device confirmed the route
+    input: Option<(u32, Option<String>, bool)>,
+    last_change: Option<RouteChange>,
+    label: Option<(String, bool)>,
+    locked: Option<bool>,
+    take_mode: Option<bool>,
+    status: Option<OutputStatus>,
+}
+
+// Outputs showing an input (1-indexed), lowest first
+fn routed_outputs(
+    output_state: &std::collections::HashMap<u32, OutputState>,
+    input: u32,
+) -> Vec<u32> {
+    let mut outputs: Vec<u32> = output_state
+        .iter()
+        .filter(|(_, state)| {
+            state
+                .input
+                .as_ref()
+                .is_some_and(|(routed, ..)| *routed == input)
+        })
+        .map(|(output, _)| output + 1)
+        .collect();
+    outputs.sort_unstable();
+    outputs
+}
+
+// Channel ends owned by the videohub task
+struct VideohubTaskChannels {
+    // Lets the task schedule follow-up commands for itself (e.g. reverting a held route)
+    command_tx: mpsc::Sender<CommandRequest>,
+    command_rx: mpsc::Receiver<CommandRequest>,
+    event_tx: EventSender,
+    rship_reconnect_rx: mpsc::Receiver<()>,
+    trace_tx: mpsc::UnboundedSender<ProtocolTraceEntry>,
+}
+
+// Main service for integrating Videohub with rship
+pub struct VideohubService {
+    sdk_client: SdkClient,
+    rship_address: String,
+    rship_port: u16,
+    videohub_host: String,
+    videohub_port: u16,
+    config: ServiceConfig,
+    log_control: Option<LogControl>,
+    observers: Vec<Arc<dyn VideohubObserver>>,
+    middleware: Vec<Arc<dyn CommandMiddleware>>,
+    health: Arc<Health>,
+}
+
+impl VideohubService {
+    pub async fn new(
+        videohub_host: String,
+        videohub_port: u16,
+        rship_address: String,
+        rship_port: u16,
+    ) -> Result<Self> {
+        let sdk_client = SdkClient::init();
+
+        Ok(Self {
+            sdk_client,
+            rship_address,
+            rship_port,
+            videohub_host,
+            videohub_port,
+            config: ServiceConfig::default(),
+            log_control: None,
+            observers: Vec::new(),
+            middleware: Vec::new(),
+            health: Arc::new(Health::default()),
+        })
+    }
+
+    // Apply optional settings
+    pub fn with_config(mut self, config: ServiceConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Register an observer that is notified of every event and command result
+    pub fn add_observer(mut self, observer: Arc<dyn VideohubObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    // Append a middleware to the command pipeline, after the built-in port validation
+    pub fn add_middleware(mut self, middleware: Arc<dyn CommandMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    // Allow the log level to be changed through rship
+    pub fn with_log_control(mut self, log_control: LogControl) -> Self {
+        self.log_control = Some(log_control);
+        self
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        log::info!("Starting Videohub service");
+
+        // First, establish connection to rship
+        let endpoints = self.rship_endpoints();
+        let active_endpoint = self.setup_rship_connection(&endpoints).await;
+
+        // Create the mpsc channels for command and event communication
+        let (command_tx, command_rx) = mpsc::channel::<CommandRequest>(100);
+        let (event_tx, event_rx) = mpsc::channel::<(EventTime, VideohubEvent)>(100);
+        self.health.watch_channel("commands", &command_tx);
+        self.health.watch_channel("events", &event_tx);
+        let event_tx = EventSender(event_tx);
+        let (rship_reconnect_tx, rship_reconnect_rx) = mpsc::channel::<()>(10);
+        let (trace_tx, trace_rx) = mpsc::unbounded_channel::<ProtocolTraceEntry>();
+        // Kept up to date by the connection monitoring, and cleared when a pulse fails
+        let rship_connected = Arc::new(AtomicBool::new(true));
+        // Wakes the emission task to deliver the pulses buffered while rship was down
+        let rship_restored = Arc::new(Notify::new());
+
+        let mut observers = self.build_observers(&command_tx);
+        if self.config.status_line {
+            let status_line = Arc::new(StatusLine::new(
+                rship_connected.clone(),
+                endpoints[active_endpoint].clone(),
+            ));
+            status_line.clone().spawn();
+            observers.push(status_line);
+        }
+        let middleware = self.build_middleware();
+        let endpoint_event_tx = event_tx.clone();
+        let redundancy_event_tx = event_tx.clone();
+        let discovery_event_tx = event_tx.clone();
+
+        let channels = VideohubTaskChannels {
+            command_tx: command_tx.clone(),
+            command_rx,
+            event_tx,
+            rship_reconnect_rx,
+            trace_tx,
+        };
+
+        self.start_control_listeners(&command_tx);
+        self.start_redundancy(&command_tx, redundancy_event_tx);
+        self.start_discovery(discovery_event_tx);
+
+        // Setup the rship instance with both command and event handling
+        self.setup_rship_instance(
+            command_tx,
+            event_rx,
+            trace_rx,
+            observers.clone(),
+            rship_connected.clone(),
+            rship_restored.clone(),
+        )
+        .await?;
+
+        // Start the videohub task
+        self.start_videohub_task(channels, observers, middleware)
+            .await?;
+
+        // Start watching rship connection status for reconnections
+        self.start_connection_monitoring(
+            endpoints,
+            active_endpoint,
+            rship_connected,
+            rship_restored,
+            rship_reconnect_tx,
+            endpoint_event_tx,
+        )
+        .await?;
+
+        // Keep the service running indefinitely
+        log::info!("Service started successfully, running indefinitely...");
+        std::future::pending::<()>().await;
+
+        Ok(())
+    }
+
+    // Run only the device side of the service, without rship: connect to the videohub and hand back
+    // the command channel and the events that would be pulsed. Used by the conformance tests.
+    pub async fn start_device_session(
+        &self,
+    ) -> Result<(
+        mpsc::Sender<CommandRequest>,
+        mpsc::Receiver<(EventTime, VideohubEvent)>,
+    )> {
+        let (command_tx, command_rx) = mpsc::channel::<CommandRequest>(100);
+        let (event_tx, event_rx) = mpsc::channel::<(EventTime, VideohubEvent)>(100);
+        // Nothing reconnects to rship or consumes traces in a device-only session
+        let (_, rship_reconnect_rx) = mpsc::channel::<()>(1);
+        let (trace_tx, _) = mpsc::unbounded_channel::<ProtocolTraceEntry>();
+
+        let channels = VideohubTaskChannels {
+            command_tx: command_tx.clone(),
+            command_rx,
+            event_tx: EventSender(event_tx),
+            rship_reconnect_rx,
+            trace_tx,
+        };
+        let observers = self.build_observers(&command_tx);
+        self.start_videohub_task(channels, observers, self.build_middleware())
+            .await?;
+
+        Ok((command_tx, event_rx))
+    }
+
+    // HTTP/OSC listeners feeding the command channel, if configured
+    fn start_control_listeners(&self, command_tx: &mpsc::Sender<CommandRequest>) {
+        #[cfg(feature = "http")]
+        if let Some(port) = self.config.control_http_port {
+            let tx = command_tx.clone();
+            let health = self.health.clone();
+            tokio::spawn(async move {
+                if let Err(e) = control::serve_http(port, tx, health).await {
+                    log::error!("HTTP control stopped: {e}");
+                }
+            });
+        }
+        #[cfg(not(feature = "http"))]
+        if self.config.control_http_port.is_some() {
+            log::warn!("CONTROL_HTTP_PORT is set but this build does not include the http feature");
+        }
+
+        #[cfg(feature = "osc")]
+        if let Some(port) = self.config.control_osc_port {
+            let tx = command_tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = control::serve_osc(port, tx).await {
+                    log::error!("OSC control stopped: {e}");
+                }
+            });
+        }
+        #[cfg(not(feature = "osc"))]
+        if self.config.control_osc_port.is_some() {
+            log::warn!("CONTROL_OSC_PORT is set but this build does not include the osc feature");
+        }
+
+        #[cfg(not(any(feature = "http", feature = "osc")))]
+        let _ = command_tx;
+    }
+
+    // Leader election with the other executor of an active/standby pair, if configured
+    fn start_redundancy(&self, command_tx: &mpsc::Sender<CommandRequest>, event_tx: EventSender) {
+        let Some(peer) = self.config.redundancy_peer.clone() else {
+            return;
+        };
+        let port = self
+            .config
+            .redundancy_port
+            .unwrap_or(DEFAULT_REDUNDANCY_PORT);
+        let priority = self.config.redundancy_priority;
+        let tx = command_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = redundancy::run(port, peer, priority, tx.clone(), event_tx).await {
+                log::error!("Redundancy stopped: {e}");
+            }
+            // Without heartbeats the peer cannot tell this executor still leads, and may take
+            // over itself; step down rather than risk two leaders
+            let standby = VideohubCommand::SetLeadership {
+                leader: false,
+                peer_alive: false,
+            };
+            if let Err(e) = tx.send(CommandRequest::internal(standby)).await {
+                log::error!("Failed to step down after redundancy stopped: {e}");
+            }
+        });
+    }
+
+    // Periodic browsing for Videohubs on the LAN, if configured
+    fn start_discovery(&self, event_tx: EventSender) {
+        if self.config.videohub_discovery.is_none() {
+            return;
+        }
+        #[cfg(feature = "discovery")]
+        tokio::spawn(crate::discovery::run(event_tx));
+        #[cfg(not(feature = "discovery"))]
+        {
+            let _ = event_tx;
+            log::warn!(
+                "VIDEOHUB_DISCOVERY is set but this build does not include the discovery feature"
+            );
+        }
+    }
+
+    // Registered observers plus the configured hooks and script
+    fn build_observers(
+        &self,
+        command_tx: &mpsc::Sender<CommandRequest>,
+    ) -> Vec<Arc<dyn VideohubObserver>> {
+        let mut observers = self.observers.clone();
+
+        if let Some(path) = self.config.hooks_file.as_deref() {
+            match HookRunner::load(path) {
+                Ok(hook_runner) => observers.push(Arc::new(hook_runner)),
+                Err(e) => log::error!("Event hooks disabled: {e}"),
+            }
+        }
+
+        if let Some(path) = self.config.notifications_file.as_deref() {
+            match Notifier::load(path) {
+                Ok(notifier) => observers.push(Arc::new(notifier)),
+                Err(e) => log::error!("Alarm notifications disabled: {e}"),
+            }
+        }
+
+        #[cfg(feature = "scripting")]
+        if let Some(path) = self.config.script_file.as_deref() {
+            match crate::scripting::ScriptHost::load(path, command_tx.clone()) {
+                Ok(script_host) => observers.push(Arc::new(script_host)),
+                Err(e) => log::error!("Scripting disabled: {e}"),
+            }
+        }
+        #[cfg(not(feature = "scripting"))]
+        if self.config.script_file.is_some() {
+            let _ = command_tx;
+            log::warn!("SCRIPT_FILE is set but this build does not include the scripting feature");
+        }
+
+        observers
+    }
+
+    // Built-in middleware followed by the registered ones
+    fn build_middleware(&self) -> Vec<Arc<dyn CommandMiddleware>> {
+        let mut middleware: Vec<Arc<dyn CommandMiddleware>> =
+            vec![Arc::new(ResolveLabels), Arc::new(ValidatePorts)];
+        middleware.extend(self.middleware.iter().cloned());
+        if self.config.audit_commands {
+            middleware.push(Arc::new(AuditLog));
+        }
+        middleware
+    }
+
+    // rship server URLs in priority order: the primary server, then the configured fallbacks
+    fn rship_endpoints(&self) -> Vec<String> {
+        let primary = format!("{}:{}", self.rship_address, self.rship_port);
+        std::iter::once(primary)
+            .chain(self.config.rship_fallback_endpoints.iter().cloned())
+            .map(|endpoint| {
+                if endpoint.contains("://") {
+                    endpoint
+                } else {
+                    format!("ws://{endpoint}/myko")
+                }
+            })
+            .collect()
+    }
+
+    fn rship_failover_after(&self) -> Duration {
+        self.config
+            .rship_failover_after_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_RSHIP_FAILOVER_AFTER)
+    }
+
+    // Connect to the first reachable endpoint, cycling through the list until one answers.
+    // Returns the index of the endpoint in use.
+    async fn setup_rship_connection(&self, endpoints: &[String]) -> usize {
+        let failover_after = self.rship_failover_after();
+        let mut index = 0;
+        loop {
+            let url = &endpoints[index];
+            log::debug!("Connecting to rship at: {url}");
+            self.sdk_client.set_address(Some(url.clone()));
+
+            if endpoints.len() == 1 {
+                self.sdk_client.await_connection().await;
+            } else if tokio::time::timeout(failover_after, self.sdk_client.await_connection())
+                .await
+                .is_err()
+            {
+                index = (index + 1) % endpoints.len();
+                log::warn!("rship at {url} unreachable, trying {}", endpoints[index]);
+                continue;
+            }
+
+            log::debug!("Connected to rship successfully");
+            return index;
+        }
+    }
+
+    async fn setup_rship_instance(
+        &self,
+        command_tx: mpsc::Sender<CommandRequest>,
+        mut event_rx: mpsc::Receiver<(EventTime, VideohubEvent)>,
+        mut trace_rx: mpsc::UnboundedReceiver<ProtocolTraceEntry>,
+        observers: Vec<Arc<dyn VideohubObserver>>,
+        rship_connected: Arc<AtomicBool>,
+        rship_restored: Arc<Notify>,
+    ) -> Result<()> {
+        // We'll need to create output subtargets dynamically once we know device capabilities
+        let command_tx_for_subtargets = command_tx.clone();
+        let display_names = self
+            .config
+            .translations_file
+            .as_deref()
+            .and_then(|path| {
+                DisplayNames::load(path)
+                    .map_err(|e| log::error!("Display name translations disabled: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        // Create the main instance; one per hub when several are controlled
+        let (instance_name, hub_suffix) = match &self.config.hub_name {
+            Some(hub) => (format!("Blackmagic Videohub {hub}"), format!("-{hub}")),
+            None => ("Blackmagic Videohub".to_string(), String::new()),
+        };
+        let instance = self
             .sdk_client
             .add_instance(InstanceArgs {
-                name: "Blackmagic Videohub".into(),
-                short_id: "blackmagic-videohub-02".into(),
+                name: instance_name,
+                short_id: format!("blackmagic-videohub-02{hub_suffix}"),
                 code: "blackmagic-videohub".into(),
-                service_id: "blackmagic-videohub-service-02".into(),
+                service_id: format!("blackmagic-videohub-service-02{hub_suffix}"),
                 cluster_id: None,
                 color: "#FF6B35".into(),
                 machine_id: hostname::get()
                     .map(|h| h.to_string_lossy().into_owned())
                     .unwrap_or("unknown-host".to_string()),
-                message: Some("Hello from Blackmagic Videohub!".into()),
+                message: Some(match &self.config.profile {
+                    Some(profile) => format!("Profile: {profile}"),
+                    None => "Hello from Blackmagic Videohub!".into(),
+                }),
                 status: rship_sdk::InstanceStatus::Available,
             })
             .await;
@@ -166,18 +1810,56 @@ impl VideohubService {
         let device_tx_for_output_label = command_tx.clone();
         let device_tx_for_output_lock = command_tx.clone();
         let device_tx_for_take_mode = command_tx.clone();
+        let device_tx_for_protocol_trace = command_tx.clone();
+        let device_tx_for_export_csv = command_tx.clone();
+        let device_tx_for_generate_report = command_tx.clone();
+        let device_tx_for_send_raw_block = command_tx.clone();
+        let device_tx_for_export_usage = command_tx.clone();
+        let device_tx_for_import_csv = command_tx.clone();
+        let device_tx_for_load_show = command_tx.clone();
+        let device_tx_for_port_info = command_tx.clone();
+        let device_tx_for_inventory = command_tx.clone();
+        let device_tx_for_identify = command_tx.clone();
+        let device_tx_for_configuration = command_tx.clone();
+        let device_tx_for_reboot = command_tx.clone();
+        let device_tx_for_device_address = command_tx.clone();
+        let device_tx_for_routing_table = command_tx.clone();
+        let device_tx_for_hold_route = command_tx.clone();
+        let device_tx_for_release_hold = command_tx.clone();
+        let device_tx_for_sequence_inputs = command_tx.clone();
+        let device_tx_for_set_destinations = command_tx.clone();
+        let device_tx_for_route_range = command_tx.clone();
+        let device_tx_for_label_range = command_tx.clone();
+        let device_tx_for_lock_range = command_tx.clone();
+        let device_tx_for_simulate = command_tx.clone();
+        let device_tx_for_stop_sequence = command_tx.clone();
+        let device_tx_for_sweep_test = command_tx.clone();
+        let device_tx_for_start_rotation = command_tx.clone();
+        let device_tx_for_stop_rotation = command_tx.clone();
+        let device_tx_for_trigger_failover = command_tx.clone();
+        let device_tx_for_revert_failover = command_tx.clone();
+        let device_tx_for_set_rule_enabled = command_tx.clone();
+        let device_tx_for_freeze_input = command_tx.clone();
+        let device_tx_for_unfreeze_input = command_tx.clone();
+        let device_tx_for_transfer_ownership = command_tx.clone();
+        let device_tx_for_claim_control = command_tx.clone();
+        let device_tx_for_release_control = command_tx.clone();
+        let device_tx_for_set_profile = command_tx.clone();
 
         device_target
             .add_action(
-                ActionArgs::<SetRouteAction>::new("Set Video Route".into(), "set-route".into()),
+                ActionArgs::<SetRouteAction>::new(
+                    display_names.get("Set Video Route"),
+                    "set-route".into(),
+                ),
                 move |_action, data| {
                     let tx = device_tx_for_route.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx
-                            .send(VideohubCommand::Route {
-                                output: data.output.clamp(1, u32::MAX) - 1,
-                                input: data.input.clamp(1, u32::MAX) - 1,
-                            })
+                            .send(
+                                CommandRequest::from_action("set-route", data.command())
+                                    .owned_by(data.owner),
+                            )
                             .await
                         {
                             log::error!("Failed to send route command: {e}");
@@ -190,17 +1872,17 @@ impl VideohubService {
         device_target
             .add_action(
                 ActionArgs::<SetInputLabelAction>::new(
-                    "Set Input Label".into(),
+                    display_names.get("Set Input Label"),
                     "set-input-label".into(),
                 ),
                 move |_action, data| {
                     let tx = device_tx_for_input_label.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx
-                            .send(VideohubCommand::InputLabel {
-                                input: data.input.clamp(1, u32::MAX) - 1,
-                                label: data.label,
-                            })
+                            .send(CommandRequest::from_action(
+                                "set-input-label",
+                                data.command(),
+                            ))
                             .await
                         {
                             log::error!("Failed to send input label command: {e}");
@@ -213,17 +1895,17 @@ impl VideohubService {
         device_target
             .add_action(
                 ActionArgs::<SetOutputLabelAction>::new(
-                    "Set Output Label".into(),
+                    display_names.get("Set Output Label"),
                     "set-output-label".into(),
                 ),
                 move |_action, data| {
                     let tx = device_tx_for_output_label.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx
-                            .send(VideohubCommand::OutputLabel {
-                                output: data.output.clamp(1, u32::MAX) - 1,
-                                label: data.label,
-                            })
+                            .send(
+                                CommandRequest::from_action("set-output-label", data.command())
+                                    .owned_by(data.owner),
+                            )
                             .await
                         {
                             log::error!("Failed to send output label command: {e}");
@@ -236,17 +1918,17 @@ impl VideohubService {
         device_target
             .add_action(
                 ActionArgs::<SetOutputLockAction>::new(
-                    "Set Output Lock".into(),
+                    display_names.get("Set Output Lock"),
                     "set-output-lock".into(),
                 ),
                 move |_action, data| {
                     let tx = device_tx_for_output_lock.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx
-                            .send(VideohubCommand::OutputLock {
-                                output: data.output.clamp(1, u32::MAX) - 1,
-                                locked: data.locked,
-                            })
+                            .send(
+                                CommandRequest::from_action("set-output-lock", data.command())
+                                    .owned_by(data.owner),
+                            )
                             .await
                         {
                             log::error!("Failed to send output lock command: {e}");
@@ -259,17 +1941,23 @@ impl VideohubService {
         device_target
             .add_action(
                 ActionArgs::<SetTakeModeAction>::new(
-                    "Set Take Mode".into(),
+                    display_names.get("Set Take Mode"),
                     "set-take-mode".into(),
                 ),
                 move |_action, data| {
                     let tx = device_tx_for_take_mode.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx
-                            .send(VideohubCommand::TakeMode {
-                                output: data.output.clamp(1, u32::MAX) - 1,
-                                enabled: data.enabled,
-                            })
+                            .send(
+                                CommandRequest::from_action(
+                                    "set-take-mode",
+                                    VideohubCommand::TakeMode {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                        enabled: data.enabled,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
                             .await
                         {
                             log::error!("Failed to send take mode command: {e}");
@@ -279,535 +1967,4247 @@ impl VideohubService {
             )
             .await;
 
-        // Add device-level emitters (device status and network interface)
-        let device_status_emitter = device_target
-            .add_emitter(EmitterArgs::<DeviceStatusEmitter>::new(
-                "Device Status".into(),
-                "device-status".into(),
-            ))
+        device_target
+            .add_action(
+                ActionArgs::<SetProtocolTraceAction>::new(
+                    display_names.get("Set Protocol Trace"),
+                    "set-protocol-trace".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_protocol_trace.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "set-protocol-trace",
+                                VideohubCommand::SetProtocolTrace {
+                                    enabled: data.enabled,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send protocol trace command: {e}");
+                        }
+                    });
+                },
+            )
             .await;
 
-        let device_network_interface_emitter = device_target
-            .add_emitter(EmitterArgs::<NetworkInterfaceEmitter>::new(
-                "Network Interface".into(),
-                "network-interface".into(),
-            ))
+        device_target
+            .add_action(
+                ActionArgs::<ExportCsvAction>::new(
+                    display_names.get("Export CSV"),
+                    "export-csv".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_export_csv.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "export-csv",
+                                VideohubCommand::ExportCsv { path: data.path },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send export CSV command: {e}");
+                        }
+                    });
+                },
+            )
             .await;
 
-        // Output subtargets will be created dynamically when we receive device info
-        log::info!("Output subtargets will be created dynamically based on device capabilities");
+        device_target
+            .add_action(
+                ActionArgs::<GenerateReportAction>::new(
+                    display_names.get("Generate Report"),
+                    "generate-report".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_generate_report.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "generate-report",
+                                VideohubCommand::GenerateReport { path: data.path },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send generate report command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
 
-        // Store instance and device target for dynamic subtarget creation
-        let instance_for_subtargets = instance.clone();
-        let device_target_for_subtargets = device_target.clone();
+        // Raw blocks bypass ownership and pending-change tracking, so only offered when enabled
+        if self.config.raw_blocks {
+            device_target
+                .add_action(
+                    ActionArgs::<SendRawBlockAction>::new(
+                        display_names.get("Send Raw Block"),
+                        "send-raw-block".into(),
+                    ),
+                    move |_action, data| {
+                        let tx = device_tx_for_send_raw_block.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = tx
+                                .send(CommandRequest::from_action(
+                                    "send-raw-block",
+                                    VideohubCommand::SendRawBlock { text: data.text },
+                                ))
+                                .await
+                            {
+                                log::error!("Failed to send raw block command: {e}");
+                            }
+                        });
+                    },
+                )
+                .await;
+        }
 
-        // Start the event emission task with dynamic output target support
-        tokio::spawn(async move {
-            log::debug!("Event emission task started");
+        device_target
+            .add_action(
+                ActionArgs::<ExportUsageReportAction>::new(
+                    display_names.get("Export Usage Report"),
+                    "export-usage-report".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_export_usage.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "export-usage-report",
+                                VideohubCommand::ExportUsageReport {
+                                    path: data.path,
+                                    reset: data.reset,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send export usage report command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<ImportCsvAction>::new(
+                    display_names.get("Import CSV"),
+                    "import-csv".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_import_csv.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "import-csv",
+                                    VideohubCommand::ImportCsv {
+                                        path: data.path,
+                                        apply_routes: data.apply_routes,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send import CSV command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<GetInventoryAction>::new(
+                    display_names.get("Get Inventory"),
+                    "get-inventory".into(),
+                ),
+                move |_action, _data| {
+                    let tx = device_tx_for_inventory.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "get-inventory",
+                                VideohubCommand::GetInventory,
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send get inventory command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SetDeviceConfigurationAction>::new(
+                    display_names.get("Set Device Configuration"),
+                    "set-device-configuration".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_configuration.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "set-device-configuration",
+                                VideohubCommand::SetConfiguration {
+                                    setting: data.setting,
+                                    value: data.value,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send device configuration command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        // Identify and Reboot are not in the documented protocol, so only offered when enabled
+        if self.config.device_functions {
+            device_target
+                .add_action(
+                    ActionArgs::<IdentifyDeviceAction>::new(
+                        display_names.get("Identify Device"),
+                        "identify-device".into(),
+                    ),
+                    move |_action, _data| {
+                        let tx = device_tx_for_identify.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = tx
+                                .send(CommandRequest::from_action(
+                                    "identify-device",
+                                    VideohubCommand::IdentifyDevice,
+                                ))
+                                .await
+                            {
+                                log::error!("Failed to send identify device command: {e}");
+                            }
+                        });
+                    },
+                )
+                .await;
+
+            device_target
+                .add_action(
+                    ActionArgs::<RebootDeviceAction>::new(
+                        display_names.get("Reboot Device"),
+                        "reboot-device".into(),
+                    ),
+                    move |_action, _data| {
+                        let tx = device_tx_for_reboot.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = tx
+                                .send(CommandRequest::from_action(
+                                    "reboot-device",
+                                    VideohubCommand::RebootDevice,
+                                ))
+                                .await
+                            {
+                                log::error!("Failed to send reboot device command: {e}");
+                            }
+                        });
+                    },
+                )
+                .await;
+        }
+
+        device_target
+            .add_action(
+                ActionArgs::<SetDeviceAddressAction>::new(
+                    display_names.get("Set Device Address"),
+                    "set-device-address".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_device_address.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "set-device-address",
+                                VideohubCommand::SetDeviceAddress {
+                                    host: data.host.trim().to_string(),
+                                    port: data.port,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send set device address command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<GetRoutingTableAction>::new(
+                    display_names.get("Get Routing Table"),
+                    "get-routing-table".into(),
+                ),
+                move |_action, _data| {
+                    let tx = device_tx_for_routing_table.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "get-routing-table",
+                                VideohubCommand::GetRoutingTable,
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send get routing table command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<GetPortInfoAction>::new(
+                    display_names.get("Get Port Info"),
+                    "get-port-info".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_port_info.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "get-port-info",
+                                VideohubCommand::GetPortInfo {
+                                    port_type: data.port_type,
+                                    port: data.port.clamp(1, u32::MAX) - 1,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send get port info command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<LoadShowAction>::new(
+                    display_names.get("Load Show"),
+                    "load-show".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_load_show.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "load-show",
+                                    VideohubCommand::LoadShow { path: data.path },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send load show command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        staging::add_actions(&mut device_target, &display_names, &command_tx).await;
+        take::add_actions(&mut device_target, &display_names, &command_tx).await;
+        salvo::add_actions(&mut device_target, &display_names, &command_tx).await;
+
+        device_target
+            .add_action(
+                ActionArgs::<HoldRouteAction>::new(
+                    display_names.get("Hold Route"),
+                    "hold-route".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_hold_route.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "hold-route",
+                                    VideohubCommand::HoldRoute {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                        input: data.input.clamp(1, u32::MAX) - 1,
+                                        duration_ms: data.duration_ms.as_millis(),
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send hold route command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<ReleaseHoldAction>::new(
+                    display_names.get("Release Hold"),
+                    "release-hold".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_release_hold.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "release-hold",
+                                    VideohubCommand::ReleaseHold {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                        hold_id: None,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send release hold command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SetDestinationsAction>::new(
+                    display_names.get("Set Destinations"),
+                    "set-destinations".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_set_destinations.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action("set-destinations", data.command())
+                                    .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send set destinations command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<RouteOutputRangeAction>::new(
+                    display_names.get("Route Output Range"),
+                    "route-output-range".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_route_range.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action("route-output-range", data.command())
+                                    .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send route output range command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<LabelOutputRangeAction>::new(
+                    display_names.get("Label Output Range"),
+                    "label-output-range".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_label_range.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action("label-output-range", data.command())
+                                    .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send label output range command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<LockOutputRangeAction>::new(
+                    display_names.get("Lock Output Range"),
+                    "lock-output-range".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_lock_range.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action("lock-output-range", data.command())
+                                    .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send lock output range command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SimulateCommandAction>::new(
+                    display_names.get("Simulate Command"),
+                    "simulate-command".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_simulate.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "simulate-command",
+                                VideohubCommand::Simulate {
+                                    action: data.command,
+                                    reply: None,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send simulate command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        transactions::add_actions(&mut device_target, &display_names, &command_tx).await;
+        batch::add_actions(&mut device_target, &display_names, &command_tx).await;
+
+        device_target
+            .add_action(
+                ActionArgs::<TransferOutputOwnershipAction>::new(
+                    display_names.get("Transfer Output Ownership"),
+                    "transfer-output-ownership".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_transfer_ownership.clone();
+                    tokio::spawn(async move {
+                        let outputs = data
+                            .outputs
+                            .into_iter()
+                            .map(|output| output.clamp(1, u32::MAX) - 1)
+                            .collect();
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "transfer-output-ownership",
+                                    VideohubCommand::TransferOwnership {
+                                        outputs,
+                                        to_owner: data.to_owner,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send transfer ownership command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<ClaimControlAction>::new(
+                    display_names.get("Claim Control"),
+                    "claim-control".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_claim_control.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "claim-control",
+                                    VideohubCommand::ClaimControl {
+                                        operator: data.operator.clone(),
+                                        ttl_ms: data.ttl_ms.as_millis(),
+                                    },
+                                )
+                                .owned_by(Some(data.operator)),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send claim control command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<ReleaseControlAction>::new(
+                    display_names.get("Release Control"),
+                    "release-control".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_release_control.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "release-control",
+                                    VideohubCommand::ReleaseControl {
+                                        operator: data.operator.clone(),
+                                    },
+                                )
+                                .owned_by(Some(data.operator)),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send release control command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SetProfileAction>::new(
+                    display_names.get("Set Profile"),
+                    "set-profile".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_set_profile.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "set-profile",
+                                VideohubCommand::SetProfile {
+                                    profile: data.profile,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send set profile command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SequenceInputsAction>::new(
+                    display_names.get("Sequence Inputs"),
+                    "sequence-inputs".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_sequence_inputs.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "sequence-inputs",
+                                    VideohubCommand::StartSequence {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                        inputs: data
+                                            .inputs
+                                            .into_iter()
+                                            .map(|input| input.clamp(1, u32::MAX) - 1)
+                                            .collect(),
+                                        dwell_ms: data.dwell_ms.as_millis(),
+                                        repeat: data.repeat,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send sequence inputs command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SweepTestAction>::new(
+                    display_names.get("Sweep Test"),
+                    "sweep-test".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_sweep_test.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "sweep-test",
+                                    VideohubCommand::StartSweep {
+                                        input: data.input.clamp(1, u32::MAX) - 1,
+                                        outputs: data
+                                            .outputs
+                                            .into_iter()
+                                            .map(|output| output.clamp(1, u32::MAX) - 1)
+                                            .collect(),
+                                        dwell_ms: data.dwell_ms.as_millis(),
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send sweep test command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<StopSequenceAction>::new(
+                    display_names.get("Stop Sequence"),
+                    "stop-sequence".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_stop_sequence.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "stop-sequence",
+                                    VideohubCommand::StopSequence {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send stop sequence command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<RotationAction>::new(
+                    display_names.get("Start Rotation"),
+                    "start-rotation".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_start_rotation.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "start-rotation",
+                                VideohubCommand::StartRotation { group: data.group },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send start rotation command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<RotationAction>::new(
+                    display_names.get("Stop Rotation"),
+                    "stop-rotation".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_stop_rotation.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "stop-rotation",
+                                VideohubCommand::StopRotation { group: data.group },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send stop rotation command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<FailoverAction>::new(
+                    display_names.get("Trigger Failover"),
+                    "trigger-failover".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_trigger_failover.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "trigger-failover",
+                                    VideohubCommand::TriggerFailover {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send trigger failover command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<FailoverAction>::new(
+                    display_names.get("Revert Failover"),
+                    "revert-failover".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_revert_failover.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(
+                                CommandRequest::from_action(
+                                    "revert-failover",
+                                    VideohubCommand::RevertFailover {
+                                        output: data.output.clamp(1, u32::MAX) - 1,
+                                    },
+                                )
+                                .owned_by(data.owner),
+                            )
+                            .await
+                        {
+                            log::error!("Failed to send revert failover command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<SetRuleEnabledAction>::new(
+                    display_names.get("Set Rule Enabled"),
+                    "set-rule-enabled".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_set_rule_enabled.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "set-rule-enabled",
+                                VideohubCommand::SetRuleEnabled {
+                                    rule_id: data.rule_id,
+                                    enabled: data.enabled,
+                                    until: data.until,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send set rule enabled command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<FreezeInputAction>::new(
+                    display_names.get("Freeze Input"),
+                    "freeze-input".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_freeze_input.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "freeze-input",
+                                VideohubCommand::FreezeInput {
+                                    input: data.input.clamp(1, u32::MAX) - 1,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send freeze input command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        device_target
+            .add_action(
+                ActionArgs::<UnfreezeInputAction>::new(
+                    display_names.get("Unfreeze Input"),
+                    "unfreeze-input".into(),
+                ),
+                move |_action, data| {
+                    let tx = device_tx_for_unfreeze_input.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = tx
+                            .send(CommandRequest::from_action(
+                                "unfreeze-input",
+                                VideohubCommand::UnfreezeInput {
+                                    input: data.input.clamp(1, u32::MAX) - 1,
+                                },
+                            ))
+                            .await
+                        {
+                            log::error!("Failed to send unfreeze input command: {e}");
+                        }
+                    });
+                },
+            )
+            .await;
+
+        // Log level control does not involve the device, so it is handled directly
+        if let Some(log_control) = self.log_control.clone() {
+            device_target
+                .add_action(
+                    ActionArgs::<SetLogLevelAction>::new(
+                        display_names.get("Set Log Level"),
+                        "set-log-level".into(),
+                    ),
+                    move |_action, data| {
+                        if let Err(e) = log_control.set_level(&data.level, data.module.as_deref()) {
+                            log::error!("Failed to set log level: {e}");
+                        }
+                    },
+                )
+                .await;
+        }
+
+        // Add device-level emitters (device status and network interface)
+        let device_status_emitter = device_target
+            .add_emitter(EmitterArgs::<DeviceStatusEmitter>::new(
+                display_names.get("Device Status"),
+                "device-status".into(),
+            ))
+            .await;
+
+        let device_network_interface_emitter = device_target
+            .add_emitter(EmitterArgs::<NetworkInterfaceEmitter>::new(
+                display_names.get("Network Interface"),
+                "network-interface".into(),
+            ))
+            .await;
+
+        let protocol_trace_emitter = device_target
+            .add_emitter(EmitterArgs::<ProtocolTraceEmitter>::new(
+                display_names.get("Protocol Trace"),
+                "protocol-trace".into(),
+            ))
+            .await;
+
+        let action_feedback_emitter = device_target
+            .add_emitter(EmitterArgs::<ActionFeedbackEmitter>::new(
+                display_names.get("Action Feedback"),
+                "action-feedback".into(),
+            ))
+            .await;
+
+        let auto_failover_emitter = device_target
+            .add_emitter(EmitterArgs::<AutoFailoverEmitter>::new(
+                display_names.get("Auto Failover"),
+                "auto-failover".into(),
+            ))
+            .await;
+
+        let device_inventory_emitter = device_target
+            .add_emitter(EmitterArgs::<DeviceInventoryEmitter>::new(
+                display_names.get("Device Inventory"),
+                "device-inventory".into(),
+            ))
+            .await;
+
+        let port_info_emitter = device_target
+            .add_emitter(EmitterArgs::<PortInfoEmitter>::new(
+                display_names.get("Port Info"),
+                "port-info".into(),
+            ))
+            .await;
+
+        let device_address_emitter = device_target
+            .add_emitter(EmitterArgs::<DeviceAddressEmitter>::new(
+                display_names.get("Device Address"),
+                "device-address".into(),
+            ))
+            .await;
+
+        let rship_endpoint_emitter = device_target
+            .add_emitter(EmitterArgs::<RshipEndpointEmitter>::new(
+                display_names.get("Rship Endpoint"),
+                "rship-endpoint".into(),
+            ))
+            .await;
+
+        let staged_changes_emitter = device_target
+            .add_emitter(EmitterArgs::<StagedChangesEmitter>::new(
+                display_names.get("Staged Changes"),
+                "staged-changes".into(),
+            ))
+            .await;
+
+        let pending_routes_emitter = device_target
+            .add_emitter(EmitterArgs::<PendingRoutesEmitter>::new(
+                display_names.get("Pending Routes"),
+                "pending-routes".into(),
+            ))
+            .await;
+
+        let salvos_emitter = device_target
+            .add_emitter(EmitterArgs::<SalvosEmitter>::new(
+                display_names.get("Salvos"),
+                "salvos".into(),
+            ))
+            .await;
+
+        let performance_degraded_emitter = device_target
+            .add_emitter(EmitterArgs::<PerformanceDegradedEmitter>::new(
+                display_names.get("Performance Degraded"),
+                "performance-degraded".into(),
+            ))
+            .await;
+
+        let device_configuration_emitter = device_target
+            .add_emitter(EmitterArgs::<DeviceConfigurationEmitter>::new(
+                display_names.get("Device Configuration"),
+                "device-configuration".into(),
+            ))
+            .await;
+
+        let command_rejected_emitter = device_target
+            .add_emitter(EmitterArgs::<CommandRejectedEmitter>::new(
+                display_names.get("Command Rejected"),
+                "command-rejected".into(),
+            ))
+            .await;
+
+        let sequence_progress_emitter = device_target
+            .add_emitter(EmitterArgs::<SequenceProgressEmitter>::new(
+                display_names.get("Sequence Progress"),
+                "sequence-progress".into(),
+            ))
+            .await;
+
+        let sweep_progress_emitter = device_target
+            .add_emitter(EmitterArgs::<SweepProgressEmitter>::new(
+                display_names.get("Sweep Progress"),
+                "sweep-progress".into(),
+            ))
+            .await;
+
+        let unknown_block_emitter = device_target
+            .add_emitter(EmitterArgs::<UnknownBlockEmitter>::new(
+                display_names.get("Unknown Block"),
+                "unknown-block".into(),
+            ))
+            .await;
+
+        let port_count_mismatch_emitter = device_target
+            .add_emitter(EmitterArgs::<PortCountMismatchEmitter>::new(
+                display_names.get("Port Count Mismatch"),
+                "port-count-mismatch".into(),
+            ))
+            .await;
+
+        let discovered_videohubs_emitter = device_target
+            .add_emitter(EmitterArgs::<DiscoveredVideohubsEmitter>::new(
+                display_names.get("Discovered Videohubs"),
+                "discovered-videohubs".into(),
+            ))
+            .await;
+
+        let raw_response_emitter = device_target
+            .add_emitter(EmitterArgs::<RawResponseEmitter>::new(
+                display_names.get("Raw Response"),
+                "raw-response".into(),
+            ))
+            .await;
+
+        let matrix_summary_emitter = device_target
+            .add_emitter(EmitterArgs::<MatrixSummaryEmitter>::new(
+                display_names.get("Matrix Summary"),
+                "matrix-summary".into(),
+            ))
+            .await;
+
+        let change_summary_emitter = device_target
+            .add_emitter(EmitterArgs::<ChangeSummaryEmitter>::new(
+                display_names.get("Change Summary"),
+                "change-summary".into(),
+            ))
+            .await;
+
+        let watchdog_emitter = device_target
+            .add_emitter(EmitterArgs::<WatchdogEmitter>::new(
+                display_names.get("Watchdog"),
+                "watchdog".into(),
+            ))
+            .await;
+
+        let interrupted_operation_emitter = device_target
+            .add_emitter(EmitterArgs::<InterruptedOperationEmitter>::new(
+                display_names.get("Interrupted Operation"),
+                "interrupted-operation".into(),
+            ))
+            .await;
+
+        let device_reboot_detected_emitter = device_target
+            .add_emitter(EmitterArgs::<DeviceRebootDetectedEmitter>::new(
+                display_names.get("Device Reboot Detected"),
+                "device-reboot-detected".into(),
+            ))
+            .await;
+
+        let transaction_status_emitter = device_target
+            .add_emitter(EmitterArgs::<TransactionStatusEmitter>::new(
+                display_names.get("Transaction Status"),
+                "transaction-status".into(),
+            ))
+            .await;
+
+        let batch_progress_emitter = device_target
+            .add_emitter(EmitterArgs::<BatchProgressEmitter>::new(
+                display_names.get("Batch Progress"),
+                "batch-progress".into(),
+            ))
+            .await;
+
+        let simulation_result_emitter = device_target
+            .add_emitter(EmitterArgs::<SimulationResultEmitter>::new(
+                display_names.get("Simulation Result"),
+                "simulation-result".into(),
+            ))
+            .await;
+
+        let state_divergence_emitter = device_target
+            .add_emitter(EmitterArgs::<StateDivergenceEmitter>::new(
+                display_names.get("State Divergence"),
+                "state-divergence".into(),
+            ))
+            .await;
+
+        let leadership_changed_emitter = device_target
+            .add_emitter(EmitterArgs::<LeadershipChangedEmitter>::new(
+                display_names.get("Leadership Changed"),
+                "leadership-changed".into(),
+            ))
+            .await;
+
+        let drift_emitter = device_target
+            .add_emitter(EmitterArgs::<DriftEmitter>::new(
+                display_names.get("Drift"),
+                "drift".into(),
+            ))
+            .await;
+
+        let intent_divergence_emitter = device_target
+            .add_emitter(EmitterArgs::<IntentDivergenceEmitter>::new(
+                display_names.get("Intent Divergence"),
+                "intent-divergence".into(),
+            ))
+            .await;
+
+        let profile_emitter = device_target
+            .add_emitter(EmitterArgs::<ProfileEmitter>::new(
+                display_names.get("Profile"),
+                "profile".into(),
+            ))
+            .await;
+
+        let control_claim_emitter = device_target
+            .add_emitter(EmitterArgs::<ControlClaimEmitter>::new(
+                display_names.get("Control Claim"),
+                "control-claim".into(),
+            ))
+            .await;
+
+        let ownership_changed_emitter = device_target
+            .add_emitter(EmitterArgs::<OwnershipChangedEmitter>::new(
+                display_names.get("Ownership Changed"),
+                "ownership-changed".into(),
+            ))
+            .await;
+
+        let registration_status_emitter = device_target
+            .add_emitter(EmitterArgs::<RegistrationStatusEmitter>::new(
+                display_names.get("Registration Status"),
+                "registration-status".into(),
+            ))
+            .await;
+
+        let ownership_violation_emitter = device_target
+            .add_emitter(EmitterArgs::<OwnershipViolationEmitter>::new(
+                display_names.get("Ownership Violation"),
+                "ownership-violation".into(),
+            ))
+            .await;
+
+        let freeze_violation_emitter = device_target
+            .add_emitter(EmitterArgs::<FreezeViolationEmitter>::new(
+                display_names.get("Freeze Violation"),
+                "freeze-violation".into(),
+            ))
+            .await;
+
+        // Raw protocol blocks are only produced while tracing is enabled
+        tokio::spawn(async move {
+            let mut sequence = 0;
+            while let Some(entry) = trace_rx.recv().await {
+                sequence += 1;
+                let data = ProtocolTraceEmitter {
+                    direction: entry.direction.to_string(),
+                    block: entry.block,
+                    timestamp_ms: entry.timestamp_ms,
+                    sequence,
+                    timestamp: entry.time.timestamp,
+                    monotonic_ms: entry.time.monotonic_ms,
+                };
+                if let Err(e) = protocol_trace_emitter.pulse(data).await {
+                    log::error!("Failed to emit protocol trace: {e}");
+                }
+            }
+        });
+
+        // Output subtargets will be created dynamically when we receive device info
+        log::info!("Output subtargets will be created dynamically based on device capabilities");
+
+        // Store instance and device target for dynamic subtarget creation
+        let instance_for_subtargets = instance.clone();
+        let device_target_for_subtargets = device_target.clone();
+
+        let port_metadata = self
+            .config
+            .port_metadata_file
+            .as_deref()
+            .and_then(|path| {
+                PortMetadata::load(path)
+                    .map_err(|e| log::error!("Port metadata disabled: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let output_targets = self
+            .config
+            .output_targets_file
+            .as_deref()
+            .and_then(|path| {
+                OutputTargets::load(path)
+                    .map_err(|e| log::error!("Output target configuration disabled: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let mut registration = Registration::new(
+            self.config
+                .registration_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_REGISTRATION_TIMEOUT),
+        );
+        let mut pulse_buffer = self
+            .config
+            .rship_buffer_size
+            .map(|size| PulseBuffer::new(size, self.config.rship_buffer_coalesce));
+        let health = self.health.clone();
+        let mut emitter_filters = EmitterFilters::new(self.config.emitter_filters.clone());
+        let warn_format_mismatch = self.config.warn_format_mismatch;
+        let videohub_host = self.videohub_host.clone();
+        let videohub_port = self.videohub_port;
+
+        // Start the event emission task with dynamic output target support
+        tokio::spawn(async move {
+            log::debug!("Event emission task started");
+
+            // Dynamic storage for output emitters by output - populated once the device sent its initial state
+            let mut output_emitters = BTreeMap::new();
+            // Emitters of the input subtargets by input, created alongside the output ones
+            let mut input_emitters = BTreeMap::new();
+            let mut targets_created = false;
+            // Port counts of the last prelude, to register missing subtargets after a reconnect
+            let mut known_outputs = None;
+            let mut known_inputs = None;
+            let mut sequences = PulseSequences::default();
+            let mut output_state: std::collections::HashMap<u32, OutputState> =
+                std::collections::HashMap::new();
+            // Input labels as last pulsed (label, original, confirmed), for new input subtargets
+            let mut input_labels: std::collections::HashMap<u32, (String, Option<String>, bool)> =
+                std::collections::HashMap::new();
+
+            // Pulses waiting for rship, sent most urgent class first
+            let mut queues = QosQueues::default();
+            let device_address = VideohubEvent::DeviceAddress {
+                host: videohub_host,
+                port: videohub_port,
+            };
+            queues.push(
+                device_address.qos_class(),
+                device_address.buffer_key(),
+                (EventTime::now(), device_address),
+            );
+
+            loop {
+                // Deliver the pulses held back while rship was down as soon as it is back
+                if let Some(buffer) = pulse_buffer.as_mut()
+                    && !buffer.is_empty()
+                    && rship_connected.load(Ordering::Relaxed)
+                {
+                    let (buffered, dropped) = buffer.drain();
+                    log::info!(
+                        "Delivering {} pulses buffered while rship was down ({dropped} dropped)",
+                        buffered.len()
+                    );
+                    for (time, event) in buffered {
+                        queues.push(event.qos_class(), event.buffer_key(), (time, event));
+                    }
+                }
+
+                // Wait only while nothing is queued, then take whatever else has arrived, so a
+                // realtime pulse can overtake the bulk pulses queued before it
+                let mut received = Vec::new();
+                if queues.is_empty() {
+                    health.idle("emission");
+                    tokio::select! {
+                        event = event_rx.recv() => match event {
+                            Some(event) => received.push(event),
+                            None => break,
+                        },
+                        _ = rship_restored.notified() => {}
+                    }
+                }
+                while let Ok(event) = event_rx.try_recv() {
+                    received.push(event);
+                }
+
+                for (time, event) in received {
+                    for observer in &observers {
+                        observer.on_event(&event);
+                    }
+
+                    // Pulses sent while rship is unreachable would be lost, hold them back
+                    if let Some(buffer) = pulse_buffer.as_mut()
+                        && !rship_connected.load(Ordering::Relaxed)
+                    {
+                        buffer.push(event.buffer_key(), (time, event));
+                        continue;
+                    }
+                    queues.push(event.qos_class(), event.buffer_key(), (time, event));
+                }
+
+                health.beat("emission");
+                let next = queues.pop();
+                health.set_pulses_queued(queues.len());
+                if let Some((time, event)) = next {
+                    log::debug!("Processing event");
+                    if let Some(emitter) = event.emitter_id()
+                        && !emitter_filters.allows(emitter)
+                    {
+                        log::trace!("Filtered out a {emitter} pulse");
+                        continue;
+                    }
+                    // Held back again if rship turns out to be unreachable
+                    let retry = pulse_buffer
+                        .is_some()
+                        .then(|| (time.clone(), event.clone()));
+                    let mut pulse_failed = false;
+                    match event {
+                        VideohubEvent::DeviceStatus {
+                            connected,
+                            unstable,
+                            model_name,
+                            video_inputs,
+                            video_outputs,
+                        } => {
+                            let data = DeviceStatusEmitter {
+                                connected,
+                                unstable,
+                                model_name,
+                                video_inputs,
+                                video_outputs,
+                                sequence: sequences.next(device_status_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = device_status_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit device status event: {e}");
+                            } else {
+                                log::debug!("Emitted device status: connected={connected}");
+                            }
+                        }
+                        VideohubEvent::PreludeComplete {
+                            video_inputs,
+                            video_outputs,
+                        } => {
+                            // Create output and input subtargets once the device sent its full state, so
+                            // each starts with its route, label and lock rather than blank. Also sent again
+                            // after rship reconnected, for the subtargets that could not be registered before.
+                            let num_inputs = video_inputs.unwrap_or(0);
+                            match video_outputs {
+                                // A device switched to with set-device-address may have more ports
+                                Some(num_outputs)
+                                    if !targets_created
+                                        || !registration.is_complete()
+                                        || known_outputs
+                                            .is_some_and(|known| num_outputs > known)
+                                        || known_inputs.is_some_and(|known| num_inputs > known) =>
+                                {
+                                    log::info!(
+                                        "Creating {num_outputs} output and {num_inputs} input subtargets dynamically"
+                                    );
+                                    known_outputs = Some(num_outputs);
+                                    known_inputs = Some(num_inputs);
+
+                                    for output in
+                                        output_targets.ordered(num_outputs.min(u32::MAX - 1))
+                                    {
+                                        if output_emitters.contains_key(&output) {
+                                            continue;
+                                        }
+                                        let output_id = output + 1;
+                                        let registered = registration
+                                            .register(&format!("output {output_id}"), || async {
+                                                // Create output subtarget
+                                                let mut output_target = instance_for_subtargets
+                                                    .add_target(TargetArgs {
+                                                        name: output_targets.name(output),
+                                                        short_id: format!("output-{output_id}"),
+                                                        category: output_targets.category(output),
+                                                        parent_targets: Some(vec![
+                                                            device_target_for_subtargets.clone(),
+                                                        ]),
+                                                    })
+                                                    .await;
+
+                                                // Add all actions to each output subtarget
+                                                let output_tx_for_route = command_tx_for_subtargets.clone();
+                                                let output_tx_for_output_label =
+                                                    command_tx_for_subtargets.clone();
+                                                let output_tx_for_output_lock =
+                                                    command_tx_for_subtargets.clone();
+                                                let output_tx_for_take_mode =
+                                                    command_tx_for_subtargets.clone();
+
+                                                output_target
+                                                .add_action(
+                                                    ActionArgs::<SetInputAction>::new(
+                                                        display_names.get("Set Input"),
+                                                        "set-input".into(),
+                                                    ),
+                                                    move |_action, data| {
+                                                        let tx = output_tx_for_route.clone();
+                                                        let current_output_id = output_id;
+                                                        tokio::spawn(async move {
+                                                            if let Err(e) = tx
+                                                                .send(CommandRequest::from_action(
+                                                                    "set-input",
+                                                                    route_command(
+                                                                        PortRef::Number(current_output_id),
+                                                                        data.input.zero_indexed(),
+                                                                        true,
+                                                                    ),
+                                                                ).owned_by(data.owner))
+                                                                .await
+                                                            {
+                                                                log::error!(
+                                                                    "Failed to send set input command: {e}"
+                                                                );
+                                                            }
+                                                        });
+                                                    },
+                                                )
+                                                .await;
+
+                                                output_target
+                                            .add_action(
+                                                ActionArgs::<SetLabelAction>::new(
+                                                    display_names.get("Set Label"),
+                                                    "set-label".into(),
+                                                ),
+                                                move |_action, data| {
+                                                    let tx = output_tx_for_output_label.clone();
+                                                    let current_output_id = output_id;
+                                                    tokio::spawn(async move {
+                                                        if let Err(e) = tx
+                                                            .send(CommandRequest::from_action("set-label", VideohubCommand::OutputLabel {
+                                                                output: current_output_id,
+                                                                label: data.label,
+                                                            }).owned_by(data.owner))
+                                                            .await
+                                                        {
+                                                            log::error!(
+                                                                "Failed to send output label command: {e}"
+                                                            );
+                                                        }
+                                                    });
+                                                },
+                                            )
+                                            .await;
+
+                                                output_target
+                                            .add_action(
+                                                ActionArgs::<SetLockAction>::new(
+                                                    display_names.get("Set Lock"),
+                                                    "set-lock".into(),
+                                                ),
+                                                move |_action, data| {
+                                                    let tx = output_tx_for_output_lock.clone();
+                                                    let current_output_id = output_id;
+                                                    tokio::spawn(async move {
+                                                        if let Err(e) = tx
+                                                            .send(CommandRequest::from_action("set-lock", VideohubCommand::OutputLock {
+                                                                output: current_output_id,
+                                                                locked: data.locked,
+                                                            }).owned_by(data.owner))
+                                                            .await
+                                                        {
+                                                            log::error!(
+                                                                "Failed to send output lock command: {e}"
+                                                            );
+                                                        }
+                                                    });
+                                                },
+                                            )
+                                            .await;
+
+                                                output_target
+                                                .add_action(
+                                                    ActionArgs::<SetTakeModeOnThisOutputAction>::new(
+                                                        display_names.get("Set Take Mode"),
+                                                        "set-take-mode".into(),
+                                                    ),
+                                                    move |_action, data| {
+                                                        let tx = output_tx_for_take_mode.clone();
+                                                        let current_output_id = output_id;
+                                                        tokio::spawn(async move {
+                                                            if let Err(e) = tx
+                                                                .send(CommandRequest::from_action(
+                                                                    "set-take-mode",
+                                                                    VideohubCommand::TakeMode {
+                                                                        output: current_output_id,
+                                                                        enabled: data.enabled,
+                                                                    },
+                                                                ).owned_by(data.owner))
+                                                                .await
+                                                            {
+                                                                log::error!(
+                                                                    "Failed to send take mode command: {e}"
+                                                                );
+                                                            }
+                                                        });
+                                                    },
+                                                )
+                                                .await;
+
+                                                // Add output-specific emitters (input-only versions)
+                                                let input_changed_emitter = output_target
+                                                    .add_emitter(EmitterArgs::<InputChangedEmitter>::new(
+                                                        display_names.get("Input Changed"),
+                                                        "input-changed".into(),
+                                                    ))
+                                                    .await;
+
+                                                let label_emitter = output_target
+                                                    .add_emitter(EmitterArgs::<LabelChangedEmitter>::new(
+                                                        display_names.get("Label Changed"),
+                                                        "label-changed".into(),
+                                                    ))
+                                                    .await;
+
+                                                let output_lock_emitter = output_target
+                                                    .add_emitter(EmitterArgs::<LockChangedEmitter>::new(
+                                                        display_names.get("Lock Changed"),
+                                                        "lock-changed".into(),
+                                                    ))
+                                                    .await;
+
+                                                let take_mode_emitter = output_target
+                                                    .add_emitter(
+                                                        EmitterArgs::<TakeModeOnThisOutputEmitter>::new(
+                                                            display_names.get("Take Mode Changed"),
+                                                            "take-mode-changed".into(),
+                                                        ),
+                                                    )
+                                                    .await;
+
+                                                let output_state_emitter = output_target
+                                                    .add_emitter(EmitterArgs::<OutputStateEmitter>::new(
+                                                        display_names.get("Output State"),
+                                                        "output-state".into(),
+                                                    ))
+                                                    .await;
+
+                                                (
+                                                    input_changed_emitter,
+                                                    label_emitter,
+                                                    output_lock_emitter,
+                                                    take_mode_emitter,
+                                                    output_state_emitter,
+                                                )
+                                            })
+                                            .await;
+                                        let Some((
+                                            input_changed_emitter,
+                                            label_emitter,
+                                            output_lock_emitter,
+                                            take_mode_emitter,
+                                            output_state_emitter,
+                                        )) = registered
+                                        else {
+                                            continue;
+                                        };
+
+                                        // Start the subtarget with the state that arrived before it existed,
+                                        // rather than leaving it blank until the next change
+                                        if let Some(state) = output_state.get(&output) {
+                                            if let Some((input, input_label, confirmed)) =
+                                                &state.input
+                                            {
+                                                let last_change = state.last_change.as_ref();
+                                                let data = InputChangedEmitter {
+                                                    input: input + 1,
+                                                    input_label: input_label.clone(),
+                                                    input_alias: port_metadata.input_alias(
+                                                        *input,
+                                                        input_label.as_deref(),
+                                                    ),
+                                                    input_group: port_metadata.input_group(*input),
+                                                    input_metadata: port_metadata.input(*input),
+                                                    confirmed: *confirmed,
+                                                    changed_by: last_change
+                                                        .map(|change| change.changed_by.clone()),
+                                                    changed_at: last_change
+                                                        .map(|change| change.changed_at.clone()),
+                                                    sequence: sequences
+                                                        .next(input_changed_emitter.id()),
+                                                    timestamp: time.timestamp.clone(),
+                                                    monotonic_ms: time.monotonic_ms,
+                                                };
+                                                if let Err(e) =
+                                                    input_changed_emitter.pulse(data).await
+                                                {
+                                                    log::error!(
+                                                        "Failed to emit initial input on output {output}: {e}"
+                                                    );
+                                                }
+                                            }
+                                            if let Some((label, confirmed)) = &state.label {
+                                                let data = LabelChangedEmitter {
+                                                    port_type: "output".into(),
+                                                    port: output,
+                                                    label: label.clone(),
+                                                    original_label: None,
+                                                    confirmed: *confirmed,
+                                                    sequence: sequences.next(label_emitter.id()),
+                                                    timestamp: time.timestamp.clone(),
+                                                    monotonic_ms: time.monotonic_ms,
+                                                };
+                                                if let Err(e) = label_emitter.pulse(data).await {
+                                                    log::error!(
+                                                        "Failed to emit initial label on output {output}: {e}"
+                                                    );
+                                                }
+                                            }
+                                            if let Some(locked) = state.locked {
+                                                let data = LockChangedEmitter {
+                                                    locked,
+                                                    sequence: sequences
+                                                        .next(output_lock_emitter.id()),
+                                                    timestamp: time.timestamp.clone(),
+                                                    monotonic_ms: time.monotonic_ms,
+                                                };
+                                                if let Err(e) =
+                                                    output_lock_emitter.pulse(data).await
+                                                {
+                                                    log::error!(
+                                                        "Failed to emit initial lock on output {output}: {e}"
+                                                    );
+                                                }
+                                            }
+                                            if let Some(enabled) = state.take_mode {
+                                                let data = TakeModeOnThisOutputEmitter {
+                                                    enabled,
+                                                    sequence: sequences
+                                                        .next(take_mode_emitter.id()),
+                                                    timestamp: time.timestamp.clone(),
+                                                    monotonic_ms: time.monotonic_ms,
+                                                };
+                                                if let Err(e) = take_mode_emitter.pulse(data).await
+                                                {
+                                                    log::error!(
+                                                        "Failed to emit initial take mode on output {output}: {e}"
+                                                    );
+                                                }
+                                            }
+                                            if let Some(status) = &state.status {
+                                                let data = status.data(
+                                                    sequences.next(output_state_emitter.id()),
+                                                    &time,
+                                                );
+                                                if let Err(e) =
+                                                    output_state_emitter.pulse(data).await
+                                                {
+                                                    log::error!(
+                                                        "Failed to emit initial state of output {output}: {e}"
+                                                    );
+                                                }
+                                            }
+                                        }
+
+                                        output_emitters.insert(
+                                            output,
+                                            (
+                                                input_changed_emitter,
+                                                label_emitter,
+                                                output_lock_emitter,
+                                                take_mode_emitter,
+                                                output_state_emitter,
+                                            ),
+                                        );
+                                    }
+
+                                    for input in 0..num_inputs.min(u32::MAX - 1) {
+                                        if input_emitters.contains_key(&input) {
+                                            continue;
+                                        }
+                                        let input_id = input + 1;
+                                        let registered = registration
+                                            .register(&format!("input {input_id}"), || async {
+                                                let mut input_target = instance_for_subtargets
+                                                    .add_target(TargetArgs {
+                                                        name: format!("Input {input_id}"),
+                                                        short_id: format!("input-{input_id}"),
+                                                        category: INPUT_TARGET_CATEGORY.into(),
+                                                        parent_targets: Some(vec![
+                                                            device_target_for_subtargets.clone(),
+                                                        ]),
+                                                    })
+                                                    .await;
+
+                                                let input_tx_for_label = command_tx_for_subtargets.clone();
+                                                let input_tx_for_route = command_tx_for_subtargets.clone();
+
+                                                input_target
+                                                    .add_action(
+                                                        ActionArgs::<SetLabelAction>::new(
+                                                            display_names.get("Set Label"),
+                                                            "set-label".into(),
+                                                        ),
+                                                        move |_action, data| {
+                                                            let tx = input_tx_for_label.clone();
+                                                            tokio::spawn(async move {
+                                                                if let Err(e) = tx
+                                                                    .send(CommandRequest::from_action(
+                                                                        "set-label",
+                                                                        VideohubCommand::InputLabel {
+                                                                            input,
+                                                                            label: data.label,
+                                                                        },
+                                                                    ).owned_by(data.owner))
+                                                                    .await
+                                                                {
+                                                                    log::error!(
+                                                                        "Failed to send input label command: {e}"
+                                                                    );
+                                                                }
+                                                            });
+                                                        },
+                                                    )
+                                                    .await;
+
+                                                input_target
+                                                    .add_action(
+                                                        ActionArgs::<RouteToOutputAction>::new(
+                                                            display_names.get("Route To Output"),
+                                                            "route-to-output".into(),
+                                                        ),
+                                                        move |_action, data| {
+                                                            let tx = input_tx_for_route.clone();
+                                                            tokio::spawn(async move {
+                                                                if let Err(e) = tx
+                                                                    .send(CommandRequest::from_action(
+                                                                        "route-to-output",
+                                                                        route_command(
+                                                                            data.output.zero_indexed(),
+                                                                            PortRef::Number(input),
+                                                                            false,
+                                                                        ),
+                                                                    ).owned_by(data.owner))
+                                                                    .await
+                                                                {
+                                                                    log::error!(
+                                                                        "Failed to send route command: {e}"
+                                                                    );
+                                                                }
+                                                            });
+                                                        },
+                                                    )
+                                                    .await;
+
+                                                let label_emitter = input_target
+                                                    .add_emitter(EmitterArgs::<LabelChangedEmitter>::new(
+                                                        display_names.get("Label Changed"),
+                                                        "label-changed".into(),
+                                                    ))
+                                                    .await;
+
+                                                let routed_outputs_emitter = input_target
+                                                    .add_emitter(EmitterArgs::<RoutedOutputsEmitter>::new(
+                                                        display_names.get("Routed Outputs"),
+                                                        "routed-outputs".into(),
+                                                    ))
+                                                    .await;
+
+                                                (label_emitter, routed_outputs_emitter)
+                                            })
+                                            .await;
+                                        let Some((label_emitter, routed_outputs_emitter)) =
+                                            registered
+                                        else {
+                                            continue;
+                                        };
+
+                                        // Start the subtarget with the label and routes that arrived before it existed
+                                        if let Some((label, original, confirmed)) =
+                                            input_labels.get(&input)
+                                        {
+                                            let data = LabelChangedEmitter {
+                                                port_type: "input".into(),
+                                                port: input,
+                                                label: label.clone(),
+                                                original_label: original.clone(),
+                                                confirmed: *confirmed,
+                                                sequence: sequences.next(label_emitter.id()),
+                                                timestamp: time.timestamp.clone(),
+                                                monotonic_ms: time.monotonic_ms,
+                                            };
+                                            if let Err(e) = label_emitter.pulse(data).await {
+                                                log::error!(
+                                                    "Failed to emit initial label on input {input}: {e}"
+                                                );
+                                            }
+                                        }
+                                        let data = RoutedOutputsEmitter {
+                                            outputs: routed_outputs(&output_state, input),
+                                            sequence: sequences.next(routed_outputs_emitter.id()),
+                                            timestamp: time.timestamp.clone(),
+                                            monotonic_ms: time.monotonic_ms,
+                                        };
+                                        if let Err(e) = routed_outputs_emitter.pulse(data).await {
+                                            log::error!(
+                                                "Failed to emit initial routed outputs of input {input}: {e}"
+                                            );
+                                        }
+
+                                        input_emitters
+                                            .insert(input, (label_emitter, routed_outputs_emitter));
+                                    }
+
+                                    targets_created = true;
+                                    log::info!(
+                                        "Registered {} of {num_outputs} output and {} of {num_inputs} input subtargets",
+                                        output_emitters.len(),
+                                        input_emitters.len()
+                                    );
+                                    let data = RegistrationStatusEmitter {
+                                        complete: registration.is_complete(),
+                                        registered: registration.registered() as u32,
+                                        missing: registration.missing(),
+                                        sequence: sequences.next(registration_status_emitter.id()),
+                                        timestamp: time.timestamp.clone(),
+                                        monotonic_ms: time.monotonic_ms,
+                                    };
+                                    if let Err(e) = registration_status_emitter.pulse(data).await {
+                                        log::error!("Failed to emit registration status: {e}");
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        VideohubEvent::Route {
+                            output,
+                            input,
+                            input_label,
+                            confirmed,
+                            last_change,
+                        } => {
+                            let state = output_state.entry(output).or_default();
+                            let previous_input =
+                                state.input.as_ref().map(|(previous, ..)| *previous);
+                            if warn_format_mismatch
+                                && confirmed
+                                && state
+                                    .input
+                                    .as_ref()
+                                    .is_none_or(|(previous, ..)| *previous != input)
+                                && let Some((input_format, output_format)) =
+                                    format_mismatch(&port_metadata, output, input)
+                            {
+                                log::warn!(
+                                    "Output {} ({output_format}) now carries input {} ({input_format})",
+                                    output + 1,
+                                    input + 1
+                                );
+                            }
+                            state.input = Some((input, input_label.clone(), confirmed));
+                            if last_change.is_some() {
+                                state.last_change = last_change;
+                            }
+                            let last_change = state.last_change.clone();
+                            let input_data = |sequence| InputChangedEmitter {
+                                input: input + 1,
+                                input_alias: port_metadata
+                                    .input_alias(input, input_label.as_deref()),
+                                input_group: port_metadata.input_group(input),
+                                input_label,
+                                input_metadata: port_metadata.input(input),
+                                confirmed,
+                                changed_by: last_change
+                                    .as_ref()
+                                    .map(|change| change.changed_by.clone()),
+                                changed_at: last_change.map(|change| change.changed_at),
+                                sequence,
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+
+                            // Emit to the specific output subtarget if it exists
+                            if let Some((input_changed_emitter, ..)) = output_emitters.get(&output)
+                            {
+                                if let Err(e) = input_changed_emitter
+                                    .pulse(input_data(sequences.next(input_changed_emitter.id())))
+                                    .await
+                                {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit input changed event on output {output}: {e}"
+                                    );
+                                } else {
+                                    log::debug!(
+                                        "Emitted input changed on output {output}: input {input}"
+                                    );
+                                }
+                            } else {
+                                log::debug!(
+                                    "Output emitters not ready or output {output} out of range"
+                                );
+                            }
+
+                            // The input the output left and the one it shows now feed other outputs
+                            let changed_inputs = previous_input
+                                .filter(|previous| *previous != input)
+                                .into_iter()
+                                .chain(std::iter::once(input));
+                            for changed in changed_inputs {
+                                let Some((_, routed_outputs_emitter)) =
+                                    input_emitters.get(&changed)
+                                else {
+                                    continue;
+                                };
+                                let data = RoutedOutputsEmitter {
+                                    outputs: routed_outputs(&output_state, changed),
+                                    sequence: sequences.next(routed_outputs_emitter.id()),
+                                    timestamp: time.timestamp.clone(),
+                                    monotonic_ms: time.monotonic_ms,
+                                };
+                                if let Err(e) = routed_outputs_emitter.pulse(data).await {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit routed outputs of input {changed}: {e}"
+                                    );
+                                }
+                            }
+                        }
+                        VideohubEvent::Label {
+                            port_type,
+                            port,
+                            label,
+                            original,
+                            confirmed,
+                        } => {
+                            if port_type == "output" {
+                                output_state.entry(port).or_default().label =
+                                    Some((label.clone(), confirmed));
+                            } else {
+                                input_labels
+                                    .insert(port, (label.clone(), original.clone(), confirmed));
+                            }
+                            let data = |sequence| LabelChangedEmitter {
+                                port_type: port_type.clone(),
+                                port,
+                                label: label.clone(),
+                                original_label: original.clone(),
+                                confirmed,
+                                sequence,
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+
+                            // For output labels, emit to the specific output subtarget
+                            if port_type == "output" {
+                                if let Some((_, label_emitter, ..)) = output_emitters.get(&port) {
+                                    if let Err(e) = label_emitter
+                                        .pulse(data(sequences.next(label_emitter.id())))
+                                        .await
+                                    {
+                                        pulse_failed = true;
+                                        log::error!(
+                                            "Failed to emit label changed event on output {port}: {e}"
+                                        );
+                                    } else {
+                                        log::debug!(
+                                            "Emitted label changed on output {port}: {port_type} port {port}"
+                                        );
+                                    }
+                                } else {
+                                    log::debug!(
+                                        "Output emitters not ready or output {port} out of range for label"
+                                    );
+                                }
+                            } else if let Some((label_emitter, _)) = input_emitters.get(&port) {
+                                // For input labels, emit to the specific input subtarget
+                                if let Err(e) = label_emitter
+                                    .pulse(data(sequences.next(label_emitter.id())))
+                                    .await
+                                {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit label changed event on input {port}: {e}"
+                                    );
+                                } else {
+                                    log::debug!("Emitted label changed on input {port}");
+                                }
+                            } else {
+                                log::debug!(
+                                    "Input emitters not ready or input {port} out of range for label"
+                                );
+                            }
+                        }
+                        VideohubEvent::OutputLock { output, locked } => {
+                            output_state.entry(output).or_default().locked = Some(locked);
+                            let data = |sequence| LockChangedEmitter {
+                                locked,
+                                sequence,
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+
+                            // Emit to the specific output subtarget
+                            if let Some((_, _, output_lock_emitter, ..)) =
+                                output_emitters.get(&output)
+                            {
+                                if let Err(e) = output_lock_emitter
+                                    .pulse(data(sequences.next(output_lock_emitter.id())))
+                                    .await
+                                {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit lock changed event on output {output}: {e}"
+                                    );
+                                } else {
+                                    log::debug!(
+                                        "Emitted lock changed on output {output}: locked={locked}"
+                                    );
+                                }
+                            } else {
+                                log::debug!(
+                                    "Output emitters not ready or output {output} out of range for lock"
+                                );
+                            }
+                        }
+                        VideohubEvent::TakeMode { output, enabled } => {
+                            output_state.entry(output).or_default().take_mode = Some(enabled);
+                            let data = |sequence| TakeModeOnThisOutputEmitter {
+                                enabled,
+                                sequence,
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+
+                            // Emit to the specific output subtarget
+                            if let Some((_, _, _, take_mode_emitter, _)) =
+                                output_emitters.get(&output)
+                            {
+                                if let Err(e) = take_mode_emitter
+                                    .pulse(data(sequences.next(take_mode_emitter.id())))
+                                    .await
+                                {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit take mode changed event on output {output}: {e}"
+                                    );
+                                } else {
+                                    log::debug!(
+                                        "Emitted take mode changed on output {output}: enabled={enabled}"
+                                    );
+                                }
+                            } else {
+                                log::debug!(
+                                    "Output emitters not ready or output {output} out of range for take mode"
+                                );
+                            }
+                        }
+                        VideohubEvent::OutputState { output, status } => {
+                            if let Some((.., output_state_emitter)) = output_emitters.get(&output) {
+                                let data =
+                                    status.data(sequences.next(output_state_emitter.id()), &time);
+                                if let Err(e) = output_state_emitter.pulse(data).await {
+                                    pulse_failed = true;
+                                    log::error!(
+                                        "Failed to emit output state on output {output}: {e}"
+                                    );
+                                }
+                            }
+                            output_state.entry(output).or_default().status = Some(status);
+                        }
+                        VideohubEvent::ActionFeedback {
+                            action,
+                            correlation_id,
+                            status,
+                            error,
+                        } => {
+                            let data = ActionFeedbackEmitter {
+                                action,
+                                correlation_id,
+                                status: status.to_string(),
+                                error,
+                                sequence: sequences.next(action_feedback_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = action_feedback_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit action feedback: {e}");
+                            }
+                        }
+                        VideohubEvent::SequenceProgress {
+                            output,
+                            step,
+                            total,
+                            input,
+                            running,
+                        } => {
+                            let data = SequenceProgressEmitter {
+                                output: output + 1,
+                                step: step + 1,
+                                total,
+                                input: input + 1,
+                                running,
+                                sequence: sequences.next(sequence_progress_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = sequence_progress_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!(
+                                    "Failed to emit sequence progress on output {output}: {e}"
+                                );
+                            }
+                        }
+                        VideohubEvent::SweepProgress {
+                            input,
+                            output,
+                            step,
+                            total,
+                            running,
+                        } => {
+                            let data = SweepProgressEmitter {
+                                input: input + 1,
+                                output: output + 1,
+                                step: step + 1,
+                                total,
+                                running,
+                                sequence: sequences.next(sweep_progress_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = sweep_progress_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit sweep progress: {e}");
+                            }
+                        }
+                        VideohubEvent::AutoFailover {
+                            output,
+                            primary,
+                            backup,
+                            error,
+                        } => {
+                            let data = AutoFailoverEmitter {
+                                output: output + 1,
+                                primary: primary + 1,
+                                backup: backup + 1,
+                                error,
+                                sequence: sequences.next(auto_failover_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = auto_failover_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit auto failover on output {output}: {e}");
+                            }
+                        }
+                        VideohubEvent::Inventory {
+                            device_info,
+                            protocol_version,
+                            network_interfaces,
+                            capabilities,
+                        } => {
+                            let info = device_info.unwrap_or_default();
+                            let data = DeviceInventoryEmitter {
+                                model_name: info.model_name,
+                                friendly_name: info.friendly_name,
+                                unique_id: info.unique_id,
+                                present: info.present.map(|present| present.to_string()),
+                                protocol_version,
+                                video_inputs: info.video_inputs,
+                                video_outputs: info.video_outputs,
+                                video_monitoring_outputs: info.video_monitoring_outputs,
+                                video_processing_units: info.video_processing_units,
+                                serial_ports: info.serial_ports,
+                                network_interfaces: network_interfaces
+                                    .into_iter()
+                                    .map(|interface| NetworkInterfaceEmitter {
+                                        interface_id: interface.id,
+                                        name: interface.name,
+                                        mac_address: interface.mac_address,
+                                        current_addresses: interface.current_addresses,
+                                        current_gateway: interface.current_gateway,
+                                        dynamic_ip: interface.dynamic_ip,
+                                        // Not pulsed on its own
+                                        sequence: 0,
+                                        timestamp: time.timestamp.clone(),
+                                        monotonic_ms: time.monotonic_ms,
+                                    })
+                                    .collect(),
+                                capabilities,
+                                input_metadata: port_metadata.inputs(),
+                                output_metadata: port_metadata.outputs(),
+                                sequence: sequences.next(device_inventory_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = device_inventory_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit device inventory: {e}");
+                            }
+                        }
+                        VideohubEvent::DeviceAddress { host, port } => {
+                            let data = DeviceAddressEmitter {
+                                host,
+                                port,
+                                sequence: sequences.next(device_address_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = device_address_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit device address: {e}");
+                            }
+                        }
+                        VideohubEvent::RshipEndpoint { url, priority } => {
+                            let data = RshipEndpointEmitter {
+                                endpoint: url,
+                                priority: priority as u32 + 1,
+                                sequence: sequences.next(rship_endpoint_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = rship_endpoint_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit rship endpoint: {e}");
+                            }
+
+                            // Sent on every reconnect; the SDK re-registers what it knows, the rest
+                            // is registered again here
+                            if !registration.is_complete()
+                                && let Some(num_outputs) = known_outputs
+                            {
+                                log::info!("Registering the missing subtargets again");
+                                queues.push(
+                                    QosClass::Bulk,
+                                    Some("prelude-complete".into()),
+                                    (
+                                        time,
+                                        VideohubEvent::PreludeComplete {
+                                            video_inputs: known_inputs,
+                                            video_outputs: Some(num_outputs),
+                                        },
+                                    ),
+                                );
+                            }
+                        }
+                        VideohubEvent::PortInfo {
+                            port_type,
+                            port,
+                            label,
+                            routed_input,
+                            capabilities,
+                        } => {
+                            let data = PortInfoEmitter {
+                                metadata: port_metadata.port(&port_type, port),
+                                capabilities,
+                                port_type,
+                                port: port + 1,
+                                label,
+                                routed_input: routed_input.map(|input| input + 1),
+                                sequence: sequences.next(port_info_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = port_info_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit port info: {e}");
+                            }
+                        }
+                        VideohubEvent::StagedChanges {
+                            source,
+                            status,
+                            changes,
+                            expires_in_ms,
+                        } => {
+                            let data = StagedChangesEmitter {
+                                source,
+                                status: status.to_string(),
+                                changes,
+                                expires_in_ms,
+                                sequence: sequences.next(staged_changes_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = staged_changes_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit staged changes: {e}");
+                            }
+                        }
+                        VideohubEvent::PendingRoutes { routes, status } => {
+                            let data = PendingRoutesEmitter {
+                                status: status.to_string(),
+                                routes,
+                                sequence: sequences.next(pending_routes_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = pending_routes_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit pending routes: {e}");
+                            }
+                        }
+                        VideohubEvent::Salvos { salvos } => {
+                            let data = SalvosEmitter {
+                                salvos,
+                                sequence: sequences.next(salvos_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = salvos_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit salvos: {e}");
+                            }
+                        }
+                        VideohubEvent::PerformanceDegraded {
+                            degraded,
+                            p95_ms,
+                            budget_ms,
+                            samples,
+                        } => {
+                            let data = PerformanceDegradedEmitter {
+                                degraded,
+                                p95_ms,
+                                budget_ms,
+                                samples: samples as u32,
+                                sequence: sequences.next(performance_degraded_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = performance_degraded_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit performance degraded: {e}");
+                            }
+                        }
+                        VideohubEvent::Configuration { setting, value } => {
+                            let data = DeviceConfigurationEmitter {
+                                setting,
+                                value,
+                                sequence: sequences.next(device_configuration_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = device_configuration_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit device configuration: {e}");
+                            }
+                        }
+                        VideohubEvent::CommandRejected {
+                            category,
+                            rejected_total,
+                            rolled_back,
+                        } => {
+                            let data = CommandRejectedEmitter {
+                                category: category.to_string(),
+                                rejected_total,
+                                rolled_back: rolled_back as u32,
+                                sequence: sequences.next(command_rejected_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = command_rejected_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit command rejected: {e}");
+                            }
+                        }
+                        VideohubEvent::UnknownBlock {
+                            header,
+                            count,
+                            total,
+                            sample,
+                        } => {
+                            let data = UnknownBlockEmitter {
+                                header,
+                                sample,
+                                count,
+                                total,
+                                sequence: sequences.next(unknown_block_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = unknown_block_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit unknown block: {e}");
+                            }
+                        }
+                        VideohubEvent::RawResponse {
+                            header,
+                            reply,
+                            blocks,
+                        } => {
+                            let data = RawResponseEmitter {
+                                header,
+                                reply: reply.to_string(),
+                                blocks,
+                                sequence: sequences.next(raw_response_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = raw_response_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit raw response: {e}");
+                            }
+                        }
+                        VideohubEvent::PortCountMismatch {
+                            port_type,
+                            reported,
+                            configured,
+                        } => {
+                            let data = PortCountMismatchEmitter {
+                                port_type: port_type.to_string(),
+                                reported,
+                                configured,
+                                sequence: sequences.next(port_count_mismatch_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = port_count_mismatch_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit port count mismatch: {e}");
+                            }
+                        }
+                        VideohubEvent::DiscoveredVideohubs { devices } => {
+                            let data = DiscoveredVideohubsEmitter {
+                                devices,
+                                sequence: sequences.next(discovered_videohubs_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = discovered_videohubs_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit discovered Videohubs: {e}");
+                            }
+                        }
+                        VideohubEvent::MatrixSummary {
+                            locked_outputs,
+                            take_mode_outputs,
+                            last_change,
+                            recent_outputs,
+                        } => {
+                            let data = MatrixSummaryEmitter {
+                                locked_outputs: locked_outputs as u32,
+                                take_mode_outputs: take_mode_outputs as u32,
+                                last_change: last_change.map(|time| time.timestamp),
+                                recent_outputs: recent_outputs
+                                    .into_iter()
+                                    .map(|output| output + 1)
+                                    .collect(),
+                                sequence: sequences.next(matrix_summary_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = matrix_summary_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit matrix summary: {e}");
+                            }
+                        }
+                        VideohubEvent::ChangeSummary {
+                            window_ms,
+                            route_changes,
+                            label_changes,
+                            lock_changes,
+                            busiest_outputs,
+                        } => {
+                            let data = ChangeSummaryEmitter {
+                                window_ms,
+                                route_changes,
+                                label_changes,
+                                lock_changes,
+                                busiest_outputs: busiest_outputs
+                                    .into_iter()
+                                    .map(|(output, changes)| BusyOutput {
+                                        output: output + 1,
+                                        changes,
+                                    })
+                                    .collect(),
+                                sequence: sequences.next(change_summary_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = change_summary_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit change summary: {e}");
+                            }
+                        }
+                        VideohubEvent::Watchdog {
+                            alarm,
+                            probe,
+                            unanswered_ms,
+                        } => {
+                            let data = WatchdogEmitter {
+                                alarm,
+                                probe,
+                                unanswered_ms,
+                                sequence: sequences.next(watchdog_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = watchdog_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit watchdog: {e}");
+                            }
+                        }
+                        VideohubEvent::LeadershipChanged { leader, peer_alive } => {
+                            let data = LeadershipChangedEmitter {
+                                leader,
+                                role: if leader { "leader" } else { "standby" }.into(),
+                                peer_alive,
+                                sequence: sequences.next(leadership_changed_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = leadership_changed_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit leadership change: {e}");
+                            }
+                        }
+                        VideohubEvent::StateDivergence {
+                            alarm,
+                            local_checksum,
+                            peer_checksum,
+                        } => {
+                            let data = StateDivergenceEmitter {
+                                alarm,
+                                local_checksum,
+                                peer_checksum,
+                                sequence: sequences.next(state_divergence_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = state_divergence_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit state divergence: {e}");
+                            }
+                        }
+                        VideohubEvent::IntentDivergence {
+                            intended_outputs,
+                            diverged,
+                        } => {
+                            let data = IntentDivergenceEmitter {
+                                aligned: diverged.is_empty(),
+                                intended_outputs: intended_outputs as u32,
+                                diverged_outputs: diverged
+                                    .into_iter()
+                                    .map(|mismatch| DivergedOutput {
+                                        output: mismatch.output + 1,
+                                        expected_input: mismatch.expected + 1,
+                                        actual_input: mismatch.actual.map(|input| input + 1),
+                                    })
+                                    .collect(),
+                                sequence: sequences.next(intent_divergence_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = intent_divergence_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit intent divergence: {e}");
+                            }
+                        }
+                        VideohubEvent::Drift { drift, planned } => {
+                            let data = DriftEmitter {
+                                score: drift.score,
+                                routes: drift.routes,
+                                input_labels: drift.input_labels,
+                                output_labels: drift.output_labels,
+                                locks: drift.locks,
+                                planned,
+                                sequence: sequences.next(drift_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = drift_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit drift: {e}");
+                            }
+                        }
+                        VideohubEvent::InterruptedOperation {
+                            source,
+                            written_at,
+                            applied,
+                            missing,
+                            completed,
+                        } => {
+                            let data = InterruptedOperationEmitter {
+                                source,
+                                written_at,
+                                applied,
+                                missing,
+                                completed,
+                                sequence: sequences.next(interrupted_operation_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = interrupted_operation_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit interrupted operation: {e}");
+                            }
+                        }
+                        VideohubEvent::DeviceRebootDetected {
+                            epoch,
+                            disconnected_ms,
+                            device_uptime_s,
+                            evidence,
+                        } => {
+                            let data = DeviceRebootDetectedEmitter {
+                                epoch,
+                                disconnected_ms,
+                                device_uptime_s,
+                                evidence,
+                                sequence: sequences.next(device_reboot_detected_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = device_reboot_detected_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit device reboot: {e}");
+                            }
+                        }
+                        VideohubEvent::TransactionStatus {
+                            transaction,
+                            status,
+                            changes,
+                            failed,
+                        } => {
+                            let data = TransactionStatusEmitter {
+                                transaction,
+                                status: status.to_string(),
+                                changes: changes as u32,
+                                failed,
+                                sequence: sequences.next(transaction_status_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = transaction_status_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit transaction status: {e}");
+                            }
+                        }
+                        VideohubEvent::BatchProgress {
+                            batch,
+                            step,
+                            total,
+                            action,
+                            status,
+                            error,
+                        } => {
+                            let data = BatchProgressEmitter {
+                                batch,
+                                step: step + 1,
+                                total,
+                                action: action.to_string(),
+                                status: status.to_string(),
+                                error,
+                                sequence: sequences.next(batch_progress_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = batch_progress_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit batch progress: {e}");
+                            }
+                        }
+                        VideohubEvent::SimulationResult {
+                            action,
+                            correlation_id,
+                            prediction,
+                        } => {
+                            let data = SimulationResultEmitter {
+                                action,
+                                correlation_id,
+                                allowed: prediction.allowed,
+                                blocked_by: prediction.blocked_by,
+                                changes: prediction.changes,
+                                sequence: sequences.next(simulation_result_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = simulation_result_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit simulation result: {e}");
+                            }
+                        }
+                        VideohubEvent::OwnershipViolation {
+                            action,
+                            correlation_id,
+                            owner,
+                            output,
+                            output_owner,
+                        } => {
+                            let data = OwnershipViolationEmitter {
+                                action,
+                                correlation_id,
+                                owner,
+                                output: output + 1,
+                                output_owner,
+                                sequence: sequences.next(ownership_violation_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = ownership_violation_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit ownership violation: {e}");
+                            }
+                        }
+                        VideohubEvent::FreezeViolation {
+                            action,
+                            correlation_id,
+                            output,
+                            frozen_input,
+                            input,
+                        } => {
+                            let data = FreezeViolationEmitter {
+                                action,
+                                correlation_id,
+                                output: output + 1,
+                                frozen_input: frozen_input + 1,
+                                input: input + 1,
+                                sequence: sequences.next(freeze_violation_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = freeze_violation_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit freeze violation: {e}");
+                            }
+                        }
+                        VideohubEvent::ControlClaim {
+                            operator,
+                            expires_in_ms,
+                        } => {
+                            let data = ControlClaimEmitter {
+                                claimed: operator.is_some(),
+                                operator,
+                                expires_in_ms,
+                                sequence: sequences.next(control_claim_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = control_claim_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit control claim: {e}");
+                            }
+                        }
+                        VideohubEvent::Profile {
+                            profile,
+                            emitter_filters: filters,
+                        } => {
+                            emitter_filters = EmitterFilters::new(filters);
+                            let data = ProfileEmitter {
+                                profile,
+                                sequence: sequences.next(profile_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = profile_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit profile: {e}");
+                            }
+                        }
+                        VideohubEvent::OwnershipChanged {
+                            outputs,
+                            to_owner,
+                            previous_owners,
+                        } => {
+                            let data = OwnershipChangedEmitter {
+                                outputs: outputs.into_iter().map(|output| output + 1).collect(),
+                                to_owner,
+                                previous_owners: previous_owners
+                                    .into_iter()
+                                    .map(|(output, owner)| (output + 1, owner))
+                                    .collect(),
+                                sequence: sequences.next(ownership_changed_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            if let Err(e) = ownership_changed_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit ownership changed: {e}");
+                            }
+                        }
+                        VideohubEvent::NetworkInterface { interface } => {
+                            let data = NetworkInterfaceEmitter {
+                                interface_id: interface.id,
+                                name: interface.name.clone(),
+                                mac_address: interface.mac_address.clone(),
+                                current_addresses: interface.current_addresses.clone(),
+                                current_gateway: interface.current_gateway.clone(),
+                                dynamic_ip: interface.dynamic_ip,
+                                sequence: sequences.next(device_network_interface_emitter.id()),
+                                timestamp: time.timestamp.clone(),
+                                monotonic_ms: time.monotonic_ms,
+                            };
+                            // Network interface emitter stays on the main device target
+                            if let Err(e) = device_network_interface_emitter.pulse(data).await {
+                                pulse_failed = true;
+                                log::error!("Failed to emit network interface event: {e}");
+                            } else {
+                                log::debug!("Emitted network interface: {}", interface.name);
+                            }
+                        }
+                    }
+                    // rship went away before the connection monitoring noticed; buffer from now on
+                    if pulse_failed
+                        && let (Some(buffer), Some((time, event))) = (pulse_buffer.as_mut(), retry)
+                    {
+                        rship_connected.store(false, Ordering::Relaxed);
+                        buffer.push(event.buffer_key(), (time, event));
+                    }
+                }
+            }
+        });
+
+        log::debug!("rship instance and targets setup complete");
+        Ok(())
+    }
+
+    // The state task of the device connection. The client's reader and writer tasks own the
+    // socket; this task applies the blocks they pass on, diffs the state into events, handles
+    // commands and reconnects. Its events go through the outbox task to the emission task.
+    // Backpressure, from the device to rship:
+    // - the reader decodes up to 1024 blocks ahead, so the socket keeps being read while this
+    //   task waits, and only then stops reading and lets TCP flow control hold the device back
+    // - events are handed to the outbox task (up to 1024 waiting), which holds them while the
+    //   emission queue (100 events) is full, so a slow rship does not hold back commands. Only
+    //   once 10,000 events are held does this task wait for room again
+    // - a command waits until its blocks are flushed, so write errors reach the command
+    async fn start_videohub_task(
+        &self,
+        channels: VideohubTaskChannels,
+        observers: Vec<Arc<dyn VideohubObserver>>,
+        middleware: Vec<Arc<dyn CommandMiddleware>>,
+    ) -> Result<()> {
+        let VideohubTaskChannels {
+            command_tx,
+            mut command_rx,
+            event_tx,
+            mut rship_reconnect_rx,
+            trace_tx,
+        } = channels;
+        let (outbox_tx, outbox_rx) = mpsc::channel(OUTBOX_CHANNEL_CAPACITY);
+        self.health.watch_channel("outbox", &outbox_tx);
+        tokio::spawn(outbox::run(outbox_rx, event_tx.0));
+        let event_tx = EventSender(outbox_tx);
+        let host = self.videohub_host.clone();
+        let port = self.videohub_port;
+        let suppress_route_echo = self.config.suppresses_own_echo("input-changed");
+        let suppress_label_echo = self.config.suppresses_own_echo("label-changed");
+        let labels_authoritative = self.config.labels_authoritative;
+        let relock_routes = self.config.relock_routes;
+        let video_inputs_override = self.config.video_inputs_override;
+        let video_outputs_override = self.config.video_outputs_override;
+        let mut label_transliteration = self
+            .config
+            .label_charset
+            .clone()
+            .map(LabelTransliteration::new);
+        let mut label_store = self.config.labels_file.clone().and_then(|path| {
+            LabelStore::load(path)
+                .map_err(|e| log::error!("Label persistence disabled: {e}"))
+                .ok()
+        });
+        let mut salvo_store = self.config.salvos_file.clone().and_then(|path| {
+            SalvoStore::load(path)
+                .map_err(|e| log::error!("Salvos disabled: {e}"))
+                .ok()
+        });
+        let rotation_groups: std::collections::HashMap<String, _> = self
+            .config
+            .rotations_file
+            .as_deref()
+            .and_then(|path| {
+                RotationsConfig::load(path)
+                    .map_err(|e| log::error!("Rotation groups disabled: {e:#}"))
+                    .ok()
+            })
+            .map(|config| config.groups)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|group| (group.name.clone(), group))
+            .collect();
+        let mut failover = self
+            .config
+            .failover_file
+            .as_deref()
+            .and_then(|path| {
+                Failover::load(path)
+                    .map_err(|e| log::error!("Failover disabled: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let staging_timeout = self.config.stage_bulk_changes.then(|| {
+            self.config
+                .staged_changes_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_STAGED_CHANGES_TIMEOUT)
+        });
+        let latency_budget = self.config.latency_budget_ms.map(Duration::from_millis);
+        let route_label_warming = self.config.route_label_warming;
+        let matrix_summary_interval = self
+            .config
+            .matrix_summary_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_MATRIX_SUMMARY_INTERVAL);
+        let change_summary_interval = self
+            .config
+            .change_summary_interval_ms
+            .map(Duration::from_millis);
+        let mut watchdog = self.config.watchdog.map(|probe| {
+            Watchdog::new(
+                probe,
+                self.config
+                    .watchdog_timeout_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_WATCHDOG_TIMEOUT),
+            )
+        });
+        let mut ownership = self.config.ownership_file.as_deref().and_then(|path| {
+            Ownership::load(path)
+                .map_err(|e| log::error!("Output ownership disabled: {e}"))
+                .ok()
+        });
+        // One of an active/standby pair starts on standby until the election makes it the leader;
+        // in shadow mode the executor never leaves it
+        let shadow_mode = self.config.shadow_mode;
+        let standby = self.config.redundancy_peer.is_some() || shadow_mode;
+        let usage_file = self.config.usage_file.clone();
+        let mut usage = match usage_file.as_deref().filter(|path| path.exists()) {
+            Some(path) => UsageReport::load(path)
+                .map(UsageTracker::from_report)
+                .unwrap_or_else(|e| {
+                    log::error!("Starting a new usage period: {e}");
+                    UsageTracker::new()
+                }),
+            None => UsageTracker::new(),
+        };
+        let mut journal = self.config.journal_file.clone().and_then(|path| {
+            Journal::open(path)
+                .map_err(|e| log::error!("Command journal disabled: {e}"))
+                .ok()
+        });
+        let complete_interrupted = self.config.complete_interrupted;
+        // A profile may override CLAIMS_ADVISORY while it is active
+        let base_claims_advisory = self.config.claims_advisory;
+        let mut claims_advisory = base_claims_advisory;
+        let mut profiles = self
+            .config
+            .profiles_file
+            .as_deref()
+            .and_then(|path| {
+                Profiles::load(path)
+                    .map_err(|e| log::error!("Profiles disabled: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let emitter_filters = self.config.emitter_filters.clone();
+        // The configured profile is switched to like any other, once the task runs
+        if let Some(profile) = &self.config.profile
+            && command_tx
+                .try_send(CommandRequest::internal(VideohubCommand::SetProfile {
+                    profile: profile.clone(),
+                }))
+                .is_err()
+        {
+            log::error!("Failed to queue the {profile} profile");
+        }
+        // Show file the device is brought in line with on start and after it restarted
+        let startup_show = match &self.config.startup_policy {
+            Some(StartupPolicy::ApplySnapshot(name)) => Some(
+                self.config
+                    .snapshots_dir
+                    .clone()
+                    .unwrap_or_default()
+                    .join(format!("{name}.json")),
+            ),
+            Some(StartupPolicy::ReconcileShowFile) => {
+                if self.config.show_file.is_none() {
+                    log::error!(
+                        "STARTUP_POLICY reconcile-show-file needs SHOW_FILE; observing instead"
+                    );
+                }
+                self.config.show_file.clone()
+            }
+            Some(StartupPolicy::Observe) | None => None,
+        };
+        // Show plan the drift is measured against: SHOW_FILE, or the snapshot applied on start
+        let mut drift = self
+            .config
+            .show_file
+            .as_deref()
+            .or(startup_show.as_deref())
+            .and_then(|path| {
+                DriftMonitor::load(path)
+                    .map_err(|e| log::error!("Drift disabled: {e}"))
+                    .ok()
+            });
+        let watchdog_interval = self
+            .config
+            .watchdog_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_WATCHDOG_INTERVAL);
+        let mut flaps = FlapDetector::new(
+            self.config
+                .reconnect_flap_threshold
+                .unwrap_or(DEFAULT_FLAP_THRESHOLD),
+            self.config
+                .reconnect_stability_window_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_STABILITY_WINDOW),
+            self.config
+                .reconnect_max_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_MAX_RECONNECT_DELAY),
+        );
+        let health = self.health.clone();
+
+        tokio::spawn(async move {
+            let mut client = VideohubClient::new(host, port);
+            client.set_standby(standby);
+            client.set_port_counts(video_inputs_override, video_outputs_override);
+
+            // Connect to videohub
+            if let Err(e) = client.connect().await {
+                log::error!("Failed to connect to videohub: {e}");
+                return;
+            }
+            flaps.connected();
+            // Each connection is an epoch; a reconnect checks whether the device restarted
+            let mut reboots = RebootDetector::default();
+            reboots.connected();
+            let mut startup_pending = true;
+
+            log::debug!("Videohub client task started");
+
+            // The client state is the device state; this tracks what of it was emitted
+            let mut state_diff = StateDiff::new();
+
+            // Commands whose effect was emitted before the device confirmed it
+            let mut pending_changes = PendingChanges::new(PENDING_CHANGE_TIMEOUT);
+            let mut latency_monitor = latency_budget.map(LatencyMonitor::new);
+            let mut pending_check = interval(Duration::from_millis(500));
+
+            // Whether the device has sent its full initial state on the current connection
+            let mut prelude_complete = false;
+            // Outputs whose route was held back or emitted before its input label was known
+            let mut unlabelled_routes: BTreeSet<u32> = BTreeSet::new();
+            // Output -> its last route change, for input-changed and state dumps
+            let mut last_route_changes: BTreeMap<u32, RouteChange> = BTreeMap::new();
+            // Inputs that must stay on the outputs they feed
+            let mut input_freezes = InputFreezes::default();
+            // Operator the device is reserved for
+            let mut control_claim = ControlClaim::default();
+
+            // Momentary routes to revert: output -> hold
+            let mut route_holds: std::collections::HashMap<u32, RouteHold> =
+                std::collections::HashMap::new();
+            let mut next_hold_id: u64 = 1;
+
+            // Running input sequences, and the rotation groups to (re)start whenever the device connects
+            let mut sequencer = Sequencer::new(command_tx.clone(), event_tx.clone());
+            // The test-signal sweep running, if any
+            let mut sweeper = Sweeper::new(command_tx.clone(), event_tx.clone());
+            let mut active_rotations: std::collections::HashSet<String> = rotation_groups
+                .values()
+                .filter(|group| group.autostart)
+                .map(|group| group.name.clone())
+                .collect();
+
+            // Bulk changes awaiting apply-staged-changes
+            let mut staged: Option<StagedChanges> = None;
+            // Transactions being built, and committed ones awaiting the device's echoes
+            let mut transactions = Transactions::new(DEFAULT_TRANSACTION_TIMEOUT);
+
+            // Last signal status reported per input by external probes
+            let mut signal_present: std::collections::HashMap<u32, bool> =
+                std::collections::HashMap::new();
+
+            // Blocks the client does not understand are reported, but not on every occurrence
+            let mut unknown_throttle = UnknownBlockThrottle::new(UNKNOWN_BLOCK_REPORT_INTERVAL);
+
+            // Commands from every source, taken in turn
+            let mut command_bus = CommandBus::default();
+            // Commands lost with the connection, resent once it is re-established
+            let mut resend_after_reconnect: Vec<CommandRequest> = Vec::new();
+            let mut reconnect_now = false;
+
+            // Aggregate for dashboards, pulsed on changes after the initial state and periodically
+            let mut matrix_summary = MatrixSummary::new();
+            let mut matrix_summary_tick = interval_at(
+                tokio::time::Instant::now() + matrix_summary_interval,
+                matrix_summary_interval,
+            );
+            // Not polled while disconnected; don't catch up on the missed pulses after reconnecting
+            matrix_summary_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            // Change counts for long-horizon dashboards, pulsed at the end of each window
+            let mut change_window = ChangeWindow::default();
+            let change_summary_period = change_summary_interval.unwrap_or(Duration::from_secs(60));
+            let mut change_summary_tick = interval_at(
+                tokio::time::Instant::now() + change_summary_period,
+                change_summary_period,
+            );
+            change_summary_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            // Routes the legacy controller intends, in shadow mode
+            let mut intent = Intent::default();
+
+            // Routes preset for the next take
+            let mut pending_routes = PendingRoutes::default();
+
+            // What was last pulsed on each output's output-state
+            let mut output_status = OutputStatusMonitor::default();
+
+            // The answer to the latest send-raw-block, while it is being collected
+            let mut raw_exchange: Option<RawExchange> = None;
+
+            // Probes showing that the device still acts on commands, not just that the socket is open
+            let mut watchdog_tick = interval(watchdog_interval);
+            watchdog_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            // Per-owner usage is saved periodically so a restart only loses the last interval
+            let mut usage_save_tick = interval_at(
+                tokio::time::Instant::now() + DEFAULT_USAGE_SAVE_INTERVAL,
+                DEFAULT_USAGE_SAVE_INTERVAL,
+            );
+
+            loop {
+                tokio::select! {
+                    // Handle rship reconnection
+                    Some(_) = rship_reconnect_rx.recv() => {
+                        log::info!("Rship reconnected - forcing full state refresh");
+                        client.force_full_state_refresh();
+                        state_diff.clear();
+                    }
+                    // Handle incoming commands
+                    Some(request) = command_bus.next(&mut command_rx) => {
+                        health.set_command_sources(command_bus.statistics());
+                        let CommandRequest { mut command, mut invocation, source, retries, lost_at } = request;
+                        send_feedback(&event_tx, invocation.as_ref(), "queued", None).await;
+
+                        // The ownership check follows the other middleware, so it sees routes given by
+                        // label as the outputs they resolve to
+                        let context = CommandContext { source, invocation: invocation.as_ref() };
+                        let accepted = middleware
+                            .iter()
+                            .map(|middleware| middleware.as_ref() as &dyn CommandMiddleware)
+                            .chain(ownership.as_ref().map(|ownership| ownership as &dyn CommandMiddleware))
+                            .try_for_each(|middleware| middleware.process_request(&mut command, &context, client.state()));
+                        if let Err(e) = accepted {
+                            if let Some(violation) = e.downcast_ref::<OwnershipViolation>()
+                                && let Some(invocation) = &invocation
+                            {
+                                send_ownership_violation(&event_tx, invocation, violation).await;
+                            }
+                            reject_command(&event_tx, &observers, &command, invocation.as_ref(), e).await;
+                            continue;
+                        }
+
+                        // Labels are sent as the device can show them
+                        if let Some(transliteration) = &mut label_transliteration {
+                            transliteration.apply(&mut command);
+                        }
+
+                        // While an operator has claimed the device, only their actions go through
+                        let context = CommandContext { source, invocation: invocation.as_ref() };
+                        if let Err(operator) = control_claim.check_request(&command, &context) {
+                            let e = anyhow::anyhow!("the device is claimed by {operator}");
+                            if claims_advisory {
+                                let owner = invocation.as_ref().and_then(|invocation| invocation.owner.as_deref());
+                                log::warn!("{command:?} from {} ({source}) while {e}", owner.unwrap_or("no owner"));
+                            } else {
+                                reject_command(&event_tx, &observers, &command, invocation.as_ref(), e).await;
+                                continue;
+                            }
+                        }
+
+                        // Frozen inputs stay on the outputs they feed, whoever issued the command
+                        if !input_freezes.is_empty()
+                            && let Err(violation) = input_freezes.check(&command, &client.state().video_output_routing)
+                        {
+                            let e = anyhow::anyhow!("output {} carries frozen input {}", violation.output + 1, violation.frozen_input + 1);
+                            if let Err(e) = event_tx.send(VideohubEvent::FreezeViolation {
+                                action: invocation.as_ref().map(|invocation| invocation.action.clone()),
+                                correlation_id: invocation.as_ref().map(|invocation| invocation.correlation_id),
+                                output: violation.output,
+                                frozen_input: violation.frozen_input,
+                                input: violation.input,
+                            }).await {
+                                log::error!("Failed to send freeze violation event: {e}");
+                            }
+                            reject_command(&event_tx, &observers, &command, invocation.as_ref(), e).await;
+                            continue;
+                        }
+
+                        // Journal what is about to be written, so a crash before the device confirms it
+                        // is noticed on the next start
+                        if let Some(journal) = &mut journal {
+                            let changes = match &command {
+                                VideohubCommand::WriteChanges { changes } => changes.clone(),
+                                command => command.expected_change().into_iter().collect(),
+                            };
+                            if !changes.is_empty() {
+                                let source = invocation.as_ref().map_or("internal", |invocation| invocation.action.as_str());
+                                journal.record(source, &changes);
+                            }
+                        }
+
+                        // Set when the command's changes were written for its action, which is
+                        // then confirmed once the device has echoed all of them
+                        let mut awaiting_echo = false;
+                        let result = match &command {
+                            VideohubCommand::Route { output, input }
+                            | VideohubCommand::SetInput { output, input } => {
+                                if relock_routes && client.state().owned_locks.contains(output) {
+                                    client.set_route_through_lock(*output, *input).await
+                                } else {
+                                    client.set_route(*output, *input).await
+                                }
+                            }
+                            VideohubCommand::InputLabel { input, label } => {
+                                client.set_input_label(*input, label.clone()).await
+                            }
+                            VideohubCommand::OutputLabel { output, label } => {
+                                client.set_output_label(*output, label.clone()).await
+                            }
+                            VideohubCommand::OutputLock { output, locked } => {
+                                client.set_output_lock(*output, *locked).await
+                            }
+                            VideohubCommand::OutputLocks { outputs, locked } => {
+                                client.set_output_locks(outputs, *locked).await
+                            }
+                            VideohubCommand::TakeMode { output, enabled } => {
+                                client.set_take_mode(*output, *enabled).await
+                            }
+                            VideohubCommand::SetProtocolTrace { enabled } => {
+                                client.set_protocol_trace(enabled.then(|| trace_tx.clone()));
+                                Ok(())
+                            }
+                            VideohubCommand::HoldRoute { output, input, duration_ms } => {
+                                let result = client.set_route(*output, *input).await;
+                                if result.is_ok() {
+                                    let hold_id = next_hold_id;
+                                    next_hold_id += 1;
+
+                                    // Keep the original route if this output is already held
+                                    let previous_input = match route_holds.get(output) {
+                                        Some(hold) => hold.previous_input,
+                                        None => client.state().video_output_routing.get(output).copied(),
+                                    };
+                                    route_holds.insert(*output, RouteHold { id: hold_id, input: *input, previous_input });
+
+                                    let tx = command_tx.clone();
+                                    let output = *output;
+                                    let duration = Duration::from_millis(*duration_ms);
+                                    tokio::spawn(async move {
+                                        tokio::time::sleep(duration).await;
+                                        let release = VideohubCommand::ReleaseHold { output, hold_id: Some(hold_id) };
+                                        if let Err(e) = tx.send(CommandRequest::internal(release)).await {
+                                            log::error!("Failed to schedule hold release for output {output}: {e}");
+                                        }
+                                    });
+                                }
+                                result
+                            }
+                            VideohubCommand::ReleaseHold { output, hold_id } => {
+                                match route_holds.get(output) {
+                                    Some(hold) if hold_id.is_none_or(|id| id == hold.id) => {
+                                        let hold = route_holds.remove(output).expect("hold exists");
+                                        match hold.previous_input {
+                                            // Only revert if nobody else has re-routed the output meanwhile
+                                            Some(previous_input) if client.state().video_output_routing.get(output) == Some(&hold.input) => {
+                                                log::info!("Releasing hold on output {output}, reverting to input {previous_input}");
+                                                client.set_route(*output, previous_input).await
+                                            }
+                                            _ => {
+                                                log::info!("Released hold on output {output} without reverting");
+                                                Ok(())
+                                            }
+                                        }
+                                    }
+                                    // Superseded by a newer hold, or already released
+                                    _ => Ok(()),
+                                }
+                            }
+                            VideohubCommand::StartSequence { output, inputs, dwell_ms, repeat } => {
+                                Sequence::new(*output, inputs.clone(), Duration::from_millis(*dwell_ms), *repeat)
+                                    .map(|sequence| sequencer.start(sequence))
+                            }
+                            VideohubCommand::StopSequence { output } => {
+                                if sequencer.stop(*output) {
+                                    log::info!("Stopped sequence on output {output}");
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::StartSweep { input, outputs, dwell_ms } => {
+                                if outputs.is_empty() {
+                                    Err(anyhow::anyhow!("sweep of input {} has no outputs", input + 1))
+                                } else if sweeper.is_running() {
+                                    Err(anyhow::anyhow!("a sweep is already running"))
+                                } else {
+                                    sweeper.start(Sweep::new(
+                                        *input,
+                                        outputs,
+                                        &client.state().video_output_routing,
+                                        Duration::from_millis((*dwell_ms).max(1)),
+                                    ));
+                                    Ok(())
+                                }
+                            }
+                            VideohubCommand::StartRotation { group } => match rotation_groups.get(group) {
+                                Some(rotation) => {
+                                    log::info!("Starting rotation group {group}");
+                                    active_rotations.insert(group.clone());
+                                    // Otherwise started once the device has sent its state
+                                    if prelude_complete {
+                                        rotation.sequences().into_iter().for_each(|sequence| sequencer.start(sequence));
+                                    }
+                                    Ok(())
+                                }
+                                None => Err(anyhow::anyhow!("unknown rotation group {group}")),
+                            },
+                            VideohubCommand::StopRotation { group } => match rotation_groups.get(group) {
+                                Some(rotation) => {
+                                    log::info!("Stopping rotation group {group}");
+                                    active_rotations.remove(group);
+                                    for sequence in rotation.sequences() {
+                                        sequencer.stop(sequence.output);
+                                    }
+                                    Ok(())
+                                }
+                                None => Err(anyhow::anyhow!("unknown rotation group {group}")),
+                            },
+                            VideohubCommand::TriggerFailover { output } => {
+                                match failover.rule_for(*output, client.state().video_output_routing.get(output).copied()) {
+                                    Ok(Some(rule)) => {
+                                        log::warn!("Failing over output {output} from input {} to backup input {}", rule.primary, rule.backup);
+                                        let result = client.set_route(*output, rule.backup).await;
+                                        if result.is_ok() {
+                                            failover.activate(rule);
+                                        }
+                                        result
+                                    }
+                                    Ok(None) => {
+                                        log::info!("Output {output} is already failed over");
+                                        Ok(())
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            VideohubCommand::RevertFailover { output } => match failover.active_rule(*output) {
+                                // Only revert if nobody else has re-routed the output meanwhile
+                                Ok(rule) if client.state().video_output_routing.get(output) == Some(&rule.backup) => {
+                                    log::info!("Reverting output {output} to primary input {}", rule.primary);
+                                    let result = client.set_route(*output, rule.primary).await;
+                                    if result.is_ok() {
+                                        failover.deactivate(*output);
+                                    }
+                                    result
+                                }
+                                Ok(_) => {
+                                    log::info!("Output {output} was re-routed since failing over, not reverting");
+                                    failover.deactivate(*output);
+                                    Ok(())
+                                }
+                                Err(e) => Err(e),
+                            },
+                            VideohubCommand::SetRuleEnabled { rule_id, enabled, until } => {
+                                match until.as_deref().map(chrono::DateTime::parse_from_rfc3339).transpose() {
+                                    Ok(until) => {
+                                        let until = until.map(|until| until.with_timezone(&chrono::Utc));
+                                        let result = failover.set_enabled(rule_id, *enabled, until);
+                                        if result.is_ok() {
+                                            match (enabled, until) {
+                                                (true, _) => log::info!("Failover rule {rule_id} enabled"),
+                                                (false, Some(until)) => log::info!("Failover rule {rule_id} disabled until {until}"),
+                                                (false, None) => log::info!("Failover rule {rule_id} disabled"),
+                                            }
+                                        }
+                                        result
+                                    }
+                                    Err(e) => Err(anyhow::anyhow!("invalid until time: {e}")),
+                                }
+                            }
+                            VideohubCommand::FreezeInput { input } => {
+                                let outputs = InputFreezes::protected_outputs(*input, &client.state().video_output_routing);
+                                if input_freezes.freeze(*input) {
+                                    log::info!("Input {input} frozen on outputs {outputs:?}");
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::UnfreezeInput { input } => {
+                                if input_freezes.unfreeze(*input) {
+                                    log::info!("Input {input} unfrozen");
+                                    Ok(())
+                                } else {
+                                    Err(anyhow::anyhow!("input {} is not frozen", input + 1))
+                                }
+                            }
+                            VideohubCommand::SignalStatus { input, present } => {
+                                let was_lost = !signal_present.insert(*input, *present).unwrap_or(true);
+                                if *present {
+                                    if was_lost {
+                                        log::info!("Signal restored on input {input}; failed over outputs stay on their backups until reverted");
+                                    }
+                                } else if !was_lost {
+                                    log::warn!("Signal lost on input {input}");
+                                    for rule in failover.rules_for_lost_input(*input, &client.state().video_output_routing) {
+                                        log::warn!("Auto failover of output {} to backup input {}", rule.output, rule.backup);
+                                        let error = match client.set_route(rule.output, rule.backup).await {
+                                            Ok(()) => {
+                                                failover.activate(rule);
+                                                None
+                                            }
+                                            Err(e) => Some(e.to_string()),
+                                        };
+                                        if let Err(e) = event_tx
+                                            .send(VideohubEvent::AutoFailover {
+                                                output: rule.output,
+                                                primary: rule.primary,
+                                                backup: rule.backup,
+                                                error,
+                                            })
+                                            .await
+                                        {
+                                            log::error!("Failed to send auto failover event: {e}");
+                                        }
+                                    }
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::ExportCsv { path } => {
+                                let rows = sheet::rows_from_state(client.state());
+                                sheet::write_csv(std::path::Path::new(path), &rows)
+                                    .map(|()| log::info!("Exported {} outputs to {path}", rows.len()))
+                            }
+                            VideohubCommand::GenerateReport { path } => {
+                                CommissioningReport::from_state(client.state())
+                                    .write(std::path::Path::new(path))
+                                    .map(|()| log::info!("Wrote commissioning report to {path}"))
+                            }
+                            VideohubCommand::SendRawBlock { text } => {
+                                if raw_exchange.is_some() {
+                                    Err(anyhow::anyhow!("still waiting for the answer to the previous raw block"))
+                                } else {
+                                    match RawBlock::parse(text) {
+                                        Ok(block) => {
+                                            log::warn!("Sending raw {} block", block.header);
+                                            let result = client.send_message(block.message()).await;
+                                            if result.is_ok() {
+                                                raw_exchange = Some(RawExchange::new(block.header));
+                                            }
+                                            result
+                                        }
+                                        Err(e) => Err(e),
+                                    }
+                                }
+                            }
+                            VideohubCommand::ImportCsv { path, apply_routes } => {
+                                match sheet::read_csv(std::path::Path::new(path)) {
+                                    Ok(rows) => {
+                                        let changes = sheet::changes(client.state(), &rows, *apply_routes);
+                                        let context = CommandContext { source, invocation: invocation.as_ref() };
+                                        let checked = check_owned_changes(&event_tx, ownership.as_ref(), &changes, &context).await;
+                                        if checked.is_ok() {
+                                            log::info!("{} changes from {} rows in {path}", changes.len(), rows.len());
+                                            staging::stage_or_queue("import-csv", changes, staging_timeout, &mut staged, &command_tx, &event_tx).await;
+                                        }
+                                        checked
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            VideohubCommand::LoadShow { path } => {
+                                match ShowFile::load(std::path::Path::new(path)) {
+                                    Ok(show) => {
+                                        let changes = show.changes(client.state());
+                                        let context = CommandContext { source, invocation: invocation.as_ref() };
+                                        let checked = check_owned_changes(&event_tx, ownership.as_ref(), &changes, &context).await;
+                                        if checked.is_ok() {
+                                            log::info!("{} changes from show file {path}", changes.len());
+                                            staging::stage_or_queue("load-show", changes, staging_timeout, &mut staged, &command_tx, &event_tx).await;
+                                        }
+                                        checked
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            VideohubCommand::WriteChanges { changes } => {
+                                let (blocks, unchanged) = pending::into_changed_blocks(changes.clone(), client.state());
+                                let messages: Vec<_> = blocks.iter().map(|block| block.message.clone()).collect();
+                                let result = if messages.is_empty() {
+                                    Ok(())
+                                } else {
+                                    client.send_pipelined(messages).await
+                                };
+                                if result.is_ok() {
+                                    log::info!("Wrote {} changes in {} blocks ({unchanged} unchanged labels left out)", changes.len() - unchanged, blocks.len());
+                                    let mut written = Vec::new();
+                                    for block in blocks {
+                                        let mut sent = Vec::new();
+                                        for expected in block.changes {
+                                            let previous = expected.current_state(client.state());
+                                            let suppressed = match &expected {
+                                                ExpectedChange::Route { .. } => suppress_route_echo,
+                                                ExpectedChange::InputLabel { .. } | ExpectedChange::OutputLabel { .. } => suppress_label_echo,
+                                            };
+                                            if !suppressed
+                                                && let Err(e) = event_tx.send(with_original_label(expected.to_event(false, &client.state().input_labels), label_transliteration.as_ref())).await {
+                                                    log::error!("Failed to send optimistic event for {expected:?}: {e}");
+                                                }
+                                            sent.push((expected, previous));
+                                        }
+                                        written.push(sent);
+                                    }
+                                    // The action waits for the device to echo the whole write
+                                    match &invocation {
+                                        Some(invocation) if !written.is_empty() => {
+                                            pending_changes.push_write(written, invocation.clone());
+                                            awaiting_echo = true;
+                                        }
+                                        _ => for sent in written {
+                                            pending_changes.push_block(sent.into_iter().map(|(expected, previous)| (expected, previous, None)).collect());
+                                        },
+                                    }
+                                }
+                                result
+                            }
+                            VideohubCommand::GetRoutingTable => {
+                                // Lets consumers resync mirrored state after a gap in the sequence numbers
+                                let mut routes: Vec<_> = client.state().video_output_routing.iter().map(|(output, input)| (*output, *input)).collect();
+                                routes.sort();
+                                for (output, input) in routes {
+                                    if let Err(e) = event_tx.send(VideohubEvent::Route {
+                                        output,
+                                        input,
+                                        input_label: client.state().input_labels.get(&input).cloned(),
+                                        confirmed: true,
+                                        last_change: None,
+                                    }).await {
+                                        log::error!("Failed to send route event: {e}");
+                                    }
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::SetConfiguration { setting, value } => {
+                                if [IDENTIFY_SETTING, REBOOT_SETTING].contains(&setting.as_str()) {
+                                    Err(anyhow::anyhow!("{setting} is triggered with its own action"))
+                                } else if client.state().supports_setting(setting) {
+                                    client.set_configuration(setting, value).await
+                                } else {
+                                    Err(anyhow::anyhow!("the device has no configuration setting {setting:?}"))
+                                }
+                            }
+                            VideohubCommand::IdentifyDevice => {
+                                if client.state().supports_setting(IDENTIFY_SETTING) {
+                                    client.set_configuration(IDENTIFY_SETTING, "true").await
+                                } else {
+                                    Err(anyhow::anyhow!("the device does not support identify"))
+                                }
+                            }
+                            VideohubCommand::RebootDevice => {
+                                // The device drops the connection; the usual reconnect picks it up again
+                                if client.state().supports_setting(REBOOT_SETTING) {
+                                    log::warn!("Rebooting the device");
+                                    client.set_configuration(REBOOT_SETTING, "true").await
+                                } else {
+                                    Err(anyhow::anyhow!("the device does not support reboot"))
+                                }
+                            }
+                            VideohubCommand::SetDeviceAddress { host, port } => {
+                                // Only let go of the current device once the new one answers
+                                let address = format!("{host}:{port}");
+                                match tokio::time::timeout(DEVICE_SWITCH_TIMEOUT, tokio::net::TcpStream::connect(&address)).await {
+                                    Ok(Ok(_)) => {
+                                        log::warn!("Switching from the videohub at {} to {address}", client.address());
+                                        client.disconnect().await;
+                                        client.set_address(host.clone(), *port);
+                                        // Changes on the previous device say nothing about this one
+                                        last_route_changes.clear();
+                                        reconnect_now = true;
+                                        if let Err(e) = event_tx.send(VideohubEvent::DeviceAddress { host: host.clone(), port: *port }).await {
+                                            log::error!("Failed to send device address event: {e}");
+                                        }
+                                        Ok(())
+                                    }
+                                    Ok(Err(e)) => Err(anyhow::anyhow!("cannot reach {address}: {e}")),
+                                    Err(_) => Err(anyhow::anyhow!("no answer from {address} within {DEVICE_SWITCH_TIMEOUT:?}")),
+                                }
+                            }
+                            VideohubCommand::Snapshot { reply } => {
+                                let mut snapshot = client.snapshot();
+                                if let Some(ownership) = &ownership {
+                                    snapshot.output_owners = ownership.owners();
+                                }
+                                snapshot.last_route_changes = last_route_changes.clone();
+                                if let Some(transliteration) = &label_transliteration {
+                                    snapshot.original_input_labels = transliteration.originals("input", &client.state().input_labels).into_iter().collect();
+                                    snapshot.original_output_labels = transliteration.originals("output", &client.state().output_labels).into_iter().collect();
+                                }
+                                reply.send(snapshot);
+                                Ok(())
+                            }
+                            VideohubCommand::Simulate { action, reply } => {
+                                let name = action.name();
+                                let (simulated, owner) = action.clone().into_command();
+                                let mut prediction = simulation::predict(simulated, owner.as_deref(), client.state(), ownership.as_ref(), &input_freezes, (!claims_advisory).then_some(&control_claim));
+                                if client.is_standby() {
+                                    prediction.allowed = false;
+                                    prediction.blocked_by.push("this executor is on standby".into());
+                                }
+                                log::info!("Simulated {name}: {} changes, allowed={}", prediction.changes.len(), prediction.allowed);
+                                match reply {
+                                    Some(reply) => reply.send(prediction),
+                                    None => {
+                                        if let Err(e) = event_tx.send(VideohubEvent::SimulationResult {
+                                            action: name.to_string(),
+                                            correlation_id: invocation.as_ref().map(|invocation| invocation.correlation_id),
+                                            prediction,
+                                        }).await {
+                                            log::error!("Failed to send simulation result event: {e}");
+                                        }
+                                    }
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::ClaimControl { operator, ttl_ms } => {
+                                match control_claim.claim(operator, Duration::from_millis(*ttl_ms)) {
+                                    Ok(_) => {
+                                        log::info!("Device claimed by {operator} for {ttl_ms} ms");
+                                        if let Err(e) = event_tx.send(VideohubEvent::ControlClaim {
+                                            operator: Some(operator.clone()),
+                                            expires_in_ms: Some(*ttl_ms),
+                                        }).await {
+                                            log::error!("Failed to send control claim event: {e}");
+                                        }
+                                        Ok(())
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            VideohubCommand::ReleaseControl { operator } => match control_claim.release(operator) {
+                                Ok(()) => {
+                                    log::info!("Device released by {operator}");
+                                    if let Err(e) = event_tx.send(VideohubEvent::ControlClaim { operator: None, expires_in_ms: None }).await {
+                                        log::error!("Failed to send control claim event: {e}");
+                                    }
+                                    Ok(())
+                                }
+                                Err(e) => Err(e),
+                            },
+                            VideohubCommand::TransferOwnership { outputs, to_owner } => match &mut ownership {
+                                Some(ownership) => {
+                                    // Time so far belongs to the previous owners
+                                    for output in outputs {
+                                        usage.settle(*output, ownership.owner(*output));
+                                    }
+                                    let previous_owners = ownership
+                                        .transfer(outputs, to_owner)
+                                        .into_iter()
+                                        .filter_map(|(output, previous)| Some((output, previous?)))
+                                        .collect();
+                                    log::info!("Outputs {outputs:?} handed over to {to_owner}");
+                                    if let Err(e) = ownership.save() {
+                                        log::error!("Failed to persist output ownership: {e}");
+                                    }
+                                    if let Err(e) = event_tx.send(VideohubEvent::OwnershipChanged {
+                                        outputs: outputs.clone(),
+                                        to_owner: to_owner.clone(),
+                                        previous_owners,
+                                    }).await {
+                                        log::error!("Failed to send ownership changed event: {e}");
+                                    }
+                                    Ok(())
+                                }
+                                None => Err(anyhow::anyhow!("output ownership is not configured (OWNERSHIP_FILE)")),
+                            },
+                            VideohubCommand::ExportUsageReport { path, reset } => match &ownership {
+                                Some(ownership) => {
+                                    let report = usage.report(ownership, client.state());
+                                    let result = report.write(std::path::Path::new(path));
+                                    if result.is_ok() {
+                                        log::info!("Exported usage of {} owners to {path}", report.owners.len());
+                                        if *reset {
+                                            usage.reset();
+                                        }
+                                    }
+                                    result
+                                }
+                                None => Err(anyhow::anyhow!("output ownership is not configured (OWNERSHIP_FILE)")),
+                            },
+                            VideohubCommand::SetProfile { profile } => match profiles.activate(profile, &mut input_freezes, &mut failover) {
+                                Ok(active) => {
+                                    log::info!("Switched to the {profile} profile");
+                                    claims_advisory = active.claims_advisory.unwrap_or(base_claims_advisory);
+                                    let mut filters = emitter_filters.clone();
+                                    filters.extend(active.emitter_filters());
+                                    if let Err(e) = event_tx.send(VideohubEvent::Profile {
+                                        profile: profile.clone(),
+                                        emitter_filters: filters,
+                                    }).await {
+                                        log::error!("Failed to send profile event: {e}");
+                                    }
+                                    Ok(())
+                                }
+                                Err(e) => Err(e),
+                            },
+                            VideohubCommand::SetIntent { routes } => {
+                                log::info!("Intent of the legacy controller updated for {} outputs", routes.len());
+                                intent.set(routes.clone());
+                                if let Some(event) = intent.compare(client.state())
+                                    && let Err(e) = event_tx.send(event).await
+                                {
+                                    log::error!("Failed to send intent divergence event: {e}");
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::SetLeadership { leader, peer_alive } => {
+                                // Shadow mode only observes, whichever executor leads
+                                client.set_standby(!leader || shadow_mode);
+                                // The startup policy is applied by whoever leads once the device state is
+                                // known: by this executor if it took over before that, by the peer otherwise
+                                if let Some(path) = &startup_show && startup_pending {
+                                    if !client.is_standby() && prelude_complete {
+                                        startup_pending = false;
+                                        apply_startup_show(path, client.state(), &command_tx);
+                                    } else if !leader && *peer_alive {
+                                        startup_pending = false;
+                                    }
+                                }
+                                if let Err(e) = event_tx.send(VideohubEvent::LeadershipChanged {
+                                    leader: *leader,
+                                    peer_alive: *peer_alive,
+                                }).await {
+                                    log::error!("Failed to send leadership event: {e}");
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::RouteByLabel { .. } => {
+                                Err(anyhow::anyhow!("port labels were not resolved"))
+                            }
+                            VideohubCommand::GetInventory => {
+                                if let Err(e) = event_tx.send(inventory_event(client.state())).await {
+                                    log::error!("Failed to send inventory event: {e}");
+                                }
+                                Ok(())
+                            }
+                            VideohubCommand::GetPortInfo { port_type, port } => {
+                                let (label, routed_input) = match port_type.as_str() {
+                                    "input" => (Ok(client.state().input_labels.get(port).cloned()), None),
+                                    "output" => (Ok(client.state().output_labels.get(port).cloned()), client.state().video_output_routing.get(port).copied()),
+                                    _ => (Err(anyhow::anyhow!("unknown port type {port_type}")), None),
+                                };
+                                match label {
+                                    Ok(label) => {
+                                        if let Err(e) = event_tx
+                                            .send(VideohubEvent::PortInfo {
+                                                port_type: port_type.clone(),
+                                                port: *port,
+                                                label,
+                                                routed_input,
+                                                capabilities: port_capabilities(client.state(), port_type, *port),
+                                            })
+                                            .await
+                                        {
+                                            log::error!("Failed to send port info event: {e}");
+                                        }
+                                        Ok(())
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            VideohubCommand::ApplyStagedChanges | VideohubCommand::DiscardStagedChanges => {
+                                let mut scope = CommandScope {
+                                    source,
+                                    invocation: &mut invocation,
+                                    state: client.state(),
+                                    ownership: ownership.as_ref(),
+                                    command_tx: &command_tx,
+                                    event_tx: &event_tx,
+                                };
+                                staging::execute(&mut staged, &command, &mut scope).await
+                            }
+                            VideohubCommand::BeginTransaction { .. }
+                            | VideohubCommand::AddToTransaction { .. }
+                            | VideohubCommand::CommitTransaction { .. }
+                            | VideohubCommand::RollbackTransaction { .. } => {
+                                let mut scope = CommandScope {
+                                    source,
+                                    invocation: &mut invocation,
+                                    state: client.state(),
+                                    ownership: ownership.as_ref(),
+                                    command_tx: &command_tx,
+                                    event_tx: &event_tx,
+                                };
+                                transactions::execute(&mut transactions, &command, &mut scope).await
+                            }
+                            VideohubCommand::RunBatch { .. } => {
+                                let scope = CommandScope {
+                                    source,
+                                    invocation: &mut invocation,
+                                    state: client.state(),
+                                    ownership: ownership.as_ref(),
+                                    command_tx: &command_tx,
+                                    event_tx: &event_tx,
+                                };
+                                batch::execute(&command, &scope)
+                            }
+                            VideohubCommand::PresetRoute { .. } | VideohubCommand::Take | VideohubCommand::ClearPending => {
+                                let mut scope = CommandScope {
+                                    source,
+                                    invocation: &mut invocation,
+                                    state: client.state(),
+                                    ownership: ownership.as_ref(),
+                                    command_tx: &command_tx,
+                                    event_tx: &event_tx,
+                                };
+                                take::execute(&mut pending_routes, &command, &mut scope).await
+                            }
+                            VideohubCommand::SaveSalvo { .. }
+                            | VideohubCommand::RecallSalvo { .. }
+                            | VideohubCommand::DeleteSalvo { .. } => {
+                                let mut scope = CommandScope {
+                                    source,
+                                    invocation: &mut invocation,
+                                    state: client.state(),
+                                    ownership: ownership.as_ref(),
+                                    command_tx: &command_tx,
+                                    event_tx: &event_tx,
+                                };
+                                salvo::execute(salvo_store.as_mut(), &command, &mut scope).await
+                            }
+                        };
 
-            // Dynamic storage for output emitters - will be populated when device info is received
-            let mut output_emitters = Vec::new();
-            let mut targets_created = false;
+                        match &result {
+                            // The connection dropped mid-write; send the command again once reconnected
+                            Err(e) if retries < MAX_SEND_RETRIES && is_connection_lost(e) => {
+                                log::warn!("Connection lost while sending {command:?}, resending after reconnecting: {e}");
+                                send_feedback(&event_tx, invocation.as_ref(), "retrying", Some(e.to_string())).await;
+                                let lost_at = lost_at.or_else(|| Some(tokio::time::Instant::now()));
+                                resend_after_reconnect.push(CommandRequest { command, invocation, source, retries: retries + 1, lost_at });
+                                reconnect_now = true;
+                                continue;
+                            }
+                            Err(e) => {
+                                log::error!("Failed to execute {command:?}: {e}");
+                                send_feedback(&event_tx, invocation.as_ref(), "failed", Some(e.to_string())).await;
+                            }
+                            Ok(()) if awaiting_echo => {
+                                send_feedback(&event_tx, invocation.as_ref(), "sent", None).await;
+                            }
+                            // Commands without a device echo are complete once executed
+                            Ok(()) if command.expected_change().is_none() => {
+                                send_feedback(&event_tx, invocation.as_ref(), "confirmed", None).await;
+                            }
+                            // Emit the expected state change optimistically once the command is on the wire
+                            Ok(()) => if let Some(expected) = command.expected_change() {
+                                send_feedback(&event_tx, invocation.as_ref(), "sent", None).await;
+                                let previous = expected.current_state(client.state());
 
-            while let Some(event) = event_rx.recv().await {
-                log::debug!("Processing event");
-
-                match event {
-                    VideohubEvent::DeviceStatus {
-                        connected,
-                        model_name,
-                        video_inputs,
-                        video_outputs,
-                    } => {
-                        // Create output subtargets when we first receive device info
-                        match video_outputs {
-                            Some(num_outputs) if connected && !targets_created => {
-                                log::info!("Creating {num_outputs} output subtargets dynamically");
-
-                                for output_id in 1..num_outputs.clamp(0, u32::MAX - 1) + 1 {
-                                    // Create output subtarget
-                                    let mut output_target = instance_for_subtargets
-                                        .add_target(TargetArgs {
-                                            name: format!("Output {output_id}"),
-                                            short_id: format!("output-{output_id}"),
-                                            category: "video".into(),
-                                            parent_targets: Some(vec![
-                                                device_target_for_subtargets.clone(),
-                                            ]),
-                                        })
-                                        .await;
-
-                                    // Add all actions to each output subtarget
-                                    let output_tx_for_route = command_tx_for_subtargets.clone();
-                                    let output_tx_for_output_label =
-                                        command_tx_for_subtargets.clone();
-                                    let output_tx_for_output_lock =
-                                        command_tx_for_subtargets.clone();
-                                    let output_tx_for_take_mode = command_tx_for_subtargets.clone();
-
-                                    output_target
-                                        .add_action(
-                                            ActionArgs::<SetInputAction>::new(
-                                                "Set Input".into(),
-                                                "set-input".into(),
-                                            ),
-                                            move |_action, data| {
-                                                let tx = output_tx_for_route.clone();
-                                                let current_output_id = output_id;
-                                                tokio::spawn(async move {
-                                                    if let Err(e) = tx
-                                                        .send(VideohubCommand::SetInput {
-                                                            output: current_output_id,
-                                                            input: data.input.clamp(1, u32::MAX)
-                                                                - 1,
-                                                        })
-                                                        .await
-                                                    {
-                                                        log::error!(
-                                                            "Failed to send set input command: {e}"
-                                                        );
-                                                    }
-                                                });
-                                            },
-                                        )
-                                        .await;
-
-                                    output_target
-                                    .add_action(
-                                        ActionArgs::<SetLabelAction>::new(
-                                            "Set Label".into(),
-                                            "set-label".into(),
-                                        ),
-                                        move |_action, data| {
-                                            let tx = output_tx_for_output_label.clone();
-                                            let current_output_id = output_id;
-                                            tokio::spawn(async move {
-                                                if let Err(e) = tx
-                                                    .send(VideohubCommand::OutputLabel {
-                                                        output: current_output_id,
-                                                        label: data.label,
-                                                    })
-                                                    .await
-                                                {
-                                                    log::error!(
-                                                        "Failed to send output label command: {e}"
-                                                    );
-                                                }
-                                            });
-                                        },
-                                    )
-                                    .await;
-
-                                    output_target
-                                    .add_action(
-                                        ActionArgs::<SetLockAction>::new(
-                                            "Set Lock".into(),
-                                            "set-lock".into(),
-                                        ),
-                                        move |_action, data| {
-                                            let tx = output_tx_for_output_lock.clone();
-                                            let current_output_id = output_id;
-                                            tokio::spawn(async move {
-                                                if let Err(e) = tx
-                                                    .send(VideohubCommand::OutputLock {
-                                                        output: current_output_id,
-                                                        locked: data.locked,
-                                                    })
-                                                    .await
-                                                {
-                                                    log::error!(
-                                                        "Failed to send output lock command: {e}"
-                                                    );
-                                                }
-                                            });
-                                        },
-                                    )
-                                    .await;
-
-                                    output_target
-                                        .add_action(
-                                            ActionArgs::<SetTakeModeOnThisOutputAction>::new(
-                                                "Set Take Mode".into(),
-                                                "set-take-mode".into(),
-                                            ),
-                                            move |_action, data| {
-                                                let tx = output_tx_for_take_mode.clone();
-                                                let current_output_id = output_id;
-                                                tokio::spawn(async move {
-                                                    if let Err(e) = tx
-                                                        .send(VideohubCommand::TakeMode {
-                                                            output: current_output_id,
-                                                            enabled: data.enabled,
-                                                        })
-                                                        .await
-                                                    {
-                                                        log::error!(
-                                                            "Failed to send take mode command: {e}"
-                                                        );
-                                                    }
-                                                });
-                                            },
-                                        )
-                                        .await;
-
-                                    // Add output-specific emitters (input-only versions)
-                                    let input_changed_emitter = output_target
-                                        .add_emitter(EmitterArgs::<InputChangedEmitter>::new(
-                                            "Input Changed".into(),
-                                            "input-changed".into(),
-                                        ))
-                                        .await;
-
-                                    let label_emitter = output_target
-                                        .add_emitter(EmitterArgs::<LabelChangedEmitter>::new(
-                                            "Label Changed".into(),
-                                            "label-changed".into(),
-                                        ))
-                                        .await;
-
-                                    let output_lock_emitter = output_target
-                                        .add_emitter(EmitterArgs::<LockChangedEmitter>::new(
-                                            "Lock Changed".into(),
-                                            "lock-changed".into(),
-                                        ))
-                                        .await;
-
-                                    let take_mode_emitter = output_target
-                                        .add_emitter(
-                                            EmitterArgs::<TakeModeOnThisOutputEmitter>::new(
-                                                "Take Mode Changed".into(),
-                                                "take-mode-changed".into(),
-                                            ),
-                                        )
-                                        .await;
-
-                                    output_emitters.push((
-                                        input_changed_emitter,
-                                        label_emitter,
-                                        output_lock_emitter,
-                                        take_mode_emitter,
-                                    ));
-                                }
+                                let suppressed = match &expected {
+                                    ExpectedChange::Route { .. } => suppress_route_echo,
+                                    ExpectedChange::InputLabel { .. } | ExpectedChange::OutputLabel { .. } => suppress_label_echo,
+                                };
 
-                                targets_created = true;
-                                log::info!("Created {num_outputs} output subtargets");
+                                if !suppressed
+                                    && let Err(e) = event_tx.send(with_original_label(expected.to_event(false, &client.state().input_labels), label_transliteration.as_ref())).await {
+                                        log::error!("Failed to send optimistic event for {expected:?}: {e}");
+                                    }
+                                pending_changes.push(expected, previous, invocation.clone());
                             }
-                            _ => {}
                         }
 
-                        let data = DeviceStatusEmitter {
-                            connected,
-                            model_name,
-                            video_inputs,
-                            video_outputs,
-                        };
-                        if let Err(e) = device_status_emitter.pulse(data).await {
-                            log::error!("Failed to emit device status event: {e}");
-                        } else {
-                            log::debug!("Emitted device status: connected={connected}");
+                        // Pending routes, holds, freezes and owners change without a device message
+                        if prelude_complete {
+                            let changes = output_status.changes(client.state(), &pending_routes, &input_freezes, ownership.as_ref(), |output| route_holds.contains_key(&output));
+                            send_output_status(&event_tx, changes).await;
                         }
-                    }
-                    VideohubEvent::Route {
-                        output,
-                        input,
-                        input_label,
-                    } => {
-                        let input_data = InputChangedEmitter {
-                            input: input + 1,
-                            input_label,
-                        };
 
-                        // Emit to the specific output subtarget if it exists
-                        if let Some((input_changed_emitter, _, _, _)) =
-                            output_emitters.get(output as usize)
-                        {
-                            if let Err(e) = input_changed_emitter.pulse(input_data).await {
-                                log::error!(
-                                    "Failed to emit input changed event on output {output}: {e}"
-                                );
-                            } else {
-                                log::debug!(
-                                    "Emitted input changed on output {output}: input {input}"
-                                );
-                            }
-                        } else {
-                            log::debug!(
-                                "Output emitters not ready or output {output} out of range"
-                            );
+                        for observer in &observers {
+                            observer.on_command_result(&command, &result);
                         }
                     }
-                    VideohubEvent::Label {
-                        port_type,
-                        port,
-                        label,
-                    } => {
-                        let data = LabelChangedEmitter {
-                            port_type: port_type.clone(),
-                            port,
-                            label: label.clone(),
-                        };
-
-                        // For output labels, emit to the specific output subtarget
-                        if port_type == "output" {
-                            if let Some((_, label_emitter, _, _)) =
-                                output_emitters.get(port as usize)
-                            {
-                                if let Err(e) = label_emitter.pulse(data).await {
-                                    log::error!(
-                                        "Failed to emit label changed event on output {port}: {e}"
-                                    );
-                                } else {
-                                    log::debug!(
-                                        "Emitted label changed on output {port}: {port_type} port {port}"
-                                    );
-                                }
-                            } else {
-                                log::debug!(
-                                    "Output emitters not ready or output {port} out of range for label"
-                                );
+                    // Roll back optimistic changes the device never confirmed
+                    scheduled = pending_check.tick() => {
+                        health.event_loop_lag(scheduled.elapsed());
+                        health.beat("state");
+                        for rule_id in failover.take_reenabled(chrono::Utc::now()) {
+                            log::info!("Failover rule {rule_id} enabled again");
+                        }
+                        if let Some(claim) = control_claim.expire() {
+                            log::info!("Claim of the device by {} lapsed", claim.operator);
+                            if let Err(e) = event_tx.send(VideohubEvent::ControlClaim { operator: None, expires_in_ms: None }).await {
+                                log::error!("Failed to send control claim event: {e}");
                             }
-                        } else {
-                            // For input labels, emit to the first available output target as an example
-                            if let Some((_, label_emitter, _, _)) = output_emitters.first() {
-                                if let Err(e) = label_emitter.pulse(data).await {
-                                    log::error!("Failed to emit input label changed event: {e}");
-                                } else {
-                                    log::debug!(
-                                        "Emitted input label changed: {port_type} port {port}"
-                                    );
+                        }
+                        if flaps.recovered() {
+                            log::info!("Videohub connection stable again, resuming normal reconnect cadence");
+                            if let Err(e) = event_tx.send(device_status_event(client.state().device_info.as_ref(), true, false)).await {
+                                log::error!("Failed to send device status event: {e}");
+                            }
+                        }
+                        if let Some(changes) = staged.take_if(|changes| changes.is_expired()) {
+                            log::warn!("Staged changes from {} were not applied in time, discarding", changes.source);
+                            staging::send_staged_changes(&event_tx, &changes, "expired").await;
+                        }
+                        if let Some(journal) = &mut journal {
+                            journal.settle(client.state());
+                        }
+                        for (id, changes) in transactions.take_expired() {
+                            log::warn!("Transaction {id} was not committed in time, discarding");
+                            transactions::send_status(&event_tx, &id, "expired", changes, &[]).await;
+                        }
+                        for outcome in transactions.verify(client.state()) {
+                            match outcome {
+                                Outcome::Committed { id, changes } => {
+                                    log::info!("Transaction {id} committed");
+                                    transactions::send_status(&event_tx, &id, "committed", changes, &[]).await;
+                                }
+                                Outcome::Failed { id, changes, failed, revert } => {
+                                    log::warn!("Transaction {id}: {} changes did not take, reverting {}", failed.len(), revert.len());
+                                    transactions::send_status(&event_tx, &id, "failed", changes, &failed).await;
+                                    queue_changes(&command_tx, revert);
                                 }
                             }
                         }
-                    }
-                    VideohubEvent::OutputLock { output, locked } => {
-                        let data = LockChangedEmitter { locked };
-
-                        // Emit to the specific output subtarget
-                        if let Some((_, _, output_lock_emitter, _)) =
-                            output_emitters.get(output as usize)
+                        if let Some(watchdog) = &mut watchdog
+                            && let Some(unanswered) = watchdog.check()
                         {
-                            if let Err(e) = output_lock_emitter.pulse(data).await {
-                                log::error!(
-                                    "Failed to emit lock changed event on output {output}: {e}"
-                                );
-                            } else {
-                                log::debug!(
-                                    "Emitted lock changed on output {output}: locked={locked}"
-                                );
+                            log::error!("Device has not answered the {} watchdog probe for {unanswered:?}", watchdog.probe.name());
+                            if let Err(e) = event_tx.send(VideohubEvent::Watchdog {
+                                alarm: true,
+                                probe: watchdog.probe.name(),
+                                unanswered_ms: Some(unanswered.as_millis() as u64),
+                            }).await {
+                                log::error!("Failed to send watchdog event: {e}");
                             }
-                        } else {
-                            log::debug!(
-                                "Output emitters not ready or output {output} out of range for lock"
-                            );
+                        }
+                        for change in pending_changes.take_expired() {
+                            log::warn!("Device did not confirm {:?} in time, rolling back", change.expected);
+                            send_change_feedback(&event_tx, &mut pending_changes, &change, Some("not confirmed by the device in time".into())).await;
+                            if let Some(previous) = change.previous
+                                && let Err(e) = event_tx.send(with_original_label(previous.to_event(true, &client.state().input_labels), label_transliteration.as_ref())).await {
+                                    log::error!("Failed to send rollback event: {e}");
+                                }
                         }
                     }
-                    VideohubEvent::TakeMode { output, enabled } => {
-                        let data = TakeModeOnThisOutputEmitter { enabled };
-
-                        // Emit to the specific output subtarget
-                        if let Some((_, _, _, take_mode_emitter)) =
-                            output_emitters.get(output as usize)
+                    _ = usage_save_tick.tick(), if usage_file.is_some() && ownership.is_some() => {
+                        if let (Some(path), Some(ownership)) = (&usage_file, &ownership)
+                            && let Err(e) = usage.report(ownership, client.state()).write(path)
                         {
-                            if let Err(e) = take_mode_emitter.pulse(data).await {
-                                log::error!(
-                                    "Failed to emit take mode changed event on output {output}: {e}"
-                                );
-                            } else {
-                                log::debug!(
-                                    "Emitted take mode changed on output {output}: enabled={enabled}"
-                                );
+                            log::error!("Failed to save usage: {e}");
+                        }
+                    }
+                    _ = watchdog_tick.tick(), if prelude_complete && watchdog.is_some() && !client.is_standby() => {
+                        if let Some(watchdog) = &mut watchdog {
+                            match client.send_message(watchdog.probe.message()).await {
+                                Ok(()) => watchdog.sent(),
+                                Err(e) => log::warn!("Failed to send watchdog probe: {e}"),
                             }
-                        } else {
-                            log::debug!(
-                                "Output emitters not ready or output {output} out of range for take mode"
-                            );
                         }
                     }
-                    VideohubEvent::NetworkInterface { interface } => {
-                        let data = NetworkInterfaceEmitter {
-                            interface_id: interface.id,
-                            name: interface.name.clone(),
-                            mac_address: interface.mac_address.clone(),
-                            current_addresses: interface.current_addresses.clone(),
-                            current_gateway: interface.current_gateway.clone(),
-                            dynamic_ip: interface.dynamic_ip,
-                        };
-                        // Network interface emitter stays on the main device target
-                        if let Err(e) = device_network_interface_emitter.pulse(data).await {
-                            log::error!("Failed to emit network interface event: {e}");
-                        } else {
-                            log::debug!("Emitted network interface: {}", interface.name);
+                    _ = matrix_summary_tick.tick(), if prelude_complete => {
+                        if let Err(e) = event_tx.send(matrix_summary.event(client.state())).await {
+                            log::error!("Failed to send matrix summary event: {e}");
                         }
                     }
-                }
-            }
-        });
-
-        log::debug!("rship instance and targets setup complete");
-        Ok(())
-    }
-
-    async fn start_videohub_task(
-        &self,
-        mut command_rx: mpsc::Receiver<VideohubCommand>,
-        event_tx: mpsc::Sender<VideohubEvent>,
-        mut rship_reconnect_rx: mpsc::Receiver<()>,
-    ) -> Result<()> {
-        let host = self.videohub_host.clone();
-        let port = self.videohub_port;
-
-        tokio::spawn(async move {
-            let mut client = VideohubClient::new(host, port);
-
-            // Connect to videohub
-            if let Err(e) = client.connect().await {
-                log::error!("Failed to connect to videohub: {e}");
-                return;
-            }
-
-            log::debug!("Videohub client task started");
-
-            // Track current state to detect changes
-            let mut current_device_info: Option<DeviceInfo> = None;
-            let mut current_routes: std::collections::HashMap<u32, u32> =
-                std::collections::HashMap::new();
-            let mut current_input_labels: std::collections::HashMap<u32, String> =
-                std::collections::HashMap::new();
-            let mut current_output_labels: std::collections::HashMap<u32, String> =
-                std::collections::HashMap::new();
-            let mut current_output_locks: std::collections::HashMap<u32, bool> =
-                std::collections::HashMap::new();
-            let mut current_take_mode: std::collections::HashMap<u32, bool> =
-                std::collections::HashMap::new();
-            let mut current_network_interfaces: std::collections::HashMap<u32, NetworkInterface> =
-                std::collections::HashMap::new();
-
-            loop {
-                tokio::select! {
-                    // Handle rship reconnection
-                    Some(_) = rship_reconnect_rx.recv() => {
-                        log::info!("Rship reconnected - forcing full state refresh");
-                        client.force_full_state_refresh();
+                    _ = tokio::time::sleep_until(raw_exchange.as_ref().map_or_else(tokio::time::Instant::now, RawExchange::deadline)), if raw_exchange.is_some() => {
+                        if let Some(exchange) = raw_exchange.take()
+                            && let Err(e) = event_tx.send(exchange.finish()).await
+                        {
+                            log::error!("Failed to send raw response event: {e}");
+                        }
                     }
-                    // Handle incoming commands
-                    Some(command) = command_rx.recv() => {
-                        match command {
-                            VideohubCommand::Route { output, input } => {
-                                if let Err(e) = client.set_route(output, input).await {
-                                    log::error!("Failed to set route: {e}");
-                                }
-                            }
-                            VideohubCommand::SetInput { output, input } => {
-                                if let Err(e) = client.set_route(output, input).await {
-                                    log::error!("Failed to set input for output {output}: {e}");
-                                }
-                            }
-                            VideohubCommand::InputLabel { input, label } => {
-                                if let Err(e) = client.set_input_label(input, label).await {
-                                    log::error!("Failed to set input label: {e}");
-                                }
-                            }
-                            VideohubCommand::OutputLabel { output, label } => {
-                                if let Err(e) = client.set_output_label(output, label).await {
-                                    log::error!("Failed to set output label: {e}");
-                                }
-                            }
-                            VideohubCommand::OutputLock { output, locked } => {
-                                log::info!("Output lock command received: output {output} locked={locked}");
-                                // Note: Output lock setting would need to be implemented in the client
-                                // For now, we'll log this as the protocol might not support setting locks
-                            }
-                            VideohubCommand::TakeMode { output, enabled } => {
-                                log::info!("Take mode command received: output {output} enabled={enabled}");
-                                // Note: Take mode setting would need to be implemented in the client
-                                // For now, we'll log this as the protocol might not support setting take mode
-                            }
+                    _ = change_summary_tick.tick(), if change_summary_interval.is_some() && prelude_complete => {
+                        if let Err(e) = event_tx.send(change_window.take()).await {
+                            log::error!("Failed to send change summary event: {e}");
                         }
                     }
                     // Handle incoming videohub messages
-                    message_result = client.receive_message() => {
+                    message_result = client.receive_message_or_end_prelude(PRELUDE_QUIET) => {
                         match message_result {
                             Ok(Some(message)) => {
                                 log::debug!("Received videohub message");
 
+                                if let Some(exchange) = &mut raw_exchange
+                                    && exchange.record(&message, client.last_reply())
+                                    && let Some(exchange) = raw_exchange.take()
+                                    && let Err(e) = event_tx.send(exchange.finish()).await
+                                {
+                                    log::error!("Failed to send raw response event: {e}");
+                                }
+
+                                // Take mode, network interfaces and configuration are parsed by the client from any block,
+                                // so check them on every message
+                                for event in state_diff.changes(client.state()) {
+                                    if prelude_complete && let VideohubEvent::TakeMode { output, .. } = &event {
+                                        matrix_summary.record_change(*output);
+                                    }
+                                    if let Err(e) = event_tx.send(event).await {
+                                        log::error!("Failed to send device state event: {e}");
+                                    }
+                                }
+
+                                if matches!(message, VideohubMessage::ACK | VideohubMessage::NAK)
+                                    && let Some(watchdog) = &mut watchdog
+                                    && watchdog.answered()
+                                {
+                                    log::info!("Device answers the {} watchdog probe again", watchdog.probe.name());
+                                    if let Err(e) = event_tx.send(VideohubEvent::Watchdog {
+                                        alarm: false,
+                                        probe: watchdog.probe.name(),
+                                        unanswered_ms: None,
+                                    }).await {
+                                        log::error!("Failed to send watchdog event: {e}");
+                                    }
+                                }
+
+                                // Which entries of this block changed the client state, in block order
+                                let changed_entries = client.changed_entries().to_vec();
+
                                 // Process messages and emit events on changes
                                 match &message {
                                     VideohubMessage::DeviceInfo(info) => {
                                         let should_emit = client.just_reconnected() ||
-                                            current_device_info.as_ref() != Some(info);
-
-                                        current_device_info = Some(info.clone());
+                                            changed_entries.first().copied().unwrap_or(true);
 
+                                        // The status shows the port counts in use, overrides included
                                         if should_emit
-                                            && let Err(e) = event_tx.send(VideohubEvent::DeviceStatus {
-                                                connected: true,
-                                                model_name: info.model_name.clone(),
-                                                video_inputs: info.video_inputs,
-                                                video_outputs: info.video_outputs,
-                                            }).await {
+                                            && let Err(e) = event_tx.send(device_status_event(client.state().device_info.as_ref(), true, flaps.is_unstable())).await {
                                                 log::error!("Failed to send device status event: {e}");
                                             }
+                                        let overrides = [
+                                            ("input", info.video_inputs, video_inputs_override),
+                                            ("output", info.video_outputs, video_outputs_override),
+                                        ];
+                                        for (port_type, reported, configured) in overrides {
+                                            if let Some(configured) = configured
+                                                && should_emit
+                                                && reported != Some(configured)
+                                            {
+                                                log::warn!("The device reports {} {port_type}s, using the configured {configured}", reported.map_or("no".to_string(), |count| count.to_string()));
+                                                if let Err(e) = event_tx.send(VideohubEvent::PortCountMismatch { port_type, reported, configured }).await {
+                                                    log::error!("Failed to send port count mismatch event: {e}");
+                                                }
+                                            }
+                                        }
                                     }
                                     VideohubMessage::VideoOutputRouting(routes) => {
-                                        for route in routes {
-                                            let should_emit = client.just_reconnected() ||
-                                                current_routes.get(&route.to_output) != Some(&route.from_input);
-
-                                            current_routes.insert(route.to_output, route.from_input);
-
-                                            if should_emit {
-                                                let input_label = current_input_labels.get(&route.from_input).cloned();
-                                                if let Err(e) = event_tx.send(VideohubEvent::Route {
-                                                    output: route.to_output,
-                                                    input: route.from_input,
-                                                    input_label,
-                                                }).await {
-                                                    log::error!("Failed to send route event for output {} to input {}: {e}", route.to_output, route.from_input);
+                                        for (route, &changed) in routes.iter().zip(&changed_entries) {
+                                            let resolved = pending_changes.confirm_with(|| ExpectedChange::Route {
+                                                output: route.to_output,
+                                                input: route.from_input,
+                                            });
+                                            send_resolved_feedback(&event_tx, &mut pending_changes, &resolved).await;
+                                            if let Some(change) = &resolved.echoed {
+                                                record_latency(&mut latency_monitor, change.latency(), &client, &command_tx, &event_tx, pending_changes.len()).await;
+                                            }
+                                            let own_echo = resolved.echoed.is_some();
+                                            let should_emit = !(own_echo && suppress_route_echo) && (client.just_reconnected() || !resolved.is_empty() || changed);
+                                            let mut last_change = None;
+                                            if prelude_complete && changed {
+                                                matrix_summary.record_change(route.to_output);
+                                                change_window.route(route.to_output);
+                                                let change = RouteChange {
+                                                    changed_by: route_change_origin(resolved.echoed.as_ref()),
+                                                    changed_at: EventTime::now().timestamp,
+                                                };
+                                                last_route_changes.insert(route.to_output, change.clone());
+                                                last_change = Some(change);
+                                            }
+                                            usage.route(
+                                                route.to_output,
+                                                route.from_input,
+                                                ownership.as_ref().and_then(|ownership| ownership.owner(route.to_output)),
+                                                prelude_complete && changed,
+                                            );
+
+                                            let input_label = client.state().input_labels.get(&route.from_input).cloned();
+                                            if input_label.is_some() {
+                                                unlabelled_routes.remove(&route.to_output);
+                                            } else if should_emit && let Some(warming) = route_label_warming {
+                                                unlabelled_routes.insert(route.to_output);
+                                                // Held back until the labels arrive or the prelude ends
+                                                if warming == RouteLabelWarming::Defer && !prelude_complete {
+                                                    continue;
                                                 }
                                             }
+
+                                            if should_emit && let Err(e) = event_tx.send(VideohubEvent::Route {
+                                                output: route.to_output,
+                                                input: route.from_input,
+                                                input_label,
+                                                confirmed: true,
+                                                last_change,
+                                            }).await {
+                                                log::error!("Failed to send route event for output {} to input {}: {e}", route.to_output, route.from_input);
+                                            }
+                                        }
+
+                                        if !intent.is_empty()
+                                            && let Some(event) = intent.compare(client.state())
+                                            && let Err(e) = event_tx.send(event).await
+                                        {
+                                            log::error!("Failed to send intent divergence event: {e}");
                                         }
                                     }
                                     VideohubMessage::InputLabels(labels) => {
-                                        for label in labels {
-                                            let should_emit = client.just_reconnected() ||
-                                                current_input_labels.get(&label.id) != Some(&label.name);
-
-                                            current_input_labels.insert(label.id, label.name.clone());
+                                        for (label, &changed) in labels.iter().zip(&changed_entries) {
+                                            let resolved = pending_changes.confirm_with(|| ExpectedChange::InputLabel {
+                                                input: label.id,
+                                                label: label.name.clone(),
+                                            });
+                                            send_resolved_feedback(&event_tx, &mut pending_changes, &resolved).await;
+                                            if let Some(change) = &resolved.echoed {
+                                                record_latency(&mut latency_monitor, change.latency(), &client, &command_tx, &event_tx, pending_changes.len()).await;
+                                            }
+                                            let own_echo = resolved.echoed.is_some();
+                                            let should_emit = !(own_echo && suppress_label_echo) && (client.just_reconnected() || !resolved.is_empty() || changed);
+                                            if prelude_complete && changed {
+                                                change_window.label();
+                                            }
 
                                             if should_emit
                                                 && let Err(e) = event_tx.send(VideohubEvent::Label {
                                                     port_type: "input".to_string(),
                                                     port: label.id,
                                                     label: label.name.clone(),
+                                                    original: label_transliteration.as_ref().and_then(|transliteration| transliteration.original("input", label.id, &label.name)),
+                                                    confirmed: true,
                                                 }).await {
                                                     log::error!("Failed to send input label event for input {}: {e}", label.id);
                                                 }
                                         }
+
+                                        emit_unlabelled_routes(&mut unlabelled_routes, client.state(), false, &event_tx).await;
+
+                                        // Persist label changes made after the initial state
+                                        if prelude_complete && let Some(store) = &mut label_store {
+                                            store.input_labels.extend(labels.iter().map(|label| (label.id, label.name.clone())));
+                                            if let Err(e) = store.save() {
+                                                log::error!("Failed to persist input labels: {e}");
+                                            }
+                                        }
                                     }
                                     VideohubMessage::OutputLabels(labels) => {
-                                        for label in labels {
-                                            let should_emit = client.just_reconnected() ||
-                                                current_output_labels.get(&label.id) != Some(&label.name);
-
-                                            current_output_labels.insert(label.id, label.name.clone());
+                                        for (label, &changed) in labels.iter().zip(&changed_entries) {
+                                            let resolved = pending_changes.confirm_with(|| ExpectedChange::OutputLabel {
+                                                output: label.id,
+                                                label: label.name.clone(),
+                                            });
+                                            send_resolved_feedback(&event_tx, &mut pending_changes, &resolved).await;
+                                            if let Some(change) = &resolved.echoed {
+                                                record_latency(&mut latency_monitor, change.latency(), &client, &command_tx, &event_tx, pending_changes.len()).await;
+                                            }
+                                            let own_echo = resolved.echoed.is_some();
+                                            let should_emit = !(own_echo && suppress_label_echo) && (client.just_reconnected() || !resolved.is_empty() || changed);
+                                            if prelude_complete && changed {
+                                                change_window.label();
+                                            }
 
                                             if should_emit
                                                 && let Err(e) = event_tx.send(VideohubEvent::Label {
                                                     port_type: "output".to_string(),
                                                     port: label.id,
                                                     label: label.name.clone(),
+                                                    original: label_transliteration.as_ref().and_then(|transliteration| transliteration.original("output", label.id, &label.name)),
+                                                    confirmed: true,
                                                 }).await {
                                                     log::error!("Failed to send output label event for output {}: {e}", label.id);
                                                 }
                                         }
+
+                                        // Persist label changes made after the initial state
+                                        if prelude_complete && let Some(store) = &mut label_store {
+                                            store.output_labels.extend(labels.iter().map(|label| (label.id, label.name.clone())));
+                                            if let Err(e) = store.save() {
+                                                log::error!("Failed to persist output labels: {e}");
+                                            }
+                                        }
                                     }
                                     VideohubMessage::VideoOutputLocks(locks) => {
-                                        for lock in locks {
+                                        for (lock, &changed) in locks.iter().zip(&changed_entries) {
                                             let is_locked = matches!(lock.state, videohub::LockState::Locked);
-                                            let should_emit = client.just_reconnected() ||
-                                                current_output_locks.get(&lock.id) != Some(&is_locked);
-
-                                            current_output_locks.insert(lock.id, is_locked);
+                                            let should_emit = client.just_reconnected() || changed;
+                                            if prelude_complete && changed {
+                                                matrix_summary.record_change(lock.id);
+                                                change_window.lock(lock.id);
+                                            }
 
                                             if should_emit
                                                 && let Err(e) = event_tx.send(VideohubEvent::OutputLock {
@@ -818,65 +6218,280 @@ impl VideohubService {
                                                 }
                                         }
                                     }
+                                    // Lock and ping blocks are answered too, but never have pending changes
+                                    VideohubMessage::ACK if replies_to_pending(client.last_reply().unwrap_or("unknown")) => {
+                                        pending_changes.ack();
+                                    }
+                                    VideohubMessage::NAK => {
+                                        let category = client.last_reply().unwrap_or("unknown");
+                                        let rejected = if replies_to_pending(category) {
+                                            pending_changes.nak()
+                                        } else {
+                                            Vec::new()
+                                        };
+                                        let rejected_total = client.state().rejected_commands.get(category).copied().unwrap_or(0);
+                                        if let Err(e) = event_tx.send(VideohubEvent::CommandRejected {
+                                            category,
+                                            rejected_total,
+                                            rolled_back: rejected.len(),
+                                        }).await {
+                                            log::error!("Failed to send command rejected event: {e}");
+                                        }
+                                        for change in rejected {
+                                            log::warn!("Device rejected {category} command {:?}, rolling back", change.expected);
+                                            send_change_feedback(&event_tx, &mut pending_changes, &change, Some(format!("rejected by the device ({category} command)"))).await;
+                                            if let Some(previous) = change.previous
+                                                && let Err(e) = event_tx.send(with_original_label(previous.to_event(true, &client.state().input_labels), label_transliteration.as_ref())).await {
+                                                    log::error!("Failed to send rollback event: {e}");
+                                                }
+                                        }
+                                    }
+                                    VideohubMessage::UnknownMessage(header, _) => {
+                                        let header = String::from_utf8_lossy(header);
+                                        let header = header.trim();
+                                        if let Some(stats) = client.state().unknown_blocks.get(header)
+                                            && unknown_throttle.should_report(header)
+                                        {
+                                            log::warn!("Device sent {} unknown {header} blocks so far", stats.count);
+                                            let total = client.state().unknown_blocks.values().map(|stats| stats.count).sum();
+                                            if let Err(e) = event_tx.send(VideohubEvent::UnknownBlock {
+                                                header: header.to_string(),
+                                                count: stats.count,
+                                                total,
+                                                sample: stats.sample.clone(),
+                                            }).await {
+                                                log::error!("Failed to send unknown block event: {e}");
+                                            }
+                                        }
+                                    }
+                                    // The prelude was assumed complete after a quiet period, and the device
+                                    // sent END PRELUDE after all
+                                    VideohubMessage::EndPrelude if prelude_complete => {
+                                        log::debug!("Ignoring END PRELUDE after the initial state was assumed complete");
+                                    }
                                     VideohubMessage::EndPrelude => {
                                         // Clear the reconnected flag after processing all initial state
                                         client.clear_reconnected_flag();
                                         log::debug!("Cleared reconnection flag after receiving full state");
-                                    }
-                                    _ => {
-                                        // Check if client state has new information that we should emit events for
-                                        let client_state = client.state();
+                                        prelude_complete = true;
+                                        // Inputs without a label will not get one; stop holding their routes back
+                                        if route_label_warming == Some(RouteLabelWarming::Defer) {
+                                            emit_unlabelled_routes(&mut unlabelled_routes, client.state(), true, &event_tx).await;
+                                        }
 
-                                        // Check take mode changes
-                                        for (&output, &enabled) in &client_state.take_mode {
-                                            let should_emit = client.just_reconnected() ||
-                                                current_take_mode.get(&output) != Some(&enabled);
+                                        if let Err(e) = event_tx.send(inventory_event(client.state())).await {
+                                            log::error!("Failed to send inventory event: {e}");
+                                        }
+                                        if let Some(store) = &salvo_store
+                                            && let Err(e) = event_tx.send(store.event()).await {
+                                                log::error!("Failed to send salvos event: {e}");
+                                            }
+                                        // Every route and label of the prelude has been sent by now
+                                        let device_info = client.state().device_info.as_ref();
+                                        let video_inputs = device_info.and_then(|info| info.video_inputs);
+                                        let video_outputs = device_info.and_then(|info| info.video_outputs);
+                                        if let Err(e) = event_tx.send(VideohubEvent::PreludeComplete { video_inputs, video_outputs }).await {
+                                            log::error!("Failed to send prelude complete event: {e}");
+                                        }
 
-                                            current_take_mode.insert(output, enabled);
+                                        let reboot = reboots.check(client.state());
+                                        let device_restarted = reboot.is_some();
+                                        if let Some(reboot) = reboot {
+                                            log::warn!(
+                                                "Videohub restarted while disconnected (epoch {}): {}",
+                                                reboot.epoch,
+                                                reboot.evidence.join(", ")
+                                            );
+                                            if let Err(e) = event_tx.send(VideohubEvent::DeviceRebootDetected {
+                                                epoch: reboot.epoch,
+                                                disconnected_ms: reboot.disconnected_for.as_millis() as u64,
+                                                device_uptime_s: reboot.device_uptime.map(|uptime| uptime.as_secs()),
+                                                evidence: reboot.evidence,
+                                            }).await {
+                                                log::error!("Failed to send device reboot event: {e}");
+                                            }
+                                        }
 
-                                            if should_emit
-                                                && let Err(e) = event_tx.send(VideohubEvent::TakeMode {
-                                                    output,
-                                                    enabled,
-                                                }).await {
-                                                    log::error!("Failed to send take mode event for output {output}: {e}");
-                                                }
+                                        // Operations the previous run was cut short in, once the full state is known
+                                        let interrupted = journal.as_mut().map(Journal::take_interrupted).unwrap_or_default();
+                                        for entry in interrupted {
+                                            let (applied, missing) = entry.check(client.state());
+                                            if missing.is_empty() {
+                                                log::info!("Journaled {} from {} was applied completely", entry.source, entry.written_at);
+                                                continue;
+                                            }
+                                            log::warn!(
+                                                "Journaled {} from {} was interrupted: {} of {} changes missing{}",
+                                                entry.source,
+                                                entry.written_at,
+                                                missing.len(),
+                                                applied.len() + missing.len(),
+                                                if complete_interrupted { ", completing it" } else { "" }
+                                            );
+                                            if let Err(e) = event_tx.send(VideohubEvent::InterruptedOperation {
+                                                source: entry.source,
+                                                written_at: entry.written_at,
+                                                applied: applied.iter().map(StagedChange::from).collect(),
+                                                missing: missing.iter().map(StagedChange::from).collect(),
+                                                completed: complete_interrupted,
+                                            }).await {
+                                                log::error!("Failed to send interrupted operation event: {e}");
+                                            }
+                                            if complete_interrupted {
+                                                queue_changes(&command_tx, missing);
+                                            }
+                                        }
+
+                                        // Return to the show state once the executor started or the device restarted.
+                                        // On standby the policy stays pending until this executor leads.
+                                        if startup_show.is_some() && device_restarted {
+                                            startup_pending = true;
+                                        }
+                                        if let Some(path) = &startup_show && startup_pending {
+                                            if client.is_standby() {
+                                                log::info!("On standby, leaving the startup policy to the leader");
+                                            } else {
+                                                startup_pending = false;
+                                                apply_startup_show(path, client.state(), &command_tx);
+                                            }
                                         }
 
-                                        // Check network interface changes
-                                        for interface in &client_state.network_interfaces {
-                                            let should_emit = client.just_reconnected() ||
-                                                current_network_interfaces.get(&interface.id) != Some(interface);
+                                        // Resume rotations, e.g. after the device was power cycled
+                                        for group in &active_rotations {
+                                            if let Some(rotation) = rotation_groups.get(group) {
+                                                log::info!("Resuming rotation group {group}");
+                                                rotation.sequences().into_iter().for_each(|sequence| sequencer.start(sequence));
+                                            }
+                                        }
 
-                                            current_network_interfaces.insert(interface.id, interface.clone());
+                                        if let Some(store) = &mut label_store {
+                                            let mut corrections = Vec::new();
+                                            if labels_authoritative {
+                                                // Push stored labels that the device disagrees with
+                                                corrections.extend(
+                                                    LabelStore::reconcile(&mut store.input_labels, &client.state().input_labels)
+                                                        .into_iter()
+                                                        .map(|(input, label)| ExpectedChange::InputLabel { input, label }),
+                                                );
+                                                corrections.extend(
+                                                    LabelStore::reconcile(&mut store.output_labels, &client.state().output_labels)
+                                                        .into_iter()
+                                                        .map(|(output, label)| ExpectedChange::OutputLabel { output, label }),
+                                                );
+                                                if !corrections.is_empty() {
+                                                    log::info!("Pushing {} stored label corrections to the device", corrections.len());
+                                                }
+                                            } else {
+                                                // Mirror the device's labels
+                                                store.input_labels = client.state().input_labels.clone();
+                                                store.output_labels = client.state().output_labels.clone();
+                                            }
+                                            if let Err(e) = store.save() {
+                                                log::error!("Failed to persist labels: {e}");
+                                            }
 
-                                            if should_emit
-                                                && let Err(e) = event_tx.send(VideohubEvent::NetworkInterface {
-                                                    interface: interface.clone(),
-                                                }).await {
-                                                    log::error!("Failed to send network interface event for interface {}: {e}", interface.id);
+                                            for expected in corrections {
+                                                let sent = match &expected {
+                                                    ExpectedChange::InputLabel { input, label } => client.set_input_label(*input, label.clone()).await,
+                                                    ExpectedChange::OutputLabel { output, label } => client.set_output_label(*output, label.clone()).await,
+                                                    ExpectedChange::Route { .. } => continue,
+                                                };
+                                                if let Err(e) = sent {
+                                                    log::error!("Failed to push stored label {expected:?}: {e}");
+                                                    continue;
                                                 }
+
+                                                let previous = expected.current_state(client.state());
+                                                if !suppress_label_echo
+                                                    && let Err(e) = event_tx.send(with_original_label(expected.to_event(false, &client.state().input_labels), label_transliteration.as_ref())).await {
+                                                        log::error!("Failed to send label correction event: {e}");
+                                                    }
+                                                pending_changes.push(expected, previous, None);
+                                            }
                                         }
                                     }
+                                    _ => {}
+                                }
+
+                                if prelude_complete
+                                    && let Some(event) = matrix_summary.changes(client.state())
+                                    && let Err(e) = event_tx.send(event).await {
+                                        log::error!("Failed to send matrix summary event: {e}");
+                                    }
+                                if prelude_complete
+                                    && let Some(drift) = &mut drift
+                                    && let Some(event) = drift.changes(client.state())
+                                {
+                                    if let VideohubEvent::Drift { drift, .. } = &event {
+                                        health.set_drift(*drift);
+                                    }
+                                    if let Err(e) = event_tx.send(event).await {
+                                        log::error!("Failed to send drift event: {e}");
+                                    }
+                                }
+                                if prelude_complete {
+                                    let changes = output_status.changes(client.state(), &pending_routes, &input_freezes, ownership.as_ref(), |output| route_holds.contains_key(&output));
+                                    send_output_status(&event_tx, changes).await;
                                 }
                             }
                             Ok(None) => {
                                 log::warn!("Videohub connection closed, attempting to reconnect...");
+                                // Full state is re-emitted after reconnecting, which supersedes any optimistic changes
+                                for change in pending_changes.clear() {
+                                    send_change_feedback(&event_tx, &mut pending_changes, &change, Some("connection to the device lost".into())).await;
+                                }
+                                prelude_complete = false;
+                                reboots.disconnected(client.state());
+                                if let Some(watchdog) = &mut watchdog {
+                                    watchdog.reset();
+                                }
+                                // Every route is emitted again after reconnecting
+                                unlabelled_routes.clear();
+                                if let Some(drift) = &mut drift {
+                                    drift.reset();
+                                }
+                                output_status.reset();
+                                // Sequences and sweeps cannot route while disconnected; active rotations resume on reconnect
+                                sequencer.stop_all();
+                                sweeper.stop();
+                                let was_unstable = flaps.is_unstable();
+                                let mut delay = flaps.disconnected();
+                                // Commands are waiting on a connection lost mid-write; try again right away
+                                if std::mem::take(&mut reconnect_now) && !flaps.is_unstable() {
+                                    delay = Duration::ZERO;
+                                }
+                                if flaps.is_unstable() {
+                                    log::warn!("Videohub keeps dropping the connection, backing off for {delay:?}");
+                                } else if was_unstable {
+                                    log::info!("Videohub connection stable again");
+                                }
                                 // Emit disconnection event
-                                if let Err(e) = event_tx.send(VideohubEvent::DeviceStatus {
-                                    connected: false,
-                                    model_name: current_device_info.as_ref().and_then(|info| info.model_name.clone()),
-                                    video_inputs: current_device_info.as_ref().and_then(|info| info.video_inputs),
-                                    video_outputs: current_device_info.as_ref().and_then(|info| info.video_outputs),
-                                }).await {
+                                if let Err(e) = event_tx.send(device_status_event(client.state().device_info.as_ref(), false, flaps.is_unstable())).await {
                                     log::error!("Failed to send device disconnection event: {e}");
                                 }
 
-                                tokio::time::sleep(Duration::from_secs(5)).await;
+                                health.idle("state");
+                                tokio::time::sleep(delay).await;
+                                health.beat("state");
                                 if let Err(e) = client.connect().await {
                                     log::error!("Failed to reconnect to videohub: {e}");
                                 } else {
-                                    log::info!("Reconnected to videohub - will emit full state on next messages");
+                                    flaps.connected();
+                                    state_diff.clear();
+                                    let epoch = reboots.connected();
+                                    log::info!("Reconnected to videohub (connection epoch {epoch}) - will emit full state on next messages");
+                                    if !resend_after_reconnect.is_empty() {
+                                        // Ahead of the commands that arrived meanwhile, unless one of them replaces it
+                                        command_bus.fill(&mut command_rx);
+                                        let requests = std::mem::take(&mut resend_after_reconnect);
+                                        let lost = requests.len();
+                                        let dropped = command_bus.requeue(requests, MAX_RESEND_AGE);
+                                        log::info!("Resending {} of {lost} commands lost with the connection", lost - dropped.len());
+                                        for (request, reason) in dropped {
+                                            log::warn!("Not resending {:?}: {reason}", request.command);
+                                            send_feedback(&event_tx, request.invocation.as_ref(), "failed", Some(reason.to_string())).await;
+                                        }
+                                    }
                                 }
                             }
                             Err(e) => {
@@ -894,17 +6509,35 @@ impl VideohubService {
 
     async fn start_connection_monitoring(
         &self,
+        endpoints: Vec<String>,
+        mut active_endpoint: usize,
+        rship_connected: Arc<AtomicBool>,
+        rship_restored: Arc<Notify>,
         rship_reconnect_tx: mpsc::Sender<()>,
+        event_tx: EventSender,
     ) -> Result<()> {
         log::info!("Starting rship connection status monitoring");
 
         let sdk_client = self.sdk_client.clone();
+        let failover_after = self.rship_failover_after();
+        let health = self.health.clone();
         tokio::spawn(async move {
             let mut was_connected = true; // Assume initially connected
+            let mut disconnected_since: Option<tokio::time::Instant> = None;
             let mut interval = interval(Duration::from_secs(5));
 
+            let endpoint_event = |priority: usize| VideohubEvent::RshipEndpoint {
+                url: endpoints[priority].clone(),
+                priority,
+            };
+            if let Err(e) = event_tx.send(endpoint_event(active_endpoint)).await {
+                log::error!("Failed to send rship endpoint event: {e}");
+            }
+
             loop {
+                health.idle("rship-monitor");
                 interval.tick().await;
+                health.beat("rship-monitor");
 
                 // Check connection by trying await_connection with timeout
                 let connection_result =
@@ -912,15 +6545,43 @@ impl VideohubService {
                         .await;
 
                 let is_connected = connection_result.is_ok();
+                // Also cleared by the emission task when a pulse fails between two checks
+                let was_flagged = rship_connected.swap(is_connected, Ordering::Relaxed);
+                if is_connected && !was_flagged {
+                    rship_restored.notify_one();
+                }
 
                 if !was_connected && is_connected {
                     log::info!("Rship SDK connection restored - triggering full state refresh");
+                    disconnected_since = None;
                     if let Err(e) = rship_reconnect_tx.send(()).await {
                         log::error!("Failed to send rship reconnection signal: {e}");
                         break;
                     }
+                    // The SDK re-registers the instance on connect; report which server it is on
+                    if let Err(e) = event_tx.send(endpoint_event(active_endpoint)).await {
+                        log::error!("Failed to send rship endpoint event: {e}");
+                    }
                 } else if was_connected && !is_connected {
                     log::warn!("Rship SDK connection lost");
+                    disconnected_since = Some(tokio::time::Instant::now());
+                }
+
+                // Move on to the next server if the active one stays down
+                if endpoints.len() > 1
+                    && let Some(since) = disconnected_since
+                    && since.elapsed() >= failover_after
+                {
+                    let previous = active_endpoint;
+                    active_endpoint = (active_endpoint + 1) % endpoints.len();
+                    log::warn!(
+                        "rship at {} down for {:?}, switching to {}",
+                        endpoints[previous],
+                        since.elapsed(),
+                        endpoints[active_endpoint]
+                    );
+                    sdk_client.set_address(Some(endpoints[active_endpoint].clone()));
+                    disconnected_since = Some(tokio::time::Instant::now());
                 }
 
                 was_connected = is_connected;
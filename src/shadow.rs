@@ -0,0 +1,102 @@
+//! Shadow mode, for migrating from a legacy controller. The legacy controller keeps driving the
+//! device and posts the routes it intends (`PUT /intent`); the executor only observes the device
+//! and reports the outputs where the device differs from that intent.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::client::VideohubState;
+use crate::service::VideohubEvent;
+
+// An output not routed as the legacy controller intends
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentMismatch {
+    pub output: u32,
+    pub expected: u32,
+    // None if the device hasn't reported the output's route
+    pub actual: Option<u32>,
+}
+
+// The intended routes (output -> input), and the mismatches last reported
+#[derive(Debug, Default)]
+pub struct Intent {
+    routes: HashMap<u32, u32>,
+    reported: Option<BTreeMap<u32, Option<u32>>>,
+}
+
+impl Intent {
+    // Replace the whole intent; outputs left out are not compared
+    pub fn set(&mut self, routes: HashMap<u32, u32>) {
+        self.routes = routes;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    // The divergence, if the mismatching outputs changed since the last report
+    pub fn compare(&mut self, state: &VideohubState) -> Option<VideohubEvent> {
+        let mismatches: BTreeMap<u32, Option<u32>> = self
+            .routes
+            .iter()
+            .filter_map(|(output, expected)| {
+                let actual = state.video_output_routing.get(output).copied();
+                (actual != Some(*expected)).then_some((*output, actual))
+            })
+            .collect();
+        if self.reported.as_ref() == Some(&mismatches) {
+            return None;
+        }
+        let diverged = mismatches
+            .iter()
+            .map(|(output, actual)| IntentMismatch {
+                output: *output,
+                expected: self.routes[output],
+                actual: *actual,
+            })
+            .collect();
+        self.reported = Some(mismatches);
+        Some(VideohubEvent::IntentDivergence {
+            intended_outputs: self.routes.len(),
+            diverged,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_outputs_routed_differently_from_the_intent() {
+        let mut state = VideohubState::default();
+        state.video_output_routing.extend([(0, 1), (1, 2)]);
+        let mut intent = Intent::default();
+        intent.set(HashMap::from([(0, 1), (1, 3), (2, 0)]));
+
+        let Some(VideohubEvent::IntentDivergence { diverged, .. }) = intent.compare(&state) else {
+            panic!("no divergence reported");
+        };
+        assert_eq!(
+            diverged,
+            vec![
+                IntentMismatch {
+                    output: 1,
+                    expected: 3,
+                    actual: Some(2),
+                },
+                IntentMismatch {
+                    output: 2,
+                    expected: 0,
+                    actual: None,
+                },
+            ]
+        );
+        assert!(intent.compare(&state).is_none());
+
+        state.video_output_routing.extend([(1, 3), (2, 0)]);
+        let Some(VideohubEvent::IntentDivergence { diverged, .. }) = intent.compare(&state) else {
+            panic!("no convergence reported");
+        };
+        assert!(diverged.is_empty());
+    }
+}
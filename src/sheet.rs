@@ -0,0 +1,122 @@
+//! CSV import/export of the crosspoint and label sheet
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::client::{VideohubClient, VideohubState};
+use crate::pending::ExpectedChange;
+
+// One output per row; port numbers are 1-indexed like the rship actions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetRow {
+    pub output: u32,
+    #[serde(default)]
+    pub output_label: String,
+    pub input: Option<u32>,
+    #[serde(default)]
+    pub input_label: String,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+// Build the sheet from the current device state, one row per output
+pub fn rows_from_state(state: &VideohubState) -> Vec<SheetRow> {
+    let num_outputs = state
+        .device_info
+        .as_ref()
+        .and_then(|info| info.video_outputs)
+        .unwrap_or(state.output_labels.len() as u32);
+
+    (0..num_outputs)
+        .map(|output| {
+            let input = state.video_output_routing.get(&output).copied();
+            SheetRow {
+                output: output + 1,
+                output_label: state
+                    .output_labels
+                    .get(&output)
+                    .cloned()
+                    .unwrap_or_default(),
+                input: input.map(|input| input + 1),
+                input_label: input
+                    .and_then(|input| state.input_labels.get(&input).cloned())
+                    .unwrap_or_default(),
+                locked: state.output_locks.get(&output).copied().unwrap_or(false),
+            }
+        })
+        .collect()
+}
+
+// Write the sheet to a CSV file
+pub fn write_csv(path: &Path, rows: &[SheetRow]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Read a sheet from a CSV file
+pub fn read_csv(path: &Path) -> Result<Vec<SheetRow>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    reader
+        .deserialize()
+        .collect::<Result<Vec<SheetRow>, _>>()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+// Changes needed to apply the labels of a sheet, and optionally its routes.
+// Only values that differ from the current device state are included.
+pub fn changes(
+    state: &VideohubState,
+    rows: &[SheetRow],
+    apply_routes: bool,
+) -> Vec<ExpectedChange> {
+    let mut changes = Vec::new();
+    for row in rows {
+        if row.output == 0 {
+            log::warn!("Skipping sheet row with output 0 (ports are 1-indexed)");
+            continue;
+        }
+        let output = row.output - 1;
+
+        if !row.output_label.is_empty()
+            && state.output_labels.get(&output) != Some(&row.output_label)
+        {
+            changes.push(ExpectedChange::OutputLabel {
+                output,
+                label: row.output_label.clone(),
+            });
+        }
+
+        let Some(input) = row.input.filter(|input| *input > 0).map(|input| input - 1) else {
+            continue;
+        };
+
+        if !row.input_label.is_empty() && state.input_labels.get(&input) != Some(&row.input_label) {
+            changes.push(ExpectedChange::InputLabel {
+                input,
+                label: row.input_label.clone(),
+            });
+        }
+
+        if apply_routes && state.video_output_routing.get(&output) != Some(&input) {
+            changes.push(ExpectedChange::Route { output, input });
+        }
+    }
+    changes
+}
+
+// Apply a sheet to the device right away
+pub async fn apply(
+    client: &mut VideohubClient,
+    rows: &[SheetRow],
+    apply_routes: bool,
+) -> Result<()> {
+    let changes = changes(client.state(), rows, apply_routes);
+    ExpectedChange::send_all(client, changes).await
+}
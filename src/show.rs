@@ -0,0 +1,162 @@
+//! Show files: saved routing, label and lock state of a device
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::client::VideohubState;
+use crate::pending::ExpectedChange;
+
+// Saved device state; port numbers are 1-indexed like the rship actions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShowFile {
+    #[serde(default)]
+    pub routes: BTreeMap<u32, u32>, // output -> input
+    #[serde(default)]
+    pub input_labels: BTreeMap<u32, String>,
+    #[serde(default)]
+    pub output_labels: BTreeMap<u32, String>,
+    #[serde(default)]
+    pub output_locks: BTreeMap<u32, bool>,
+}
+
+// A single mismatch between a show file and the device
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShowDifference {
+    pub kind: &'static str, // "route", "input-label", "output-label" or "lock"
+    pub port: u32,
+    pub expected: String,
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for ShowDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: expected {}, actual {}",
+            self.kind,
+            self.port,
+            self.expected,
+            self.actual.as_deref().unwrap_or("(none)")
+        )
+    }
+}
+
+impl ShowFile {
+    // Capture the current device state
+    pub fn from_state(state: &VideohubState) -> Self {
+        Self {
+            routes: state
+                .video_output_routing
+                .iter()
+                .map(|(output, input)| (output + 1, input + 1))
+                .collect(),
+            input_labels: state
+                .input_labels
+                .iter()
+                .map(|(input, label)| (input + 1, label.clone()))
+                .collect(),
+            output_labels: state
+                .output_labels
+                .iter()
+                .map(|(output, label)| (output + 1, label.clone()))
+                .collect(),
+            output_locks: state
+                .output_locks
+                .iter()
+                .map(|(output, locked)| (output + 1, *locked))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read show file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse show file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write show file {}", path.display()))
+    }
+
+    // Route and label changes needed to make the device match this show file.
+    // Locks cannot be written yet and are left as they are.
+    pub fn changes(&self, state: &VideohubState) -> Vec<ExpectedChange> {
+        let mut changes = Vec::new();
+        for (&output, &input) in &self.routes {
+            let (output, input) = (output.saturating_sub(1), input.saturating_sub(1));
+            if state.video_output_routing.get(&output) != Some(&input) {
+                changes.push(ExpectedChange::Route { output, input });
+            }
+        }
+        for (&input, label) in &self.input_labels {
+            let input = input.saturating_sub(1);
+            if state.input_labels.get(&input) != Some(label) {
+                changes.push(ExpectedChange::InputLabel {
+                    input,
+                    label: label.clone(),
+                });
+            }
+        }
+        for (&output, label) in &self.output_labels {
+            let output = output.saturating_sub(1);
+            if state.output_labels.get(&output) != Some(label) {
+                changes.push(ExpectedChange::OutputLabel {
+                    output,
+                    label: label.clone(),
+                });
+            }
+        }
+        changes
+    }
+
+    // Everything in this show file that the actual state does not match.
+    // Ports the show file does not mention are ignored.
+    pub fn diff(&self, actual: &ShowFile) -> Vec<ShowDifference> {
+        let mut differences = Vec::new();
+        diff_map(&mut differences, "route", &self.routes, &actual.routes);
+        diff_map(
+            &mut differences,
+            "input-label",
+            &self.input_labels,
+            &actual.input_labels,
+        );
+        diff_map(
+            &mut differences,
+            "output-label",
+            &self.output_labels,
+            &actual.output_labels,
+        );
+        diff_map(
+            &mut differences,
+            "lock",
+            &self.output_locks,
+            &actual.output_locks,
+        );
+        differences
+    }
+}
+
+fn diff_map<T: PartialEq + ToString>(
+    differences: &mut Vec<ShowDifference>,
+    kind: &'static str,
+    expected: &BTreeMap<u32, T>,
+    actual: &BTreeMap<u32, T>,
+) {
+    for (port, value) in expected {
+        let actual_value = actual.get(port);
+        if actual_value != Some(value) {
+            differences.push(ShowDifference {
+                kind,
+                port: *port,
+                expected: value.to_string(),
+                actual: actual_value.map(ToString::to_string),
+            });
+        }
+    }
+}
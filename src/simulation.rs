@@ -0,0 +1,308 @@
+//! "What-if" checks for automation: the state change a routing, label or lock action would
+//! make and what would refuse it, worked out against the observed device state without
+//! sending anything to the device
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    LabelOutputRangeAction, LockOutputRangeAction, RouteOutputRangeAction, SetDestinationsAction,
+    SetInputLabelAction, SetOutputLabelAction, SetOutputLockAction, SetRouteAction,
+};
+use crate::claim::ControlClaim;
+use crate::client::VideohubState;
+use crate::freeze::InputFreezes;
+use crate::middleware::{CommandMiddleware, ResolveLabels, ValidatePorts};
+use crate::ownership::Ownership;
+use crate::pending::ExpectedChange;
+use crate::service::VideohubCommand;
+
+// A routing, label or lock action and its usual payload, to simulate or to add to a
+// transaction, e.g. {"action": "set-route", "data": {"output": 3, "input": 1}}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "action", content = "data", rename_all = "kebab-case")]
+pub enum ActionPayload {
+    SetRoute(SetRouteAction),
+    SetDestinations(SetDestinationsAction),
+    RouteOutputRange(RouteOutputRangeAction),
+    SetInputLabel(SetInputLabelAction),
+    SetOutputLabel(SetOutputLabelAction),
+    LabelOutputRange(LabelOutputRangeAction),
+    SetOutputLock(SetOutputLockAction),
+    LockOutputRange(LockOutputRangeAction),
+}
+
+impl ActionPayload {
+    // Action id, as the action is registered with rship
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SetRoute(_) => "set-route",
+            Self::SetDestinations(_) => "set-destinations",
+            Self::RouteOutputRange(_) => "route-output-range",
+            Self::SetInputLabel(_) => "set-input-label",
+            Self::SetOutputLabel(_) => "set-output-label",
+            Self::LabelOutputRange(_) => "label-output-range",
+            Self::SetOutputLock(_) => "set-output-lock",
+            Self::LockOutputRange(_) => "lock-output-range",
+        }
+    }
+
+    // The command the action would queue, and the owner it acts for
+    pub fn into_command(self) -> (VideohubCommand, Option<String>) {
+        match self {
+            Self::SetRoute(data) => (data.command(), data.owner),
+            Self::SetDestinations(data) => (data.command(), data.owner),
+            Self::RouteOutputRange(data) => (data.command(), data.owner),
+            Self::SetInputLabel(data) => (data.command(), None),
+            Self::SetOutputLabel(data) => (data.command(), data.owner),
+            Self::LabelOutputRange(data) => (data.command(), data.owner),
+            Self::SetOutputLock(data) => (data.command(), data.owner),
+            Self::LockOutputRange(data) => (data.command(), data.owner),
+        }
+    }
+}
+
+// A port the command would change; ports are 1-indexed like the rship actions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PredictedChange {
+    pub kind: String, // "route", "input-label", "output-label" or "output-lock"
+    pub port: u32,
+    // Current value (input number, label or lock state), if the device reported one
+    pub from: Option<String>,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Prediction {
+    // Whether the command would be sent to the device and accepted by it
+    pub allowed: bool,
+    // Why it would not be: validation, output ownership, an operator claim, frozen inputs and
+    // locks held by other controllers
+    pub blocked_by: Vec<String>,
+    // Ports already in the requested state are left out
+    pub changes: Vec<PredictedChange>,
+}
+
+// Predict the effect of a command. Only the built-in middleware is run, since registered
+// middleware may act on the commands it sees; it may still reject the real command.
+pub fn predict(
+    mut command: VideohubCommand,
+    owner: Option<&str>,
+    state: &VideohubState,
+    ownership: Option<&Ownership>,
+    freezes: &InputFreezes,
+    // The operator claim, unless claims are only advisory
+    claim: Option<&ControlClaim>,
+) -> Prediction {
+    let validated = [&ResolveLabels as &dyn CommandMiddleware, &ValidatePorts]
+        .iter()
+        .try_for_each(|middleware| middleware.process(&mut command, state));
+    if let Err(e) = validated {
+        return Prediction {
+            allowed: false,
+            blocked_by: vec![e.to_string()],
+            changes: Vec::new(),
+        };
+    }
+
+    let mut blocked_by = Vec::new();
+    if let Some(ownership) = ownership
+        && let Err(violation) = ownership.check(&command.outputs(), owner)
+    {
+        blocked_by.push(format!(
+            "output {} belongs to {}",
+            violation.output + 1,
+            violation.output_owner
+        ));
+    }
+
+    if let Some(claim) = claim
+        && let Err(operator) = claim.check(owner)
+    {
+        blocked_by.push(format!("the device is claimed by {operator}"));
+    }
+    if let Err(violation) = freezes.check(&command, &state.video_output_routing) {
+        blocked_by.push(format!(
+            "output {} carries frozen input {}",
+            violation.output + 1,
+            violation.frozen_input + 1
+        ));
+    }
+
+    let expected = match &command {
+        VideohubCommand::WriteChanges { changes } => changes.clone(),
+        command => command.expected_change().into_iter().collect(),
+    };
+    let mut changes: Vec<PredictedChange> = expected
+        .iter()
+        .filter_map(|change| predicted_change(change, state))
+        .collect();
+
+    let locks = match &command {
+        VideohubCommand::OutputLock { output, locked } => vec![(*output, *locked)],
+        VideohubCommand::OutputLocks { outputs, locked } => {
+            outputs.iter().map(|output| (*output, *locked)).collect()
+        }
+        _ => Vec::new(),
+    };
+    for (output, locked) in locks {
+        let from = lock_state(output, state);
+        let to = if locked { "locked" } else { "unlocked" };
+        if from != to {
+            changes.push(PredictedChange {
+                kind: "output-lock".into(),
+                port: output + 1,
+                from: Some(from.into()),
+                to: to.into(),
+            });
+        }
+    }
+
+    // The device refuses routes to, and lock changes on, outputs another controller locked
+    for change in &changes {
+        if matches!(change.kind.as_str(), "route" | "output-lock")
+            && state.output_locks.get(&(change.port - 1)) == Some(&true)
+        {
+            blocked_by.push(format!(
+                "output {} is locked by another controller",
+                change.port
+            ));
+        }
+    }
+
+    Prediction {
+        allowed: blocked_by.is_empty(),
+        blocked_by,
+        changes,
+    }
+}
+
+// The change as reported to the caller, or None if the port is already in that state
+fn predicted_change(change: &ExpectedChange, state: &VideohubState) -> Option<PredictedChange> {
+    let current = change.current_state(state);
+    if current.as_ref() == Some(change) {
+        return None;
+    }
+    let (kind, port, to) = describe(change);
+    Some(PredictedChange {
+        kind: kind.into(),
+        port,
+        from: current.as_ref().map(|current| describe(current).2),
+        to,
+    })
+}
+
+fn describe(change: &ExpectedChange) -> (&'static str, u32, String) {
+    match change {
+        ExpectedChange::Route { output, input } => ("route", output + 1, (input + 1).to_string()),
+        ExpectedChange::InputLabel { input, label } => ("input-label", input + 1, label.clone()),
+        ExpectedChange::OutputLabel { output, label } => {
+            ("output-label", output + 1, label.clone())
+        }
+    }
+}
+
+fn lock_state(output: u32, state: &VideohubState) -> &'static str {
+    if state.owned_locks.contains(&output) {
+        "locked"
+    } else if state.output_locks.get(&output) == Some(&true) {
+        "locked by another controller"
+    } else {
+        "unlocked"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_out_unchanged_ports_and_reports_foreign_locks() {
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 0);
+        state.video_output_routing.insert(1, 2);
+        state.output_locks.insert(1, true);
+
+        let action: ActionPayload = serde_json::from_str(
+            r#"{"action": "route-output-range", "data": {"input": 1, "outputs": {"start": 1, "end": 2}}}"#,
+        )
+        .unwrap();
+        let (command, owner) = action.into_command();
+        let prediction = predict(
+            command,
+            owner.as_deref(),
+            &state,
+            None,
+            &InputFreezes::default(),
+            None,
+        );
+
+        assert!(!prediction.allowed);
+        assert_eq!(
+            prediction.blocked_by,
+            vec!["output 2 is locked by another controller"]
+        );
+        assert_eq!(
+            prediction.changes,
+            vec![PredictedChange {
+                kind: "route".into(),
+                port: 2,
+                from: Some("3".into()),
+                to: "1".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_routes_off_a_frozen_input() {
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 4);
+        let mut freezes = InputFreezes::default();
+        freezes.freeze(4);
+
+        let route = |output, input| VideohubCommand::Route { output, input };
+        let prediction = predict(route(0, 1), None, &state, None, &freezes, None);
+        assert!(!prediction.allowed);
+        assert_eq!(
+            prediction.blocked_by,
+            vec!["output 1 carries frozen input 5"]
+        );
+        assert!(predict(route(1, 1), None, &state, None, &freezes, None).allowed);
+    }
+
+    #[test]
+    fn reports_a_claim_held_by_another_operator() {
+        let state = VideohubState::default();
+        let freezes = InputFreezes::default();
+        let mut claim = ControlClaim::default();
+        claim
+            .claim("director", tokio::time::Duration::from_secs(60))
+            .unwrap();
+
+        let route = VideohubCommand::Route {
+            output: 0,
+            input: 1,
+        };
+        let prediction = predict(
+            route.clone(),
+            Some("assistant"),
+            &state,
+            None,
+            &freezes,
+            Some(&claim),
+        );
+        assert_eq!(
+            prediction.blocked_by,
+            vec!["the device is claimed by director"]
+        );
+        let prediction = predict(
+            route,
+            Some("director"),
+            &state,
+            None,
+            &freezes,
+            Some(&claim),
+        );
+        assert!(prediction.allowed);
+    }
+}
@@ -0,0 +1,307 @@
+//! Bulk changes held back for review before they are written to the device
+
+use anyhow::{Context, bail};
+use rship_sdk::TargetProxy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::actions::{ApplyStagedChangesAction, DiscardStagedChangesAction, add_command_action};
+use crate::pending::ExpectedChange;
+use crate::service::{
+    ActionInvocation, CommandRequest, CommandScope, EventSender, VideohubCommand, VideohubEvent,
+};
+use crate::translations::DisplayNames;
+
+// How long staged changes wait for an apply before they are discarded
+pub const DEFAULT_STAGED_CHANGES_TIMEOUT: Duration = Duration::from_secs(60);
+
+// One intended device change, as shown in the preview (ports are 1-indexed)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StagedChange {
+    pub kind: String, // "route", "input-label" or "output-label"
+    pub port: u32,
+    pub value: String,
+}
+
+impl From<&ExpectedChange> for StagedChange {
+    fn from(change: &ExpectedChange) -> Self {
+        let (kind, port, value) = match change {
+            ExpectedChange::Route { output, input } => ("route", *output, (input + 1).to_string()),
+            ExpectedChange::InputLabel { input, label } => ("input-label", *input, label.clone()),
+            ExpectedChange::OutputLabel { output, label } => {
+                ("output-label", *output, label.clone())
+            }
+        };
+        Self {
+            kind: kind.to_string(),
+            port: port + 1,
+            value,
+        }
+    }
+}
+
+impl TryFrom<&StagedChange> for ExpectedChange {
+    type Error = anyhow::Error;
+
+    fn try_from(change: &StagedChange) -> anyhow::Result<Self> {
+        let port = change.port.checked_sub(1).context("ports start at 1")?;
+        Ok(match change.kind.as_str() {
+            "route" => Self::Route {
+                output: port,
+                input: change
+                    .value
+                    .parse::<u32>()?
+                    .checked_sub(1)
+                    .context("inputs start at 1")?,
+            },
+            "input-label" => Self::InputLabel {
+                input: port,
+                label: change.value.clone(),
+            },
+            "output-label" => Self::OutputLabel {
+                output: port,
+                label: change.value.clone(),
+            },
+            kind => bail!("unknown change kind {kind:?}"),
+        })
+    }
+}
+
+// Changes from one bulk operation awaiting an explicit apply
+#[derive(Debug)]
+pub struct StagedChanges {
+    // Operation that produced the changes, e.g. "import-csv"
+    pub source: String,
+    pub changes: Vec<ExpectedChange>,
+    pub deadline: Instant,
+}
+
+impl StagedChanges {
+    pub fn new(source: &str, changes: Vec<ExpectedChange>, timeout: Duration) -> Self {
+        Self {
+            source: source.to_string(),
+            changes,
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    pub fn preview(&self) -> Vec<StagedChange> {
+        self.changes.iter().map(StagedChange::from).collect()
+    }
+}
+
+// Queue changes as one ordinary command, so they are validated and confirmed like any other
+// but written to the device in as few blocks as possible.
+// Sent from a separate task because the caller may be the command channel's consumer.
+pub fn queue_changes(command_tx: &mpsc::Sender<CommandRequest>, changes: Vec<ExpectedChange>) {
+    if !changes.is_empty() {
+        queue_changes_for(command_tx, changes, None);
+    }
+}
+
+// Like queue_changes, for an action that hands its feedback over to the write: it is confirmed
+// once the device has echoed every change, and right away if there is nothing to write
+pub fn queue_changes_for(
+    command_tx: &mpsc::Sender<CommandRequest>,
+    changes: Vec<ExpectedChange>,
+    invocation: Option<ActionInvocation>,
+) {
+    let tx = command_tx.clone();
+    tokio::spawn(async move {
+        let command = VideohubCommand::WriteChanges { changes };
+        if let Err(e) = tx
+            .send(CommandRequest::on_behalf_of(command, invocation))
+            .await
+        {
+            log::error!("Failed to queue bulk changes: {e}");
+        }
+    });
+}
+
+// Hold bulk changes for review if staging is enabled, otherwise queue them right away.
+// New staged changes replace any that were not applied yet.
+pub async fn stage_or_queue(
+    source: &str,
+    changes: Vec<ExpectedChange>,
+    staging_timeout: Option<Duration>,
+    staged: &mut Option<StagedChanges>,
+    command_tx: &mpsc::Sender<CommandRequest>,
+    event_tx: &EventSender,
+) {
+    let Some(timeout) = staging_timeout else {
+        queue_changes(command_tx, changes);
+        return;
+    };
+
+    if let Some(replaced) = staged.take() {
+        send_staged_changes(event_tx, &replaced, "discarded").await;
+    }
+    let changes = StagedChanges::new(source, changes, timeout);
+    log::info!(
+        "Staged {} changes from {source}, waiting for apply-staged-changes",
+        changes.changes.len()
+    );
+    send_staged_changes(event_tx, &changes, "staged").await;
+    *staged = Some(changes);
+}
+
+// Report what happened to a set of staged changes
+pub async fn send_staged_changes(
+    event_tx: &EventSender,
+    staged: &StagedChanges,
+    status: &'static str,
+) {
+    let expires_in_ms = (status == "staged").then(|| {
+        staged
+            .deadline
+            .saturating_duration_since(Instant::now())
+            .as_millis() as u64
+    });
+    if let Err(e) = event_tx
+        .send(VideohubEvent::StagedChanges {
+            source: staged.source.clone(),
+            status,
+            changes: staged.preview(),
+            expires_in_ms,
+        })
+        .await
+    {
+        log::error!("Failed to send staged changes event: {e}");
+    }
+}
+
+// Applying writes the staged changes, so it acts for the owner named in the payload
+pub fn apply_request(data: ApplyStagedChangesAction) -> CommandRequest {
+    CommandRequest::from_action("apply-staged-changes", VideohubCommand::ApplyStagedChanges)
+        .owned_by(data.owner)
+}
+
+pub fn discard_request(_data: DiscardStagedChangesAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "discard-staged-changes",
+        VideohubCommand::DiscardStagedChanges,
+    )
+}
+
+pub async fn add_actions(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    add_command_action(
+        target,
+        display_names,
+        "Apply Staged Changes",
+        "apply-staged-changes",
+        command_tx,
+        apply_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Discard Staged Changes",
+        "discard-staged-changes",
+        command_tx,
+        discard_request,
+    )
+    .await;
+}
+
+// Run a staging command on the device task. Staged changes the caller may not write are left
+// staged for a caller allowed to apply them.
+pub async fn execute(
+    staged: &mut Option<StagedChanges>,
+    command: &VideohubCommand,
+    scope: &mut CommandScope<'_>,
+) -> anyhow::Result<()> {
+    match command {
+        VideohubCommand::ApplyStagedChanges => {
+            let Some(changes) = staged.take() else {
+                bail!("no staged changes to apply");
+            };
+            if let Err(e) = scope.check_changes(&changes.changes).await {
+                *staged = Some(changes);
+                return Err(e);
+            }
+            log::info!(
+                "Applying {} staged changes from {}",
+                changes.changes.len(),
+                changes.source
+            );
+            send_staged_changes(scope.event_tx, &changes, "applied").await;
+            scope.write_changes(changes.changes);
+        }
+        VideohubCommand::DiscardStagedChanges => {
+            if let Some(changes) = staged.take() {
+                log::info!(
+                    "Discarded {} staged changes from {}",
+                    changes.changes.len(),
+                    changes.source
+                );
+                send_staged_changes(scope.event_tx, &changes, "discarded").await;
+            }
+        }
+        command => bail!("not a staging command: {command:?}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::VideohubState;
+    use crate::ownership::Ownership;
+
+    #[tokio::test]
+    async fn leaves_changes_staged_for_an_owner_allowed_to_apply_them() {
+        let path = std::env::temp_dir().join(format!("staging-owners-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"owners": {"news": [2]}}"#).unwrap();
+        let ownership = Ownership::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let state = VideohubState::default();
+        let (command_tx, mut command_rx) = mpsc::channel(1);
+        let (event_tx, _event_rx) = mpsc::channel(8);
+        let event_tx = EventSender(event_tx);
+        let route = ExpectedChange::Route {
+            output: 1,
+            input: 4,
+        };
+        let mut staged = Some(StagedChanges::new(
+            "import-csv",
+            vec![route.clone()],
+            DEFAULT_STAGED_CHANGES_TIMEOUT,
+        ));
+
+        for (owner, applied) in [(None, false), (Some("news"), true)] {
+            let request = apply_request(ApplyStagedChangesAction {
+                owner: owner.map(str::to_string),
+            });
+            let mut invocation = request.invocation;
+            let mut scope = CommandScope {
+                source: request.source,
+                invocation: &mut invocation,
+                state: &state,
+                ownership: Some(&ownership),
+                command_tx: &command_tx,
+                event_tx: &event_tx,
+            };
+            let result = execute(&mut staged, &request.command, &mut scope).await;
+            assert_eq!(result.is_ok(), applied);
+            assert_eq!(staged.is_none(), applied);
+        }
+
+        // Written for the apply action, which is confirmed once the device echoes the route
+        let write = command_rx.recv().await.unwrap();
+        assert!(
+            matches!(write.command, VideohubCommand::WriteChanges { changes } if changes == vec![route])
+        );
+        assert_eq!(write.invocation.unwrap().action, "apply-staged-changes");
+    }
+}
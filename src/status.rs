@@ -0,0 +1,206 @@
+//! Single-screen console summary for engineers watching the executor during an event
+//! (`--status-line`), drawn on stderr and kept apart from the logs on stdout
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{IsTerminal, Write as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, interval};
+
+use crate::observer::VideohubObserver;
+use crate::service::VideohubEvent;
+
+// How often the screen is redrawn
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+// Number of route changes shown
+const RECENT_ROUTES: usize = 5;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Default)]
+struct StatusView {
+    device_connected: bool,
+    device_unstable: bool,
+    device: Option<String>,
+    rship_endpoint: Option<String>,
+    // Only known once the redundancy election has decided
+    leader: Option<bool>,
+    profile: Option<String>,
+    watchdog_alarm: bool,
+    performance_degraded: bool,
+    state_divergence: bool,
+    // "HH:MM:SS  Output 3 <- Input 5 (CAM 5)", most recent first
+    recent_routes: VecDeque<String>,
+}
+
+// Observer keeping the summary up to date, and the task drawing it
+pub struct StatusLine {
+    view: Mutex<StatusView>,
+    rship_connected: Arc<AtomicBool>,
+    color: bool,
+}
+
+impl StatusLine {
+    pub fn new(rship_connected: Arc<AtomicBool>, rship_endpoint: String) -> Self {
+        Self {
+            view: Mutex::new(StatusView {
+                rship_endpoint: Some(rship_endpoint),
+                ..StatusView::default()
+            }),
+            rship_connected,
+            color: std::io::stderr().is_terminal(),
+        }
+    }
+
+    // Redraw the screen until the process exits
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut redraw_tick = interval(REDRAW_INTERVAL);
+            loop {
+                redraw_tick.tick().await;
+                let screen = self.render();
+                let mut stderr = std::io::stderr().lock();
+                // Home the cursor and clear the screen, then draw
+                let _ = write!(stderr, "\x1b[H\x1b[J{screen}");
+                let _ = stderr.flush();
+            }
+        });
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn render(&self) -> String {
+        let view = self
+            .view
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut screen = String::new();
+
+        let device = match (view.device_connected, view.device_unstable) {
+            (true, false) => self.paint(GREEN, "connected"),
+            (true, true) => self.paint(YELLOW, "unstable"),
+            (false, _) => self.paint(RED, "offline"),
+        };
+        let _ = writeln!(
+            screen,
+            "{} {device}  {}",
+            self.paint(BOLD, "Videohub"),
+            view.device.as_deref().unwrap_or("")
+        );
+
+        let rship = if self.rship_connected.load(Ordering::Relaxed) {
+            self.paint(GREEN, "connected")
+        } else {
+            self.paint(RED, "offline")
+        };
+        let _ = writeln!(
+            screen,
+            "{}    {rship}  {}",
+            self.paint(BOLD, "rship"),
+            view.rship_endpoint.as_deref().unwrap_or("")
+        );
+
+        if let Some(leader) = view.leader {
+            let role = if leader {
+                self.paint(GREEN, "leader")
+            } else {
+                self.paint(YELLOW, "standby")
+            };
+            let _ = writeln!(screen, "{}     {role}", self.paint(BOLD, "Role"));
+        }
+
+        if let Some(profile) = &view.profile {
+            let _ = writeln!(screen, "{}  {profile}", self.paint(BOLD, "Profile"));
+        }
+
+        let alarms: Vec<&str> = [
+            (view.watchdog_alarm, "watchdog"),
+            (view.performance_degraded, "performance degraded"),
+            (view.state_divergence, "state divergence"),
+        ]
+        .into_iter()
+        .filter_map(|(raised, name)| raised.then_some(name))
+        .collect();
+        let alarms = if alarms.is_empty() {
+            self.paint(GREEN, "none")
+        } else {
+            self.paint(RED, &alarms.join(", "))
+        };
+        let _ = writeln!(screen, "{}   {alarms}", self.paint(BOLD, "Alarms"));
+
+        let _ = writeln!(screen, "\n{}", self.paint(BOLD, "Recent routes"));
+        if view.recent_routes.is_empty() {
+            let _ = writeln!(screen, "  none yet");
+        }
+        for route in &view.recent_routes {
+            let _ = writeln!(screen, "  {route}");
+        }
+        screen
+    }
+}
+
+impl VideohubObserver for StatusLine {
+    fn on_event(&self, event: &VideohubEvent) {
+        let mut view = self
+            .view
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match event {
+            VideohubEvent::DeviceStatus {
+                connected,
+                unstable,
+                model_name,
+                video_inputs,
+                video_outputs,
+            } => {
+                view.device_connected = *connected;
+                view.device_unstable = *unstable;
+                if let Some(model_name) = model_name {
+                    view.device = Some(format!(
+                        "{model_name} ({} in / {} out)",
+                        video_inputs.unwrap_or(0),
+                        video_outputs.unwrap_or(0)
+                    ));
+                }
+            }
+            VideohubEvent::Route {
+                output,
+                input,
+                input_label,
+                ..
+            } => {
+                let label = input_label
+                    .as_deref()
+                    .map(|label| format!(" ({label})"))
+                    .unwrap_or_default();
+                view.recent_routes.push_front(format!(
+                    "{}  Output {} <- Input {}{label}",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    output + 1,
+                    input + 1
+                ));
+                view.recent_routes.truncate(RECENT_ROUTES);
+            }
+            VideohubEvent::RshipEndpoint { url, .. } => view.rship_endpoint = Some(url.clone()),
+            VideohubEvent::LeadershipChanged { leader, .. } => view.leader = Some(*leader),
+            VideohubEvent::Profile { profile, .. } => view.profile = Some(profile.clone()),
+            VideohubEvent::Watchdog { alarm, .. } => view.watchdog_alarm = *alarm,
+            VideohubEvent::PerformanceDegraded { degraded, .. } => {
+                view.performance_degraded = *degraded
+            }
+            VideohubEvent::StateDivergence { alarm, .. } => view.state_divergence = *alarm,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Compact aggregate of the whole matrix, sized for dashboard tiles rather than full state
+
+use std::collections::VecDeque;
+use tokio::time::Duration;
+
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+use crate::service::VideohubEvent;
+
+// How often the summary is pulsed when nothing changes
+pub const DEFAULT_MATRIX_SUMMARY_INTERVAL: Duration = Duration::from_secs(30);
+// Number of most recently changed outputs included in the summary
+const RECENT_OUTPUTS: usize = 5;
+
+// Outputs whose route, lock or take mode changed, most recent first, and what was last pulsed
+#[derive(Debug, Default)]
+pub struct MatrixSummary {
+    recent_outputs: VecDeque<u32>,
+    last_change: Option<EventTime>,
+    changed: bool,
+    // Locked and take mode output counts of the last summary
+    emitted_counts: Option<(usize, usize)>,
+}
+
+impl MatrixSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Note a change on an output, moving it to the front of the recent outputs
+    pub fn record_change(&mut self, output: u32) {
+        self.recent_outputs.retain(|&recent| recent != output);
+        self.recent_outputs.push_front(output);
+        self.recent_outputs.truncate(RECENT_OUTPUTS);
+        self.last_change = Some(EventTime::now());
+        self.changed = true;
+    }
+
+    // The summary, if a change was recorded or the counts moved since the last one
+    pub fn changes(&mut self, state: &VideohubState) -> Option<VideohubEvent> {
+        (self.changed || self.emitted_counts != Some(counts(state))).then(|| self.event(state))
+    }
+
+    // The summary regardless of changes, e.g. for the periodic pulse
+    pub fn event(&mut self, state: &VideohubState) -> VideohubEvent {
+        let (locked_outputs, take_mode_outputs) = counts(state);
+        self.changed = false;
+        self.emitted_counts = Some((locked_outputs, take_mode_outputs));
+        VideohubEvent::MatrixSummary {
+            locked_outputs,
+            take_mode_outputs,
+            last_change: self.last_change.clone(),
+            recent_outputs: self.recent_outputs.iter().copied().collect(),
+        }
+    }
+}
+
+// Outputs locked by any client, and outputs with take mode enabled
+fn counts(state: &VideohubState) -> (usize, usize) {
+    let locked = state
+        .output_locks
+        .iter()
+        .filter(|(output, locked)| **locked || state.owned_locks.contains(output))
+        .count();
+    let take_mode = state.take_mode.values().filter(|enabled| **enabled).count();
+    (locked, take_mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recently_changed_outputs_first() {
+        let mut summary = MatrixSummary::new();
+        for output in [1, 2, 3, 4, 5, 6, 2] {
+            summary.record_change(output);
+        }
+        let VideohubEvent::MatrixSummary { recent_outputs, .. } =
+            summary.event(&VideohubState::default())
+        else {
+            panic!("not a matrix summary");
+        };
+        assert_eq!(recent_outputs, vec![2, 6, 5, 4, 3]);
+    }
+
+    #[test]
+    fn reports_changes_once() {
+        let mut summary = MatrixSummary::new();
+        let mut state = VideohubState::default();
+        assert!(summary.changes(&state).is_some());
+        assert!(summary.changes(&state).is_none());
+
+        state.take_mode.insert(3, true);
+        assert!(summary.changes(&state).is_some());
+        assert!(summary.changes(&state).is_none());
+
+        summary.record_change(3);
+        assert!(summary.changes(&state).is_some());
+        assert!(summary.changes(&state).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! Test-signal sweeps for load-in: a test generator input is routed to one output after another,
+//! so the crew can check every destination sees bars. Each output gets its original input back
+//! once its dwell is over, before the sweep moves on.
+
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::service::{CommandRequest, EventSender, VideohubCommand, VideohubEvent};
+
+// A sweep of one input across outputs (ports are 0-indexed)
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub input: u32,
+    // Each output with the input it is restored to, if the device reported one
+    pub outputs: Vec<(u32, Option<u32>)>,
+    pub dwell: Duration,
+}
+
+impl Sweep {
+    // Remember the current route of every output; outputs listed twice are swept once
+    pub fn new(input: u32, outputs: &[u32], routes: &HashMap<u32, u32>, dwell: Duration) -> Self {
+        let mut swept = Vec::with_capacity(outputs.len());
+        for &output in outputs {
+            if !swept.iter().any(|(swept, _)| *swept == output) {
+                swept.push((output, routes.get(&output).copied()));
+            }
+        }
+        Self {
+            input,
+            outputs: swept,
+            dwell,
+        }
+    }
+
+    fn route(&self, step: usize) -> VideohubCommand {
+        VideohubCommand::Route {
+            output: self.outputs[step].0,
+            input: self.input,
+        }
+    }
+
+    // Give an output its route back; nothing to do if it had no route or already showed the input
+    fn restore(&self, step: usize) -> Option<VideohubCommand> {
+        let (output, original) = self.outputs[step];
+        original
+            .filter(|original| *original != self.input)
+            .map(|input| VideohubCommand::Route { output, input })
+    }
+}
+
+// The sweep running, if any. Only one runs at a time, so a sweep never takes another sweep's
+// test signal for an output's original route.
+pub struct Sweeper {
+    running: Option<CancellationToken>,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+}
+
+impl Sweeper {
+    pub fn new(command_tx: mpsc::Sender<CommandRequest>, event_tx: EventSender) -> Self {
+        Self {
+            running: None,
+            command_tx,
+            event_tx,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+            .as_ref()
+            .is_some_and(|stop| !stop.is_cancelled())
+    }
+
+    pub fn start(&mut self, sweep: Sweep) {
+        self.stop();
+        log::info!(
+            "Sweeping input {} across {} outputs",
+            sweep.input + 1,
+            sweep.outputs.len()
+        );
+        self.running = Some(spawn_sweep(
+            sweep,
+            self.command_tx.clone(),
+            self.event_tx.clone(),
+        ));
+    }
+
+    // Stop the sweep after restoring the output it is on; returns whether one was running
+    pub fn stop(&mut self) -> bool {
+        let Some(stop) = self.running.take() else {
+            return false;
+        };
+        let was_running = !stop.is_cancelled();
+        stop.cancel();
+        was_running
+    }
+}
+
+// Run a sweep on its own task, routing through the command channel like a sequence.
+// Cancel the returned token to stop it; the final progress event is sent either way.
+fn spawn_sweep(
+    sweep: Sweep,
+    command_tx: mpsc::Sender<CommandRequest>,
+    event_tx: EventSender,
+) -> CancellationToken {
+    let stop = CancellationToken::new();
+    let stopped = stop.clone();
+
+    tokio::spawn(async move {
+        let mut step = 0;
+        loop {
+            if command_tx
+                .send(CommandRequest::internal(sweep.route(step)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+            send_progress(&event_tx, &sweep, step, true).await;
+
+            let cancelled = tokio::select! {
+                _ = stopped.cancelled() => true,
+                _ = tokio::time::sleep(sweep.dwell) => false,
+            };
+            if let Some(restore) = sweep.restore(step)
+                && command_tx
+                    .send(CommandRequest::internal(restore))
+                    .await
+                    .is_err()
+            {
+                break;
+            }
+            if cancelled || step + 1 == sweep.outputs.len() {
+                break;
+            }
+            step += 1;
+        }
+        send_progress(&event_tx, &sweep, step, false).await;
+        // Mark finished sweeps so a later stop does not report them as running
+        stopped.cancel();
+    });
+
+    stop
+}
+
+async fn send_progress(event_tx: &EventSender, sweep: &Sweep, step: usize, running: bool) {
+    if let Err(e) = event_tx
+        .send(VideohubEvent::SweepProgress {
+            input: sweep.input,
+            output: sweep.outputs[step].0,
+            step: step as u32,
+            total: sweep.outputs.len() as u32,
+            running,
+        })
+        .await
+    {
+        log::error!("Failed to send sweep progress event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_each_output_to_its_original_route() {
+        let routes = HashMap::from([(0, 4), (1, 7)]);
+        let sweep = Sweep::new(7, &[0, 1, 2, 0], &routes, Duration::from_secs(2));
+        assert_eq!(sweep.outputs, vec![(0, Some(4)), (1, Some(7)), (2, None)]);
+
+        assert!(matches!(
+            sweep.route(2),
+            VideohubCommand::Route {
+                output: 2,
+                input: 7
+            }
+        ));
+        assert!(matches!(
+            sweep.restore(0),
+            Some(VideohubCommand::Route {
+                output: 0,
+                input: 4
+            })
+        ));
+        // Already showing the test input, or never reported
+        assert!(sweep.restore(1).is_none());
+        assert!(sweep.restore(2).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+//! Take-bar workflow as in broadcast control panels: routes preset with `preset-route` wait on
+//! `pending-routes` until `take` writes them all at once, or `clear-pending` drops them. Unlike
+//! the device's take mode, which the router applies per output, pending routes are held by the
+//! executor and can span any number of outputs.
+
+use anyhow::{Result, bail};
+use rship_sdk::TargetProxy;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+
+use crate::actions::{ClearPendingAction, PresetRouteAction, TakeAction, add_command_action};
+use crate::client::VideohubState;
+use crate::emitters::PendingRoute;
+use crate::pending::ExpectedChange;
+use crate::service::{CommandRequest, CommandScope, VideohubCommand, VideohubEvent};
+use crate::translations::DisplayNames;
+
+// Preset routes, output -> input (0-indexed); presetting an output again replaces its route
+#[derive(Debug, Default)]
+pub struct PendingRoutes {
+    routes: BTreeMap<u32, u32>,
+}
+
+impl PendingRoutes {
+    pub fn preset(&mut self, output: u32, input: u32) {
+        self.routes.insert(output, input);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    // Input preset for the output, if any
+    pub fn input(&self, output: u32) -> Option<u32> {
+        self.routes.get(&output).copied()
+    }
+
+    // Drop the pending routes, returning them
+    pub fn clear(&mut self) -> BTreeMap<u32, u32> {
+        std::mem::take(&mut self.routes)
+    }
+
+    // The pending routes as the changes to write, leaving none pending
+    pub fn take(&mut self) -> (BTreeMap<u32, u32>, Vec<ExpectedChange>) {
+        let routes = self.clear();
+        let changes = routes
+            .iter()
+            .map(|(&output, &input)| ExpectedChange::Route { output, input })
+            .collect();
+        (routes, changes)
+    }
+
+    pub fn event(&self, state: &VideohubState) -> VideohubEvent {
+        event(&self.routes, "pending", state)
+    }
+}
+
+// Pending routes (1-indexed, with the input labels) and what became of them: "pending", "taken"
+// or "cleared"
+pub fn event(
+    routes: &BTreeMap<u32, u32>,
+    status: &'static str,
+    state: &VideohubState,
+) -> VideohubEvent {
+    VideohubEvent::PendingRoutes {
+        routes: routes
+            .iter()
+            .map(|(&output, &input)| PendingRoute {
+                output: output + 1,
+                input: input + 1,
+                input_label: state.input_labels.get(&input).cloned(),
+            })
+            .collect(),
+        status,
+    }
+}
+
+// A preset route is checked against the output owners in the pipeline like a route, so it acts
+// for the owner named in the payload
+pub fn preset_request(data: PresetRouteAction) -> CommandRequest {
+    CommandRequest::from_action("preset-route", data.command()).owned_by(data.owner)
+}
+
+pub fn take_request(_data: TakeAction) -> CommandRequest {
+    CommandRequest::from_action("take", VideohubCommand::Take)
+}
+
+pub fn clear_request(_data: ClearPendingAction) -> CommandRequest {
+    CommandRequest::from_action("clear-pending", VideohubCommand::ClearPending)
+}
+
+pub async fn add_actions(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    add_command_action(
+        target,
+        display_names,
+        "Preset Route",
+        "preset-route",
+        command_tx,
+        preset_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Take",
+        "take",
+        command_tx,
+        take_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Clear Pending",
+        "clear-pending",
+        command_tx,
+        clear_request,
+    )
+    .await;
+}
+
+// Run a take-bar command on the device task. A take is confirmed once the device has echoed
+// every route it wrote.
+pub async fn execute(
+    pending: &mut PendingRoutes,
+    command: &VideohubCommand,
+    scope: &mut CommandScope<'_>,
+) -> Result<()> {
+    match command {
+        VideohubCommand::PresetRoute { output, input } => {
+            pending.preset(*output, *input);
+            send_pending_routes(pending.event(scope.state), scope).await;
+        }
+        VideohubCommand::Take => {
+            if pending.is_empty() {
+                bail!("no routes are pending");
+            }
+            let (routes, changes) = pending.take();
+            log::info!("Taking {} pending routes", routes.len());
+            send_pending_routes(event(&routes, "taken", scope.state), scope).await;
+            scope.write_changes(changes);
+        }
+        VideohubCommand::ClearPending => {
+            let routes = pending.clear();
+            if !routes.is_empty() {
+                log::info!("Cleared {} pending routes", routes.len());
+                send_pending_routes(event(&routes, "cleared", scope.state), scope).await;
+            }
+        }
+        command => bail!("not a take command: {command:?}"),
+    }
+    Ok(())
+}
+
+async fn send_pending_routes(event: VideohubEvent, scope: &CommandScope<'_>) {
+    if let Err(e) = scope.event_tx.send(event).await {
+        log::error!("Failed to send pending routes event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::EventSender;
+
+    #[test]
+    fn takes_all_preset_routes_at_once() {
+        let mut pending = PendingRoutes::default();
+        pending.preset(0, 3);
+        pending.preset(1, 4);
+        pending.preset(0, 5);
+        let mut state = VideohubState::default();
+        state.input_labels.insert(5, "CAM 6".to_string());
+
+        let VideohubEvent::PendingRoutes { routes, status } = pending.event(&state) else {
+            panic!("not a pending routes event");
+        };
+        assert_eq!(status, "pending");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].input, 6);
+        assert_eq!(routes[0].input_label.as_deref(), Some("CAM 6"));
+
+        let (taken, changes) = pending.take();
+        assert_eq!(taken, BTreeMap::from([(0, 5), (1, 4)]));
+        assert_eq!(
+            changes,
+            vec![
+                ExpectedChange::Route {
+                    output: 0,
+                    input: 5
+                },
+                ExpectedChange::Route {
+                    output: 1,
+                    input: 4
+                },
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn takes_preset_routes_as_zero_indexed_changes() {
+        let state = VideohubState::default();
+        let (command_tx, mut command_rx) = tokio::sync::mpsc::channel(1);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(4);
+        let event_tx = EventSender(event_tx);
+        let mut pending = PendingRoutes::default();
+
+        // Nothing to take before a route is preset
+        let take = take_request(TakeAction {});
+        let mut invocation = take.invocation.clone();
+        let mut scope = CommandScope {
+            source: take.source,
+            invocation: &mut invocation,
+            state: &state,
+            ownership: None,
+            command_tx: &command_tx,
+            event_tx: &event_tx,
+        };
+        let e = execute(&mut pending, &take.command, &mut scope)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "no routes are pending");
+
+        let preset = preset_request(PresetRouteAction {
+            output: 3,
+            input: 1,
+            owner: Some("news".into()),
+        });
+        assert_eq!(
+            preset.invocation.as_ref().unwrap().owner.as_deref(),
+            Some("news")
+        );
+        execute(&mut pending, &preset.command, &mut scope)
+            .await
+            .unwrap();
+        assert!(matches!(
+            event_rx.recv().await,
+            Some((
+                _,
+                VideohubEvent::PendingRoutes {
+                    status: "pending",
+                    ..
+                }
+            ))
+        ));
+
+        execute(&mut pending, &take.command, &mut scope)
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+        let request = command_rx.recv().await.unwrap();
+        assert_eq!(request.invocation.unwrap().action, "take");
+        let VideohubCommand::WriteChanges { changes } = request.command else {
+            panic!("not a write");
+        };
+        assert_eq!(
+            changes,
+            vec![ExpectedChange::Route {
+                output: 2,
+                input: 0
+            }]
+        );
+    }
+}
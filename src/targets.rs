@@ -0,0 +1,101 @@
+//! How output subtargets are presented in rship: category, name prefix and creation order
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// Category of output subtargets without a configured one
+const DEFAULT_CATEGORY: &str = "video";
+
+// Presentation of one output subtarget, e.g. {"category": "record", "prefix": "🔴", "order": 1}
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OutputTarget {
+    pub category: Option<String>,
+    // Prepended to the target name ("🔴 Output 3")
+    pub prefix: Option<String>,
+    // Outputs with an order are created first, lowest first; the rest follow by number
+    pub order: Option<i64>,
+}
+
+// On-disk format; output numbers are 1-indexed like the rship actions
+#[derive(Debug, Default, Deserialize)]
+struct OutputTargetsFile {
+    #[serde(default)]
+    outputs: BTreeMap<u32, OutputTarget>,
+}
+
+// Output subtarget presentation (outputs are 0-indexed in memory)
+#[derive(Debug, Clone, Default)]
+pub struct OutputTargets {
+    outputs: HashMap<u32, OutputTarget>,
+}
+
+impl OutputTargets {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read output targets file {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Failed to parse output targets file {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let file: OutputTargetsFile = serde_json::from_str(contents)?;
+        Ok(Self {
+            outputs: file
+                .outputs
+                .into_iter()
+                .filter(|(output, _)| *output > 0)
+                .map(|(output, target)| (output - 1, target))
+                .collect(),
+        })
+    }
+
+    pub fn category(&self, output: u32) -> String {
+        self.outputs
+            .get(&output)
+            .and_then(|target| target.category.clone())
+            .unwrap_or_else(|| DEFAULT_CATEGORY.to_string())
+    }
+
+    // Target name, numbered from 1
+    pub fn name(&self, output: u32) -> String {
+        match self
+            .outputs
+            .get(&output)
+            .and_then(|target| target.prefix.as_deref())
+        {
+            Some(prefix) => format!("{prefix} Output {}", output + 1),
+            None => format!("Output {}", output + 1),
+        }
+    }
+
+    // The device's outputs in the order their subtargets are created
+    pub fn ordered(&self, num_outputs: u32) -> Vec<u32> {
+        let mut outputs: Vec<u32> = (0..num_outputs).collect();
+        outputs.sort_by_key(|output| {
+            let order = self.outputs.get(output).and_then(|target| target.order);
+            (order.is_none(), order, *output)
+        });
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_configured_outputs_first() {
+        let targets = OutputTargets::parse(
+            r#"{"outputs": {"4": {"order": 2, "prefix": "REC"}, "2": {"order": 1, "category": "monitor"}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(targets.ordered(5), vec![1, 3, 0, 2, 4]);
+        assert_eq!(targets.name(3), "REC Output 4");
+        assert_eq!(targets.name(0), "Output 1");
+        assert_eq!(targets.category(1), "monitor");
+        assert_eq!(targets.category(3), "video");
+    }
+}
@@ -0,0 +1,441 @@
+//! Routing and label changes collected over several actions, e.g. while an operator builds a
+//! change interactively, and written as one salvo on commit. A commit is verified against the
+//! state the device echoes: if any change did not take, the ones that did are reverted, so the
+//! salvo applies completely or not at all.
+
+use anyhow::{Result, bail};
+use rship_sdk::TargetProxy;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::actions::{
+    AddToTransactionAction, BeginTransactionAction, CommitTransactionAction,
+    RollbackTransactionAction, add_command_action,
+};
+use crate::client::VideohubState;
+use crate::pending::{ExpectedChange, PENDING_CHANGE_TIMEOUT};
+use crate::service::{CommandRequest, CommandScope, EventSender, VideohubCommand, VideohubEvent};
+use crate::staging::StagedChange;
+use crate::translations::DisplayNames;
+
+// How long an open transaction waits for its commit or rollback
+pub const DEFAULT_TRANSACTION_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+struct OpenTransaction {
+    changes: Vec<ExpectedChange>,
+    deadline: Instant,
+}
+
+// A salvo that was written and waits for the device to echo all of its changes
+#[derive(Debug)]
+struct CommittedTransaction {
+    id: String,
+    changes: Vec<ExpectedChange>,
+    // State of each changed port before the commit, for reverting it
+    previous: Vec<Option<ExpectedChange>>,
+    deadline: Instant,
+}
+
+// What became of a committed transaction
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Committed {
+        id: String,
+        changes: usize,
+    },
+    // The changes that did not take, and what restores the ports whose change did
+    Failed {
+        id: String,
+        changes: usize,
+        failed: Vec<ExpectedChange>,
+        revert: Vec<ExpectedChange>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Transactions {
+    timeout: Duration,
+    open: HashMap<String, OpenTransaction>,
+    committed: Vec<CommittedTransaction>,
+}
+
+impl Transactions {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            open: HashMap::new(),
+            committed: Vec::new(),
+        }
+    }
+
+    pub fn begin(&mut self, id: &str) -> Result<()> {
+        if self.open.contains_key(id) {
+            bail!("transaction {id} is already open");
+        }
+        self.open.insert(
+            id.to_string(),
+            OpenTransaction {
+                changes: Vec::new(),
+                deadline: Instant::now() + self.timeout,
+            },
+        );
+        Ok(())
+    }
+
+    // Add a validated command to an open transaction; returns the number of changes it holds.
+    // A later change to the same port replaces the earlier one.
+    pub fn add(&mut self, id: &str, command: &VideohubCommand) -> Result<usize> {
+        let Some(transaction) = self.open.get_mut(id) else {
+            bail!("no open transaction {id}");
+        };
+        let changes = match command {
+            VideohubCommand::WriteChanges { changes } => changes.clone(),
+            command => match command.expected_change() {
+                Some(change) => vec![change],
+                None => bail!("only routes and labels can be part of a transaction"),
+            },
+        };
+        for change in changes {
+            transaction
+                .changes
+                .retain(|existing| !existing.same_port(&change));
+            transaction.changes.push(change);
+        }
+        Ok(transaction.changes.len())
+    }
+
+    // Close a transaction for writing; returns its changes, to be written as one salvo
+    pub fn commit(&mut self, id: &str, state: &VideohubState) -> Result<Vec<ExpectedChange>> {
+        let Some(transaction) = self.open.remove(id) else {
+            bail!("no open transaction {id}");
+        };
+        let previous = transaction
+            .changes
+            .iter()
+            .map(|change| change.current_state(state))
+            .collect();
+        self.committed.push(CommittedTransaction {
+            id: id.to_string(),
+            changes: transaction.changes.clone(),
+            previous,
+            deadline: Instant::now() + PENDING_CHANGE_TIMEOUT,
+        });
+        Ok(transaction.changes)
+    }
+
+    // Discard an open transaction; returns the number of changes it held
+    pub fn rollback(&mut self, id: &str) -> Result<usize> {
+        match self.open.remove(id) {
+            Some(transaction) => Ok(transaction.changes.len()),
+            None => bail!("no open transaction {id}"),
+        }
+    }
+
+    // Remove open transactions that were neither committed nor rolled back in time
+    pub fn take_expired(&mut self) -> Vec<(String, usize)> {
+        let now = Instant::now();
+        let expired: Vec<String> = self
+            .open
+            .iter()
+            .filter(|(_, transaction)| transaction.deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| {
+                let transaction = self.open.remove(&id)?;
+                Some((id, transaction.changes.len()))
+            })
+            .collect()
+    }
+
+    // Compare committed transactions with the state the device reported. A transaction is
+    // committed once every change shows; it fails if some still don't when its time is up.
+    pub fn verify(&mut self, state: &VideohubState) -> Vec<Outcome> {
+        let now = Instant::now();
+        let mut outcomes = Vec::new();
+        self.committed.retain(|transaction| {
+            let (applied, failed): (Vec<_>, Vec<_>) = transaction
+                .changes
+                .iter()
+                .zip(&transaction.previous)
+                .partition(|(change, _)| change.current_state(state).as_ref() == Some(change));
+            if failed.is_empty() {
+                outcomes.push(Outcome::Committed {
+                    id: transaction.id.clone(),
+                    changes: transaction.changes.len(),
+                });
+                return false;
+            }
+            if transaction.deadline > now {
+                return true;
+            }
+            outcomes.push(Outcome::Failed {
+                id: transaction.id.clone(),
+                changes: transaction.changes.len(),
+                failed: failed
+                    .into_iter()
+                    .map(|(change, _)| change.clone())
+                    .collect(),
+                revert: applied
+                    .into_iter()
+                    .filter_map(|(change, previous)| previous.clone().filter(|p| p != change))
+                    .collect(),
+            });
+            false
+        });
+        outcomes
+    }
+}
+
+pub async fn send_status(
+    event_tx: &EventSender,
+    transaction: &str,
+    status: &'static str,
+    changes: usize,
+    failed: &[ExpectedChange],
+) {
+    if let Err(e) = event_tx
+        .send(VideohubEvent::TransactionStatus {
+            transaction: transaction.to_string(),
+            status,
+            changes,
+            failed: failed.iter().map(StagedChange::from).collect(),
+        })
+        .await
+    {
+        log::error!("Failed to send transaction status event: {e}");
+    }
+}
+
+pub fn begin_request(data: BeginTransactionAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "begin-transaction",
+        VideohubCommand::BeginTransaction {
+            id: data.transaction,
+        },
+    )
+}
+
+// The added action's command is checked in the pipeline for the owner named in its payload
+pub fn add_request(data: AddToTransactionAction) -> CommandRequest {
+    let (command, owner) = data.command.into_command();
+    CommandRequest::from_action(
+        "add-to-transaction",
+        VideohubCommand::AddToTransaction {
+            id: data.transaction,
+            command: Box::new(command),
+        },
+    )
+    .owned_by(owner)
+}
+
+pub fn commit_request(data: CommitTransactionAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "commit-transaction",
+        VideohubCommand::CommitTransaction {
+            id: data.transaction,
+        },
+    )
+}
+
+pub fn rollback_request(data: RollbackTransactionAction) -> CommandRequest {
+    CommandRequest::from_action(
+        "rollback-transaction",
+        VideohubCommand::RollbackTransaction {
+            id: data.transaction,
+        },
+    )
+}
+
+pub async fn add_actions(
+    target: &mut TargetProxy,
+    display_names: &DisplayNames,
+    command_tx: &mpsc::Sender<CommandRequest>,
+) {
+    add_command_action(
+        target,
+        display_names,
+        "Begin Transaction",
+        "begin-transaction",
+        command_tx,
+        begin_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Add To Transaction",
+        "add-to-transaction",
+        command_tx,
+        add_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Commit Transaction",
+        "commit-transaction",
+        command_tx,
+        commit_request,
+    )
+    .await;
+    add_command_action(
+        target,
+        display_names,
+        "Rollback Transaction",
+        "rollback-transaction",
+        command_tx,
+        rollback_request,
+    )
+    .await;
+}
+
+// Run a transaction command on the device task. A commit is confirmed once the device has echoed
+// every change; whether the transaction took is reported on transaction-status once verified.
+pub async fn execute(
+    transactions: &mut Transactions,
+    command: &VideohubCommand,
+    scope: &mut CommandScope<'_>,
+) -> Result<()> {
+    match command {
+        VideohubCommand::BeginTransaction { id } => {
+            transactions.begin(id)?;
+            log::info!("Opened transaction {id}");
+            send_status(scope.event_tx, id, "open", 0, &[]).await;
+        }
+        VideohubCommand::AddToTransaction { id, command } => {
+            let changes = transactions.add(id, command)?;
+            log::info!("Transaction {id} now holds {changes} changes");
+        }
+        VideohubCommand::CommitTransaction { id } => {
+            let changes = transactions.commit(id, scope.state)?;
+            log::info!("Committing transaction {id} with {} changes", changes.len());
+            send_status(scope.event_tx, id, "committing", changes.len(), &[]).await;
+            scope.write_changes(changes);
+        }
+        VideohubCommand::RollbackTransaction { id } => {
+            let changes = transactions.rollback(id)?;
+            log::info!("Rolled back transaction {id} with {changes} changes");
+            send_status(scope.event_tx, id, "rolled-back", changes, &[]).await;
+        }
+        command => bail!("not a transaction command: {command:?}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverts_the_changes_that_took_when_one_did_not() {
+        let mut state = VideohubState::default();
+        state.video_output_routing.insert(0, 0);
+        state.video_output_routing.insert(1, 0);
+
+        let mut transactions = Transactions::new(DEFAULT_TRANSACTION_TIMEOUT);
+        transactions.begin("recall").unwrap();
+        let route = |output, input| VideohubCommand::Route { output, input };
+        transactions.add("recall", &route(0, 3)).unwrap();
+        transactions.add("recall", &route(1, 4)).unwrap();
+        assert_eq!(transactions.add("recall", &route(1, 5)).unwrap(), 2);
+        assert_eq!(transactions.commit("recall", &state).unwrap().len(), 2);
+
+        // Only output 1 was echoed; nothing is decided before the deadline
+        state.video_output_routing.insert(0, 3);
+        assert!(transactions.verify(&state).is_empty());
+
+        transactions.committed[0].deadline = Instant::now();
+        assert_eq!(
+            transactions.verify(&state),
+            vec![Outcome::Failed {
+                id: "recall".into(),
+                changes: 2,
+                failed: vec![ExpectedChange::Route {
+                    output: 1,
+                    input: 5
+                }],
+                revert: vec![ExpectedChange::Route {
+                    output: 0,
+                    input: 0
+                }],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn commits_the_actions_added_to_a_transaction() {
+        let state = VideohubState::default();
+        let (command_tx, mut command_rx) = mpsc::channel(1);
+        let (event_tx, _event_rx) = mpsc::channel(8);
+        let event_tx = EventSender(event_tx);
+        let mut transactions = Transactions::new(DEFAULT_TRANSACTION_TIMEOUT);
+
+        let add: AddToTransactionAction = serde_json::from_str(
+            r#"{"transaction": "cue", "command": {"action": "set-route", "data": {"output": 2, "input": 5, "owner": "news"}}}"#,
+        )
+        .unwrap();
+        let add = add_request(add);
+        assert_eq!(
+            add.invocation.as_ref().unwrap().owner.as_deref(),
+            Some("news")
+        );
+        let requests = [
+            begin_request(BeginTransactionAction {
+                transaction: "cue".into(),
+            }),
+            add,
+            commit_request(CommitTransactionAction {
+                transaction: "cue".into(),
+            }),
+        ];
+        for request in requests {
+            let mut invocation = request.invocation;
+            let mut scope = CommandScope {
+                source: request.source,
+                invocation: &mut invocation,
+                state: &state,
+                ownership: None,
+                command_tx: &command_tx,
+                event_tx: &event_tx,
+            };
+            execute(&mut transactions, &request.command, &mut scope)
+                .await
+                .unwrap();
+        }
+
+        let write = command_rx.recv().await.unwrap();
+        assert_eq!(write.invocation.unwrap().action, "commit-transaction");
+        let VideohubCommand::WriteChanges { changes } = write.command else {
+            panic!("not a write");
+        };
+        assert_eq!(
+            changes,
+            vec![ExpectedChange::Route {
+                output: 1,
+                input: 4
+            }]
+        );
+
+        // The transaction is closed once committed
+        let rollback = rollback_request(RollbackTransactionAction {
+            transaction: "cue".into(),
+        });
+        let mut invocation = rollback.invocation;
+        let mut scope = CommandScope {
+            source: rollback.source,
+            invocation: &mut invocation,
+            state: &state,
+            ownership: None,
+            command_tx: &command_tx,
+            event_tx: &event_tx,
+        };
+        let e = execute(&mut transactions, &rollback.command, &mut scope)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "no open transaction cue");
+    }
+}
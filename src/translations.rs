@@ -0,0 +1,30 @@
+//! Translated display names of actions and emitters, e.g. for an rship UI standardized on French
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+// English display name -> translation; names without a translation are kept
+#[derive(Debug, Clone, Default)]
+pub struct DisplayNames {
+    names: HashMap<String, String>,
+}
+
+impl DisplayNames {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read translations file {}", path.display()))?;
+        let names: HashMap<String, String> = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse translations file {}", path.display()))?;
+        log::info!("Loaded {} display name translations", names.len());
+        Ok(Self { names })
+    }
+
+    // Display name to register under
+    pub fn get(&self, name: &str) -> String {
+        self.names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+}
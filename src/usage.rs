@@ -0,0 +1,225 @@
+//! Per-owner usage of their outputs (routes changed, time each input spent on them), for rental
+//! billing and post-event reviews
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::time::{Duration, Instant};
+
+use crate::client::VideohubState;
+use crate::clock::EventTime;
+use crate::ownership::Ownership;
+
+// How often the usage is written to USAGE_FILE
+pub const DEFAULT_USAGE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// One input on one output of an owner; ports are 1-indexed like the rship actions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRow {
+    pub owner: String,
+    pub output: u32,
+    pub input: u32,
+    #[serde(default)]
+    pub input_label: String,
+    // How often the input was routed to the output
+    pub routes: u32,
+    // How long the input was on the output
+    pub occupied_seconds: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OwnerTotals {
+    pub routes_changed: u32,
+    pub occupied_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    // Start and end of the reported period (ISO-8601 UTC)
+    pub since: String,
+    pub until: String,
+    #[serde(default)]
+    pub owners: BTreeMap<String, OwnerTotals>,
+    #[serde(default)]
+    pub rows: Vec<UsageRow>,
+}
+
+impl UsageReport {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read usage report {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse usage report {}", path.display()))
+    }
+
+    // Write the report as CSV (one row per owner, output and input) if the path ends in .csv,
+    // as JSON otherwise
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let is_csv = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            let mut writer = csv::Writer::from_path(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            for row in &self.rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+            return Ok(());
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    routes: u32,
+    occupied: Duration,
+}
+
+// Usage accumulated by the device task (ports are 0-indexed in memory)
+#[derive(Debug)]
+pub struct UsageTracker {
+    since: String,
+    // (owner, output, input) -> usage
+    usage: BTreeMap<(String, u32, u32), Usage>,
+    // Input currently on each output, and since when it was last credited
+    current: HashMap<u32, (u32, Instant)>,
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self {
+            since: EventTime::now().timestamp,
+            usage: BTreeMap::new(),
+            current: HashMap::new(),
+        }
+    }
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Continue the period saved in a report
+    pub fn from_report(report: UsageReport) -> Self {
+        let mut tracker = Self {
+            since: report.since,
+            ..Self::default()
+        };
+        for row in report
+            .rows
+            .into_iter()
+            .filter(|row| row.output > 0 && row.input > 0)
+        {
+            tracker.usage.insert(
+                (row.owner, row.output - 1, row.input - 1),
+                Usage {
+                    routes: row.routes,
+                    occupied: Duration::from_secs_f64(row.occupied_seconds.max(0.0)),
+                },
+            );
+        }
+        tracker
+    }
+
+    // The device reported a route. `counted` routes were changes rather than initial state.
+    pub fn route(&mut self, output: u32, input: u32, owner: Option<&str>, counted: bool) {
+        if self
+            .current
+            .get(&output)
+            .is_some_and(|(current, _)| *current == input)
+        {
+            return;
+        }
+        self.settle(output, owner);
+        self.current.insert(output, (input, Instant::now()));
+        if counted && let Some(owner) = owner {
+            self.usage
+                .entry((owner.to_string(), output, input))
+                .or_default()
+                .routes += 1;
+        }
+    }
+
+    // Credit the time the current input spent on an output so far to the output's owner,
+    // e.g. before the output is handed over
+    pub fn settle(&mut self, output: u32, owner: Option<&str>) {
+        let Some((input, since)) = self.current.get_mut(&output) else {
+            return;
+        };
+        let now = Instant::now();
+        let occupied = now - *since;
+        *since = now;
+        if let Some(owner) = owner {
+            self.usage
+                .entry((owner.to_string(), output, *input))
+                .or_default()
+                .occupied += occupied;
+        }
+    }
+
+    // Usage up to now, crediting running routes to the outputs' current owners
+    pub fn report(&mut self, ownership: &Ownership, state: &VideohubState) -> UsageReport {
+        let outputs: Vec<u32> = self.current.keys().copied().collect();
+        for output in outputs {
+            self.settle(output, ownership.owner(output));
+        }
+
+        let mut owners: BTreeMap<String, OwnerTotals> = BTreeMap::new();
+        let mut rows = Vec::new();
+        for ((owner, output, input), usage) in &self.usage {
+            let totals = owners.entry(owner.clone()).or_default();
+            totals.routes_changed += usage.routes;
+            totals.occupied_seconds += usage.occupied.as_secs_f64();
+            rows.push(UsageRow {
+                owner: owner.clone(),
+                output: output + 1,
+                input: input + 1,
+                input_label: state.input_labels.get(input).cloned().unwrap_or_default(),
+                routes: usage.routes,
+                occupied_seconds: usage.occupied.as_secs_f64(),
+            });
+        }
+        UsageReport {
+            since: self.since.clone(),
+            until: EventTime::now().timestamp,
+            owners,
+            rows,
+        }
+    }
+
+    // Start a new period; routes on the device keep being timed from now
+    pub fn reset(&mut self) {
+        let now = Instant::now();
+        self.current
+            .values_mut()
+            .for_each(|(_, since)| *since = now);
+        self.usage.clear();
+        self.since = EventTime::now().timestamp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_changes_per_owner_but_not_initial_routes() {
+        let mut tracker = UsageTracker::new();
+        tracker.route(0, 4, Some("news"), false);
+        tracker.route(0, 4, Some("news"), true);
+        tracker.route(0, 5, Some("news"), true);
+        tracker.route(1, 5, None, true);
+
+        let routes: Vec<_> = tracker
+            .usage
+            .iter()
+            .map(|((owner, output, input), usage)| (owner.as_str(), *output, *input, usage.routes))
+            .collect();
+        assert_eq!(routes, vec![("news", 0, 4, 0), ("news", 0, 5, 1)]);
+    }
+}
@@ -0,0 +1,116 @@
+//! End-to-end check that the device still acts on commands, beyond the TCP connection being up
+
+use tokio::time::{Duration, Instant};
+use videohub::{Route, VideohubMessage};
+
+// How often a probe is sent
+pub const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+// How long a probe may go unanswered before the watchdog alarms
+pub const DEFAULT_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(5);
+
+// What the watchdog sends; ports are 0-indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogProbe {
+    Ping,
+    // Re-assert a route that is never changed otherwise (a "heartbeat crosspoint")
+    Route { output: u32, input: u32 },
+}
+
+impl WatchdogProbe {
+    // "ping", or "route:<output>:<input>" with 1-indexed ports
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        if value == "ping" {
+            return Some(Self::Ping);
+        }
+        let mut parts = value.strip_prefix("route:")?.split(':');
+        let output: u32 = parts.next()?.trim().parse().ok()?;
+        let input: u32 = parts.next()?.trim().parse().ok()?;
+        (parts.next().is_none() && output > 0 && input > 0).then(|| Self::Route {
+            output: output - 1,
+            input: input - 1,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Ping => "ping".to_string(),
+            Self::Route { output, input } => format!("route:{}:{}", output + 1, input + 1),
+        }
+    }
+
+    pub fn message(&self) -> VideohubMessage {
+        match *self {
+            Self::Ping => VideohubMessage::Ping,
+            Self::Route { output, input } => VideohubMessage::VideoOutputRouting(vec![Route {
+                to_output: output,
+                from_input: input,
+            }]),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Watchdog {
+    pub probe: WatchdogProbe,
+    timeout: Duration,
+    // When the oldest unanswered probe was sent
+    unanswered_since: Option<Instant>,
+    alarmed: bool,
+}
+
+impl Watchdog {
+    pub fn new(probe: WatchdogProbe, timeout: Duration) -> Self {
+        Self {
+            probe,
+            timeout,
+            unanswered_since: None,
+            alarmed: false,
+        }
+    }
+
+    pub fn sent(&mut self) {
+        self.unanswered_since.get_or_insert_with(Instant::now);
+    }
+
+    // The device answered (ACK or NAK). Returns true if this ends an alarm.
+    pub fn answered(&mut self) -> bool {
+        self.unanswered_since = None;
+        std::mem::take(&mut self.alarmed)
+    }
+
+    // Returns how long the probe has gone unanswered when this starts an alarm
+    pub fn check(&mut self) -> Option<Duration> {
+        let unanswered = self.unanswered_since?.elapsed();
+        if self.alarmed || unanswered < self.timeout {
+            return None;
+        }
+        self.alarmed = true;
+        Some(unanswered)
+    }
+
+    // The connection was lost; probes on the next one start afresh
+    pub fn reset(&mut self) {
+        self.unanswered_since = None;
+        self.alarmed = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probes() {
+        assert_eq!(WatchdogProbe::parse("ping"), Some(WatchdogProbe::Ping));
+        assert_eq!(
+            WatchdogProbe::parse("route:40:1"),
+            Some(WatchdogProbe::Route {
+                output: 39,
+                input: 0
+            })
+        );
+        assert_eq!(WatchdogProbe::parse("route:0:1"), None);
+        assert_eq!(WatchdogProbe::parse("route:3"), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Protocol conformance: replays device session transcripts through the client and the service's
+//! device task, and checks the emitted events field by field.
+//!
+//! Each `tests/transcripts/<model>.txt` is the raw text a device sends after a client connects.
+//! The transcripts are modelled on the preludes of the respective models from the protocol
+//! documentation, not captured from real devices; captures can be added alongside them. The
+//! routes, labels, locks and take modes a transcript lists are read from it directly, so each
+//! test checks that the service reports exactly those once, plus what is specific to the model.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rship_blackmagic_videohub::service::VideohubEvent;
+use rship_blackmagic_videohub::{ServiceConfig, VideohubService};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::time::timeout;
+
+// How long the service may stay silent before the session counts as fully processed; longer than
+// the quiet period after which a prelude without END PRELUDE is assumed complete
+const QUIET_PERIOD: Duration = Duration::from_millis(2500);
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn transcript(model: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/transcripts")
+        .join(format!("{model}.txt"));
+    std::fs::read_to_string(path).expect("transcript")
+}
+
+// The "<id> <value>" lines of the first block with this header
+fn entries(transcript: &str, header: &str) -> BTreeMap<u32, String> {
+    transcript
+        .split("\n\n")
+        .find_map(|block| block.trim_start().strip_prefix(header))
+        .expect(header)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(id, value)| (id.parse().expect("port number"), value.to_string()))
+        .collect()
+}
+
+// Serve the transcript to a single client and return the events the service emitted for it,
+// in the order they were emitted
+async fn replay(transcript: String) -> Vec<VideohubEvent> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let device = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(transcript.as_bytes()).await.unwrap();
+        // Stay connected, like a device with nothing more to report
+        std::future::pending::<()>().await;
+    });
+
+    let service = VideohubService::new("127.0.0.1".to_string(), port, "127.0.0.1".to_string(), 0)
+        .await
+        .unwrap()
+        .with_config(ServiceConfig::default());
+    let (_command_tx, mut event_rx) = service.start_device_session().await.unwrap();
+
+    let mut events = Vec::new();
+    let session = async {
+        while let Ok(Some((_, event))) = timeout(QUIET_PERIOD, event_rx.recv()).await {
+            events.push(event);
+        }
+    };
+    timeout(SESSION_TIMEOUT, session)
+        .await
+        .expect("the service kept emitting events");
+    device.abort();
+    events
+}
+
+// Collect one value per port, failing if a port is reported twice
+fn per_port<T>(
+    events: &[VideohubEvent],
+    kind: &str,
+    entry: impl Fn(&VideohubEvent) -> Option<(u32, T)>,
+) -> BTreeMap<u32, T> {
+    let mut ports = BTreeMap::new();
+    for (port, value) in events.iter().filter_map(entry) {
+        assert!(
+            ports.insert(port, value).is_none(),
+            "{kind} of port {port} reported twice"
+        );
+    }
+    ports
+}
+
+fn routes(events: &[VideohubEvent]) -> BTreeMap<u32, String> {
+    per_port(events, "route", |event| match event {
+        VideohubEvent::Route {
+            output,
+            input,
+            confirmed: true,
+            last_change: None,
+            ..
+        } => Some((*output, input.to_string())),
+        _ => None,
+    })
+}
+
+fn labels(events: &[VideohubEvent], kind: &str) -> BTreeMap<u32, String> {
+    per_port(events, kind, |event| match event {
+        VideohubEvent::Label {
+            port_type,
+            port,
+            label,
+            confirmed: true,
+            ..
+        } if port_type == kind => Some((*port, label.clone())),
+        _ => None,
+    })
+}
+
+fn locks(events: &[VideohubEvent]) -> BTreeMap<u32, bool> {
+    per_port(events, "lock", |event| match event {
+        VideohubEvent::OutputLock { output, locked } => Some((*output, *locked)),
+        _ => None,
+    })
+}
+
+fn take_modes(events: &[VideohubEvent]) -> BTreeMap<u32, String> {
+    per_port(events, "take mode", |event| match event {
+        VideohubEvent::TakeMode { output, enabled } => Some((*output, enabled.to_string())),
+        _ => None,
+    })
+}
+
+fn prelude_complete(events: &[VideohubEvent]) -> Option<(Option<u32>, Option<u32>)> {
+    let mut completions = events.iter().filter_map(|event| match event {
+        VideohubEvent::PreludeComplete {
+            video_inputs,
+            video_outputs,
+        } => Some((*video_inputs, *video_outputs)),
+        _ => None,
+    });
+    let first = completions.next();
+    assert!(completions.next().is_none(), "prelude completed twice");
+    first
+}
+
+// The state every transcript lists, checked against what the transcript says
+async fn check_session(model: &str, model_name: &str, ports: u32) -> Vec<VideohubEvent> {
+    let transcript = transcript(model);
+    let events = replay(transcript.clone()).await;
+
+    let statuses: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::DeviceStatus {
+                connected,
+                unstable,
+                model_name,
+                video_inputs,
+                video_outputs,
+            } => Some((
+                *connected,
+                *unstable,
+                model_name.clone(),
+                *video_inputs,
+                *video_outputs,
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![(
+            true,
+            false,
+            Some(model_name.to_string()),
+            Some(ports),
+            Some(ports)
+        )]
+    );
+
+    assert_eq!(
+        routes(&events),
+        entries(&transcript, "VIDEO OUTPUT ROUTING:")
+    );
+    assert_eq!(routes(&events).len(), ports as usize);
+    assert_eq!(
+        labels(&events, "input"),
+        entries(&transcript, "INPUT LABELS:")
+    );
+    assert_eq!(
+        labels(&events, "output"),
+        entries(&transcript, "OUTPUT LABELS:")
+    );
+    // Only locks taken by another controller count; "O" is this connection's own lock
+    let expected_locks: BTreeMap<u32, bool> = entries(&transcript, "VIDEO OUTPUT LOCKS:")
+        .into_iter()
+        .map(|(output, state)| (output, state == "L"))
+        .collect();
+    assert_eq!(locks(&events), expected_locks);
+
+    // Output and input subtargets are created from this, with the port counts it carries
+    assert_eq!(prelude_complete(&events), Some((Some(ports), Some(ports))));
+    events
+}
+
+#[tokio::test]
+async fn micro_videohub_16x16() {
+    let transcript = transcript("micro_videohub_16x16");
+    assert!(!transcript.contains("END PRELUDE:"));
+    let events = check_session("micro_videohub_16x16", "Blackmagic Micro Videohub", 16).await;
+    // The Micro does not end its prelude; it is completed after the state it sent
+    let prelude_complete = events
+        .iter()
+        .position(|event| matches!(event, VideohubEvent::PreludeComplete { .. }))
+        .unwrap();
+    assert!(
+        events[..prelude_complete]
+            .iter()
+            .any(|event| matches!(event, VideohubEvent::Route { output: 15, .. }))
+    );
+    assert!(events[prelude_complete..].iter().all(|event| !matches!(
+        event,
+        VideohubEvent::Route { .. }
+            | VideohubEvent::Label { .. }
+            | VideohubEvent::OutputLock { .. }
+    )));
+    // No take mode or network blocks on the Micro
+    assert!(take_modes(&events).is_empty());
+    assert!(
+        !events
+            .iter()
+            .any(|event| matches!(event, VideohubEvent::NetworkInterface { .. }))
+    );
+}
+
+#[tokio::test]
+async fn smart_videohub_12g_12x12() {
+    let events = check_session(
+        "smart_videohub_12g_12x12",
+        "Blackmagic Smart Videohub 12G 12x12",
+        12,
+    )
+    .await;
+    let configuration: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::Configuration { setting, value } => {
+                Some((setting.as_str(), value.as_str()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(configuration, vec![("Take Mode", "false")]);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        VideohubEvent::MatrixSummary {
+            locked_outputs: 2,
+            take_mode_outputs: 0,
+            ..
+        }
+    )));
+}
+
+#[tokio::test]
+async fn smart_videohub_40x40() {
+    let events = check_session(
+        "smart_videohub_40x40",
+        "Blackmagic Smart Videohub 40 x 40",
+        40,
+    )
+    .await;
+    let transcript = transcript("smart_videohub_40x40");
+    assert_eq!(take_modes(&events), entries(&transcript, "TAKE MODE:"));
+
+    let interfaces: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::NetworkInterface { interface } => Some(interface),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(interfaces.len(), 1);
+    assert_eq!(interfaces[0].name, "Ethernet");
+    assert_eq!(
+        interfaces[0].current_addresses.as_deref(),
+        Some("10.20.0.40/255.255.255.0")
+    );
+    assert!(events.iter().any(|event| matches!(
+        event,
+        VideohubEvent::Inventory { capabilities, protocol_version, .. }
+            if capabilities.contains(&"take-mode".to_string())
+                && protocol_version.as_deref() == Some("2.7")
+    )));
+}
+
+#[tokio::test]
+async fn universal_videohub_288() {
+    let events = check_session(
+        "universal_videohub_288",
+        "Blackmagic Universal Videohub 288",
+        288,
+    )
+    .await;
+    // The alarm block is not modelled; it is reported once as unknown
+    let unknown: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::UnknownBlock { header, count, .. } => Some((header.as_str(), *count)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(unknown, vec![("ALARM STATUS:", 1)]);
+}
+
+// The device info arrives first in the prelude, before any label or route. The output and input
+// subtargets are created on PreludeComplete, which must come after all of them so the
+// subtargets start with the full state instead of blank.
+#[tokio::test]
+async fn prelude_completes_after_the_device_state() {
+    let events = replay(transcript("smart_videohub_40x40")).await;
+    let position =
+        |is_kind: fn(&VideohubEvent) -> bool| events.iter().position(|event| is_kind(event));
+    let last_position =
+        |is_kind: fn(&VideohubEvent) -> bool| events.iter().rposition(|event| is_kind(event));
+
+    let device_status = position(|event| matches!(event, VideohubEvent::DeviceStatus { .. }))
+        .expect("device status");
+    let prelude_complete = position(|event| matches!(event, VideohubEvent::PreludeComplete { .. }))
+        .expect("prelude complete");
+    assert!(
+        device_status
+            < position(|event| matches!(event, VideohubEvent::Label { .. })).expect("labels")
+    );
+    let state_kinds: [(&str, fn(&VideohubEvent) -> bool); 4] = [
+        ("label", |event| {
+            matches!(event, VideohubEvent::Label { .. })
+        }),
+        ("route", |event| {
+            matches!(event, VideohubEvent::Route { .. })
+        }),
+        ("lock", |event| {
+            matches!(event, VideohubEvent::OutputLock { .. })
+        }),
+        ("take mode", |event| {
+            matches!(event, VideohubEvent::TakeMode { .. })
+        }),
+    ];
+    for (kind, is_kind) in state_kinds {
+        assert!(
+            last_position(is_kind).expect(kind) < prelude_complete,
+            "{kind} emitted after the prelude completed"
+        );
+    }
+}
+
+// Take mode and network interfaces are diffed for every block, so changes after the prelude are
+// emitted once each, and a routing block in between does not report them again
+#[tokio::test]
+async fn diffs_take_mode_and_network_after_the_prelude() {
+    let mut transcript = transcript("smart_videohub_40x40");
+    transcript.push_str(
+        "VIDEO OUTPUT ROUTING:\n0 5\n\n\
+         TAKE MODE:\n10 true\n\n\
+         NETWORK INTERFACE 0:\n\
+         Name: Ethernet\n\
+         Priority: 1\n\
+         MAC Address: 7c:2e:0d:0c:4f:10\n\
+         Dynamic IP: false\n\
+         Current Addresses: 10.20.0.41/255.255.255.0\n\
+         Current Gateway: 10.20.0.1\n\
+         Static Addresses: 10.20.0.41/255.255.255.0\n\
+         Static Gateway: 10.20.0.1\n\n\
+         VIDEO OUTPUT ROUTING:\n1 5\n\n",
+    );
+    let events = replay(transcript).await;
+    let prelude_complete = events
+        .iter()
+        .position(|event| matches!(event, VideohubEvent::PreludeComplete { .. }))
+        .expect("prelude complete");
+    let after = &events[prelude_complete..];
+
+    let take_modes: Vec<_> = after
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::TakeMode { output, enabled } => Some((*output, *enabled)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(take_modes, vec![(10, true)]);
+    let interfaces: Vec<_> = after
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::NetworkInterface { interface } => interface.current_addresses.as_deref(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(interfaces, vec!["10.20.0.41/255.255.255.0"]);
+    let routes: Vec<_> = after
+        .iter()
+        .filter_map(|event| match event {
+            VideohubEvent::Route { output, input, .. } => Some((*output, *input)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(routes, vec![(0, 5), (1, 5)]);
+}
@@ -0,0 +1,970 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+use videohub::{
+    DeviceInfo, Label, Lock, LockState, Route, Setting, VideohubCodec, VideohubMessage,
+};
+
+use crate::clock::EventTime;
+use crate::connection::Connection;
+use crate::snapshot::VideohubStateSnapshot;
+use crate::unknown::UnknownBlockStats;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkInterface {
+    pub id: u32,
+    pub name: String,
+    pub priority: Option<u32>,
+    pub mac_address: Option<String>,
+    pub dynamic_ip: Option<bool>,
+    pub current_addresses: Option<String>,
+    pub current_gateway: Option<String>,
+    pub static_addresses: Option<String>,
+    pub static_gateway: Option<String>,
+}
+
+// A raw protocol block recorded while protocol tracing is enabled
+#[derive(Debug, Clone)]
+pub struct ProtocolTraceEntry {
+    pub direction: &'static str, // "sent" or "received"
+    pub block: String,
+    pub timestamp_ms: u64, // milliseconds since the UNIX epoch
+    pub time: EventTime,
+}
+
+// Represents the current state of a Videohub device
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct VideohubState {
+    pub device_info: Option<DeviceInfo>,
+    pub input_labels: HashMap<u32, String>,
+    pub output_labels: HashMap<u32, String>,
+    pub video_output_routing: HashMap<u32, u32>, // output -> input
+    pub take_mode: HashMap<u32, bool>,           // output -> take_mode_enabled
+    pub output_locks: HashMap<u32, bool>,        // output -> locked by another client
+    pub owned_locks: HashSet<u32>,               // outputs locked by this connection
+    pub protocol_version: Option<String>,
+    pub network_interfaces: Vec<NetworkInterface>,
+    pub connected: bool,
+    pub reconnected: bool, // Flag to indicate if we just reconnected and need to send full state
+    pub rejected_commands: HashMap<&'static str, u64>, // command category -> NAKs received
+    pub configuration: HashMap<String, String>, // CONFIGURATION block setting -> value
+    pub unknown_blocks: HashMap<String, UnknownBlockStats>, // header -> blocks received
+    pub input_status: HashMap<u32, String>, // input -> its interface, e.g. "BNC" or "Optical"
+    pub output_status: HashMap<u32, String>, // output -> its interface
+}
+
+// Header of the per-output take mode block, which the videohub crate does not parse
+const TAKE_MODE_HEADER: &str = "TAKE MODE:";
+
+// Configuration settings that trigger device functions. They are not part of the documented
+// Videohub Ethernet protocol, so callers should only write them when the device lists them in
+// its CONFIGURATION block and the user has opted in.
+pub const IDENTIFY_SETTING: &str = "Identify";
+pub const REBOOT_SETTING: &str = "Reboot";
+
+impl VideohubState {
+    // Whether the device reported this setting in its CONFIGURATION block
+    pub fn supports_setting(&self, setting: &str) -> bool {
+        self.configuration.contains_key(setting)
+    }
+}
+
+// Store the interfaces of a VIDEO INPUT STATUS or VIDEO OUTPUT STATUS block ("<port> <interface>")
+fn handle_port_status(status: &mut HashMap<u32, String>, body: &str) {
+    for line in body.lines() {
+        if let Some((port, interface)) = line.trim().split_once(' ')
+            && let Ok(port) = port.parse::<u32>()
+        {
+            status.insert(port, interface.trim().to_string());
+        }
+    }
+}
+
+// Kind of command a block sent to the device represents, for attributing its ACK or NAK
+pub fn command_category(message: &VideohubMessage) -> &'static str {
+    match message {
+        VideohubMessage::VideoOutputRouting(_) => "route",
+        VideohubMessage::InputLabels(_) => "input-label",
+        VideohubMessage::OutputLabels(_) => "output-label",
+        VideohubMessage::VideoOutputLocks(_) => "lock",
+        VideohubMessage::Ping => "ping",
+        VideohubMessage::UnknownMessage(header, _)
+            if header.starts_with(TAKE_MODE_HEADER.as_bytes()) =>
+        {
+            "take-mode"
+        }
+        // Blocks passed through as written (send-raw-block)
+        VideohubMessage::UnknownMessage(..) => "raw",
+        _ => "other",
+    }
+}
+
+// Store a reported label, reusing the existing allocation. Returns whether the label changed.
+pub fn update_label(labels: &mut HashMap<u32, String>, label: &Label) -> bool {
+    match labels.get_mut(&label.id) {
+        Some(existing) if *existing == label.name => false,
+        Some(existing) => {
+            existing.clone_from(&label.name);
+            true
+        }
+        None => {
+            labels.insert(label.id, label.name.clone());
+            true
+        }
+    }
+}
+
+// How long the device may stay silent after identifying itself before its prelude is assumed
+// complete, for models that do not send END PRELUDE
+pub const PRELUDE_QUIET: Duration = Duration::from_secs(2);
+
+// Whether an error means the connection to the device is gone, so the command that failed
+// may go through after reconnecting
+pub fn is_connection_lost(error: &anyhow::Error) -> bool {
+    use std::io::ErrorKind;
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|e| {
+            matches!(
+                e.kind(),
+                ErrorKind::BrokenPipe
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::UnexpectedEof
+                    | ErrorKind::WriteZero
+                    | ErrorKind::TimedOut
+            )
+        })
+}
+
+fn not_connected() -> anyhow::Error {
+    anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotConnected))
+        .context("Not connected to videohub")
+}
+
+// VideohubCodec, except that NAK blocks are decoded as NAK; the videohub crate parses them as ACK
+#[derive(Debug, Default)]
+pub struct DeviceCodec;
+
+impl Decoder for DeviceCodec {
+    type Item = VideohubMessage;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let is_nak = src
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .and_then(|start| src.get(start..start + 3))
+            .is_some_and(|header| header.eq_ignore_ascii_case(b"NAK"));
+        match VideohubCodec.decode(src)? {
+            Some(VideohubMessage::ACK) if is_nak => Ok(Some(VideohubMessage::NAK)),
+            message => Ok(message),
+        }
+    }
+}
+
+impl Encoder<VideohubMessage> for DeviceCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, item: VideohubMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match item {
+            // Blocks the videohub crate has no message for: header line, body, blank line
+            VideohubMessage::UnknownMessage(header, body) => {
+                dst.extend_from_slice(&header);
+                dst.extend_from_slice(b"\n");
+                dst.extend_from_slice(&body);
+                dst.extend_from_slice(b"\n");
+                Ok(())
+            }
+            item => VideohubCodec.encode(item, dst),
+        }
+    }
+}
+
+// Lock entry taking ("O", owned by this connection) or releasing ("U") an output
+fn output_lock(output: u32, locked: bool) -> Lock {
+    Lock {
+        id: output,
+        state: if locked {
+            LockState::Owned
+        } else {
+            LockState::Unlocked
+        },
+    }
+}
+
+// Client for communicating with a Blackmagic Videohub device
+pub struct VideohubClient {
+    host: String,
+    port: u16,
+    state: VideohubState,
+    connection: Option<Connection>,
+    initial_state_received: bool, // Track if we've received initial state after connection
+    // When the latest message of a prelude without END PRELUDE yet arrived, once the device has
+    // identified itself
+    prelude_last_message: Option<Instant>,
+    trace_tx: Option<mpsc::UnboundedSender<ProtocolTraceEntry>>, // Set while protocol tracing is enabled
+    awaiting_reply: VecDeque<&'static str>, // Categories of sent blocks not yet ACKed or NAKed, oldest first
+    last_reply: Option<&'static str>,       // Category of the block the latest ACK or NAK refers to
+    changed_entries: Vec<bool>, // Whether each entry of the latest received block changed the state
+    standby: bool,              // Observe only: another executor is in charge of the device
+    // Port counts used instead of the ones the device reports (inputs, outputs)
+    port_counts: (Option<u32>, Option<u32>),
+}
+
+impl VideohubClient {
+    pub fn new(host: String, port: u16) -> Self {
+        Self {
+            host,
+            port,
+            state: VideohubState::default(),
+            connection: None,
+            initial_state_received: false,
+            prelude_last_message: None,
+            trace_tx: None,
+            awaiting_reply: VecDeque::new(),
+            last_reply: None,
+            changed_entries: Vec::new(),
+            standby: false,
+            port_counts: (None, None),
+        }
+    }
+
+    // Override the input and output counts of the device info, for firmware that under-reports
+    // its ports while still accepting routes on the higher ones
+    pub fn set_port_counts(&mut self, video_inputs: Option<u32>, video_outputs: Option<u32>) {
+        self.port_counts = (video_inputs, video_outputs);
+    }
+
+    // In standby the client keeps receiving state but refuses to send anything except pings
+    pub fn set_standby(&mut self, standby: bool) {
+        self.standby = standby;
+    }
+
+    pub fn is_standby(&self) -> bool {
+        self.standby
+    }
+
+    fn check_standby(&self, message: &VideohubMessage) -> Result<()> {
+        if self.standby && !matches!(message, VideohubMessage::Ping) {
+            return Err(anyhow!(
+                "this executor is on standby; only the leader sends commands"
+            ));
+        }
+        Ok(())
+    }
+
+    // Enable protocol tracing by providing a sink for raw blocks, or disable it with None
+    pub fn set_protocol_trace(
+        &mut self,
+        trace_tx: Option<mpsc::UnboundedSender<ProtocolTraceEntry>>,
+    ) {
+        log::info!(
+            "Protocol tracing {}",
+            if trace_tx.is_some() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        self.trace_tx = trace_tx;
+    }
+
+    // Record a raw block if protocol tracing is enabled
+    fn trace(&mut self, direction: &'static str, message: &VideohubMessage) {
+        let Some(trace_tx) = &self.trace_tx else {
+            return;
+        };
+
+        // Re-encode the message to get the exact block as it appears on the wire
+        let mut buffer = BytesMut::new();
+        let block = match VideohubCodec.encode(message.clone(), &mut buffer) {
+            Ok(()) => String::from_utf8_lossy(&buffer).into_owned(),
+            Err(_) => format!("{message:?}"),
+        };
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+
+        if trace_tx
+            .send(ProtocolTraceEntry {
+                direction,
+                block,
+                timestamp_ms,
+                time: EventTime::now(),
+            })
+            .is_err()
+        {
+            log::warn!("Protocol trace receiver dropped, disabling tracing");
+            self.trace_tx = None;
+        }
+    }
+
+    // Host and port of the device, as "host:port"
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    // Point the client at another device, used from the next connect. The state of the previous
+    // device is dropped, since the new one may have other ports, labels and routes.
+    pub fn set_address(&mut self, host: String, port: u16) {
+        self.host = host;
+        self.port = port;
+        self.state = VideohubState::default();
+        self.initial_state_received = false;
+        self.prelude_last_message = None;
+    }
+
+    // Connect to the videohub device
+    pub async fn connect(&mut self) -> Result<()> {
+        log::debug!("Connecting to videohub at {}:{}", self.host, self.port);
+
+        let stream = TcpStream::connect(format!("{}:{}", self.host, self.port)).await?;
+
+        self.connection = Some(Connection::open(stream));
+        self.state.connected = true;
+        self.state.reconnected = !self.initial_state_received; // Mark as reconnected if not first connection
+        self.initial_state_received = false; // Reset flag to track new connection state
+        self.prelude_last_message = None;
+        self.awaiting_reply.clear();
+
+        log::debug!("Connected to videohub successfully");
+        Ok(())
+    }
+
+    // Disconnect from the videohub device
+    pub async fn disconnect(&mut self) {
+        if let Some(conn) = self.connection.take() {
+            conn.close().await;
+        }
+        self.state.connected = false;
+        self.initial_state_received = false; // Reset on disconnect
+        self.prelude_last_message = None;
+        log::info!("Disconnected from videohub");
+    }
+
+    // Check if connected to the videohub
+    #[allow(dead_code)]
+    pub fn is_connected(&self) -> bool {
+        self.state.connected && self.connection.is_some()
+    }
+
+    // Socket details for diagnosing a slow control network
+    pub fn socket_diagnostics(&self) -> String {
+        match &self.connection {
+            Some(connection) => connection.diagnostics(),
+            None => "not connected".to_string(),
+        }
+    }
+
+    // Get the current videohub state
+    pub fn state(&self) -> &VideohubState {
+        &self.state
+    }
+
+    // Owned copy of the current state, e.g. to serialize it
+    pub fn snapshot(&self) -> VideohubStateSnapshot {
+        VideohubStateSnapshot::from(&self.state)
+    }
+
+    // Send a message to the videohub
+    pub async fn send_message(&mut self, message: VideohubMessage) -> Result<()> {
+        self.check_standby(&message)?;
+        if self.connection.is_some() {
+            self.trace("sent", &message);
+        }
+        let Some(conn) = &self.connection else {
+            return Err(not_connected());
+        };
+        let category = command_category(&message);
+        let sent = conn.send(vec![message]).await;
+        if let Err(e) = sent {
+            return Err(self.write_failed(e));
+        }
+        self.awaiting_reply.push_back(category);
+        Ok(())
+    }
+
+    // Write several blocks back to back and flush them together, without waiting in between
+    pub async fn send_pipelined(&mut self, messages: Vec<VideohubMessage>) -> Result<()> {
+        for message in &messages {
+            self.check_standby(message)?;
+        }
+        let Some(conn) = &self.connection else {
+            return Err(not_connected());
+        };
+        if let Err(e) = conn.send(messages.clone()).await {
+            return Err(self.write_failed(e));
+        }
+        for message in &messages {
+            self.trace("sent", message);
+            self.awaiting_reply.push_back(command_category(message));
+        }
+        Ok(())
+    }
+
+    // A write to the device failed. A connection lost mid-write is dropped, so that the next
+    // receive_message reports it closed and the caller reconnects.
+    fn write_failed(&mut self, e: std::io::Error) -> anyhow::Error {
+        let message = format!("Failed to send message: {e}");
+        let error = anyhow::Error::new(e).context(message);
+        if is_connection_lost(&error) {
+            log::warn!("Connection to videohub lost while writing");
+            self.connection = None;
+            self.state.connected = false;
+        }
+        error
+    }
+
+    // Receive the next message from the videohub; None once the connection is closed or lost
+    pub async fn receive_message(&mut self) -> Result<Option<VideohubMessage>> {
+        if let Some(conn) = &mut self.connection {
+            match conn.receive().await {
+                Some(Ok(message)) => {
+                    self.trace("received", &message);
+                    self.handle_message(&message);
+                    if !self.initial_state_received
+                        && (self.prelude_last_message.is_some()
+                            || matches!(message, VideohubMessage::DeviceInfo(_)))
+                    {
+                        self.prelude_last_message = Some(Instant::now());
+                    }
+                    Ok(Some(message))
+                }
+                Some(Err(e)) => Err(anyhow!("Failed to receive message: {}", e)),
+                None => {
+                    // Connection closed
+                    self.state.connected = false;
+                    Ok(None)
+                }
+            }
+        } else {
+            // Never connected, or dropped after a failed write
+            Ok(None)
+        }
+    }
+
+    // Like `receive_message`, but ends the prelude once the device has identified itself and then
+    // sent nothing for `quiet`. Some models (the Micro Videohub) send their initial state without
+    // END PRELUDE; for them an EndPrelude message is made up, so the caller treats the state as
+    // complete either way.
+    pub async fn receive_message_or_end_prelude(
+        &mut self,
+        quiet: Duration,
+    ) -> Result<Option<VideohubMessage>> {
+        let Some(last_message) = self.prelude_last_message else {
+            return self.receive_message().await;
+        };
+        match tokio::time::timeout_at(last_message + quiet, self.receive_message()).await {
+            Ok(received) => received,
+            Err(_) => {
+                log::info!(
+                    "No END PRELUDE after {quiet:?} without messages; assuming the initial state is complete"
+                );
+                let message = VideohubMessage::EndPrelude;
+                self.handle_message(&message);
+                Ok(Some(message))
+            }
+        }
+    }
+
+    // Receive messages until the device has sent its complete initial state
+    pub async fn receive_initial_state(&mut self) -> Result<()> {
+        loop {
+            match self.receive_message_or_end_prelude(PRELUDE_QUIET).await? {
+                Some(VideohubMessage::EndPrelude) => return Ok(()),
+                Some(_) => {}
+                None => {
+                    return Err(anyhow!(
+                        "Connection closed before initial state was received"
+                    ));
+                }
+            }
+        }
+    }
+
+    // Whether each entry of the latest received block (device info, routes, labels or locks)
+    // changed the state, in the order of the block
+    pub fn changed_entries(&self) -> &[bool] {
+        &self.changed_entries
+    }
+
+    // Handle incoming messages and update state
+    fn handle_message(&mut self, message: &VideohubMessage) {
+        self.changed_entries.clear();
+        match message {
+            VideohubMessage::DeviceInfo(info) => {
+                log::info!(
+                    "Device connected: {} | Inputs: {} | Outputs: {} | ID: {}",
+                    info.model_name.as_deref().unwrap_or("Unknown"),
+                    info.video_inputs.unwrap_or(0),
+                    info.video_outputs.unwrap_or(0),
+                    info.unique_id.as_deref().unwrap_or("Unknown")
+                );
+                let mut info = info.clone();
+                let (video_inputs, video_outputs) = self.port_counts;
+                info.video_inputs = video_inputs.or(info.video_inputs);
+                info.video_outputs = video_outputs.or(info.video_outputs);
+                self.changed_entries
+                    .push(self.state.device_info.as_ref() != Some(&info));
+                self.state.device_info = Some(info);
+            }
+            // Blocks may only list the ports that changed, so update in place
+            VideohubMessage::InputLabels(labels) => {
+                log::debug!("Received input labels: {} labels", labels.len());
+                for label in labels {
+                    let changed = update_label(&mut self.state.input_labels, label);
+                    self.changed_entries.push(changed);
+                }
+            }
+            VideohubMessage::OutputLabels(labels) => {
+                log::debug!("Received output labels: {} labels", labels.len());
+                for label in labels {
+                    let changed = update_label(&mut self.state.output_labels, label);
+                    self.changed_entries.push(changed);
+                }
+            }
+            VideohubMessage::VideoOutputRouting(routes) => {
+                log::debug!("Received video output routing: {} routes", routes.len());
+                for route in routes {
+                    let previous = self
+                        .state
+                        .video_output_routing
+                        .insert(route.to_output, route.from_input);
+                    self.changed_entries
+                        .push(previous != Some(route.from_input));
+                }
+            }
+            VideohubMessage::ACK => {
+                log::debug!("Received ACK");
+                self.last_reply = Some(self.awaiting_reply.pop_front().unwrap_or("unknown"));
+            }
+            VideohubMessage::NAK => {
+                // The device answers blocks in order, so the NAK most likely refers to the oldest unanswered one
+                let category = self.awaiting_reply.pop_front().unwrap_or("unknown");
+                log::warn!("Device rejected a {category} command (NAK)");
+                *self.state.rejected_commands.entry(category).or_default() += 1;
+                self.last_reply = Some(category);
+            }
+            VideohubMessage::Ping => {
+                log::debug!("Received ping");
+            }
+            VideohubMessage::Configuration(settings) => {
+                log::debug!("Received configuration: {} settings", settings.len());
+                for setting in settings {
+                    log::debug!(
+                        "Configuration setting: {} = {}",
+                        setting.setting,
+                        setting.value
+                    );
+                    self.state
+                        .configuration
+                        .insert(setting.setting.clone(), setting.value.clone());
+                }
+            }
+            VideohubMessage::EndPrelude => {
+                log::debug!("Received end of prelude - device initialization complete");
+                self.initial_state_received = true;
+                self.prelude_last_message = None;
+                self.state.reconnected = false; // Clear reconnected flag after receiving full state
+            }
+            VideohubMessage::Preamble(preamble) => {
+                log::debug!("Received protocol preamble: version {}", preamble.version);
+                self.state.protocol_version = Some(preamble.version.clone());
+            }
+            VideohubMessage::VideoOutputLocks(locks) => {
+                log::debug!("Received video output locks: {} locks", locks.len());
+                // After the initial state the device only reports the locks that changed
+                for lock in locks {
+                    let is_locked = matches!(lock.state, LockState::Locked);
+                    let previous = self.state.output_locks.insert(lock.id, is_locked);
+                    self.changed_entries.push(previous != Some(is_locked));
+                    if lock.state == LockState::Owned {
+                        self.state.owned_locks.insert(lock.id);
+                    } else {
+                        self.state.owned_locks.remove(&lock.id);
+                    }
+                    log::debug!(
+                        "Output {} lock state: {}",
+                        lock.id,
+                        if is_locked { "locked" } else { "unlocked" }
+                    );
+                }
+            }
+            VideohubMessage::UnknownMessage(header, body) => {
+                let header_str = String::from_utf8_lossy(header);
+                let body_str = String::from_utf8_lossy(body);
+                log::debug!(
+                    "Received unknown message: {} with body: {}",
+                    header_str.trim(),
+                    body_str.trim()
+                );
+
+                // Handle specific unknown messages that we can parse
+                match header_str.trim() {
+                    TAKE_MODE_HEADER => {
+                        log::debug!("Processing take mode configuration");
+                        self.handle_take_mode(&body_str);
+                    }
+                    "VIDEO INPUT STATUS:" => {
+                        handle_port_status(&mut self.state.input_status, &body_str);
+                    }
+                    "VIDEO OUTPUT STATUS:" => {
+                        handle_port_status(&mut self.state.output_status, &body_str);
+                    }
+                    "NETWORK:" => {
+                        log::debug!("Processing network configuration");
+                        self.handle_network_config(&body_str);
+                    }
+                    header if header.starts_with("NETWORK INTERFACE ") => {
+                        if let Some(interface_id) = header
+                            .strip_prefix("NETWORK INTERFACE ")
+                            .and_then(|s| s.strip_suffix(":"))
+                            .and_then(|s| s.parse::<u32>().ok())
+                        {
+                            log::debug!(
+                                "Processing network interface {interface_id} configuration"
+                            );
+                            self.handle_network_interface(interface_id, &body_str);
+                        }
+                    }
+                    header => {
+                        log::debug!("Unhandled unknown message: {header}");
+                        self.state
+                            .unknown_blocks
+                            .entry(header.to_string())
+                            .or_default()
+                            .record(&body_str);
+                    }
+                }
+            }
+            _ => {
+                log::debug!("Received unhandled message: {message:?}");
+            }
+        }
+    }
+
+    // Set a video output route
+    pub async fn set_route(&mut self, output: u32, input: u32) -> Result<()> {
+        log::info!("Setting route: output {output} -> input {input}");
+
+        let route = Route {
+            to_output: output,
+            from_input: input,
+        };
+
+        let message = VideohubMessage::VideoOutputRouting(vec![route]);
+        self.send_message(message).await?;
+
+        Ok(())
+    }
+
+    // Category of the command the most recent ACK or NAK refers to
+    pub fn last_reply(&self) -> Option<&'static str> {
+        self.last_reply
+    }
+
+    // Take or release this connection's lock on an output
+    pub async fn set_output_lock(&mut self, output: u32, locked: bool) -> Result<()> {
+        log::info!(
+            "{} output {output}",
+            if locked { "Locking" } else { "Unlocking" }
+        );
+
+        let message = VideohubMessage::VideoOutputLocks(vec![output_lock(output, locked)]);
+        self.send_message(message).await
+    }
+
+    // Take or release this connection's lock on several outputs in one block
+    pub async fn set_output_locks(&mut self, outputs: &[u32], locked: bool) -> Result<()> {
+        log::info!(
+            "{} {} outputs",
+            if locked { "Locking" } else { "Unlocking" },
+            outputs.len()
+        );
+
+        let locks = outputs
+            .iter()
+            .map(|output| output_lock(*output, locked))
+            .collect();
+        self.send_message(VideohubMessage::VideoOutputLocks(locks))
+            .await
+    }
+
+    // Enable or disable take mode on an output. The videohub crate has no message for the
+    // TAKE MODE block, so it is written as a raw block (see DeviceCodec); the device echoes the
+    // new value.
+    pub async fn set_take_mode(&mut self, output: u32, enabled: bool) -> Result<()> {
+        log::info!(
+            "{} take mode on output {output}",
+            if enabled { "Enabling" } else { "Disabling" }
+        );
+
+        let message = VideohubMessage::UnknownMessage(
+            TAKE_MODE_HEADER.as_bytes().to_vec().into(),
+            format!("{output} {enabled}\n").into_bytes().into(),
+        );
+        self.send_message(message).await
+    }
+
+    // Write a setting of the CONFIGURATION block
+    pub async fn set_configuration(&mut self, setting: &str, value: &str) -> Result<()> {
+        log::info!("Setting configuration {setting} to: {value}");
+
+        let message = VideohubMessage::Configuration(vec![Setting {
+            setting: setting.to_string(),
+            value: value.to_string(),
+        }]);
+        self.send_message(message).await
+    }
+
+    // Route an output this connection has locked: unlock, route and lock again in one write,
+    // so no other controller can take the output in between
+    pub async fn set_route_through_lock(&mut self, output: u32, input: u32) -> Result<()> {
+        log::info!("Setting route through lock: output {output} -> input {input}");
+
+        let route = Route {
+            to_output: output,
+            from_input: input,
+        };
+        self.send_pipelined(vec![
+            VideohubMessage::VideoOutputLocks(vec![output_lock(output, false)]),
+            VideohubMessage::VideoOutputRouting(vec![route]),
+            VideohubMessage::VideoOutputLocks(vec![output_lock(output, true)]),
+        ])
+        .await
+    }
+
+    // Check if this client just reconnected and needs to send full state
+    pub fn just_reconnected(&self) -> bool {
+        self.state.reconnected
+    }
+
+    // Clear the reconnected flag (called after full state has been processed)
+    pub fn clear_reconnected_flag(&mut self) {
+        self.state.reconnected = false;
+    }
+
+    // Force a full state refresh (called when rship SDK reconnects)
+    pub fn force_full_state_refresh(&mut self) {
+        self.state.reconnected = true;
+        log::info!("Forced full state refresh - will emit all state on next messages");
+    }
+
+    // Set an input label
+    pub async fn set_input_label(&mut self, input: u32, label: String) -> Result<()> {
+        log::info!("Setting input {input} label to: {label}");
+
+        let label_msg = Label {
+            id: input,
+            name: label,
+        };
+
+        let message = VideohubMessage::InputLabels(vec![label_msg]);
+        self.send_message(message).await?;
+
+        Ok(())
+    }
+
+    // Set an output label
+    pub async fn set_output_label(&mut self, output: u32, label: String) -> Result<()> {
+        log::info!("Setting output {output} label to: {label}");
+
+        let label_msg = Label {
+            id: output,
+            name: label,
+        };
+
+        let message = VideohubMessage::OutputLabels(vec![label_msg]);
+        self.send_message(message).await?;
+
+        Ok(())
+    }
+
+    // Request device information
+    #[allow(dead_code)]
+    pub async fn request_device_info(&mut self) -> Result<()> {
+        log::debug!("Requesting device info");
+        // Videohub protocol sends device info automatically on connection
+        // We can send a ping to trigger a response
+        let message = VideohubMessage::Ping;
+        self.send_message(message).await?;
+        Ok(())
+    }
+
+    // Handle take mode configuration from unknown message. After the initial state the device only
+    // reports the outputs that changed.
+    fn handle_take_mode(&mut self, body: &str) {
+        for line in body.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 2 {
+                continue;
+            }
+            if let Ok(output_id) = parts[0].parse::<u32>() {
+                let take_mode_enabled = parts[1] == "true";
+                self.state.take_mode.insert(output_id, take_mode_enabled);
+                log::debug!("Take mode for output {output_id}: {take_mode_enabled}");
+            }
+        }
+
+        log::info!(
+            "Updated take mode configuration for {} outputs",
+            self.state.take_mode.len()
+        );
+    }
+
+    // Handle network configuration from unknown message
+    fn handle_network_config(&mut self, body: &str) {
+        log::debug!("Processing network configuration");
+        for line in body.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once(": ") {
+                log::debug!("Network config: {key} = {value}");
+            }
+        }
+    }
+
+    // Handle network interface configuration from unknown message
+    fn handle_network_interface(&mut self, interface_id: u32, body: &str) {
+        let mut interface = NetworkInterface {
+            id: interface_id,
+            name: String::new(),
+            priority: None,
+            mac_address: None,
+            dynamic_ip: None,
+            current_addresses: None,
+            current_gateway: None,
+            static_addresses: None,
+            static_gateway: None,
+        };
+
+        for line in body.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once(": ") {
+                match key {
+                    "Name" => interface.name = value.to_string(),
+                    "Priority" => interface.priority = value.parse().ok(),
+                    "MAC Address" => interface.mac_address = Some(value.to_string()),
+                    "Dynamic IP" => interface.dynamic_ip = Some(value == "true"),
+                    "Current Addresses" => interface.current_addresses = Some(value.to_string()),
+                    "Current Gateway" => interface.current_gateway = Some(value.to_string()),
+                    "Static Addresses" => interface.static_addresses = Some(value.to_string()),
+                    "Static Gateway" => interface.static_gateway = Some(value.to_string()),
+                    _ => log::debug!("Unknown network interface field: {key} = {value}"),
+                }
+            }
+        }
+
+        // Update or add the interface
+        if let Some(existing) = self
+            .state
+            .network_interfaces
+            .iter_mut()
+            .find(|iface| iface.id == interface_id)
+        {
+            *existing = interface.clone();
+        } else {
+            self.state.network_interfaces.push(interface.clone());
+        }
+
+        log::debug!(
+            "Updated network interface {interface_id}: {}",
+            interface.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_nak_and_ack() {
+        let mut buffer = BytesMut::from(&b"ACK\n\nNAK\r\n\r\n"[..]);
+        assert_eq!(
+            DeviceCodec.decode(&mut buffer).unwrap(),
+            Some(VideohubMessage::ACK)
+        );
+        assert_eq!(
+            DeviceCodec.decode(&mut buffer).unwrap(),
+            Some(VideohubMessage::NAK)
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decodes_nak_after_blank_lines_and_across_reads() {
+        // Stray blank lines before the reply, CRLF line endings, and the reply split by TCP
+        let mut buffer = BytesMut::from(&b"\r\n\r\nNA"[..]);
+        assert_eq!(DeviceCodec.decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(b"K\r\n\r\n");
+        assert_eq!(
+            DeviceCodec.decode(&mut buffer).unwrap(),
+            Some(VideohubMessage::NAK)
+        );
+
+        // The NAK is counted against the oldest block awaiting a reply
+        let mut client = VideohubClient::new("localhost".into(), 9990);
+        client.awaiting_reply.extend(["output-label", "route"]);
+        client.handle_message(&VideohubMessage::NAK);
+        assert_eq!(client.last_reply(), Some("output-label"));
+        assert_eq!(client.state.rejected_commands.get("output-label"), Some(&1));
+        client.handle_message(&VideohubMessage::ACK);
+        assert_eq!(client.last_reply(), Some("route"));
+    }
+
+    #[test]
+    fn encodes_take_mode_blocks_and_applies_partial_updates() {
+        let mut buffer = BytesMut::new();
+        let message = VideohubMessage::UnknownMessage(
+            TAKE_MODE_HEADER.as_bytes().to_vec().into(),
+            b"3 true\n".to_vec().into(),
+        );
+        assert_eq!(command_category(&message), "take-mode");
+        DeviceCodec.encode(message, &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"TAKE MODE:\n3 true\n\n");
+
+        let mut client = VideohubClient::new("localhost".into(), 9990);
+        client.handle_take_mode("0 false\n3 false\n");
+        client.handle_take_mode("3 true\n");
+        assert_eq!(client.state.take_mode.get(&0), Some(&false));
+        assert_eq!(client.state.take_mode.get(&3), Some(&true));
+    }
+
+    #[test]
+    fn port_count_override_replaces_the_reported_counts() {
+        let mut client = VideohubClient::new("localhost".into(), 9990);
+        client.set_port_counts(None, Some(40));
+        client.handle_message(&VideohubMessage::DeviceInfo(DeviceInfo {
+            video_inputs: Some(40),
+            video_outputs: Some(20),
+            ..Default::default()
+        }));
+        let info = client.state.device_info.as_ref().unwrap();
+        assert_eq!(info.video_inputs, Some(40));
+        assert_eq!(info.video_outputs, Some(40));
+    }
+}
@@ -0,0 +1,26 @@
+//! Timestamps taken when events are produced, for correlating logs across systems
+
+use chrono::{SecondsFormat, Utc};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static MONOTONIC_START: OnceLock<Instant> = OnceLock::new();
+
+// When an event happened, by the wall clock and by a monotonic clock
+#[derive(Debug, Clone)]
+pub struct EventTime {
+    // ISO-8601 UTC time, e.g. "2025-01-31T20:15:02.123Z"
+    pub timestamp: String,
+    // Milliseconds since the first timestamp taken by this process; unaffected by clock adjustments
+    pub monotonic_ms: u64,
+}
+
+impl EventTime {
+    pub fn now() -> Self {
+        let start = MONOTONIC_START.get_or_init(Instant::now);
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            monotonic_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Socket I/O of a client connection, split into a reader task and a writer task so the socket
+//! keeps being read while the owner of the client is busy, e.g. waiting for a slow consumer of
+//! the events it derives from the device state.
+//!
+//! Backpressure: the reader decodes blocks ahead into a queue of `READ_QUEUE_CAPACITY` blocks.
+//! Once the queue is full it stops reading, and TCP flow control holds the device back just as
+//! if nobody read the socket. Writes are handed to the writer task one batch at a time and the
+//! sender waits until its batch is flushed, so blocks go out in the order they were sent and a
+//! failed write is reported to the command that caused it.
+
+use futures_util::{SinkExt, StreamExt};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use videohub::VideohubMessage;
+
+use crate::client::DeviceCodec;
+
+// Received blocks the client has not processed yet; a full prelude of a 288x288 router is
+// about a dozen blocks, so this only fills up when the client stalls
+pub(crate) const READ_QUEUE_CAPACITY: usize = 1024;
+// Batches waiting for the writer; each sender waits for its own batch, so this rarely exceeds one
+const WRITE_QUEUE_CAPACITY: usize = 16;
+
+type Batch = (Vec<VideohubMessage>, oneshot::Sender<io::Result<()>>);
+
+pub(crate) struct Connection {
+    received: mpsc::Receiver<io::Result<VideohubMessage>>,
+    batches: mpsc::Sender<Batch>,
+    reader: JoinHandle<()>,
+    writer: JoinHandle<()>,
+    // Socket options and addresses, taken when connecting
+    socket: String,
+}
+
+impl Connection {
+    pub(crate) fn open(stream: TcpStream) -> Self {
+        let address = |address: io::Result<std::net::SocketAddr>| {
+            address.map_or_else(|e| e.to_string(), |address| address.to_string())
+        };
+        let socket = format!(
+            "peer {}, local {}, nodelay {:?}, ttl {:?}",
+            address(stream.peer_addr()),
+            address(stream.local_addr()),
+            stream.nodelay(),
+            stream.ttl()
+        );
+        let (read, write) = stream.into_split();
+        Self::new(read, write, socket)
+    }
+
+    fn new<R, W>(read: R, write: W, socket: String) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (received_tx, received) = mpsc::channel(READ_QUEUE_CAPACITY);
+        let (batches, batches_rx) = mpsc::channel(WRITE_QUEUE_CAPACITY);
+        Self {
+            received,
+            batches,
+            reader: tokio::spawn(read_blocks(read, received_tx)),
+            writer: tokio::spawn(write_batches(write, batches_rx)),
+            socket,
+        }
+    }
+
+    // The next block from the device; None once the device closed the connection. Cancel safe.
+    pub(crate) async fn receive(&mut self) -> Option<io::Result<VideohubMessage>> {
+        self.received.recv().await
+    }
+
+    // Write blocks back to back and flush them together
+    pub(crate) async fn send(&self, messages: Vec<VideohubMessage>) -> io::Result<()> {
+        let (done, written) = oneshot::channel();
+        if self.batches.send((messages, done)).await.is_err() {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+        written
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::NotConnected.into()))
+    }
+
+    // Flush what is queued for writing and shut the socket down
+    pub(crate) async fn close(mut self) {
+        let (closed, _) = mpsc::channel(1);
+        drop(std::mem::replace(&mut self.batches, closed));
+        let _ = (&mut self.writer).await;
+    }
+
+    pub(crate) fn diagnostics(&self) -> String {
+        format!(
+            "{}, {} blocks received but not processed, {} writes queued",
+            self.socket,
+            self.received.len(),
+            self.batches.max_capacity() - self.batches.capacity()
+        )
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // The reader would otherwise keep the socket open until the device sends something
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+async fn read_blocks<R: AsyncRead + Unpin>(
+    read: R,
+    received: mpsc::Sender<io::Result<VideohubMessage>>,
+) {
+    let mut blocks = FramedRead::new(read, DeviceCodec);
+    while let Some(block) = blocks.next().await {
+        // Waits while the queue is full, leaving the rest in the socket
+        if received.send(block).await.is_err() {
+            return;
+        }
+    }
+    // Dropping the sender tells the client the connection is closed
+}
+
+async fn write_batches<W: AsyncWrite + Unpin>(write: W, mut batches: mpsc::Receiver<Batch>) {
+    let mut sink = FramedWrite::new(write, DeviceCodec);
+    while let Some((messages, done)) = batches.recv().await {
+        let mut written = Ok(());
+        for message in messages {
+            written = sink.feed(message).await;
+            if written.is_err() {
+                break;
+            }
+        }
+        if written.is_ok() {
+            written = sink.flush().await;
+        }
+        // The sender may have given up waiting; the blocks were written regardless
+        let _ = done.send(written);
+    }
+    if let Err(e) = sink.close().await {
+        log::warn!("Error closing videohub connection: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn keeps_reading_while_the_client_is_busy() {
+        let (client_side, device_side) = tokio::io::duplex(64);
+        let (read, write) = tokio::io::split(client_side);
+        let (device_read, mut device_write) = tokio::io::split(device_side);
+        let mut connection = Connection::new(read, write, "test".into());
+
+        // More than the duplex buffer holds: only a reader running on its own drains it
+        for _ in 0..20 {
+            device_write.write_all(b"ACK\n\n").await.unwrap();
+        }
+        for _ in 0..20 {
+            assert_eq!(
+                connection.receive().await.unwrap().unwrap(),
+                VideohubMessage::ACK
+            );
+        }
+
+        connection
+            .send(vec![VideohubMessage::Ping, VideohubMessage::Ping])
+            .await
+            .unwrap();
+        let mut written = FramedRead::new(device_read, DeviceCodec);
+        for _ in 0..2 {
+            assert_eq!(
+                written.next().await.unwrap().unwrap(),
+                VideohubMessage::Ping
+            );
+        }
+
+        // The device closing the connection ends the stream
+        drop(device_write);
+        drop(written);
+        assert!(connection.receive().await.is_none());
+    }
+}
@@ -0,0 +1,24 @@
+//! # videohub-executor-core
+//!
+//! Connection to a Blackmagic Videohub and the device state it reports, without rship.
+//!
+//! This is the part of [rship-blackmagic-videohub](https://docs.rs/rship-blackmagic-videohub)
+//! that other tools can embed. It runs on the caller's tokio runtime, where each connection to
+//! the device gets a reader and a writer task. The types re-exported here follow semver; fields
+//! may be added to `VideohubState` in minor releases, so construct it with `Default`.
+
+pub mod client;
+pub mod clock;
+mod connection;
+pub mod snapshot;
+pub mod unknown;
+
+pub use client::{
+    DeviceCodec, NetworkInterface, ProtocolTraceEntry, VideohubClient, VideohubState,
+    command_category, is_connection_lost,
+};
+pub use clock::EventTime;
+pub use snapshot::{RouteChange, VideohubStateSnapshot};
+pub use unknown::{UnknownBlockStats, UnknownBlockThrottle};
+// The protocol types used in the public API
+pub use videohub;
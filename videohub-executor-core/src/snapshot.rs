@@ -0,0 +1,74 @@
+//! Owned, serializable copy of the device state
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::client::{NetworkInterface, VideohubState};
+
+// Everything known about the device at one point in time; ports are 0-indexed like the protocol
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VideohubStateSnapshot {
+    pub connected: bool,
+    pub model_name: Option<String>,
+    pub friendly_name: Option<String>,
+    pub unique_id: Option<String>,
+    pub protocol_version: Option<String>,
+    pub video_inputs: Option<u32>,
+    pub video_outputs: Option<u32>,
+    pub routes: BTreeMap<u32, u32>, // output -> input
+    pub input_labels: BTreeMap<u32, String>,
+    pub output_labels: BTreeMap<u32, String>,
+    pub output_locks: BTreeMap<u32, bool>, // output -> locked by another client
+    pub owned_locks: BTreeSet<u32>,        // outputs locked by this connection
+    pub take_mode: BTreeMap<u32, bool>,
+    pub network_interfaces: Vec<NetworkInterface>,
+    pub configuration: BTreeMap<String, String>,
+    // Output -> owner, filled in by executors that assign outputs to owners
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub output_owners: BTreeMap<u32, String>,
+    // Output -> its last route change, filled in by executors that track them
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_route_changes: BTreeMap<u32, RouteChange>,
+    // Port -> label as requested, for labels the executor sent transliterated
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub original_input_labels: BTreeMap<u32, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub original_output_labels: BTreeMap<u32, String>,
+}
+
+// Who changed the route of an output, and when (ISO-8601)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteChange {
+    pub changed_by: String,
+    pub changed_at: String,
+}
+
+impl From<&VideohubState> for VideohubStateSnapshot {
+    fn from(state: &VideohubState) -> Self {
+        let info = state.device_info.as_ref();
+        let mut network_interfaces = state.network_interfaces.clone();
+        network_interfaces.sort_by_key(|interface| interface.id);
+
+        Self {
+            connected: state.connected,
+            model_name: info.and_then(|info| info.model_name.clone()),
+            friendly_name: info.and_then(|info| info.friendly_name.clone()),
+            unique_id: info.and_then(|info| info.unique_id.clone()),
+            protocol_version: state.protocol_version.clone(),
+            video_inputs: info.and_then(|info| info.video_inputs),
+            video_outputs: info.and_then(|info| info.video_outputs),
+            routes: state.video_output_routing.clone().into_iter().collect(),
+            input_labels: state.input_labels.clone().into_iter().collect(),
+            output_labels: state.output_labels.clone().into_iter().collect(),
+            output_locks: state.output_locks.clone().into_iter().collect(),
+            owned_locks: state.owned_locks.iter().copied().collect(),
+            take_mode: state.take_mode.clone().into_iter().collect(),
+            network_interfaces,
+            configuration: state.configuration.clone().into_iter().collect(),
+            output_owners: BTreeMap::new(),
+            last_route_changes: BTreeMap::new(),
+            original_input_labels: BTreeMap::new(),
+            original_output_labels: BTreeMap::new(),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Telemetry for protocol blocks the client does not understand, e.g. ones introduced by newer firmware
+
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+
+// How often each unknown block header is reported at most
+pub const UNKNOWN_BLOCK_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+// Lines of the block body kept as a sample
+const SAMPLE_LINES: usize = 8;
+
+// Occurrences of an unknown block header, with the body of the latest one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnknownBlockStats {
+    pub count: u64,
+    pub sample: String,
+}
+
+impl UnknownBlockStats {
+    pub fn record(&mut self, body: &str) {
+        self.count += 1;
+        self.sample = body
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .take(SAMPLE_LINES)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+// Limits reports of unknown blocks to one per header and interval; the first one is always reported
+#[derive(Debug)]
+pub struct UnknownBlockThrottle {
+    interval: Duration,
+    last_reported: HashMap<String, Instant>,
+}
+
+impl UnknownBlockThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_reported: HashMap::new(),
+        }
+    }
+
+    // Whether a block with this header should be reported now
+    pub fn should_report(&mut self, header: &str) -> bool {
+        let now = Instant::now();
+        match self.last_reported.get_mut(header) {
+            Some(last) if now.duration_since(*last) < self.interval => false,
+            Some(last) => {
+                *last = now;
+                true
+            }
+            None => {
+                self.last_reported.insert(header.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_the_latest_body() {
+        let mut stats = UnknownBlockStats::default();
+        stats.record("Fan: ok\n\n");
+        stats.record("Fan: failed\nTemperature: ok\n");
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.sample, "Fan: failed\nTemperature: ok");
+    }
+
+    #[test]
+    fn reports_each_header_once_per_interval() {
+        let mut throttle = UnknownBlockThrottle::new(Duration::from_secs(60));
+        assert!(throttle.should_report("ALARM STATUS:"));
+        assert!(!throttle.should_report("ALARM STATUS:"));
+        assert!(throttle.should_report("VIDEO INPUT STATUS:"));
+
+        let mut unthrottled = UnknownBlockThrottle::new(Duration::ZERO);
+        assert!(unthrottled.should_report("ALARM STATUS:"));
+        assert!(unthrottled.should_report("ALARM STATUS:"));
+    }
+}